@@ -0,0 +1,158 @@
+use super::{parse_format, read_subtitles, FileArgs};
+use simple_sub_sync::subtitle_parser::align::{AlignMode, DEFAULT_THRESHOLD};
+use simple_sub_sync::subtitle_parser::dtw::DtwOptions;
+use simple_sub_sync::subtitle_parser::encoding;
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::timing;
+
+const HELP: &str = "
+subsync align --input <file> --reference <file> --output <file> [--mode transfer|fit|timing] [options]
+
+Retimes subtitles after a correctly timed file in the same language, such as the original of an edited
+translation: cues are matched by their text, allowing for rewording and typo fixes, and the timing of
+the reference is carried over. Cues that match no reference cue are listed. A translation can be aligned
+to a transcript of the video in another language (see subsync transcribe --help) with --mode timing,
+which doesn't compare the text.
+
+    --input = the subtitles to retime: file path, or - for standard input, or a .zip or .rar archive
+        holding the subtitle. Mandatory
+    --reference = the correctly timed subtitles, in any supported format. Mandatory
+    --reference-format = format of the reference when its file extension doesn't tell. Optional
+    --reference-encoding = text encoding of the reference. Optional, detected otherwise
+    --output = output file path, or - for standard output. Mandatory
+    --mode = transfer, fit or timing. Optional, defaults to transfer.
+        transfer gives matched cues the times of their reference cue, and moves the other cues linearly
+        between the matched cues around them. fit moves and stretches every cue by the single shift and
+        scale that best fits the matched cues, keeping their durations in proportion. timing ignores the
+        text and warps the timing of the cues onto the reference by dynamic time warping, after the offset
+        and framerate ratio that best fit the whole file, so offsets can change at every cut
+    --band = with --mode timing, how far the warp can stray from the best offset and ratio of the whole
+        file, as 20s or 1:00. Optional, defaults to 20s; raise it for longer cuts
+    --threshold = how similar the text of two cues has to be for them to match, from 0 to 1. Optional,
+        defaults to 0.6
+    --member = name of the subtitle file to read in an archive. Optional
+    --format = format of the input and output when the file extension doesn't tell. Optional
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output. Optional
+    -h, --help Display this help.
+";
+
+// Create a function that runs the align subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut reference_file = String::new();
+    let mut reference_format = None;
+    let mut reference_encoding = None;
+    let mut mode = AlignMode::Transfer;
+    let mut threshold = DEFAULT_THRESHOLD;
+    let mut dtw_options = DtwOptions::default();
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--reference" {
+            reference_file = value(i)?;
+            i += 1;
+        } else if args[i] == "--reference-format" {
+            reference_format = Some(parse_format(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--reference-encoding" {
+            reference_encoding = Some(encoding::from_label(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--mode" {
+            let name = value(i)?;
+            mode = AlignMode::from_name(&name).ok_or_else(|| {
+                format!(
+                    "Invalid value '{}' for --mode, expected transfer, fit or timing",
+                    name
+                )
+            })?;
+            i += 1;
+        } else if args[i] == "--band" {
+            dtw_options.band_ms = timing::parse_duration(&value(i)?)?;
+            if dtw_options.band_ms <= 0 {
+                return Err("The --band has to be longer than 0".to_string());
+            }
+            i += 1;
+        } else if args[i] == "--threshold" {
+            let value = value(i)?;
+            threshold = value
+                .parse::<f64>()
+                .ok()
+                .filter(|threshold| (0.0..=1.0).contains(threshold))
+                .ok_or_else(|| format!("Invalid threshold '{}', expected 0 to 1", value))?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+    if reference_file.is_empty() {
+        return Err("--reference is required. Use --help for help.".to_string());
+    }
+
+    let (mut subtitles, input_newline) = files.read()?;
+    let (reference, _) = read_subtitles(
+        &reference_file,
+        None,
+        reference_format,
+        &files.options,
+        reference_encoding,
+    )?;
+    let transform = if mode == AlignMode::Timing {
+        let transform = subtitles.align_timing_to(&reference, &dtw_options)?;
+        if let Transform::Warp { anchors } = &transform {
+            eprintln!("Warped the timing through {} anchors", anchors.len());
+        }
+        Some(transform)
+    } else {
+        let (unmatched, transform) = subtitles.align_to(&reference, mode, threshold)?;
+        for i in &unmatched {
+            let cue = &subtitles.cues[*i];
+            eprintln!("Unmatched cue {}: {}", cue.index, cue.lines.join(" / "));
+        }
+        eprintln!(
+            "Matched {} of {} cues",
+            subtitles.cues.len() - unmatched.len(),
+            subtitles.cues.len()
+        );
+        transform
+    };
+    let cut = subtitles.clamp_to_zero();
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would start before 0:00, they were cut to start at 0:00 or removed",
+            cut
+        );
+    }
+    // Transferred timings can't be recorded as a retiming to revert, only a fit or a warp can.
+    files.save(
+        &subtitles,
+        &files.write_options(input_newline),
+        transform
+            .map(|transform| Step::new(transform).losing(cut))
+            .into_iter()
+            .collect(),
+    )
+}
@@ -0,0 +1,644 @@
+use super::config::{self, check_share, check_tolerance, parse_candidates};
+use super::speech::{parse_correlate_option, parse_vad_option};
+use super::video::probe_video;
+use super::{audio, parse_format, read_subtitles, write_file};
+use serde::Serialize;
+use simple_sub_sync::subtitle_parser::correlate::{
+    self, CorrelateOptions, DriftPoint, Misalignment,
+};
+use simple_sub_sync::subtitle_parser::detect::{self, Detection, Evidence, Region};
+use simple_sub_sync::subtitle_parser::vad::VadOptions;
+use simple_sub_sync::subtitle_parser::{encoding, timing, vtt, ParseOptions};
+use std::collections::BTreeMap;
+
+// Below this confidence, the misalignment found may well be a coincidence of the speech pattern.
+const LOW_CONFIDENCE: f64 = 0.2;
+
+// How much more speech parts of the file with offsets of their own have to cover than a single offset.
+const JUMPS_GAIN: f64 = 0.1;
+
+const HELP: &str = "
+subsync analyze <file> [--video <file> [--classify] [--drift-curve <file.csv>]] [--reference <file>]
+        [--known-runtime <time>] [--region pal|ntsc|film] [--candidates <list>] [--top <n>]
+        [--explain | --json] [options]
+
+Detects the framerate a subtitle file was timed for, to give as -if when converting it. Several methods
+are combined: the framerate the file declares, whether the intervals between cue times are whole
+numbers of frames, with --video whether the subtitles end with the video, and with --reference the
+slope of the cue times against correctly timed subtitles. Each method has a weight, how far it can be
+trusted, and the confidence in a framerate grows as methods agree. The region a release comes from, and
+hints in the names of the files (23.976, 25fps, PAL, NTSC, BluRay, WEB-DL, DVDRip), are only priors:
+they are weighed in when no evidence from the file is strong, and never outrank it. Subtitles timed on
+a frame grid and then converted with the wrong framerates, or converted twice, sit on the grid of an
+unusual framerate, which is reported with the factor that undoes the conversion. Every candidate
+framerate is listed from the most to the least likely, with its confidence and the score each method
+gave it, so a borderline result can be checked by converting with the runner-up too. Subtitles timed
+against telecined video (23.976 fps film shown at 29.97 fps with 3:2 pulldown) follow a cadence of
+changes 50 and 33 ms apart, which is recognized rather than mislabeled as 30 fps. Each half of the file
+is also detected on its own, and when they confidently disagree the confidence is lowered and a warning
+printed. The candidates and tolerances can also be set for every run in the configuration file, see the
+README. With --classify, the cues are also laid over the speech of the video to tell how they are off:
+by a constant offset (fixed by subsync shift), drifting from a framerate mismatch (fixed by converting
+the framerate with an offset), or jumping between parts of the file, from a different cut (fixed by
+subsync retime through anchors). The command fixing it is printed with its parameters.
+
+    <file> = input file path, or - for standard input, also given as --input <file>. Mandatory
+    --video = path of the video the subtitles are played with. Its duration and framerate are read with
+        ffprobe, which has to be installed. Optional
+    --known-runtime = the correct runtime of the film, as 1:41:53, from IMDb or the video file. The
+        runtime divided by the end of the last cue is the speed factor the subtitles are off by, mapped
+        to the nearest pair of framerates with a confidence. Optional
+    --reference = path of subtitles correctly timed for the video, in any language. The cue times of the
+        input are regressed against them, matched by text or else by timing, and the slope is the ratio of
+        the framerate of the input to the one of the reference (of the video when --video is given).
+        Optional
+    --region = pal, ntsc or film, the region the release comes from, whose usual framerates (25 fps for
+        PAL, 29.97 or 23.976 for NTSC, 23.976 or 24 for film) are the prior. Optional, no prior otherwise
+    --classify = find how the cues are off from the speech of the --video and the fix for it. The
+        soundtrack is decoded with ffmpeg, which has to be installed, unless it is a WAV file. Optional
+    --drift-curve = CSV file the offset of the cues along the file is written to, from the speech of the
+        --video, or - for standard output: the best offset of every --window, starting every quarter of
+        it, with columns time, time_ms, offset_ms, cues and coverage. A flat curve is a constant offset, a
+        sloped line a framerate mismatch, steps a different cut. Optional
+    --overwrite = replace the CSV file if it exists. Optional, an existing file is never replaced otherwise
+    --max-offset, --window, --no-drift = how far the cues are searched for over the speech, see subsync
+        sync --help. Optional
+    --margin, --min-speech, --min-gap = how speech is detected, see subsync speech --help. Optional
+    --candidates = framerates considered, as 23.976,24,25,48 (24000/1001 works too), for unusual
+        material such as silent-film transfers or 48 fps HFR. Optional, defaults to the candidates of the
+        configuration file, or 23.976,24,25,29.97,30
+    --tolerance = how far from the frame grid a cue time can be and still count as on it, as 5ms. Raise it
+        for sloppy fan subtitles or centisecond formats (10ms for ASS), keep it at 1ms for frame-exact
+        broadcast subtitles. Optional, defaults to 1ms
+    --threshold = how similar the text of two cues has to be to match them with the --reference, from 0
+        to 1. Optional, defaults to 0.6
+    --borderline = how far ahead of the next candidate the best one has to be, in confidence, not to be
+        flagged as borderline. Optional, defaults to 0.15
+    --top = number of candidates listed. Optional, all of them by default
+    --explain = print under every method the evidence behind its scores: the share of its samples on the
+        frame grid of every candidate, the most common intervals between cue times, and the score of
+        every candidate, to audit why a framerate was chosen. Optional
+    --json = print the detection as JSON instead of text, with the same evidence as --explain and whether
+        each method was counted. Can't be used with --classify. Optional
+    --format = format of the input when the file extension doesn't tell. Optional
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    -h, --help Display this help.
+";
+
+// Create a function that runs the analyze subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input_file = String::new();
+    let mut format = None;
+    let mut input_encoding = None;
+    let mut video = None;
+    let mut options = config::load()?.detect;
+    let mut top = usize::MAX;
+    let mut explain = false;
+    let mut json = false;
+    let mut classify = false;
+    let mut drift_curve = None;
+    let mut overwrite = false;
+    let mut reference = None;
+    let mut vad_options = VadOptions::default();
+    let mut correlate_options = CorrelateOptions::default();
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = parse_vad_option(args, i, &mut vad_options)?
+            + parse_correlate_option(args, i, &mut correlate_options)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--explain" {
+            explain = true;
+        } else if args[i] == "--json" {
+            json = true;
+        } else if args[i] == "--classify" {
+            classify = true;
+        } else if args[i] == "--drift-curve" {
+            drift_curve = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--overwrite" {
+            overwrite = true;
+        } else if args[i] == "--format" {
+            format = Some(parse_format(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--input-encoding" {
+            input_encoding = Some(encoding::from_label(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--video" {
+            video = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--known-runtime" {
+            let runtime = timing::parse_duration(&value(i)?)?;
+            if runtime <= 0 {
+                return Err("The --known-runtime has to be longer than 0".to_string());
+            }
+            options.runtime_ms = Some(runtime);
+            i += 1;
+        } else if args[i] == "--reference" {
+            reference = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--region" {
+            options.region = Some(Region::from_name(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--candidates" {
+            options.candidates = parse_candidates(&value(i)?)?;
+            i += 1;
+        } else if args[i] == "--tolerance" {
+            let value = value(i)?;
+            options.tolerance_ms = check_tolerance(
+                timing::parse_duration(&value)
+                    .map_err(|_| format!("Invalid tolerance '{}'", value))? as f64,
+            )?;
+            i += 1;
+        } else if args[i] == "--threshold" || args[i] == "--borderline" {
+            let value = value(i)?;
+            let share = check_share(
+                &args[i][2..],
+                value
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid {} '{}'", &args[i][2..], value))?,
+            )?;
+            if args[i] == "--threshold" {
+                options.match_threshold = share;
+            } else {
+                options.borderline = share;
+            }
+            i += 1;
+        } else if args[i] == "--top" {
+            let value = value(i)?;
+            top = value
+                .parse::<usize>()
+                .ok()
+                .filter(|top| *top > 0)
+                .ok_or_else(|| format!("Invalid number of candidates '{}'", value))?;
+            i += 1;
+        } else if args[i] == "--input" {
+            input_file = value(i)?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else if args[i] == "-" || !args[i].starts_with('-') {
+            input_file = args[i].clone();
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if input_file.is_empty() {
+        return Err("No input file provided. Use --help for help.".to_string());
+    }
+    if classify && video.is_none() {
+        return Err("--classify needs the --video. Use --help for help.".to_string());
+    }
+    if drift_curve.is_some() && video.is_none() {
+        return Err("--drift-curve needs the --video. Use --help for help.".to_string());
+    }
+    if json && (classify || drift_curve.as_deref() == Some("-")) {
+        return Err(
+            "--json reports the detection only, it can't be mixed with --classify or a --drift-curve \
+             on standard output. Use --help for help."
+                .to_string(),
+        );
+    }
+
+    let (subtitles, _) = read_subtitles(
+        &input_file,
+        None,
+        format,
+        &ParseOptions::default(),
+        input_encoding,
+    )?;
+    if input_file != "-" {
+        options.names.push(input_file.clone());
+    }
+    if let Some(video) = &video {
+        options.names.push(video.clone());
+        let info = probe_video(video)?;
+        options.video = info.duration_ms.map(|duration| (duration, info.framerate));
+    }
+    if let Some(reference) = &reference {
+        let (reference, _) = read_subtitles(reference, None, None, &ParseOptions::default(), None)?;
+        options.reference = Some(reference);
+    }
+    let detection = detect::detect_framerate(&subtitles, &options);
+    if json {
+        let output = serde_json::to_string_pretty(&report(&detection, top))
+            .map_err(|error| format!("Unable to write the report: {}", error))?;
+        println!("{}", output);
+    } else {
+        print_detection(&detection, top, explain);
+    }
+    if let (true, Some(video)) = (classify || drift_curve.is_some(), &video) {
+        let intervals = audio::speech_intervals(video, &vad_options)?;
+        let cues: Vec<(i32, i32)> = subtitles
+            .cues
+            .iter()
+            .filter(|cue| cue.end_ms > cue.start_ms)
+            .map(|cue| (cue.start_ms, cue.end_ms))
+            .collect();
+        if classify {
+            print_misalignment(&input_file, &cues, &intervals, &correlate_options);
+        }
+        if let Some(output_file) = &drift_curve {
+            let points = correlate::drift_curve(&cues, &intervals, &correlate_options);
+            let output = drift_csv(&points);
+            if output_file == "-" {
+                print!("{}", output);
+            } else {
+                write_file(output_file, output.as_bytes(), overwrite)?;
+                eprintln!(
+                    "Drift curve: {} points written to {}",
+                    points.len(),
+                    output_file
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// Create a function to print what the detection found, the candidates and the evidence of every method.
+fn print_detection(detection: &Detection, top: usize, explain: bool) {
+    match detection.best() {
+        Some(best) => println!(
+            "Framerate:  {} fps, confidence {:.2}",
+            best.framerate, best.confidence
+        ),
+        None => println!("Framerate:  unknown, nothing points to one; give it with -if"),
+    }
+    if detection.best().is_some() && detection.is_borderline() {
+        println!("Borderline: compare the candidates, for example with -compare over the video");
+    }
+    if detection
+        .evidence
+        .iter()
+        .any(|evidence| evidence.method == detect::Method::Telecine)
+    {
+        println!(
+            "Telecine:   the times follow the 3:2 pulldown of 23.976 fps film shown as 29.97 fps video; they \
+             play unchanged over the film, use -if 23.976 and don't convert them as 29.97 or 30 fps"
+        );
+    }
+    if let Some(runtime) = detection.runtime {
+        let conversion = match runtime.conversion {
+            Some((from, to)) => format!("converted from {} to {} fps", from, to),
+            None => "no speed change".to_string(),
+        };
+        println!(
+            "Runtime:    {:.4}x the end of the last cue, {} (confidence {:.2})",
+            runtime.factor, conversion, runtime.confidence
+        );
+    }
+    if let Some(halves) = detection.halves {
+        let half = |candidate: Option<detect::Candidate>| {
+            candidate.map_or("unknown".to_string(), |candidate| {
+                format!("{} fps ({:.2})", candidate.framerate, candidate.confidence)
+            })
+        };
+        println!(
+            "Halves:     {} before {}, {} after",
+            half(halves.first),
+            vtt::convert_to_time(halves.split_ms),
+            half(halves.second)
+        );
+        if halves.disagree() {
+            println!(
+                "Warning: the halves of the file point to different framerates, the confidence was lowered; \
+                 the drift changes along the file or part of it was edited, compare them with --classify"
+            );
+        }
+    }
+    if let Some(conversion) = detection.conversion {
+        println!(
+            "Conversion: the file appears to have been scaled by {:.4}x from a {} fps original; \
+             subsync scale --factor {:.6} undoes it",
+            conversion.factor,
+            conversion.original,
+            1.0 / conversion.factor
+        );
+    }
+    println!("Candidates:");
+    for candidate in detection.candidates.iter().take(top) {
+        let scores: Vec<String> = detection
+            .evidence
+            .iter()
+            .map(|evidence| {
+                format!(
+                    "{} {:.2}",
+                    evidence.method.name(),
+                    evidence.score(candidate.framerate)
+                )
+            })
+            .collect();
+        println!(
+            "  {:<7} {:.2}{}{}",
+            candidate.framerate,
+            candidate.confidence,
+            if scores.is_empty() { "" } else { "  " },
+            scores.join(", ")
+        );
+    }
+    println!("Methods:");
+    for evidence in &detection.evidence {
+        let samples = if evidence.samples > 1 {
+            format!(", {} samples", evidence.samples)
+        } else {
+            String::new()
+        };
+        print!(
+            "  {} (weight {:.2}{}): {}",
+            evidence.method.name(),
+            evidence.weight,
+            samples,
+            evidence.detail
+        );
+        match Detection::favoured(evidence) {
+            Some((framerate, score)) => println!(", favours {} fps ({:.2})", framerate, score),
+            None => println!(),
+        }
+        if explain {
+            print_explanation(evidence);
+        }
+    }
+}
+
+// Create a function to print the evidence behind the scores of a method, under its line: the share of
+// the samples that fit every candidate, the most common intervals, and the score of every candidate.
+fn print_explanation(evidence: &Evidence) {
+    if !evidence.ratios.is_empty() {
+        let ratios: Vec<String> = evidence
+            .ratios
+            .iter()
+            .map(|(framerate, ratio)| format!("{} {:.0}%", framerate, ratio * 100.0))
+            .collect();
+        println!("      samples on the grid: {}", ratios.join(", "));
+    }
+    if !evidence.histogram.is_empty() {
+        let intervals: Vec<String> = evidence
+            .histogram
+            .iter()
+            .map(|(interval, count)| format!("{}ms ({})", interval, count))
+            .collect();
+        println!("      most common intervals: {}", intervals.join(", "));
+    }
+    let scores: Vec<String> = evidence
+        .scores
+        .iter()
+        .map(|(framerate, score)| format!("{} {:.2}", framerate, score))
+        .collect();
+    println!("      scores: {}", scores.join(", "));
+}
+
+// The detection as written by --json.
+#[derive(Debug, Serialize)]
+struct Report {
+    framerate: Option<f64>,
+    confidence: Option<f64>,
+    borderline: bool,
+    // Whether the priors were weighed in, see Detection.
+    used_priors: bool,
+    candidates: Vec<CandidateReport>,
+    methods: Vec<MethodReport>,
+    halves: Option<HalvesReport>,
+    conversion: Option<ConversionReport>,
+    runtime: Option<RuntimeReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct CandidateReport {
+    framerate: f64,
+    confidence: f64,
+    // The score every method gave the candidate, by method name.
+    scores: BTreeMap<&'static str, f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct MethodReport {
+    method: &'static str,
+    weight: f64,
+    samples: usize,
+    // Whether the method counted towards the confidences, priors only do when no evidence is strong.
+    counted: bool,
+    detail: String,
+    favours: Option<f64>,
+    // Scores and shares of the samples on the grid, by candidate framerate.
+    scores: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    ratios: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    histogram: Vec<IntervalCount>,
+}
+
+#[derive(Debug, Serialize)]
+struct IntervalCount {
+    interval_ms: i32,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct HalvesReport {
+    split_ms: i32,
+    first: Option<f64>,
+    second: Option<f64>,
+    disagree: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ConversionReport {
+    original: f64,
+    factor: f64,
+    score: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct RuntimeReport {
+    factor: f64,
+    from: Option<f64>,
+    to: Option<f64>,
+    confidence: f64,
+}
+
+// Create a function to key scores by framerate, for the JSON report.
+fn by_framerate(values: &[(f64, f64)]) -> BTreeMap<String, f64> {
+    values
+        .iter()
+        .map(|(framerate, value)| (framerate.to_string(), *value))
+        .collect()
+}
+
+// Create a function to build the JSON report of a detection, with the first candidates only.
+fn report(detection: &Detection, top: usize) -> Report {
+    let best = detection.best();
+    Report {
+        framerate: best.map(|best| best.framerate),
+        confidence: best.map(|best| best.confidence),
+        borderline: best.is_some() && detection.is_borderline(),
+        used_priors: detection.used_priors,
+        candidates: detection
+            .candidates
+            .iter()
+            .take(top)
+            .map(|candidate| CandidateReport {
+                framerate: candidate.framerate,
+                confidence: candidate.confidence,
+                scores: detection
+                    .evidence
+                    .iter()
+                    .map(|evidence| (evidence.method.name(), evidence.score(candidate.framerate)))
+                    .collect(),
+            })
+            .collect(),
+        methods: detection
+            .evidence
+            .iter()
+            .map(|evidence| MethodReport {
+                method: evidence.method.name(),
+                weight: evidence.weight,
+                samples: evidence.samples,
+                counted: !evidence.method.is_prior() || detection.used_priors,
+                detail: evidence.detail.clone(),
+                favours: Detection::favoured(evidence).map(|(framerate, _)| framerate),
+                scores: by_framerate(&evidence.scores),
+                ratios: by_framerate(&evidence.ratios),
+                histogram: evidence
+                    .histogram
+                    .iter()
+                    .map(|(interval_ms, count)| IntervalCount {
+                        interval_ms: *interval_ms,
+                        count: *count,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        halves: detection.halves.map(|halves| HalvesReport {
+            split_ms: halves.split_ms,
+            first: halves.first.map(|first| first.framerate),
+            second: halves.second.map(|second| second.framerate),
+            disagree: halves.disagree(),
+        }),
+        conversion: detection.conversion.map(|conversion| ConversionReport {
+            original: conversion.original,
+            factor: conversion.factor,
+            score: conversion.score,
+        }),
+        runtime: detection.runtime.map(|runtime| RuntimeReport {
+            factor: runtime.factor,
+            from: runtime.conversion.map(|(from, _)| from),
+            to: runtime.conversion.map(|(_, to)| to),
+            confidence: runtime.confidence,
+        }),
+    }
+}
+
+// Create a function to build the CSV rows of a drift curve, one per window; the offset is empty for
+// windows with too few cues.
+fn drift_csv(points: &[DriftPoint]) -> String {
+    let mut output = String::from("time,time_ms,offset_ms,cues,coverage\n");
+    for point in points {
+        output.push_str(&format!(
+            "{},{},{},{},{:.3}\n",
+            vtt::convert_to_time(point.time_ms),
+            point.time_ms,
+            point
+                .offset
+                .map_or(String::new(), |offset| offset.to_string()),
+            point.cues,
+            point.coverage
+        ));
+    }
+    output
+}
+
+// Create a function to find the common framerates converting between which multiplies times by a ratio.
+fn conversion_for(ratio: f64) -> Option<(f64, f64)> {
+    detect::COMMON_FRAMERATES.iter().find_map(|from| {
+        detect::COMMON_FRAMERATES
+            .iter()
+            .find(|to| (from / *to / ratio - 1.0).abs() < 0.0001)
+            .map(|to| (*from, *to))
+    })
+}
+
+// Create a function to print how the cues are off from the speech intervals and the command fixing it.
+fn print_misalignment(
+    input_file: &str,
+    cues: &[(i32, i32)],
+    intervals: &[(i32, i32)],
+    options: &CorrelateOptions,
+) {
+    let Some(correlation) = correlate::correlate(cues, intervals, options) else {
+        println!(
+            "Misalignment: nothing to measure, the file has no cues or the soundtrack no speech"
+        );
+        return;
+    };
+    let files = format!("--input {} --output <output>", input_file);
+    let misalignment = correlation.misalignment();
+    // Parts of the file with offsets of their own always leave the best offset a close runner-up, they
+    // are trusted when they lay the cues over the speech clearly better than a single offset.
+    let trusted = match misalignment {
+        Misalignment::Jumps(_) => {
+            let coverages: Vec<f64> = correlation
+                .windows
+                .iter()
+                .filter(|window| window.offset.is_some())
+                .map(|window| window.coverage)
+                .collect();
+            coverages.iter().sum::<f64>() / coverages.len() as f64
+                >= correlation.coverage + JUMPS_GAIN
+        }
+        _ => correlation.confidence >= LOW_CONFIDENCE,
+    };
+    match misalignment {
+        Misalignment::InSync => println!("Misalignment: none, the cues fall on the speech"),
+        Misalignment::Offset(offset) => {
+            println!("Misalignment: constant offset of {}ms", offset);
+            println!("Fix:          subsync shift {} --by {}ms", files, offset);
+        }
+        Misalignment::Drift { ratio, offset } => {
+            println!(
+                "Misalignment: drift, times should be multiplied by {:.6} and moved by {}ms",
+                ratio, offset
+            );
+            match conversion_for(ratio) {
+                Some((from, to)) => println!(
+                    "Fix:          subsync -i {} -o <output> -if {} -of {} --offset {}ms",
+                    input_file, from, to, offset
+                ),
+                None => println!(
+                    "Fix:          subsync scale {} --factor {:.6}, then subsync shift --by {}ms",
+                    files, ratio, offset
+                ),
+            }
+        }
+        Misalignment::Jumps(anchors) => {
+            println!("Misalignment: the offset jumps between parts of the file, a different cut");
+            let anchors: Vec<String> = anchors
+                .iter()
+                .map(|(time, new_time)| {
+                    format!(
+                        "--anchor {}={}",
+                        vtt::convert_to_time(*time),
+                        vtt::convert_to_time(*new_time)
+                    )
+                })
+                .collect();
+            println!(
+                "Fix:          subsync retime {} {}",
+                files,
+                anchors.join(" ")
+            );
+        }
+    }
+    if !trusted {
+        println!("Warning: the result is uncertain, check the fix before keeping it");
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{Cursor, ErrorKind};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use simple_sub_sync::subtitle_parser::Registry;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// Create a function to tell whether a path is an archive that subtitles are read from or written to.
+pub fn is_archive(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".zip") || path.ends_with(".rar")
+}
+
+// Create a function to run unrar, which has to be installed and on the PATH, and return what it wrote
+// to standard output.
+fn run_unrar(args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("unrar")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| match error.kind() {
+            ErrorKind::NotFound => {
+                "unrar was not found, install it to read RAR archives".to_string()
+            }
+            _ => format!("Unable to run unrar: {}", error),
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "unrar failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+// Create a function to pick the member to read out of the names of the files of an archive: the given
+// member, by its path or file name, or else the only subtitle file, picked by its extension.
+fn pick_member(path: &str, names: &[String], member: Option<&str>) -> Result<String, String> {
+    let names: Vec<&String> = names
+        .iter()
+        .filter(|name| !name.ends_with('/') && !name.starts_with("__MACOSX/"))
+        .collect();
+    if let Some(member) = member {
+        return names
+            .iter()
+            .find(|name| **name == member || Path::new(name).file_name() == Some(member.as_ref()))
+            .map(|name| name.to_string())
+            .ok_or_else(|| format!("No member {} in {}", member, path));
+    }
+    let registry = Registry::default();
+    let subtitles: Vec<&String> = names
+        .into_iter()
+        .filter(|name| registry.from_extension(name).is_some())
+        .collect();
+    match subtitles.as_slice() {
+        [name] => Ok(name.to_string()),
+        [] => Err(format!("No subtitle file found in {}", path)),
+        _ => Err(format!(
+            "{} holds several subtitle files, pick one with --member: {}",
+            path,
+            subtitles
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )),
+    }
+}
+
+// Create a function to read a subtitle file out of a .zip or .rar archive. Without a member name, the
+// archive has to hold a single subtitle file, picked by its extension. RAR archives are read with
+// unrar. Returns the member name and contents.
+pub fn read_member(path: &str, member: Option<&str>) -> Result<(String, Vec<u8>), String> {
+    if path.to_lowercase().ends_with(".rar") {
+        let listing = run_unrar(&["lb", "-p-", "--", path])?;
+        let names: Vec<String> = String::from_utf8_lossy(&listing)
+            .lines()
+            .map(String::from)
+            .collect();
+        let name = pick_member(path, &names, member)?;
+        let contents = run_unrar(&["p", "-inul", "-p-", "--", path, &name])?;
+        return Ok((name, contents));
+    }
+    let file = File::open(path).map_err(|error| format!("Unable to open {}: {}", path, error))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|error| format!("Unable to read archive {}: {}", path, error))?;
+    let names: Vec<String> = archive
+        .file_names()
+        .filter_map(|name| name.ok())
+        .map(|name| name.to_string())
+        .collect();
+    let name = pick_member(path, &names, member)?;
+    let mut entry = archive
+        .by_name(&name)
+        .map_err(|error| format!("Unable to read {} in {}: {}", name, path, error))?;
+    let mut contents = Vec::new();
+    entry
+        .read_to_end(&mut contents)
+        .map_err(|error| format!("Unable to read {} in {}: {}", name, path, error))?;
+    Ok((name, contents))
+}
+
+// Create a function to build a new .zip archive holding a single file.
+pub fn archive_member(path: &str, member: &str, contents: &[u8]) -> Result<Vec<u8>, String> {
+    if !path.to_lowercase().ends_with(".zip") {
+        return Err(format!(
+            "Unable to write {}, only .zip archives can be written",
+            path
+        ));
+    }
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    archive
+        .start_file(member, options)
+        .and_then(|_| archive.write_all(contents).map_err(Into::into))
+        .and_then(|_| archive.finish())
+        .map(|cursor| cursor.into_inner())
+        .map_err(|error| format!("Unable to write {}: {}", path, error))
+}
@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::process::{Command, Stdio};
+
+use simple_sub_sync::subtitle_parser::vad::{self, EnergyMeter, VadOptions};
+
+// Sample rate ffmpeg decodes soundtracks at, plenty for speech.
+const DECODE_RATE: u32 = 16000;
+
+// Create a function to feed 16-bit little endian samples of interleaved channels to a meter, keeping
+// a trailing partial sample frame for the next call. Channels are mixed down to one.
+fn feed(meter: &mut EnergyMeter, bytes: &[u8], channels: usize, pending: &mut Vec<u8>) {
+    pending.extend_from_slice(bytes);
+    let frame = 2 * channels;
+    let complete = pending.len() / frame * frame;
+    let samples: Vec<i16> = pending[..complete]
+        .chunks_exact(frame)
+        .map(|frame| {
+            let sum: i32 = frame
+                .chunks_exact(2)
+                .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as i32)
+                .sum();
+            (sum / channels as i32) as i16
+        })
+        .collect();
+    meter.push(&samples);
+    pending.drain(..complete);
+}
+
+// Create a function to measure the frame energies of a 16-bit PCM WAV file, read directly.
+fn wav_energies(path: &str) -> Result<Vec<f32>, String> {
+    let file = File::open(path).map_err(|error| format!("Unable to open {}: {}", path, error))?;
+    let mut reader = BufReader::new(file);
+    let error = |error: std::io::Error| format!("Unable to read {}: {}", path, error);
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header).map_err(error)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(format!("{} is not a WAV file", path));
+    }
+    let mut format = None;
+    loop {
+        let mut chunk = [0u8; 8];
+        reader
+            .read_exact(&mut chunk)
+            .map_err(|_| format!("{} has no audio data", path))?;
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+        if &chunk[0..4] == b"fmt " {
+            // The chunk is read as far as the file goes, whatever size a corrupt header gives it.
+            let mut fmt = Vec::new();
+            (&mut reader)
+                .take(size)
+                .read_to_end(&mut fmt)
+                .map_err(error)?;
+            if fmt.len() < 16 || fmt.len() as u64 != size {
+                return Err(format!("{} has an invalid format chunk", path));
+            }
+            let tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+            let channels = u16::from_le_bytes([fmt[2], fmt[3]]) as usize;
+            let rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+            let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+            // 0xFFFE is WAVE_FORMAT_EXTENSIBLE, which wraps the same samples.
+            if !(tag == 1 || tag == 0xFFFE) || bits != 16 || channels == 0 {
+                return Err(format!(
+                    "{} isn't 16-bit PCM, convert it or let ffmpeg read the video instead",
+                    path
+                ));
+            }
+            format = Some((channels, rate));
+            if size % 2 == 1 {
+                reader.read_exact(&mut [0u8]).map_err(error)?;
+            }
+        } else if &chunk[0..4] == b"data" {
+            let (channels, rate) = format.ok_or_else(|| format!("{} has no format chunk", path))?;
+            let mut meter = EnergyMeter::new(rate);
+            let mut pending = Vec::new();
+            let mut buffer = vec![0u8; 1 << 16];
+            let mut data = reader.take(size);
+            loop {
+                let read = data.read(&mut buffer).map_err(error)?;
+                if read == 0 {
+                    break;
+                }
+                feed(&mut meter, &buffer[..read], channels, &mut pending);
+            }
+            return Ok(meter.finish());
+        } else {
+            std::io::copy(
+                &mut (&mut reader).take(size + size % 2),
+                &mut std::io::sink(),
+            )
+            .map_err(error)?;
+        }
+    }
+}
+
+// Create a function to measure the frame energies of the soundtrack of a video with ffmpeg, which has
+// to be installed. The audio is decoded as it is read, so long films aren't held in memory.
+fn ffmpeg_energies(path: &str) -> Result<Vec<f32>, String> {
+    let rate = DECODE_RATE.to_string();
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-v", "error", "-i", path, "-vn", "-ac", "1", "-ar", &rate, "-f", "s16le", "pipe:1",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| match error.kind() {
+            ErrorKind::NotFound => "ffmpeg was not found, install FFmpeg first".to_string(),
+            _ => format!("Unable to run ffmpeg: {}", error),
+        })?;
+    let mut stdout = child.stdout.take().unwrap();
+    // Messages are read on their own thread, so ffmpeg never blocks on a full error pipe.
+    let mut stderr = child.stderr.take().unwrap();
+    let messages = std::thread::spawn(move || {
+        let mut messages = Vec::new();
+        stderr.read_to_end(&mut messages).map(|_| messages)
+    });
+    let mut meter = EnergyMeter::new(DECODE_RATE);
+    let mut pending = Vec::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let read = stdout
+            .read(&mut buffer)
+            .map_err(|error| format!("Unable to read the audio of {}: {}", path, error))?;
+        if read == 0 {
+            break;
+        }
+        feed(&mut meter, &buffer[..read], 1, &mut pending);
+    }
+    let status = child
+        .wait()
+        .map_err(|error| format!("Unable to run ffmpeg: {}", error))?;
+    if !status.success() {
+        let messages = messages.join().ok().and_then(|messages| messages.ok());
+        return Err(format!(
+            "ffmpeg couldn't decode the audio of {}: {}",
+            path,
+            String::from_utf8_lossy(&messages.unwrap_or_default()).trim()
+        ));
+    }
+    Ok(meter.finish())
+}
+
+// Create a function to find the speech intervals of the soundtrack of a video or audio file, as start
+// and end times in miliseconds. WAV files are read directly, other files are decoded with ffmpeg.
+pub fn speech_intervals(path: &str, options: &VadOptions) -> Result<Vec<(i32, i32)>, String> {
+    let energies = if path.to_lowercase().ends_with(".wav") {
+        wav_energies(path)?
+    } else {
+        ffmpeg_energies(path)?
+    };
+    if energies.is_empty() {
+        return Err(format!("{} has no audio", path));
+    }
+    Ok(vad::speech_intervals(&energies, options))
+}
@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use super::video::{run_tool, shell_quote, Burn};
+use super::{write_subtitles, FileArgs, WriteOptions};
+use simple_sub_sync::subtitle_parser::timing::{self, TimeRange};
+use simple_sub_sync::subtitle_parser::{Format, DEFAULT_FRAMERATE};
+
+const HELP: &str = "
+subsync burn --video <file> --input <file> [--to-fps <fps>] [--emit-cmd] [options]
+
+Hardcodes subtitles into a copy of a video with ffmpeg's subtitles filter, for players that can't show
+a subtitle track. The subtitles are converted first like the framerate conversion does, written to a
+temporary file, and drawn over the video by ffmpeg, which has to be installed. With --emit-cmd the
+ffmpeg command is printed instead, to run it later or adjust the encoding; the temporary file is kept
+for it.
+
+    --video = path of the video. Mandatory
+    --input = subtitle file path, or a .zip or .rar archive holding the subtitle. Mandatory
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --output = path of the new video. Optional, defaults to <video>.burned.<ext> next to the video
+    --from-fps = framerate the subtitles were timed for. Optional, defaults to the framerate declared by
+        the file, or 29.97 for files that declare none
+    --to-fps = framerate of the video, the subtitles are converted from --from-fps to it. Optional, they
+        are drawn as they are otherwise
+    --by = offset added to every time after the conversion, as -600ms, 1.5s or 1:02.5. Optional
+    --font = font family the text is drawn with, such as Arial. Optional, overrides the styles of ASS files
+    --font-size = font size, in ASS units (a 288 pixel tall script by default, 18 to 28 suits most videos).
+        Optional
+    --fonts-dir = directory of font files to look for the font in, besides the installed fonts. Optional
+    --emit-cmd = print the ffmpeg command instead of running it. Optional
+    --overwrite = replace the new video if it exists. Optional, an existing file is never replaced otherwise
+    --format = format of the input when the file extension doesn't tell. Optional
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate used to read frame-based formats (MicroDVD, EBU STL). Optional, defaults to the
+        framerate declared by the input file
+    --default-duration = duration of cues in formats without end times (TMPlayer), in miliseconds.
+        Optional, defaults to 3000
+    -h, --help Display this help.
+";
+
+// Create a function that runs the burn subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut burn = Burn::default();
+    let mut from_fps = None;
+    let mut to_fps = None;
+    let mut offset = 0;
+    let mut emit = false;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let framerate = |value: String| {
+        value
+            .parse::<f32>()
+            .ok()
+            .filter(|framerate| *framerate > 0.0 && framerate.is_finite())
+            .ok_or_else(|| format!("Invalid framerate '{}'", value))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--video" {
+            burn.video = value(i)?;
+            i += 1;
+        } else if args[i] == "--from-fps" {
+            from_fps = Some(framerate(value(i)?)?);
+            i += 1;
+        } else if args[i] == "--to-fps" {
+            to_fps = Some(framerate(value(i)?)?);
+            i += 1;
+        } else if args[i] == "--by" {
+            offset = timing::parse_duration(&value(i)?)?;
+            i += 1;
+        } else if args[i] == "--font" {
+            burn.font = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--font-size" {
+            let size = value(i)?;
+            burn.font_size = Some(
+                size.parse::<u32>()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .ok_or_else(|| format!("Invalid font size '{}'", size))?,
+            );
+            i += 1;
+        } else if args[i] == "--fonts-dir" {
+            burn.fonts_dir = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--emit-cmd" {
+            emit = true;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if burn.video.is_empty() || files.input_file.is_empty() {
+        return Err("Both --video and --input are required. Use --help for help.".to_string());
+    }
+    if !files.output_file.is_empty() {
+        burn.output = Some(files.output_file.clone());
+    }
+    if !files.overwrite && !emit && Path::new(&burn.output_file()).exists() {
+        return Err(format!(
+            "{} already exists, use --overwrite to replace it",
+            burn.output_file()
+        ));
+    }
+
+    let (mut subtitles, _) = files.read()?;
+    let conversion = to_fps.map(|to_fps| {
+        let from_fps = from_fps.or(subtitles.framerate).unwrap_or_else(|| {
+            eprintln!(
+                "{} doesn't declare its framerate, converting from {} fps, give another with --from-fps",
+                files.input_file, DEFAULT_FRAMERATE
+            );
+            DEFAULT_FRAMERATE
+        });
+        (from_fps, to_fps)
+    });
+    let factor = conversion.map_or(1.0, |(from_fps, to_fps)| from_fps as f64 / to_fps as f64);
+    subtitles.check_retiming(&TimeRange::default(), factor, 0, offset)?;
+    if let Some((from_fps, to_fps)) = conversion {
+        subtitles.scale(factor, 0);
+        eprintln!("Converted from {} to {} fps", from_fps, to_fps);
+    }
+    let cut = subtitles.shift(offset);
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would start before 0:00, they were cut to start at 0:00 or removed",
+            cut
+        );
+    }
+    // libass draws ASS files with their own styles, everything else is drawn from SRT.
+    let extension = if subtitles.format == Format::Ass {
+        "ass"
+    } else {
+        "srt"
+    };
+    let subtitle_file = std::env::temp_dir()
+        .join(format!(
+            "subsync-burn-{}.{}",
+            uuid::Uuid::new_v4(),
+            extension
+        ))
+        .display()
+        .to_string();
+    write_subtitles(&subtitles, &subtitle_file, None, &WriteOptions::default())?;
+
+    let ffmpeg_args = burn.ffmpeg_args(&subtitle_file, files.overwrite);
+    if emit {
+        let command: Vec<String> = std::iter::once("ffmpeg".to_string())
+            .chain(ffmpeg_args.iter().map(|arg| shell_quote(arg)))
+            .collect();
+        println!("{}", command.join(" "));
+        return Ok(());
+    }
+    let ffmpeg_args: Vec<&str> = ffmpeg_args.iter().map(|arg| arg.as_str()).collect();
+    let result = run_tool("ffmpeg", &ffmpeg_args);
+    let _ = std::fs::remove_file(&subtitle_file);
+    result?;
+    eprintln!("Burned {} into {}", files.input_file, burn.output_file());
+    Ok(())
+}
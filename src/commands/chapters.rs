@@ -0,0 +1,104 @@
+use super::{read_input, FileArgs};
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::{chapters, decode_text, vtt};
+
+const HELP: &str = "
+subsync chapters --input <file> --output <file> --source-chapters <file> --target-chapters <file> [options]
+
+Moves the cues of each chapter by its own offset, computed from the chapters of the release the
+subtitles were made for and of the release they're played with, for releases whose chapters are shifted
+by different amounts (a longer intro, a recap cut from one episode). The offsets are listed on standard
+error.
+
+    --input = input file path, or - for standard input, or a .zip or .rar archive holding the subtitle. Mandatory
+    --output = output file path, or - for standard output. Mandatory
+    --source-chapters = chapters of the release the subtitles were made for: Matroska chapters XML
+        (mkvextract chapters movie.mkv) or OGM chapters text (CHAPTER01=00:00:00.000). Mandatory
+    --target-chapters = chapters of the release to play the subtitles with, in the same formats. Mandatory.
+        Chapters are paired by name when they all have distinct names found in both files, by position
+        otherwise
+    --member = name of the subtitle file to read in an archive. Optional
+    --format = format of the input and output when the file extension doesn't tell. Optional
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output. Optional
+    -h, --help Display this help.
+";
+
+// Create a function to read a chapter file.
+fn read_chapters(path: &str) -> Result<Vec<chapters::Chapter>, String> {
+    let contents = read_input(path)?;
+    chapters::parse_chapters(decode_text(&contents)?)
+        .map_err(|error| format!("Unable to read {}: {}", path, error))
+}
+
+// Create a function that runs the chapters subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut source_file = String::new();
+    let mut target_file = String::new();
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--source-chapters" {
+            source_file = value(i)?;
+            i += 1;
+        } else if args[i] == "--target-chapters" {
+            target_file = value(i)?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+    if source_file.is_empty() || target_file.is_empty() {
+        return Err(
+            "Both --source-chapters and --target-chapters are required. Use --help for help."
+                .to_string(),
+        );
+    }
+    let segments =
+        chapters::chapter_segments(&read_chapters(&source_file)?, &read_chapters(&target_file)?)?;
+    for segment in &segments {
+        eprintln!(
+            "Chapter from {}: {:+} ms",
+            vtt::convert_to_time(segment.range.after.unwrap_or(0)),
+            segment.offset
+        );
+    }
+
+    let (mut subtitles, input_newline) = files.read()?;
+    let cut = subtitles.apply_segments(&segments);
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would start before 0:00, they were cut to start at 0:00 or removed",
+            cut
+        );
+    }
+    files.save(
+        &subtitles,
+        &files.write_options(input_newline),
+        vec![Step::new(Transform::Segments { segments }).losing(cut)],
+    )
+}
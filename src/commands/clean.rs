@@ -0,0 +1,269 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{config, write_subtitles, FileArgs};
+use simple_sub_sync::subtitle_parser::merge::{self, Merge, MergeLimits};
+use simple_sub_sync::subtitle_parser::ocr::{self, OcrRules};
+use simple_sub_sync::subtitle_parser::sdh::{self, Annotation};
+use simple_sub_sync::subtitle_parser::{encoding, validate, vtt};
+
+const HELP: &str = "
+subsync clean --input <file> --output <file> [--remove-sdh] [--fix-ocr] [--merge-duplicates] [--merge-short] [options]
+subsync clean --input <file> [--remove-sdh] [--fix-ocr] [--merge-duplicates] [--merge-short] --dry-run [options]
+
+With --remove-sdh, removes the annotations of subtitles for the deaf and hard of hearing (SDH), to get
+plain subtitles: speaker labels in capitals at the start of a line (JOHN:, MAN #2:), sound descriptions
+in brackets or parentheses ([door slams], (sighs)), and cues with only music notes (♪, ♫ or #). Lines
+left empty or with a lone dialogue dash are removed, as are the cues left without text, and the cues
+are renumbered. Lyrics between music notes are kept, and every removal is printed with the time of its
+cue:
+
+    00:01:12.400 --> 00:01:14.900: Cue 18: sound description '[door slams]'
+
+With --fix-ocr, corrects the mistakes OCR makes reading subtitles ripped from DVD and Blu-ray images,
+and prints every correction with the time of its cue:
+
+    00:01:12.400 --> 00:01:14.900: Cue 18: 'l'm' -> 'I'm'
+
+Pipes read for I or l (|'m, he|lo), an uppercase I read for l inside a lowercase word (wiII), two
+apostrophes read for a double quote, and common English words with l read for I (lt's) or rn read for
+m (tirne) are corrected. More replacements of whole words go in a table, a text file with one
+`wrong -> right` replacement per line (lines starting with # are comments): ocr.txt in the subsync
+configuration directory (~/.config/subsync/ocr.txt) is always read, and replaces the built-in ones.
+OCR mistakes are corrected before the annotations are removed.
+
+With --merge-duplicates, merges the cues with the same text that follow each other closely, as rips
+often split one cue in two, into a cue running from the start of the first to the end of the last:
+
+    00:01:12.400 --> 00:01:16.100: Cue 18: merged cue 19, 'Run!'
+
+Annotations are removed and OCR mistakes corrected first, so cues that only differed by them are
+merged too. Cues are renumbered.
+
+With --merge-short, merges the cues shorter than a second with a neighbour, as machine-generated
+captions flash a few words at a time, when the cue made still reads comfortably: 7 seconds long at
+most, and its text fitting on 2 lines of 42 characters, the lines of the two cues being joined when
+they fit on one. Duplicates are merged first.
+
+Every change is printed with the number of its cue in the input file, and the cues are renumbered
+once cleaned. --dry-run prints what would be changed, removed and merged instead, writing nothing.
+
+    --input = input file path, or - for standard input, or a .zip or .rar archive holding the subtitle. Mandatory
+    --output = output file path, or - for standard output. Mandatory unless --dry-run is given
+    --remove-sdh = remove the hearing-impaired annotations. Optional
+    --fix-ocr = correct the OCR mistakes. Optional
+    --ocr-table = text file of `wrong -> right` replacements, over the built-in ones and ocr.txt. Can be
+        repeated. Optional, implies --fix-ocr
+    --merge-duplicates = merge the cues with the same text that follow each other. Optional
+    --merge-short = merge the cues too short to read with a neighbour. Optional
+    --min-duration = cues shorter than this, in miliseconds, are merged by --merge-short. Optional,
+        defaults to 1000
+    --max-duration = longest cue --merge-short makes, in miliseconds. Optional, defaults to 7000
+    --max-lines, --max-chars-per-line = most lines of the cue --merge-short makes, and most characters on
+        each. Optional, default to 2 and 42
+    --max-gap = most time between two cues merged, in miliseconds. Optional, defaults to 500
+    --dry-run = print what would be changed, removed and merged and write nothing. Optional
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --format = format of the input and output when the file extension doesn't tell. Optional
+    --output-encoding, --bom, --newline = how the output is written, see subsync shift --help. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    -h, --help Display this help.
+";
+
+// Create a function to read a table of OCR replacements.
+fn read_table(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let contents =
+        fs::read(path).map_err(|error| format!("Unable to read {}: {}", path.display(), error))?;
+    let (text, _) = encoding::decode(&contents);
+    ocr::parse_table(&text).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+// Create a function to print the merges made, then how many.
+fn report_merges(merges: &[Merge], report: &impl Fn(String), dry_run: bool) {
+    for merge in merges {
+        report(format!(
+            "{} --> {}: Cue {}: merged cue {}, '{}'",
+            vtt::convert_to_time(merge.start_ms),
+            vtt::convert_to_time(merge.end_ms),
+            merge.cue,
+            merge.merged,
+            merge.lines.join(" / ")
+        ));
+    }
+    report(format!(
+        "{} cues {}",
+        merges.len(),
+        if dry_run { "would be merged" } else { "merged" }
+    ));
+}
+
+// Create a function that runs the clean subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut remove_sdh = false;
+    let mut fix_ocr = false;
+    let mut tables = Vec::new();
+    let mut merge_duplicates = false;
+    let mut merge_short = false;
+    let mut limits = MergeLimits::default();
+    let mut dry_run = false;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--remove-sdh" {
+            remove_sdh = true;
+        } else if args[i] == "--fix-ocr" {
+            fix_ocr = true;
+        } else if args[i] == "--ocr-table" {
+            tables.push(PathBuf::from(value(i)?));
+            fix_ocr = true;
+            i += 1;
+        } else if args[i] == "--merge-duplicates" {
+            merge_duplicates = true;
+        } else if args[i] == "--merge-short" {
+            merge_short = true;
+        } else if ["--max-gap", "--min-duration", "--max-duration"].contains(&args[i].as_str()) {
+            let value = value(i)?;
+            let duration = value
+                .parse::<i32>()
+                .ok()
+                .filter(|duration| *duration >= 0)
+                .ok_or_else(|| format!("Invalid duration '{}'", value))?;
+            match args[i].as_str() {
+                "--max-gap" => limits.max_gap = duration,
+                "--min-duration" => limits.min_duration = duration,
+                _ => limits.max_duration = duration,
+            }
+            i += 1;
+        } else if args[i] == "--max-lines" || args[i] == "--max-chars-per-line" {
+            let value = value(i)?;
+            let count = value
+                .parse::<usize>()
+                .ok()
+                .filter(|count| *count > 0)
+                .ok_or_else(|| format!("Invalid number '{}'", value))?;
+            if args[i] == "--max-lines" {
+                limits.max_lines = count;
+            } else {
+                limits.max_chars_per_line = count;
+            }
+            i += 1;
+        } else if args[i] == "--dry-run" {
+            dry_run = true;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if !remove_sdh && !fix_ocr && !merge_duplicates && !merge_short {
+        return Err(
+            "Nothing to clean, use --remove-sdh, --fix-ocr, --merge-duplicates or \
+            --merge-short. Use --help for help."
+                .to_string(),
+        );
+    }
+    if files.input_file.is_empty() || (files.output_file.is_empty() && !dry_run) {
+        return Err(
+            "Both --input and --output are required, or --input and --dry-run. Use --help for help."
+                .to_string(),
+        );
+    }
+
+    let mut rules = OcrRules::default();
+    if fix_ocr {
+        if let Some(path) = config::user_directory()
+            .map(|directory| directory.join("ocr.txt"))
+            .filter(|path| path.is_file())
+        {
+            rules.add(read_table(&path)?);
+        }
+        for path in &tables {
+            rules.add(read_table(path)?);
+        }
+    }
+
+    let (mut subtitles, input_newline) = files.read()?;
+    // The report goes to standard error when the subtitles go to standard output.
+    let report = |line: String| {
+        if files.output_file == "-" && !dry_run {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    };
+    if fix_ocr {
+        let corrections = ocr::fix_ocr(&mut subtitles, &rules);
+        for correction in &corrections {
+            report(correction.message());
+        }
+        report(format!(
+            "{} OCR mistakes {}",
+            corrections.len(),
+            if dry_run {
+                "would be corrected"
+            } else {
+                "corrected"
+            }
+        ));
+    }
+    if remove_sdh {
+        let removals = sdh::remove_sdh(&mut subtitles);
+        for removal in &removals {
+            let what = match removal.annotation {
+                Annotation::EmptyCue => "nothing left, cue removed".to_string(),
+                Annotation::Music => format!("music only, cue removed '{}'", removal.text),
+                annotation => format!("{} '{}'", annotation.name(), removal.text),
+            };
+            report(format!(
+                "{} --> {}: Cue {}: {}",
+                vtt::convert_to_time(removal.start_ms),
+                vtt::convert_to_time(removal.end_ms),
+                removal.cue,
+                what
+            ));
+        }
+        report(format!(
+            "{} annotations {}",
+            removals.len(),
+            if dry_run {
+                "would be removed"
+            } else {
+                "removed"
+            }
+        ));
+    }
+    if merge_duplicates {
+        let merges = merge::merge_duplicates(&mut subtitles, limits.max_gap);
+        report_merges(&merges, &report, dry_run);
+    }
+    if merge_short {
+        let merges = merge::merge_short(&mut subtitles, &limits);
+        report_merges(&merges, &report, dry_run);
+    }
+    if dry_run {
+        return Ok(());
+    }
+    validate::renumber(&mut subtitles);
+    write_subtitles(
+        &subtitles,
+        &files.output_file,
+        files.format,
+        &files.write_options(input_newline),
+    )
+}
@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use simple_sub_sync::subtitle_parser::detect::{DetectOptions, COMMON_FRAMERATES};
+use simple_sub_sync::subtitle_parser::timecode;
+use simple_sub_sync::subtitle_parser::validate::{Limit, LineLimits, Rules, Severity};
+
+// A framerate in the configuration file: a number (23.976) or a ratio ("24000/1001").
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Framerate {
+    Number(f64),
+    Text(String),
+}
+
+// Create a function to read a candidate framerate, 24000/1001 reading as 23.976 so reports show it so.
+fn parse_framerate(value: &str) -> Result<f64, String> {
+    let framerate = timecode::parse_framerate(value)?;
+    Ok(COMMON_FRAMERATES
+        .into_iter()
+        .find(|common| (common - framerate).abs() < 0.001)
+        .unwrap_or(framerate))
+}
+
+impl Framerate {
+    // Create a function to read a framerate of the configuration file.
+    fn value(&self) -> Result<f64, String> {
+        match self {
+            Framerate::Number(number) => parse_framerate(&number.to_string()),
+            Framerate::Text(text) => parse_framerate(text),
+        }
+    }
+}
+
+// The [detect] table of the configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DetectTable {
+    candidates: Option<Vec<Framerate>>,
+    tolerance: Option<f64>,
+    threshold: Option<f64>,
+    borderline: Option<f64>,
+}
+
+// A rule of the [validate] table or of a rules file: a limit (max_cps = 17), a severity (overlap =
+// "error", or "off"), or both (max_cps = { limit = 17, severity = "error" }).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RuleSetting {
+    Limit(f64),
+    Severity(String),
+    Table {
+        limit: Option<f64>,
+        severity: Option<String>,
+    },
+}
+
+// The [validate] table of the configuration file, also the layout of a rules file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesTable {
+    // A style guide whose rules the others change, see Rules::preset.
+    preset: Option<String>,
+    max_cps: Option<RuleSetting>,
+    max_wpm: Option<RuleSetting>,
+    min_duration: Option<RuleSetting>,
+    max_duration: Option<RuleSetting>,
+    max_lines: Option<RuleSetting>,
+    max_chars_per_line: Option<RuleSetting>,
+    min_gap: Option<RuleSetting>,
+    overlap: Option<RuleSetting>,
+    empty: Option<RuleSetting>,
+    numbering: Option<RuleSetting>,
+    mojibake: Option<RuleSetting>,
+    ads: Option<RuleSetting>,
+    // Line limits by language, as [languages.ja] tables.
+    #[serde(default)]
+    languages: BTreeMap<String, LanguageTable>,
+}
+
+// The line limits of a language in the [validate] table or a rules file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LanguageTable {
+    max_lines: Option<RuleSetting>,
+    max_chars_per_line: Option<RuleSetting>,
+}
+
+// The configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    detect: DetectTable,
+    #[serde(default)]
+    validate: RulesTable,
+}
+
+// Settings read from the configuration file, which command line options override.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    // The framerates the detection considers and how strict it is.
+    pub detect: DetectOptions,
+    // The rules subsync validate checks cues against.
+    pub validate: Rules,
+}
+
+// Create a function to find subsync.toml in the current directory or the nearest parent, the
+// configuration of a project.
+fn project_config() -> Option<PathBuf> {
+    let directory = env::current_dir().ok()?;
+    directory
+        .ancestors()
+        .map(|directory| directory.join("subsync.toml"))
+        .find(|path| path.is_file())
+}
+
+// Create a function to find the configuration file: the SUBSYNC_CONFIG environment variable, subsync.toml
+// in the current directory or a parent, or subsync/config.toml in the user configuration directory
+// ($XDG_CONFIG_HOME, ~/.config or %APPDATA%).
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("SUBSYNC_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    if let Some(path) = project_config() {
+        return Some(path);
+    }
+    Some(user_directory()?.join("config.toml"))
+}
+
+// Create a function to find the directory of the user's subsync files: subsync in the user configuration
+// directory ($XDG_CONFIG_HOME, ~/.config or %APPDATA%).
+pub fn user_directory() -> Option<PathBuf> {
+    let directory = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(directory.join("subsync"))
+}
+
+// Create a function to read the configuration file, with every setting left at its default when there
+// is none. A file named by SUBSYNC_CONFIG has to exist.
+//     [detect]
+//     candidates = [23.976, 24, 25, 48]
+//     tolerance = 5
+//     threshold = 0.6
+//     borderline = 0.15
+//     [validate]
+//     max_cps = 17
+pub fn load() -> Result<Config, String> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    if !path.exists() && env::var_os("SUBSYNC_CONFIG").is_none() {
+        return Ok(Config::default());
+    }
+    let invalid = |error: String| {
+        format!(
+            "Invalid configuration file {}: {}",
+            path.display(),
+            error.trim()
+        )
+    };
+    let text = fs::read_to_string(&path).map_err(|error| invalid(error.to_string()))?;
+    let file: ConfigFile = toml::from_str(&text).map_err(|error| invalid(error.to_string()))?;
+    let mut detect = DetectOptions::default();
+    if let Some(candidates) = file.detect.candidates {
+        let candidates = candidates
+            .iter()
+            .map(Framerate::value)
+            .collect::<Result<Vec<f64>, String>>()
+            .map_err(invalid)?;
+        if candidates.is_empty() {
+            return Err(invalid("candidates can't be empty".to_string()));
+        }
+        detect.candidates = candidates;
+    }
+    if let Some(tolerance) = file.detect.tolerance {
+        detect.tolerance_ms = check_tolerance(tolerance).map_err(invalid)?;
+    }
+    if let Some(threshold) = file.detect.threshold {
+        detect.match_threshold = check_share("threshold", threshold).map_err(invalid)?;
+    }
+    if let Some(borderline) = file.detect.borderline {
+        detect.borderline = check_share("borderline", borderline).map_err(invalid)?;
+    }
+    let validate = read_rules(file.validate, Rules::default()).map_err(invalid)?;
+    Ok(Config { detect, validate })
+}
+
+// Create a function to read a rules file, laid out as the [validate] table of the configuration file,
+// over the given rules.
+//     preset = "netflix"
+//     max_cps = 17
+//     min_duration = { limit = 833, severity = "error" }
+//     overlap = "off"
+pub fn load_rules(path: &str, rules: Rules) -> Result<Rules, String> {
+    let invalid = |error: String| format!("Invalid rules file {}: {}", path, error.trim());
+    let text = fs::read_to_string(path).map_err(|error| invalid(error.to_string()))?;
+    let table: RulesTable = toml::from_str(&text).map_err(|error| invalid(error.to_string()))?;
+    read_rules(table, rules).map_err(invalid)
+}
+
+// Create a function to apply the rules of a [validate] table or rules file over the given rules, or
+// over the rules of its preset.
+fn read_rules(table: RulesTable, rules: Rules) -> Result<Rules, String> {
+    let mut rules = match &table.preset {
+        Some(preset) => Rules::preset(preset)?,
+        None => rules,
+    };
+    let limits = [
+        ("max_cps", table.max_cps, &mut rules.max_cps),
+        ("max_wpm", table.max_wpm, &mut rules.max_wpm),
+        ("min_duration", table.min_duration, &mut rules.min_duration),
+        ("max_duration", table.max_duration, &mut rules.max_duration),
+        ("max_lines", table.max_lines, &mut rules.max_lines),
+        (
+            "max_chars_per_line",
+            table.max_chars_per_line,
+            &mut rules.max_chars_per_line,
+        ),
+        ("min_gap", table.min_gap, &mut rules.min_gap),
+    ];
+    for (name, setting, rule) in limits {
+        apply_limit(name, setting, rule)?;
+    }
+    for (name, setting, rule) in [
+        ("overlap", table.overlap, &mut rules.overlap),
+        ("empty", table.empty, &mut rules.empty),
+        ("numbering", table.numbering, &mut rules.numbering),
+        ("mojibake", table.mojibake, &mut rules.mojibake),
+        ("ads", table.ads, &mut rules.ads),
+    ] {
+        *rule = match setting {
+            None => continue,
+            Some(RuleSetting::Severity(severity))
+            | Some(RuleSetting::Table {
+                limit: None,
+                severity: Some(severity),
+            }) => Severity::from_name(&severity)?,
+            Some(_) => return Err(format!("{} only takes a severity", name)),
+        };
+    }
+    // A language starts from the limits the preset gives it, or from the general ones.
+    for (language, table) in table.languages {
+        let language = language.to_lowercase();
+        let mut limits = rules
+            .languages
+            .get(&language)
+            .copied()
+            .unwrap_or(LineLimits {
+                max_lines: rules.max_lines,
+                max_chars_per_line: rules.max_chars_per_line,
+            });
+        apply_limit("max_lines", table.max_lines, &mut limits.max_lines)?;
+        apply_limit(
+            "max_chars_per_line",
+            table.max_chars_per_line,
+            &mut limits.max_chars_per_line,
+        )?;
+        rules.languages.insert(language, limits);
+    }
+    Ok(rules)
+}
+
+// Create a function to apply the setting of a rule with a limit over the rule.
+fn apply_limit(
+    name: &str,
+    setting: Option<RuleSetting>,
+    rule: &mut Option<Limit>,
+) -> Result<(), String> {
+    let Some(setting) = setting else {
+        return Ok(());
+    };
+    let (limit, severity) = match setting {
+        RuleSetting::Limit(limit) => (Some(limit), None),
+        RuleSetting::Severity(severity) => (None, Some(severity)),
+        RuleSetting::Table { limit, severity } => (limit, severity),
+    };
+    if let Some(limit) = limit.filter(|limit| !limit.is_finite() || *limit < 0.0) {
+        return Err(format!("Invalid limit {} for {}", limit, name));
+    }
+    let severity = match severity {
+        Some(severity) => Severity::from_name(&severity)?,
+        None => Some(rule.map_or(Severity::Warning, |rule| rule.severity)),
+    };
+    *rule = match (severity, limit.or(rule.map(|rule| rule.value))) {
+        (None, _) => None,
+        (Some(severity), Some(value)) => Some(Limit { value, severity }),
+        (Some(_), None) => return Err(format!("{} needs a limit", name)),
+    };
+    Ok(())
+}
+
+// Create a function to check a frame grid tolerance, in miliseconds.
+pub fn check_tolerance(tolerance: f64) -> Result<f64, String> {
+    if tolerance.is_finite() && (0.0..=20.0).contains(&tolerance) {
+        Ok(tolerance)
+    } else {
+        Err(format!(
+            "Invalid tolerance '{}', expected 0 to 20 miliseconds",
+            tolerance
+        ))
+    }
+}
+
+// Create a function to check a setting that goes from 0 to 1.
+pub fn check_share(name: &str, value: f64) -> Result<f64, String> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("Invalid {} '{}', expected 0 to 1", name, value))
+    }
+}
+
+// Create a function to read a comma-separated list of framerates, such as 23.976,24,25,48.
+pub fn parse_candidates(value: &str) -> Result<Vec<f64>, String> {
+    let mut candidates = Vec::new();
+    for framerate in value
+        .split(',')
+        .filter(|framerate| !framerate.trim().is_empty())
+    {
+        let framerate = parse_framerate(framerate)?;
+        if !candidates.contains(&framerate) {
+            candidates.push(framerate);
+        }
+    }
+    if candidates.is_empty() {
+        return Err(format!("No framerate in '{}'", value));
+    }
+    Ok(candidates)
+}
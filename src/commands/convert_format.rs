@@ -0,0 +1,161 @@
+use super::{save_subtitles, FileArgs, WriteOptions};
+use simple_sub_sync::subtitle_parser::colors::{self, ColorPolicy};
+use simple_sub_sync::subtitle_parser::downconvert::{Action, DownconvertPolicy};
+use simple_sub_sync::subtitle_parser::hls;
+
+const HELP: &str = "
+subsync convert-format --input <file> --output <file> [options]
+
+Translates a subtitle file between any two supported formats, picked from the file extensions.
+Timings are not changed.
+
+    --input = input file path, or - for standard input, or an HLS playlist (.m3u8) or directory of WebVTT
+        segments, which are stitched into one file, or a .zip or .rar archive holding the subtitle. Mandatory
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --output = output file path, or - for standard output. An .m3u8 path writes an HLS playlist of WebVTT segments,
+        a .zip path a new archive holding the file, named after the archive. Mandatory
+    --format = format of the input and output when the file extension doesn't tell (for example json
+        when reading or writing -). Optional, the input format is otherwise detected from its contents
+        and the output format defaults to the input format
+    --styles = keep or strip. Optional, defaults to keep.
+        keep leaves inline formatting (<i>, <font>, {\\i1}, ...) in the text, strip removes it along with style names.
+    --positions = keep or strip. Optional, defaults to keep.
+        keep carries positioning over where the output format supports it, strip removes cue settings,
+        regions, margins and ASS positioning overrides ({\\an8}, {\\pos(x,y)}).
+    --keep, --strip, --approximate = comma separated ASS features to keep, strip or approximate when
+        reading an ASS file, for example --keep italics,newlines --strip karaoke --approximate signs. Optional,
+        everything is kept by default. The features are:
+        italics, bold, underline = keep or strip the {\\i1}, {\\b1} and {\\u1} overrides
+        colors = keep, strip or approximate colors with the closest basic color
+        newlines = keep the lines of an event, or strip them to join the event on a single line
+        karaoke = keep or strip karaoke timing overrides ({\\k20})
+        signs = keep, strip (drop) or approximate sign events (positioned with \\pos or \\move, or with a
+            style or name mentioning signs or titles): approximated signs are shown at the top of the screen
+        positions = keep or strip positioning overrides ({\\an8}, {\\pos(x,y)}, {\\move(...)})
+    --colors = keep, normalize, strip or speakers. Optional, defaults to keep. Handles the <font color> tags
+        found on nearly every line of subtitles ripped from teletext: normalize replaces each color by the
+        closest teletext color (yellow, cyan, lime, magenta, red, blue) and drops white, strip removes them,
+        speakers removes them and labels lines with the speaker given by --speaker for their color
+    --speaker = <color>=<name>, for example --speaker yellow=ANNA. Can be repeated. With --colors speakers,
+        lines in that color are prefixed with \"ANNA: \" when the speaker changes
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        windows-1250, iso-8859-2, shift_jis, gbk, big5, ... Optional, defaults to utf-8. Fails if the text
+        has characters the encoding can't represent
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output, which some players need. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...), for files
+        whose encoding is guessed wrong. Optional, detected otherwise. Fails with the offsets of the bytes
+        that aren't valid in that encoding
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    --default-duration = duration of cues in formats without end times (TMPlayer), in miliseconds.
+        Optional, defaults to 3000
+    --segment-duration = length of the WebVTT segments written when the output is an HLS playlist (.m3u8),
+        in seconds. Optional, defaults to 6
+    -h, --help Display this help.
+";
+
+// How a format specific feature is carried over to the output format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Policy {
+    Keep,
+    Strip,
+}
+
+// Create a function to parse a keep/strip policy argument.
+fn parse_policy(name: &str, value: &str) -> Result<Policy, String> {
+    match value {
+        "keep" => Ok(Policy::Keep),
+        "strip" => Ok(Policy::Strip),
+        _ => Err(format!(
+            "Invalid value '{}' for {}, expected keep or strip",
+            value, name
+        )),
+    }
+}
+
+// Create a function that runs the convert-format subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut styles = Policy::Keep;
+    let mut positions = Policy::Keep;
+    let mut segment_duration = hls::DEFAULT_SEGMENT_DURATION;
+    let mut policy = DownconvertPolicy::default();
+    let mut color_policy = ColorPolicy::Keep;
+    let mut speakers = Vec::new();
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--styles" {
+            styles = parse_policy(&args[i], &value(i)?)?;
+            i += 1;
+        } else if args[i] == "--positions" {
+            positions = parse_policy(&args[i], &value(i)?)?;
+            i += 1;
+        } else if args[i] == "--keep" {
+            policy.set(&value(i)?, Action::Keep)?;
+            i += 1;
+        } else if args[i] == "--strip" {
+            policy.set(&value(i)?, Action::Strip)?;
+            i += 1;
+        } else if args[i] == "--approximate" {
+            policy.set(&value(i)?, Action::Approximate)?;
+            i += 1;
+        } else if args[i] == "--colors" {
+            let name = value(i)?;
+            color_policy = ColorPolicy::from_name(&name).ok_or_else(|| {
+                format!(
+                    "Invalid value '{}' for --colors, expected keep, normalize, strip or speakers",
+                    name
+                )
+            })?;
+            i += 1;
+        } else if args[i] == "--speaker" {
+            speakers.push(colors::parse_speaker(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--segment-duration" {
+            let duration = value(i)?;
+            segment_duration = duration
+                .parse::<f32>()
+                .ok()
+                .filter(|duration| *duration > 0.0)
+                .map(|duration| (duration * 1000.0) as i32)
+                .ok_or_else(|| format!("Invalid segment duration '{}'", duration))?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+
+    let (mut subtitles, input_newline) = files.read()?;
+    subtitles.downconvert(&policy);
+    subtitles.apply_color_policy(color_policy, &speakers);
+    if styles == Policy::Strip {
+        subtitles.strip_styles();
+    }
+    if positions == Policy::Strip {
+        subtitles.strip_positions();
+    }
+    let write_options = WriteOptions {
+        segment_duration,
+        ..files.write_options(input_newline)
+    };
+    save_subtitles(&subtitles, &files.output_file, files.format, &write_options)
+}
@@ -0,0 +1,90 @@
+use super::FileArgs;
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::timecode::TimecodeRate;
+
+const HELP: &str = "
+subsync dropframe --input <file> --output <file> --to df|ndf [--rate 29.97|59.94] [options]
+
+Reinterprets the times of a file between drop-frame and non-drop-frame timecodes. Both count frames at
+29.97 (or 59.94) fps, but drop-frame timecodes skip frame labels to keep up with the clock, so the same
+timecode is 3.6 seconds per hour later in non-drop-frame. Use it when subtitles were timed against
+timecodes read the wrong way: the drift grows by about 1 second every 4.6 minutes, unlike a framerate
+mismatch. This isn't a framerate conversion, which scales times instead.
+
+    --input = input file path, or - for standard input, or a .zip or .rar archive holding the subtitle. Mandatory
+    --output = output file path, or - for standard output. Mandatory
+    --to = df to turn times read as non-drop-frame timecodes into drop-frame ones, ndf for the
+        other way around. Mandatory
+    --rate = 29.97 or 59.94, the timecode rate. Optional, defaults to 29.97
+    --member = name of the subtitle file to read in an archive. Optional
+    --format = format of the input and output when the file extension doesn't tell. Optional
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output. Optional
+    -h, --help Display this help.
+";
+
+// Create a function that runs the dropframe subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut to_drop_frame = None;
+    let mut rate = 29.97;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--to" {
+            to_drop_frame = Some(match value(i)?.to_lowercase().as_str() {
+                "df" => true,
+                "ndf" => false,
+                other => {
+                    return Err(format!(
+                        "Invalid value '{}' for --to, expected df or ndf",
+                        other
+                    ))
+                }
+            });
+            i += 1;
+        } else if args[i] == "--rate" {
+            let value = value(i)?;
+            rate = value
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid timecode rate '{}'", value))?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+    let to_drop_frame = to_drop_frame.ok_or("--to is required. Use --help for help.")?;
+    let from = TimecodeRate::new(rate, !to_drop_frame)?;
+    let to = TimecodeRate::new(rate, to_drop_frame)?;
+
+    let (mut subtitles, input_newline) = files.read()?;
+    subtitles.relabel_timecodes(&from, &to);
+    files.save(
+        &subtitles,
+        &files.write_options(input_newline),
+        vec![Step::new(Transform::Relabel { from, to })],
+    )
+}
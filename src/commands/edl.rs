@@ -0,0 +1,85 @@
+use super::{parse_framerate, read_input, FileArgs};
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::{decode_text, edl, DEFAULT_FRAMERATE};
+
+const HELP: &str = "
+subsync edl --input <file> --output <file> --cuts <file> [options]
+
+Remaps subtitles across an edit decision list, so subtitles made for a recording with commercials line
+up with the commercial-free cut: cues in kept parts move back by the length removed before them, and
+cues in removed parts are dropped.
+
+    --input = input file path, or - for standard input, or a .zip or .rar archive holding the subtitle. Mandatory
+    --output = output file path, or - for standard output. Mandatory
+    --cuts = the edit decision list: a CMX3600 EDL listing the kept events (source in and out, record in
+        and out timecodes), a comskip cut list (.txt, FILE PROCESSING COMPLETE ... FRAMES AT 2997 then
+        the start and end frames of each commercial), or an MPlayer EDL as written by comskip (.edl, the
+        start and end seconds and action of each commercial). Mandatory
+    --edl-fps = framerate of the CMX3600 timecodes, 25, 29.97 or 30000/1001. Optional, defaults to 29.97.
+        Drop-frame timecodes (HH:MM:SS;FF) are always 29.97
+    --member = name of the subtitle file to read in an archive. Optional
+    --format = format of the input and output when the file extension doesn't tell. Optional
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output. Optional
+    -h, --help Display this help.
+";
+
+// Create a function that runs the edl subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut cuts_file = String::new();
+    let mut framerate = DEFAULT_FRAMERATE as f64;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--cuts" {
+            cuts_file = value(i)?;
+            i += 1;
+        } else if args[i] == "--edl-fps" {
+            framerate = parse_framerate(&value(i)?)?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+    if cuts_file.is_empty() {
+        return Err("--cuts is required. Use --help for help.".to_string());
+    }
+    let contents = read_input(&cuts_file)?;
+    let segments = edl::parse_cut_list(decode_text(&contents)?, framerate)
+        .map_err(|error| format!("Unable to read {}: {}", cuts_file, error))?;
+
+    let (mut subtitles, input_newline) = files.read()?;
+    let removed = subtitles.keep_segments(&segments);
+    if removed > 0 {
+        eprintln!("Removed {} cues from the cut parts", removed);
+    }
+    files.save(
+        &subtitles,
+        &files.write_options(input_newline),
+        vec![Step::new(Transform::Segments { segments }).losing(removed)],
+    )
+}
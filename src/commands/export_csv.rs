@@ -0,0 +1,111 @@
+use super::{parse_format, parse_fps, read_subtitles, write_file};
+use simple_sub_sync::subtitle_parser::{encoding, ParseOptions, SubtitleFile};
+
+const HELP: &str = "
+subsync export-csv --input <file> [--output <file.csv>] [options]
+
+Writes the timing of every cue as CSV, one row per cue, so it can be reviewed in a spreadsheet.
+The columns are index, start_ms, end_ms, duration, cps (characters per second) and text_length
+(characters without formatting tags and line breaks).
+
+    --input = input file path, or - for standard input, or a .zip or .rar archive holding the subtitle. Mandatory
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --output = CSV file path. Optional, defaults to standard output
+    --overwrite = replace the CSV file if it exists. Optional, an existing file is never replaced otherwise
+    --format = format of the input when the file extension doesn't tell. Optional
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...), for files
+        whose encoding is guessed wrong. Optional, detected otherwise. Fails with the offsets of the bytes
+        that aren't valid in that encoding
+    --fps = framerate used to read frame-based formats (MicroDVD, EBU STL). Optional, defaults to the
+        framerate declared by the input file
+    --default-duration = duration of cues in formats without end times (TMPlayer), in miliseconds.
+        Optional, defaults to 3000
+    -h, --help Display this help.
+";
+
+// Create a function to build the CSV rows for every cue.
+pub fn to_csv(subtitles: &SubtitleFile) -> String {
+    let mut output = String::from("index,start_ms,end_ms,duration,cps,text_length\n");
+    for cue in &subtitles.cues {
+        output.push_str(&format!(
+            "{},{},{},{},{:.2},{}\n",
+            cue.index,
+            cue.start_ms,
+            cue.end_ms,
+            cue.end_ms - cue.start_ms,
+            cue.chars_per_second(),
+            cue.text_length()
+        ));
+    }
+    output
+}
+
+// Create a function that runs the export-csv subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input_file = String::new();
+    let mut output_file = String::from("-");
+    let mut options = ParseOptions::default();
+    let mut format = None;
+    let mut member = None;
+    let mut input_encoding = None;
+    let mut overwrite = false;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--input" {
+            input_file = value(i)?;
+            i += 1;
+        } else if args[i] == "--output" {
+            output_file = value(i)?;
+            i += 1;
+        } else if args[i] == "--member" {
+            member = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--format" {
+            format = Some(parse_format(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--input-encoding" {
+            input_encoding = Some(encoding::from_label(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--overwrite" {
+            overwrite = true;
+        } else if args[i] == "--fps" {
+            options.framerate = Some(parse_fps(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--default-duration" {
+            let duration = value(i)?;
+            options.default_duration = duration
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid duration '{}'", duration))?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if input_file.is_empty() {
+        return Err("--input is required. Use --help for help.".to_string());
+    }
+
+    let (subtitles, _) = read_subtitles(
+        &input_file,
+        member.as_deref(),
+        format,
+        &options,
+        input_encoding,
+    )?;
+    let output = to_csv(&subtitles);
+    if output_file == "-" {
+        print!("{}", output);
+        return Ok(());
+    }
+    write_file(&output_file, output.as_bytes(), overwrite)
+}
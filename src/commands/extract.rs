@@ -0,0 +1,186 @@
+use super::video::{run_tool, subtitle_tracks, Track};
+use super::FileArgs;
+use simple_sub_sync::subtitle_parser::encoding::Newline;
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::timing::{self, TimeRange};
+use simple_sub_sync::subtitle_parser::{matroska, Format, Registry};
+
+const HELP: &str = "
+subsync extract --video <file> [--track <index>] [--output <file>] [--by <offset>] [--factor <factor>] [options]
+
+Dumps an embedded text subtitle track of a video file and writes it out, retimed right away if --by or
+--factor is given. Without --track, the subtitle tracks of the video are listed. Matroska files (.mkv,
+.mks, .webm) are read directly, with their SRT, ASS, SSA and WebVTT tracks; other containers (.mp4, ...)
+are read with ffmpeg, which has to be installed.
+
+    --video = path of the video file. Mandatory
+    --track = index of the subtitle track, as listed without --track (the stream index ffmpeg and mkvmerge
+        number tracks with). Optional, the tracks are listed when it is missing
+    --output = output file path, or - for standard output. Its extension picks the format, so .ass keeps
+        the track as ASS. Mandatory with --track
+    --factor = number every cue time is multiplied by, like subsync scale. Optional
+    --by = offset added to every cue after --factor, like subsync shift. Optional
+    --format = format of the output when the file extension doesn't tell. Optional, defaults to ASS for
+        ASS tracks and SRT for the others
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to lf
+    -h, --help Display this help.
+";
+
+// Create a function that runs the extract subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut video = None;
+    let mut track = None;
+    let mut factor = None;
+    let mut offset = None;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--video" {
+            video = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--track" {
+            let value = value(i)?;
+            track = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid track '{}'", value))?,
+            );
+            i += 1;
+        } else if args[i] == "--factor" {
+            let value = value(i)?;
+            factor = Some(
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|factor| *factor > 0.0 && factor.is_finite())
+                    .ok_or_else(|| format!("Invalid factor '{}'", value))?,
+            );
+            i += 1;
+        } else if args[i] == "--by" {
+            offset = Some(timing::parse_duration(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    let video = video.ok_or_else(|| "No --video provided. Use --help for help.".to_string())?;
+    // Matroska files are read without ffmpeg.
+    let native = matroska::is_matroska(&video);
+    let tracks = if native {
+        matroska::subtitle_tracks(&video)?
+            .into_iter()
+            .map(|track| Track {
+                index: track.index,
+                text: track.is_text(),
+                codec: track.codec,
+                language: track.language,
+                title: track.name,
+            })
+            .collect()
+    } else {
+        subtitle_tracks(&video)?
+    };
+    let track = match track {
+        Some(track) => tracks
+            .iter()
+            .find(|candidate| candidate.index == track)
+            .ok_or_else(|| format!("{} has no subtitle track {}", video, track))?,
+        None => {
+            if tracks.is_empty() {
+                println!("{} has no subtitle tracks", video);
+            }
+            for track in &tracks {
+                println!(
+                    "{}: {}{}{}{}",
+                    track.index,
+                    track.codec,
+                    track
+                        .language
+                        .as_ref()
+                        .map_or(String::new(), |language| format!(", {}", language)),
+                    track
+                        .title
+                        .as_ref()
+                        .map_or(String::new(), |title| format!(", \"{}\"", title)),
+                    if track.text { "" } else { " (not text)" }
+                );
+            }
+            return Ok(());
+        }
+    };
+    if !track.text {
+        return Err(format!(
+            "Track {} holds {} subtitles, which can't be extracted as text",
+            track.index, track.codec
+        ));
+    }
+    if files.output_file.is_empty() {
+        return Err("No --output provided. Use --help for help.".to_string());
+    }
+
+    let mut subtitles = if native {
+        matroska::read_track(&video, track.index, &files.options)?
+    } else {
+        // ASS tracks are dumped as ASS to keep their styling, others as SRT.
+        let (muxer, format) = match track.codec.as_str() {
+            "ass" | "ssa" => ("ass", Format::Ass),
+            _ => ("srt", Format::Srt),
+        };
+        let map = format!("0:{}", track.index);
+        let contents = run_tool(
+            "ffmpeg",
+            &[
+                "-v", "error", "-i", &video, "-map", &map, "-f", muxer, "pipe:1",
+            ],
+        )?;
+        Registry::default()
+            .parse(&contents, format, &files.options)
+            .map_err(|error| format!("Unable to parse track {}: {}", track.index, error))?
+    };
+    let factor = factor.unwrap_or(1.0);
+    let offset = offset.unwrap_or(0);
+    subtitles.check_retiming(&TimeRange::default(), factor, 0, offset)?;
+    subtitles.scale(factor, 0);
+    let cut = subtitles.shift(offset);
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would start before 0:00, they were cut to start at 0:00 or removed",
+            cut
+        );
+    }
+    let mut steps = Vec::new();
+    if factor != 1.0 || offset != 0 {
+        steps.push(
+            Step::new(Transform::Linear {
+                factor,
+                origin: 0,
+                offset,
+                keep_durations: false,
+            })
+            .losing(cut),
+        );
+    }
+    files.input_file = video;
+    files.save(&subtitles, &files.write_options(Newline::Lf), steps)
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use super::video::probe_video;
+use super::{read_subtitles, save_subtitles, FileArgs};
+use simple_sub_sync::subtitle_parser::{timing, vtt, SubtitleFile};
+
+const HELP: &str = "
+subsync join <part>... --output <file> [--offset2 <time>]... [--video <file>]... [options]
+
+Joins the parts of a subtitle file, such as those of a release on two CDs, into one file: the cues of
+every part are moved later by the time the part starts at, put in order and numbered from 1, and the
+result is checked for timing problems, printed as warnings, before it is written. The first part starts
+at zero; the others start at the time given with --offset2, --offset3..., or at the end of the videos
+of the parts before them, their durations read with ffprobe from the --video files given in order:
+
+    subsync join cd1.srt cd2.srt --offset2 51:48 --output movie.srt
+    subsync join cd1.srt cd2.srt --video cd1.avi --output movie.srt
+
+The output has the format, header and styles of the first part.
+
+    <part> = input file paths in order, or - for standard input, or .zip archives holding the subtitle, also
+        given as --input <file>. Mandatory, at least two
+    --output = output file path, or - for standard output. Mandatory
+    --offset2, --offset3... = time the second, third... part starts at in the joined file, as 51:48,
+        3108s or 3108000ms. Optional, --offset1 moves the first part
+    --video = video file of a part, in order, whose duration is where the next part starts when it has
+        no offset. Can be repeated. Optional, needs ffprobe
+    --format = format of the parts and output when the file extension doesn't tell. Optional
+    --output-encoding, --bom, --newline = how the output is written, see subsync shift --help. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --input-encoding = text encoding of the parts (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    -h, --help Display this help.
+";
+
+// Create a function that runs the join subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut input_files = Vec::new();
+    let mut offsets = BTreeMap::new();
+    let mut videos = Vec::new();
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        // Every --input is one more input file, in order with the others.
+        if args[i] == "--input" {
+            input_files.push(value(i)?);
+            i += 2;
+            continue;
+        }
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if let Some(part) = args[i].strip_prefix("--offset") {
+            let part = part
+                .parse::<usize>()
+                .ok()
+                .filter(|part| *part > 0)
+                .ok_or_else(|| format!("Unknown option {}. Use --help for help.", args[i]))?;
+            offsets.insert(part, timing::parse_duration(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--video" {
+            videos.push(value(i)?);
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else if args[i] == "-" || !args[i].starts_with('-') {
+            input_files.push(args[i].clone());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if input_files.len() < 2 || files.output_file.is_empty() {
+        return Err(
+            "At least two parts and the --output are required. Use --help for help.".to_string(),
+        );
+    }
+    if let Some(part) = offsets.keys().find(|part| **part > input_files.len()) {
+        return Err(format!(
+            "--offset{} given for {} parts",
+            part,
+            input_files.len()
+        ));
+    }
+
+    let mut parts: Vec<SubtitleFile> = Vec::new();
+    let mut newline = None;
+    for input_file in &input_files {
+        let (part, input_newline) = read_subtitles(
+            input_file,
+            files.member.as_deref(),
+            files.format,
+            &files.options,
+            files.input_encoding,
+        )
+        .map_err(|error| format!("{}: {}", input_file, error))?;
+        newline.get_or_insert(input_newline);
+        parts.push(part);
+    }
+    // A part without an offset starts where the video of the part before ends.
+    let mut starts = Vec::new();
+    for part in 1..=input_files.len() {
+        let start = match (offsets.get(&part), part) {
+            (Some(offset), _) => *offset,
+            (None, 1) => 0,
+            (None, _) => {
+                let video = videos.get(part - 2).ok_or_else(|| {
+                    format!(
+                        "No --offset{} or --video for the part before it, to tell where part {} starts",
+                        part, part
+                    )
+                })?;
+                let duration = probe_video(video)?
+                    .duration_ms
+                    .ok_or_else(|| format!("ffprobe found no duration in {}", video))?;
+                starts[part - 2] + duration
+            }
+        };
+        eprintln!(
+            "{}: starts at {}",
+            input_files[part - 1],
+            vtt::convert_to_time(start)
+        );
+        starts.push(start);
+    }
+    let joined = SubtitleFile::join(parts, &starts).ok_or("No part to join")?;
+    save_subtitles(
+        &joined,
+        &files.output_file,
+        files.format,
+        &files.write_options(newline.unwrap_or_default()),
+    )
+}
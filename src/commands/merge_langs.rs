@@ -0,0 +1,145 @@
+use super::{read_subtitles, write_subtitles, FileArgs};
+use simple_sub_sync::subtitle_parser::ass::{self, Style};
+use simple_sub_sync::subtitle_parser::merge;
+use simple_sub_sync::subtitle_parser::registry::Registry;
+use simple_sub_sync::subtitle_parser::{Format, SubtitleFile, TagDialect};
+
+// How each language looks in .ass output unless given --primary-style and --secondary-style.
+const PRIMARY_STYLE: &str = "Arial,20,white";
+const SECONDARY_STYLE: &str = "Arial,16,#ffff00";
+
+const HELP: &str = "
+subsync merge-langs <primary> <secondary> --output <file> [--primary-style <style>] [--secondary-style <style>] [options]
+
+Interleaves subtitles in two languages, such as English and Chinese, into one file showing both: the
+text of every secondary cue is stacked under the text of the primary cues it overlaps for at least
+half of the shorter of the two, keeping the times of the primary cues, and a secondary cue shown with
+no primary cue gets a cue of its own. The two files must be timed for the same video, see subsync
+align otherwise.
+
+Written as .ass (with an .ass --output, or --format ass), each language gets a style of its own, by
+default white Arial 20 for the primary and yellow Arial 16 for the secondary; a style is given as
+font,size,color, followed by bold or italic:
+
+    subsync merge-langs movie.en.srt movie.zh.srt --output movie.en-zh.ass --secondary-style \"Noto Sans CJK SC,18,#ffff00\"
+
+In other formats the text of both languages is stacked as it is.
+
+    <primary> = file path of the primary language, on top, or - for standard input. Mandatory
+    <secondary> = file path of the secondary language, below it. Mandatory. Either file can also be given as
+        --input <file>, in the same order
+    --output = output file path, or - for standard output. Mandatory
+    --primary-style, --secondary-style = font,size,color[,bold][,italic] of each language in .ass output,
+        as Arial,20,white or \"Noto Sans CJK SC,18,#ffff00,italic\", fields left empty keeping their
+        default. Optional, default to Arial,20,white and Arial,16,#ffff00
+    --format = format of the output when its extension doesn't tell, and of the inputs. Optional, defaults
+        to the format of the primary file
+    --output-encoding, --bom, --newline = how the output is written, see subsync shift --help. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --input-encoding = text encoding of the inputs (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    -h, --help Display this help.
+";
+
+// Create a function that runs the merge-langs subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut input_files = Vec::new();
+    let mut primary_style = PRIMARY_STYLE.to_string();
+    let mut secondary_style = SECONDARY_STYLE.to_string();
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        // Every --input is one more input file, in order with the others.
+        if args[i] == "--input" {
+            input_files.push(value(i)?);
+            i += 2;
+            continue;
+        }
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--primary-style" {
+            primary_style = value(i)?;
+            i += 1;
+        } else if args[i] == "--secondary-style" {
+            secondary_style = value(i)?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else if args[i] == "-" || !args[i].starts_with('-') {
+            input_files.push(args[i].clone());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if input_files.len() != 2 || files.output_file.is_empty() {
+        return Err(
+            "Two input files, the primary and secondary language, and the --output are required. \
+             Use --help for help."
+                .to_string(),
+        );
+    }
+    let styles = [
+        Style::parse("Primary", &primary_style)?,
+        Style::parse("Secondary", &secondary_style)?,
+    ];
+
+    let read = |input_file: &String| {
+        read_subtitles(
+            input_file,
+            files.member.as_deref(),
+            files.format,
+            &files.options,
+            files.input_encoding,
+        )
+        .map_err(|error| format!("{}: {}", input_file, error))
+    };
+    let (mut primary, input_newline) = read(&input_files[0])?;
+    let (mut secondary, _) = read(&input_files[1])?;
+
+    // The text of both languages is brought to the same tag dialect before it is stacked: that of
+    // .ass output, where each language gets its style, or else that of the primary file.
+    let registry = Registry::default();
+    let format = registry
+        .from_extension(&files.output_file)
+        .or(files.format)
+        .unwrap_or(primary.format);
+    let dialect =
+        |file: &SubtitleFile| registry.get(file.format).map(|format| format.tag_dialect());
+    let merged = if format == Format::Ass {
+        for file in [&mut primary, &mut secondary] {
+            let from = dialect(file)?;
+            file.translate_tags(from, TagDialect::Ass);
+        }
+        let mut merged =
+            merge::merge_languages(&primary, &secondary, Some(("Primary", "Secondary")));
+        if primary.format != Format::Ass {
+            merged.trailer.clear();
+            for cue in merged.cues.iter_mut() {
+                cue.comments.clear();
+            }
+        }
+        merged.format = Format::Ass;
+        merged.header = vec![ass::header(&styles)];
+        merged
+    } else {
+        secondary.translate_tags(dialect(&secondary)?, dialect(&primary)?);
+        merge::merge_languages(&primary, &secondary, None)
+    };
+    write_subtitles(
+        &merged,
+        &files.output_file,
+        files.format,
+        &files.write_options(input_newline),
+    )
+}
@@ -0,0 +1,414 @@
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+use encoding_rs::{Encoding, UTF_8};
+use simple_sub_sync::subtitle_parser::encoding::{Newline, OutputEncoding};
+use simple_sub_sync::subtitle_parser::provenance::{self, Provenance, Step};
+pub use simple_sub_sync::subtitle_parser::timecode::parse_framerate;
+use simple_sub_sync::subtitle_parser::{
+    decode_text, encoding, hls, Format, ParseOptions, Registry, SubtitleFile,
+};
+
+pub mod align;
+pub mod analyze;
+pub mod archive;
+pub mod audio;
+pub mod burn;
+pub mod chapters;
+pub mod clean;
+pub mod config;
+pub mod convert_format;
+pub mod dropframe;
+pub mod edl;
+pub mod export_csv;
+pub mod extract;
+pub mod join;
+pub mod merge_langs;
+pub mod probe;
+pub mod renumber;
+pub mod retime;
+pub mod revert;
+pub mod scale;
+pub mod shift;
+pub mod speech;
+pub mod spellcheck;
+pub mod split;
+pub mod sync;
+pub mod transcribe;
+pub mod validate;
+pub mod video;
+pub mod whisper;
+
+// Create a function that reads the raw contents of a file, or standard input when the path is "-".
+pub fn read_input(input_file: &str) -> Result<Vec<u8>, String> {
+    let mut contents = Vec::new();
+    if input_file == "-" {
+        std::io::stdin()
+            .read_to_end(&mut contents)
+            .map_err(|error| format!("Unable to read standard input: {}", error))?;
+    } else {
+        let mut file = File::open(input_file)
+            .map_err(|error| format!("Unable to open {}: {}", input_file, error))?;
+        file.read_to_end(&mut contents)
+            .map_err(|error| format!("Unable to read {}: {}", input_file, error))?;
+    }
+    Ok(contents)
+}
+
+// Create a function to pick the format of an input file and decode it to UTF-8. The format comes from
+// the extension, then the given format, then the contents. Text formats are decoded from the given
+// encoding, or the one they are detected to use, which is returned; binary formats are left as they are.
+pub fn decode_input(
+    registry: &Registry,
+    input_file: &str,
+    format: Option<Format>,
+    contents: Vec<u8>,
+    input_encoding: Option<&'static Encoding>,
+) -> Result<(Format, Vec<u8>, Option<&'static Encoding>), String> {
+    let known = registry.from_extension(input_file).or(format);
+    let detected = known.unwrap_or_else(|| registry.detect(&contents));
+    if input_encoding.is_none()
+        && registry
+            .get(detected)
+            .is_ok_and(|format| format.is_binary())
+    {
+        return Ok((detected, contents, None));
+    }
+    let (text, source) = match input_encoding {
+        Some(input_encoding) => (
+            encoding::decode_as(&contents, input_encoding)
+                .map_err(|error| format!("Unable to decode {}: {}", input_file, error))?,
+            input_encoding,
+        ),
+        None => {
+            let (text, source) = encoding::decode(&contents);
+            (text.into_owned(), source)
+        }
+    };
+    let decoded = text.into_bytes();
+    // Files in UTF-16 or a legacy encoding can only be recognized once decoded.
+    let format = known.unwrap_or_else(|| registry.detect(&decoded));
+    Ok((format, decoded, Some(source)))
+}
+
+// Create a function that reads and parses a subtitle file, or standard input when the path is "-".
+// The format comes from the extension, then the given format, then the contents. Text that isn't
+// UTF-8 is decoded, and its encoding reported. The line endings of the input are returned along with
+// the file, so they can be kept on output.
+// HLS playlists (.m3u8) and directories of WebVTT segments are stitched into a single file.
+// Subtitles are read out of .zip archives, from the given member or the only subtitle file they hold.
+// The input encoding overrides the detected one, for files it guesses wrong.
+pub fn read_subtitles(
+    input_file: &str,
+    member: Option<&str>,
+    format: Option<Format>,
+    options: &ParseOptions,
+    input_encoding: Option<&'static Encoding>,
+) -> Result<(SubtitleFile, Newline), String> {
+    if hls::is_playlist(input_file) || Path::new(input_file).is_dir() {
+        let mut subtitles = hls::read_playlist(input_file)?;
+        subtitles.framerate = options.framerate;
+        return Ok((subtitles, Newline::Lf));
+    }
+    let (name, contents) = if archive::is_archive(input_file) {
+        archive::read_member(input_file, member)?
+    } else {
+        (input_file.to_string(), read_input(input_file)?)
+    };
+    let registry = Registry::default();
+    let (format, contents, source) =
+        decode_input(&registry, &name, format, contents, input_encoding)?;
+    if let Some(source) = source.filter(|source| *source != UTF_8 && input_encoding.is_none()) {
+        eprintln!("Decoded {} from {}", input_file, source.name());
+    }
+    let newline = match source {
+        Some(_) => Newline::detect(decode_text(&contents)?),
+        None => Newline::Lf,
+    };
+    let mut subtitles = registry
+        .parse(&contents, format, options)
+        .map_err(|error| format!("Unable to parse {}: {}", name, error))?;
+    // A framerate given to read the input is also the one frame-based output is written at.
+    if options.framerate.is_some() {
+        subtitles.framerate = options.framerate;
+    }
+    Ok((subtitles, newline))
+}
+
+// How output files are written.
+#[derive(Debug, Clone, Copy)]
+pub struct WriteOptions {
+    // Text encoding of text formats.
+    pub encoding: OutputEncoding,
+    // Line endings of text formats.
+    pub newline: Newline,
+    // Replace an existing output file instead of failing.
+    pub overwrite: bool,
+    // Length of the WebVTT segments written for an HLS playlist, in miliseconds.
+    pub segment_duration: i32,
+    // Framerate whose frame boundaries retimed cues are snapped to.
+    pub snap_framerate: Option<f64>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            encoding: OutputEncoding::default(),
+            newline: Newline::default(),
+            overwrite: false,
+            segment_duration: hls::DEFAULT_SEGMENT_DURATION,
+            snap_framerate: None,
+        }
+    }
+}
+
+// Create a function to write a file atomically: the contents go to a temporary file next to it, which
+// then replaces the file, so a failure halfway never leaves a truncated file (or a destroyed input
+// when writing over it). An existing file is only replaced when overwrite is set.
+pub fn write_file(output_file: &str, contents: &[u8], overwrite: bool) -> Result<(), String> {
+    let path = Path::new(output_file);
+    if !overwrite && path.exists() {
+        return Err(format!(
+            "{} already exists, use --overwrite to replace it",
+            output_file
+        ));
+    }
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("output");
+    let temporary = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    let result = File::create(&temporary)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temporary, path));
+    result.map_err(|error| {
+        let _ = fs::remove_file(&temporary);
+        format!("Unable to write {}: {}", output_file, error)
+    })
+}
+
+// Create a function that writes a subtitle file, or standard output when the path is "-".
+// The format comes from the extension, then the given format, then the input format.
+// An .m3u8 output path writes an HLS playlist of WebVTT segments, always in UTF-8 as HLS requires.
+// Text formats are written in the given encoding and with the given line endings, binary formats as they are.
+// A .zip output path writes a new archive holding the file, named after the archive.
+pub fn write_subtitles(
+    subtitles: &SubtitleFile,
+    output_file: &str,
+    format: Option<Format>,
+    options: &WriteOptions,
+) -> Result<(), String> {
+    if hls::is_playlist(output_file) {
+        if !options.overwrite && Path::new(output_file).exists() {
+            return Err(format!(
+                "{} already exists, use --overwrite to replace it",
+                output_file
+            ));
+        }
+        return hls::write_playlist(subtitles, output_file, options.segment_duration);
+    }
+    let registry = Registry::default();
+    let format = registry
+        .from_extension(output_file)
+        .or(format)
+        .unwrap_or(subtitles.format);
+    let mut output = registry.serialize(subtitles, format)?;
+    if !registry.get(format)?.is_binary() {
+        output = options
+            .encoding
+            .encode(&options.newline.apply(decode_text(&output)?))?;
+    }
+    if archive::is_archive(output_file) {
+        let stem = Path::new(output_file)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("subtitles");
+        let extension = registry.get(format)?.extensions()[0];
+        output = archive::archive_member(output_file, &format!("{}.{}", stem, extension), &output)?;
+    }
+    if output_file == "-" {
+        return std::io::stdout()
+            .write_all(&output)
+            .map_err(|error| format!("Unable to write standard output: {}", error));
+    }
+    write_file(output_file, &output, options.overwrite)
+}
+
+// Create a function that snaps retimed subtitles to frame boundaries when asked to, checks them for
+// timing problems, printing them as warnings, and writes them like write_subtitles. Every command that
+// writes retimed subtitles goes through it.
+pub fn save_subtitles(
+    subtitles: &SubtitleFile,
+    output_file: &str,
+    format: Option<Format>,
+    options: &WriteOptions,
+) -> Result<(), String> {
+    let mut subtitles = Cow::Borrowed(subtitles);
+    if let Some(framerate) = options.snap_framerate {
+        subtitles.to_mut().snap_to_frames(framerate);
+    }
+    for issue in subtitles.validate() {
+        eprintln!("Warning: {}", issue.message());
+    }
+    write_subtitles(&subtitles, output_file, format, options)
+}
+
+// Create a function to write the provenance of an output file next to it: the retiming steps applied,
+// after those recorded for the input file when it was written by subsync too, so `subsync revert` can
+// go back to the original timing. Nothing is written for standard output, or without retiming steps.
+pub fn write_provenance(
+    input_file: &str,
+    output_file: &str,
+    steps: Vec<Step>,
+) -> Result<(), String> {
+    if output_file == "-" || hls::is_playlist(output_file) || steps.is_empty() {
+        return Ok(());
+    }
+    let input_sidecar = provenance::sidecar_path(input_file);
+    let provenance = match fs::read_to_string(&input_sidecar) {
+        Ok(contents) if input_file != "-" => Provenance::parse(&contents)
+            .map_err(|error| format!("Unable to read {}: {}", input_sidecar, error))?
+            .then(steps),
+        _ => Provenance::new(input_file, steps),
+    };
+    write_file(
+        &provenance::sidecar_path(output_file),
+        provenance.serialize().as_bytes(),
+        true,
+    )
+}
+
+// The options of the subcommands that read a subtitle file and write it back, as given on the command line.
+#[derive(Debug, Default)]
+pub struct FileArgs {
+    pub input_file: String,
+    pub output_file: String,
+    pub member: Option<String>,
+    pub format: Option<Format>,
+    pub input_encoding: Option<&'static Encoding>,
+    pub options: ParseOptions,
+    pub output_encoding: OutputEncoding,
+    pub bom: bool,
+    // Line endings of the output, defaults to those of the input.
+    pub newline: Option<Newline>,
+    pub overwrite: bool,
+    pub snap_framerate: Option<f64>,
+    // Don't write the provenance of the output next to it.
+    pub no_provenance: bool,
+}
+
+// Create a function to parse the --fps of frame-based formats, as parse_framerate does, refusing the
+// framerates too small or too large to hold as an f32.
+pub fn parse_fps(value: &str) -> Result<f32, String> {
+    Some(parse_framerate(value)? as f32)
+        .filter(|framerate| framerate.is_normal())
+        .ok_or_else(|| format!("Invalid framerate '{}'", value))
+}
+
+impl FileArgs {
+    // Create a function to read the option at args[i] if it is one of the input and output options.
+    // Returns how many arguments it used: 2 for an option and its value, 1 for a switch, and 0 when it
+    // isn't one of them.
+    pub fn parse(&mut self, args: &[String], i: usize) -> Result<usize, String> {
+        let value = || {
+            args.get(i + 1)
+                .cloned()
+                .ok_or_else(|| format!("Missing value for {}", args[i]))
+        };
+        let switch = |flag: &mut bool| {
+            *flag = true;
+            1
+        };
+        Ok(match args[i].as_str() {
+            "--bom" => switch(&mut self.bom),
+            "--overwrite" => switch(&mut self.overwrite),
+            "--no-provenance" => switch(&mut self.no_provenance),
+            option => {
+                match option {
+                    "--input" => self.input_file = value()?,
+                    "--output" => self.output_file = value()?,
+                    "--member" => self.member = Some(value()?),
+                    "--format" => self.format = Some(parse_format(&value()?)?),
+                    "--input-encoding" => {
+                        self.input_encoding = Some(encoding::from_label(&value()?)?)
+                    }
+                    "--output-encoding" => {
+                        self.output_encoding = OutputEncoding::from_name(&value()?)?
+                    }
+                    "--newline" => self.newline = Some(Newline::from_name(&value()?)?),
+                    "--snap-to-frames" => self.snap_framerate = Some(parse_framerate(&value()?)?),
+                    "--fps" => self.options.framerate = Some(parse_fps(&value()?)?),
+                    "--default-duration" => {
+                        let duration = value()?;
+                        self.options.default_duration = duration
+                            .parse::<i32>()
+                            .map_err(|_| format!("Invalid duration '{}'", duration))?;
+                    }
+                    _ => return Ok(0),
+                }
+                2
+            }
+        })
+    }
+
+    // Create a function to check that both the input and output files were given.
+    pub fn check(&self) -> Result<(), String> {
+        if self.input_file.is_empty() || self.output_file.is_empty() {
+            return Err("Both --input and --output are required. Use --help for help.".to_string());
+        }
+        Ok(())
+    }
+
+    // Create a function to read the input file, see read_subtitles.
+    pub fn read(&self) -> Result<(SubtitleFile, Newline), String> {
+        read_subtitles(
+            &self.input_file,
+            self.member.as_deref(),
+            self.format,
+            &self.options,
+            self.input_encoding,
+        )
+    }
+
+    // Create a function to write retimed subtitles to the output file with save_subtitles, along with
+    // the provenance of the retiming steps applied to them.
+    pub fn save(
+        &self,
+        subtitles: &SubtitleFile,
+        options: &WriteOptions,
+        steps: Vec<Step>,
+    ) -> Result<(), String> {
+        save_subtitles(subtitles, &self.output_file, self.format, options)?;
+        if self.no_provenance {
+            return Ok(());
+        }
+        write_provenance(&self.input_file, &self.output_file, steps)
+    }
+
+    // Create a function to build the write options, keeping the line endings of the input by default.
+    pub fn write_options(&self, input_newline: Newline) -> WriteOptions {
+        WriteOptions {
+            encoding: if self.bom {
+                self.output_encoding.with_bom()
+            } else {
+                self.output_encoding
+            },
+            newline: self.newline.unwrap_or(input_newline),
+            overwrite: self.overwrite,
+            snap_framerate: self.snap_framerate,
+            ..WriteOptions::default()
+        }
+    }
+}
+
+// Create a function to parse a --format argument.
+pub fn parse_format(name: &str) -> Result<Format, String> {
+    Registry::default()
+        .from_name(name)
+        .ok_or_else(|| format!("Unknown subtitle format '{}'", name))
+}
@@ -0,0 +1,247 @@
+use regex::Regex;
+use serde::Serialize;
+
+use super::video::probe_video;
+use super::{decode_input, parse_format, parse_fps, read_input};
+use simple_sub_sync::subtitle_parser::timing::DurationCheck;
+use simple_sub_sync::subtitle_parser::{
+    encoding, vtt, Format, ParseOptions, Registry, SubtitleFile, TagDialect,
+};
+
+const HELP: &str = "
+subsync probe <file> [--json] [--video <file>] [options]
+
+Reports what a subtitle file is made of: its format and version, the tag dialect of its inline styling,
+its text encoding and line endings, whether it uses styling or positioning, its number of cues and the
+time they span. Scripts can use --json to branch on these.
+
+    <file> = input file path, or - for standard input, also given as --input <file>. Mandatory
+    --json = print the report as JSON instead of text. Optional
+    --format = format of the input when the file extension doesn't tell. Optional, detected from the
+        contents otherwise
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate used to read frame-based formats (MicroDVD, EBU STL). Optional, defaults to the
+        framerate declared by the input file
+    --video = path of the video the subtitles are played with. Its framerate and duration are read with
+        ffprobe, which has to be installed, and reported along with whether the cues fit in the video,
+        and the framerate the cues look timed for when the video is about 4% longer or shorter. Optional
+    -h, --help Display this help.
+";
+
+// What probe found out about a file.
+#[derive(Debug, Serialize)]
+struct Report {
+    format: String,
+    version: Option<String>,
+    dialect: String,
+    encoding: String,
+    line_endings: String,
+    framerate: Option<f32>,
+    styling: bool,
+    positioning: bool,
+    cues: usize,
+    start_ms: Option<i32>,
+    end_ms: Option<i32>,
+    duration_ms: i32,
+    video_framerate: Option<f64>,
+    video_duration_ms: Option<i32>,
+    // Framerate the subtitles look timed for when the video is 4% longer or shorter, see DurationCheck.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timed_for_framerate: Option<f32>,
+}
+
+// Create a function to name the line endings of a text file: CRLF, LF, CR, mixed, or none for a single line.
+fn line_endings(contents: &[u8]) -> String {
+    let crlf = contents.windows(2).filter(|pair| pair == b"\r\n").count();
+    let lf = contents.iter().filter(|byte| **byte == b'\n').count() - crlf;
+    let cr = contents.iter().filter(|byte| **byte == b'\r').count() - crlf;
+    let name = match (crlf > 0, lf > 0, cr > 0) {
+        (false, false, false) => "none",
+        (true, false, false) => "CRLF",
+        (false, true, false) => "LF",
+        (false, false, true) => "CR",
+        _ => "mixed",
+    };
+    name.to_string()
+}
+
+// Create a function to tell the version of the formats that have several: ASS v4+ or SSA v4.
+fn version(subtitles: &SubtitleFile) -> Option<String> {
+    let header = subtitles.header.first()?;
+    match subtitles.format {
+        Format::Ass if header.to_lowercase().contains("[v4+ styles]") => Some("v4+".to_string()),
+        Format::Ass => Some("v4".to_string()),
+        _ => None,
+    }
+}
+
+// Create a function to build the report of a parsed file, from its raw and decoded contents. Binary
+// files have no encoding or line endings.
+fn probe(raw: &[u8], contents: &[u8], binary: bool, subtitles: &SubtitleFile) -> Report {
+    let registry = Registry::default();
+    let (name, dialect) = registry
+        .get(subtitles.format)
+        .map_or(("unknown", TagDialect::Html), |format| {
+            (format.names()[0], format.tag_dialect())
+        });
+    let tag = Regex::new(r"</?[a-zA-Z][^>]*>|\{\\[^}]*\}").unwrap();
+    let styling = subtitles.cues.iter().any(|cue| {
+        cue.lines.iter().any(|line| tag.is_match(line))
+            || cue.style.as_deref().is_some_and(|style| style != "Default")
+    });
+    let positioning = subtitles
+        .cues
+        .iter()
+        .any(|cue| subtitles.cue_alignment(cue).is_some());
+    let start_ms = subtitles.cues.iter().map(|cue| cue.start_ms).min();
+    let end_ms = subtitles.cues.iter().map(|cue| cue.end_ms).max();
+    Report {
+        format: name.to_string(),
+        version: version(subtitles),
+        dialect: format!("{:?}", dialect).to_lowercase(),
+        encoding: if binary {
+            "binary".to_string()
+        } else {
+            encoding::describe(raw)
+        },
+        line_endings: if binary {
+            "none".to_string()
+        } else {
+            line_endings(contents)
+        },
+        framerate: subtitles.framerate,
+        styling,
+        positioning,
+        cues: subtitles.cues.len(),
+        start_ms,
+        end_ms,
+        duration_ms: end_ms.unwrap_or(0) - start_ms.unwrap_or(0),
+        video_framerate: None,
+        video_duration_ms: None,
+        timed_for_framerate: None,
+    }
+}
+
+// Create a function to write a report as text, one property per line.
+fn to_text(report: &Report) -> String {
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+    let mut output = format!(
+        "Format:       {}{}\n",
+        report.format.to_uppercase(),
+        report
+            .version
+            .as_ref()
+            .map_or(String::new(), |version| format!(" {}", version))
+    );
+    output.push_str(&format!("Tag dialect:  {}\n", report.dialect));
+    output.push_str(&format!("Encoding:     {}\n", report.encoding));
+    output.push_str(&format!("Line endings: {}\n", report.line_endings));
+    if let Some(framerate) = report.framerate {
+        output.push_str(&format!("Framerate:    {}\n", framerate));
+    }
+    output.push_str(&format!("Styling:      {}\n", yes_no(report.styling)));
+    output.push_str(&format!("Positioning:  {}\n", yes_no(report.positioning)));
+    output.push_str(&format!("Cues:         {}\n", report.cues));
+    if let (Some(start), Some(end)) = (report.start_ms, report.end_ms) {
+        output.push_str(&format!(
+            "Timespan:     {} --> {} ({})\n",
+            vtt::convert_to_time(start),
+            vtt::convert_to_time(end),
+            vtt::convert_to_time(report.duration_ms)
+        ));
+    }
+    if let Some(framerate) = report.video_framerate {
+        output.push_str(&format!("Video:        {:.3} fps", framerate));
+        if let Some(duration) = report.video_duration_ms {
+            output.push_str(&format!(", {} long", vtt::convert_to_time(duration)));
+            let fits = report.end_ms.is_none_or(|end| end <= duration + 1000);
+            output.push_str(&format!("\nFits video:   {}", yes_no(fits)));
+            if let Some(framerate) = report.timed_for_framerate {
+                output.push_str(&format!(
+                    "\nTimed for:    {} fps, from the durations",
+                    framerate
+                ));
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// Create a function that runs the probe subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input_file = String::new();
+    let mut json = false;
+    let mut options = ParseOptions::default();
+    let mut format = None;
+    let mut input_encoding = None;
+    let mut video = None;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--json" {
+            json = true;
+        } else if args[i] == "--format" {
+            format = Some(parse_format(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--input-encoding" {
+            input_encoding = Some(encoding::from_label(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--fps" {
+            options.framerate = Some(parse_fps(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--video" {
+            video = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--input" {
+            input_file = value(i)?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else if args[i] == "-" || !args[i].starts_with('-') {
+            input_file = args[i].clone();
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if input_file.is_empty() {
+        return Err("No input file provided. Use --help for help.".to_string());
+    }
+
+    let raw = read_input(&input_file)?;
+    let registry = Registry::default();
+    let (format, contents, source) =
+        decode_input(&registry, &input_file, format, raw.clone(), input_encoding)?;
+    let subtitles = registry
+        .parse(&contents, format, &options)
+        .map_err(|error| format!("Unable to parse {}: {}", input_file, error))?;
+    let mut report = probe(&raw, &contents, source.is_none(), &subtitles);
+    if let Some(input_encoding) = input_encoding {
+        report.encoding = input_encoding.name().to_string();
+    }
+    if let Some(video) = video {
+        let info = probe_video(&video)?;
+        report.video_framerate = Some(info.framerate);
+        report.video_duration_ms = info.duration_ms;
+        if let (Some(end), Some(duration)) = (report.end_ms, info.duration_ms) {
+            report.timed_for_framerate = DurationCheck::new(end, duration, info.framerate)
+                .conversion
+                .map(|(from, _)| from);
+        }
+    }
+    if json {
+        let output = serde_json::to_string_pretty(&report)
+            .map_err(|error| format!("Unable to write the report: {}", error))?;
+        println!("{}", output);
+    } else {
+        print!("{}", to_text(&report));
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use super::{write_subtitles, FileArgs};
+use simple_sub_sync::subtitle_parser::validate;
+
+const HELP: &str = "
+subsync renumber --input <file> --output <file> [--start <n>] [options]
+
+Numbers the cues one after the other, 1, 2, 3... or from --start, in the order of the file, changing
+nothing else, as many players and tools choke on duplicate or missing indices, after cues were removed
+or merged for example. Cue identifiers of WebVTT files are left alone.
+
+    --input = input file path, or - for standard input, or a .zip or .rar archive holding the subtitle. Mandatory
+    --output = output file path, or - for standard output. Mandatory
+    --start = index of the first cue. Optional, defaults to 1
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --format = format of the input and output when the file extension doesn't tell. Optional
+    --output-encoding, --bom, --newline = how the output is written, see subsync shift --help. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    -h, --help Display this help.
+";
+
+// Create a function that runs the renumber subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut start = 1;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--start" {
+            let value = value(i)?;
+            start = value
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid index '{}'", value))?;
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+
+    let (mut subtitles, input_newline) = files.read()?;
+    let changes = validate::renumber_from(&mut subtitles, start);
+    eprintln!("{} cues renumbered", changes.len());
+    write_subtitles(
+        &subtitles,
+        &files.output_file,
+        files.format,
+        &files.write_options(input_newline),
+    )
+}
@@ -0,0 +1,121 @@
+use super::FileArgs;
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::{timing, vtt};
+
+const HELP: &str = "
+subsync retime --input <file> --output <file> --anchor <time>=<new time> [--anchor ...] [options]
+subsync retime --input <file> --output <file> --match <text> --at <time> [--match ... --at ...] [options]
+
+Retimes the cues through anchors: pairs of a time in the subtitles and the time it should be at in the
+video, found by looking up a few lines in both. Between two anchors, cues are moved and stretched
+linearly; before the first and after the last anchor, they follow the nearest section. Give an anchor
+on each side of every scene that was added or removed from the cut (extra scenes, removed logos), since
+a single shift or scale can't fix the whole file then.
+
+    --input = input file path, or - for standard input, or an HLS playlist (.m3u8) or directory of WebVTT
+        segments, or a .zip or .rar archive holding the subtitle. Mandatory
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --output = output file path, or - for standard output, or an .m3u8 or .zip path. Mandatory
+    --anchor = <time>=<new time>, for example --anchor 0:45:10.2=0:45:12, with times given like shift
+        --by (1500ms, 2.5s, 1:02.5, 1:00:02.5, 01:00:10:12@25). Repeat it for every anchor. Mandatory, a
+        single anchor shifts every cue, two anchors scale them
+    --match, --at = a piece of dialogue and the time its cue should start at, for example
+        --match \"I'll be back\" --at 00:12:03,400, so times don't have to be read out of the subtitles.
+        Case, punctuation and formatting are ignored, and the text has to be found in a single cue.
+        Repeat them for every anchor, --match-first and --match-last are the same as --match; two lines
+        give the linear transform between them. They can be mixed with --anchor
+    --format = format of the input and output when the file extension doesn't tell. Optional, the input
+        format is otherwise detected from its contents and the output format defaults to the input format
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    --default-duration = duration of cues in formats without end times (TMPlayer), in miliseconds.
+        Optional, defaults to 3000
+    -h, --help Display this help.
+";
+
+// Create a function that runs the retime subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut anchors = Vec::new();
+    let mut matches: Vec<(String, Option<i32>)> = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--anchor" {
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| format!("Missing value for {}", args[i]))?;
+            anchors.push(timing::parse_anchor(value)?);
+            i += 1;
+        } else if ["--match", "--match-first", "--match-last"].contains(&args[i].as_str()) {
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| format!("Missing value for {}", args[i]))?;
+            matches.push((value.clone(), None));
+            i += 1;
+        } else if args[i] == "--at" {
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| format!("Missing value for {}", args[i]))?;
+            let (text, at) = matches
+                .last_mut()
+                .filter(|(_, at)| at.is_none())
+                .ok_or("--at has to follow a --match")?;
+            *at = Some(
+                timing::parse_duration(value)
+                    .map_err(|error| format!("{} for '{}'", error, text))?,
+            );
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+
+    let (mut subtitles, input_newline) = files.read()?;
+    for (text, at) in &matches {
+        let at = at.ok_or_else(|| format!("Missing --at for '{}'", text))?;
+        let cue = subtitles.find_cue(text)?;
+        eprintln!(
+            "Found '{}' in cue {} at {}",
+            text,
+            cue.index,
+            vtt::convert_to_time(cue.start_ms)
+        );
+        anchors.push((cue.start_ms, at));
+    }
+    timing::check_anchors(&mut anchors)
+        .map_err(|error| format!("{}. Use --help for help.", error))?;
+    subtitles.check_warp(&anchors)?;
+    let cut = subtitles.warp(&anchors);
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would start before 0:00, they were cut to start at 0:00 or removed",
+            cut
+        );
+    }
+    files.save(
+        &subtitles,
+        &files.write_options(input_newline),
+        vec![Step::new(Transform::Warp { anchors }).losing(cut)],
+    )
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::Path;
+
+use super::{save_subtitles, FileArgs};
+use simple_sub_sync::subtitle_parser::provenance::{self, Provenance};
+
+const HELP: &str = "
+subsync revert <file> [--output <file>] [options]
+
+Undoes the retimings subsync applied to a file, to get back the timing of the original file. Every
+command that retimes subtitles records what it did in a <file>.subsync.json file written next to its
+output, after the retimings of its input when it was written by subsync too, so a file retimed several
+times goes back to the very first one. Times can be a milisecond off from rounding. A file whose retiming
+cut cues at 0:00 or at their neighbours, or removed them, can't be reverted, since the original times of
+those cues weren't kept. A retiming limited to --after/--before only reverts the cues it moved, wherever
+they landed.
+
+    <file> = a file written by subsync, with its .subsync.json file next to it, also given as --input <file>.
+        Mandatory
+    --output = output file path, or - for standard output. Optional, defaults to <name>.reverted.<ext>
+        next to the file
+    --format = format of the input and output when the file extension doesn't tell. Optional
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output. Optional
+    -h, --help Display this help.
+";
+
+// Create a function to build the default output file name of a reverted file, next to it.
+fn default_output_file(input_file: &str) -> String {
+    let path = Path::new(input_file);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => format!(
+            "{}.reverted.{}",
+            path.with_extension("").display(),
+            extension
+        ),
+        None => format!("{}.reverted", input_file),
+    }
+}
+
+// Create a function that runs the revert subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else if !args[i].starts_with('-') {
+            files.input_file = args[i].clone();
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if files.input_file.is_empty() {
+        return Err("No input file provided. Use --help for help.".to_string());
+    }
+    if files.output_file.is_empty() {
+        files.output_file = default_output_file(&files.input_file);
+    }
+    let sidecar = provenance::sidecar_path(&files.input_file);
+    let contents = fs::read_to_string(&sidecar).map_err(|_| {
+        format!(
+            "No retiming recorded for {}, {} is missing",
+            files.input_file, sidecar
+        )
+    })?;
+    let provenance = Provenance::parse(&contents)
+        .map_err(|error| format!("Unable to read {}: {}", sidecar, error))?;
+
+    let lost = provenance.lost();
+    if lost > 0 {
+        return Err(format!(
+            "Can't revert {} exactly: its retiming cut or removed {} cues, whose original times weren't kept",
+            files.input_file, lost
+        ));
+    }
+    if !provenance.steps.iter().all(|step| step.knows_moved_cues()) {
+        return Err(format!(
+            "Can't revert {} exactly: its retiming was limited to --after/--before by an older version of subsync, which didn't record the cues it moved",
+            files.input_file
+        ));
+    }
+
+    let (mut subtitles, input_newline) = files.read()?;
+    for step in provenance.inverse() {
+        subtitles.apply_step(&step);
+    }
+    eprintln!(
+        "Reverted {} retimings, back to the timing of {}",
+        provenance.steps.len(),
+        provenance.input
+    );
+    save_subtitles(
+        &subtitles,
+        &files.output_file,
+        files.format,
+        &files.write_options(input_newline),
+    )
+}
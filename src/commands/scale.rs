@@ -0,0 +1,155 @@
+use super::FileArgs;
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::timing::{self, Rounding, TimeRange};
+
+const HELP: &str = "
+subsync scale --input <file> --output <file> (--factor <factor> | --from-duration <d> --to-duration <d>) [options]
+
+Stretches every cue time by a factor, for subtitles that drift further off as the video plays when the
+drift isn't one of the common framerate ratios (PAL speedup combined with a trimmed intro, for example).
+Times are scaled from the start of the video, or from --after, so use shift afterwards to fix a remaining
+constant offset.
+
+    --input = input file path, or - for standard input, or an HLS playlist (.m3u8) or directory of WebVTT
+        segments, or a .zip or .rar archive holding the subtitle. Mandatory
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --output = output file path, or - for standard output, or an .m3u8 or .zip path. Mandatory
+    --factor = number every cue time is multiplied by, for example 1.0427 to make cues later and longer,
+        0.959 to make them earlier and shorter. Mandatory unless --from-duration and --to-duration are given
+    --from-duration, --to-duration = the length of the video the subtitles were timed for and the length of
+        the video to play them with, as 1:32:10.5, 5530.5s or 5530500ms. The factor is their ratio
+    --after = only retime the cues starting at or after this time, as 00:45:00, 2700s or 2700000ms, for
+        example after a reel change. Optional, the other cues are left untouched
+    --before = only retime the cues starting before this time. Optional
+    --keep-durations = scale only the start times, every cue keeping its duration, for durations authored
+        to reading-speed standards. Optional, cues that would run into the next one are cut at its start
+    --format = format of the input and output when the file extension doesn't tell. Optional, the input
+        format is otherwise detected from its contents and the output format defaults to the input format
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    --default-duration = duration of cues in formats without end times (TMPlayer), in miliseconds.
+        Optional, defaults to 3000
+    -h, --help Display this help.
+";
+
+// Create a function that runs the scale subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut after = None;
+    let mut before = None;
+    let mut factor = None;
+    let mut from_duration = None;
+    let mut to_duration = None;
+    let mut keep_durations = false;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--factor" {
+            let value = value(i)?;
+            factor = Some(
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|factor| *factor > 0.0 && factor.is_finite())
+                    .ok_or_else(|| format!("Invalid factor '{}'", value))?,
+            );
+            i += 1;
+        } else if args[i] == "--from-duration" {
+            from_duration = Some(timing::parse_duration(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--to-duration" {
+            to_duration = Some(timing::parse_duration(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--keep-durations" {
+            keep_durations = true;
+        } else if args[i] == "--after" || args[i] == "--before" {
+            let time = Some(timing::parse_duration(&value(i)?)?);
+            if args[i] == "--after" {
+                after = time;
+            } else {
+                before = time;
+            }
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+    let range = TimeRange::new(after, before)?;
+    let factor = match (factor, from_duration, to_duration) {
+        (Some(factor), None, None) => factor,
+        (None, Some(from), Some(to)) if from > 0 && to > 0 => to as f64 / from as f64,
+        (None, Some(_), Some(_)) => {
+            return Err("--from-duration and --to-duration must be positive".to_string())
+        }
+        _ => return Err(
+            "Give either --factor, or both --from-duration and --to-duration. Use --help for help."
+                .to_string(),
+        ),
+    };
+
+    let (mut subtitles, input_newline) = files.read()?;
+    let origin = range.after.unwrap_or(0);
+    subtitles.check_retiming(&range, factor, origin, 0)?;
+    let moved = subtitles.positions_within(&range);
+    let (cut, squeezed) = subtitles.within(&range, |part| {
+        if keep_durations {
+            part.scale_starts(factor, origin, Rounding::Nearest)
+        } else {
+            part.scale(factor, origin);
+            0
+        }
+    });
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would run into the next cue, they were cut at its start",
+            cut
+        );
+    }
+    if squeezed > 0 {
+        eprintln!(
+            "Warning: {} retimed cues would overlap the cues around the range, they were cut at them or removed",
+            squeezed
+        );
+    }
+    let transform = Transform::Linear {
+        factor,
+        origin,
+        offset: 0,
+        keep_durations,
+    };
+    files.save(
+        &subtitles,
+        &files.write_options(input_newline),
+        vec![Step {
+            range,
+            transform,
+            moved,
+            lost: cut + squeezed,
+        }],
+    )
+}
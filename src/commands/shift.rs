@@ -0,0 +1,144 @@
+use super::{read_input, FileArgs};
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::timing::{self, TimeRange};
+use simple_sub_sync::subtitle_parser::{decode_text, segments};
+
+const HELP: &str = "
+subsync shift --input <file> --output <file> (--by <offset> | --segments <file>) [options]
+
+Moves every cue earlier or later by a constant offset, for subtitles that are right except for starting
+too soon or too late. Cues moved before the start of the video are cut or removed, with a warning.
+With --segments, each time range listed in a file is moved by its own offset instead, for example to
+remove the ad breaks at known points of a recording.
+
+    --input = input file path, or - for standard input, or an HLS playlist (.m3u8) or directory of WebVTT
+        segments, or a .zip or .rar archive holding the subtitle. Mandatory
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --output = output file path, or - for standard output, or an .m3u8 or .zip path. Mandatory
+    --by = offset to add to every cue, negative to show them earlier: 1500ms (or 1500), -2.5s, 1:02.5
+        (minutes and seconds), 1:00:02.5, or a timecode and its framerate (00:00:01:12@25, or
+        00:01:00;02 for 29.97 drop-frame). Mandatory unless --segments is given
+    --segments = TOML or CSV file of time ranges and their offsets, applied in one pass. Segments can't
+        overlap, before or after being moved; cues outside every segment are left untouched. A CSV file
+        has a start,end,offset row per segment (00:12:30,00:27:45,-30s), a TOML file a [[segment]] table
+        with start, end and offset keys. An empty or missing start or end runs from the start or to the
+        end of the file
+    --after = only retime the cues starting at or after this time, as 00:45:00, 2700s or 2700000ms, for
+        example after a reel change. Optional, the other cues are left untouched
+    --before = only retime the cues starting before this time. Optional
+    --format = format of the input and output when the file extension doesn't tell. Optional, the input
+        format is otherwise detected from its contents and the output format defaults to the input format
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    --default-duration = duration of cues in formats without end times (TMPlayer), in miliseconds.
+        Optional, defaults to 3000
+    -h, --help Display this help.
+";
+
+// Create a function that runs the shift subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut after = None;
+    let mut before = None;
+    let mut offset = None;
+    let mut segments = None;
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--by" {
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| format!("Missing value for {}", args[i]))?;
+            offset = Some(timing::parse_duration(value)?);
+            i += 1;
+        } else if args[i] == "--segments" {
+            let path = args
+                .get(i + 1)
+                .ok_or_else(|| format!("Missing value for {}", args[i]))?;
+            let contents = read_input(path)?;
+            segments = Some(segments::parse_segments(path, decode_text(&contents)?)?);
+            i += 1;
+        } else if args[i] == "--after" || args[i] == "--before" {
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| format!("Missing value for {}", args[i]))?;
+            let time = Some(timing::parse_duration(value)?);
+            if args[i] == "--after" {
+                after = time;
+            } else {
+                before = time;
+            }
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    files.check()?;
+    let range = TimeRange::new(after, before)?;
+    if offset.is_some() == segments.is_some() {
+        return Err("Give either --by or --segments. Use --help for help.".to_string());
+    }
+
+    let (mut subtitles, input_newline) = files.read()?;
+    match &segments {
+        Some(segments) => segments.iter().try_for_each(|segment| {
+            subtitles.check_retiming(&segment.range, 1.0, 0, segment.offset)
+        })?,
+        None => subtitles.check_retiming(&range, 1.0, 0, offset.unwrap_or(0))?,
+    }
+    let moved = subtitles.positions_within(&range);
+    let (cut, squeezed) = match &segments {
+        Some(segments) => subtitles.within(&range, |part| part.apply_segments(segments)),
+        None => subtitles.within(&range, |part| part.shift(offset.unwrap_or(0))),
+    };
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would start before 0:00, they were cut to start at 0:00 or removed",
+            cut
+        );
+    }
+    if squeezed > 0 {
+        eprintln!(
+            "Warning: {} retimed cues would overlap the cues around the range, they were cut at them or removed",
+            squeezed
+        );
+    }
+    let transform = match segments {
+        Some(segments) => Transform::Segments { segments },
+        None => Transform::Linear {
+            factor: 1.0,
+            origin: 0,
+            offset: offset.unwrap_or(0),
+            keep_durations: false,
+        },
+    };
+    files.save(
+        &subtitles,
+        &files.write_options(input_newline),
+        vec![Step {
+            range,
+            transform,
+            moved,
+            lost: cut + squeezed,
+        }],
+    )
+}
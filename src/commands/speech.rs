@@ -0,0 +1,209 @@
+use super::audio;
+use super::FileArgs;
+use simple_sub_sync::subtitle_parser::correlate::{self, CorrelateOptions};
+use simple_sub_sync::subtitle_parser::encoding::Newline;
+use simple_sub_sync::subtitle_parser::vad::VadOptions;
+use simple_sub_sync::subtitle_parser::{timing, vtt, Cue, Format, SubtitleFile};
+
+const HELP: &str = "
+subsync speech --video <file> [--output <file>] [--input <file>] [options]
+
+Finds where someone speaks in the soundtrack of a video, by voice activity detection: frames of audio
+louder than the noise floor by a margin count as speech. The speech intervals are printed, or written
+as cues to check them against the subtitles in a player. WAV files (16-bit PCM) are read directly, other
+files are decoded with ffmpeg, which has to be installed.
+
+With --input, the cues of a subtitle file are laid over the speech instead and the offset and framerate
+ratio that fit them best are printed, with the offset that fits every 10 minutes best on their own: when
+those disagree the subtitles drift in a way a single offset and ratio can't fix. Nothing is written.
+
+    --video = path of the video or audio file. Mandatory
+    --output = subtitle file to write the speech intervals to as cues, in the format of its extension.
+        Optional, they are printed otherwise
+    --input = subtitle file to measure against the speech. Optional
+    --max-offset = largest offset tried either way, as 90s or 1:30. Optional, defaults to 60s
+    --window = length of the parts of the file measured on their own, as 5:00. Optional, defaults to 10:00
+    --no-drift = only look for an offset, don't try the common framerate ratios. Optional
+    --margin = how far above the noise floor audio has to be to count as speech, in dB. Optional,
+        defaults to 10; raise it for noisy soundtracks, lower it for quiet dialogue
+    --min-speech = shortest speech interval kept, as 200ms or 0.2s. Optional, defaults to 200ms
+    --min-gap = pauses shorter than this are bridged, as 300ms or 0.3s. Optional, defaults to 300ms
+    --format = format of the output when the file extension doesn't tell. Optional, defaults to SRT
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    -h, --help Display this help.
+";
+
+// Create a function to read the voice activity detection options of a command.
+// Returns how many arguments it used, like FileArgs::parse.
+pub fn parse_vad_option(
+    args: &[String],
+    i: usize,
+    options: &mut VadOptions,
+) -> Result<usize, String> {
+    let value = || {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    match args[i].as_str() {
+        "--margin" => {
+            let margin = value()?;
+            options.margin_db = margin
+                .parse::<f32>()
+                .ok()
+                .filter(|margin| margin.is_finite() && *margin > 0.0)
+                .ok_or_else(|| format!("Invalid margin '{}'", margin))?;
+        }
+        "--min-speech" => options.min_speech_ms = timing::parse_duration(&value()?)?,
+        "--min-gap" => options.min_gap_ms = timing::parse_duration(&value()?)?,
+        _ => return Ok(0),
+    }
+    Ok(2)
+}
+
+// Create a function to read the options of the search for the offset and framerate ratio of a command.
+// Returns how many arguments it used, like FileArgs::parse.
+pub fn parse_correlate_option(
+    args: &[String],
+    i: usize,
+    options: &mut CorrelateOptions,
+) -> Result<usize, String> {
+    let value = || {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    match args[i].as_str() {
+        "--max-offset" => options.max_offset_ms = timing::parse_duration(&value()?)?.abs(),
+        "--window" => {
+            options.window_ms = timing::parse_duration(&value()?)?;
+            if options.window_ms <= 0 {
+                return Err("The --window has to be longer than 0".to_string());
+            }
+        }
+        "--no-drift" => {
+            options.drift = false;
+            return Ok(1);
+        }
+        _ => return Ok(0),
+    }
+    Ok(2)
+}
+
+// Create a function to print how the cues of a subtitle file fit the speech intervals.
+fn print_correlation(
+    subtitles: &SubtitleFile,
+    intervals: &[(i32, i32)],
+    options: &CorrelateOptions,
+) {
+    let cues: Vec<(i32, i32)> = subtitles
+        .cues
+        .iter()
+        .filter(|cue| cue.end_ms > cue.start_ms)
+        .map(|cue| (cue.start_ms, cue.end_ms))
+        .collect();
+    let Some(correlation) = correlate::correlate(&cues, intervals, options) else {
+        println!("Nothing to measure, the file has no cues or the soundtrack no speech");
+        return;
+    };
+    println!("Offset: {}ms", correlation.offset);
+    println!("Ratio: {:.6}", correlation.ratio);
+    println!("Speech coverage: {:.1}%", correlation.coverage * 100.0);
+    println!("Confidence: {:.2}", correlation.confidence);
+    for window in &correlation.windows {
+        let offset = match window.offset {
+            Some(offset) => format!("{}ms", offset),
+            None => "too few cues".to_string(),
+        };
+        println!(
+            "  {} - {}: {} cues, offset {}, speech coverage {:.1}%",
+            vtt::convert_to_time(window.start_ms),
+            vtt::convert_to_time(window.end_ms),
+            window.cues,
+            offset,
+            window.coverage * 100.0
+        );
+    }
+    if correlation.is_non_linear(500) {
+        println!(
+            "Warning: the offset changes along the file, a single offset and ratio won't fix it"
+        );
+    }
+}
+
+// Create a function that runs the speech subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut video = None;
+    let mut options = VadOptions::default();
+    let mut correlate_options = CorrelateOptions::default();
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?
+            + parse_vad_option(args, i, &mut options)?
+            + parse_correlate_option(args, i, &mut correlate_options)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--video" {
+            video = Some(
+                args.get(i + 1)
+                    .cloned()
+                    .ok_or_else(|| "Missing value for --video".to_string())?,
+            );
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    let video = video.ok_or_else(|| "No --video provided. Use --help for help.".to_string())?;
+    let input = if files.input_file.is_empty() {
+        None
+    } else {
+        Some(files.read()?.0)
+    };
+
+    let intervals = audio::speech_intervals(&video, &options)?;
+    let speech: i32 = intervals.iter().map(|(start, end)| end - start).sum();
+    eprintln!(
+        "{} speech intervals, {} of speech",
+        intervals.len(),
+        vtt::convert_to_time(speech)
+    );
+    if let Some(subtitles) = input {
+        print_correlation(&subtitles, &intervals, &correlate_options);
+        return Ok(());
+    }
+    if files.output_file.is_empty() {
+        for (start, end) in &intervals {
+            println!(
+                "{} --> {}",
+                vtt::convert_to_time(*start),
+                vtt::convert_to_time(*end)
+            );
+        }
+        return Ok(());
+    }
+    let subtitles = SubtitleFile {
+        format: Format::Srt,
+        framerate: None,
+        header: Vec::new(),
+        cues: intervals
+            .iter()
+            .enumerate()
+            .map(|(i, (start, end))| Cue {
+                index: i + 1,
+                start_ms: *start,
+                end_ms: *end,
+                lines: vec!["[speech]".to_string()],
+                ..Default::default()
+            })
+            .collect(),
+        trailer: Vec::new(),
+    };
+    files.save(&subtitles, &files.write_options(Newline::Lf), Vec::new())
+}
@@ -0,0 +1,233 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{config, parse_format, parse_fps, read_subtitles};
+use simple_sub_sync::subtitle_parser::spelling::{self, Dictionary};
+use simple_sub_sync::subtitle_parser::{encoding, validate, vtt, ParseOptions};
+
+// Exit codes, as those of validate.
+const UNREADABLE: i32 = 1;
+const MISSPELLED: i32 = 2;
+
+const HELP: &str = "
+subsync spellcheck <file>... [--lang en_US] [--dict <file.dic>] [--user-dict <file>] [--list] [options]
+
+Checks the words of every cue against a Hunspell dictionary, and prints every word it doesn't know with
+the time of its cue and up to three suggestions:
+
+    movie.srt: 00:01:12.400 --> 00:01:14.900: Cue 18: unknown word 'recieve'; did you mean receive?
+
+Formatting tags and numbers are left out, and a capitalized or uppercase word is right when its
+lowercase form is. The dictionary of the language is looked for where Hunspell looks, in the
+directories of the DICPATH environment variable, ~/.local/share/hunspell, /usr/share/hunspell and
+/usr/share/myspell; on Debian and Ubuntu, apt install hunspell-en-us installs en_US. Prefix and
+suffix rules are applied, compound words are not.
+
+Names and other words the dictionary doesn't have go in a user dictionary, a text file with one word
+per line (lines starting with # are comments). dictionary.txt in the subsync configuration directory
+(~/.config/subsync/dictionary.txt) is always read; --list prints the unknown words once each, to review
+and add to it.
+
+The exit code is 0 when every word is known, 1 when a file can't be read, and 2 when words are unknown.
+
+    <file> = input file paths, or - for standard input, or .zip archives holding the subtitle, also given as
+        --input <file>. Mandatory
+    --lang = language of the dictionary, as en_US or de_DE, or en for the first English one found.
+        Mandatory unless --dict is given or the file name tells it (movie.en.srt)
+    --dict = path of the .dic file of the dictionary, its .aff file being next to it. Optional, replaces
+        --lang
+    --user-dict = text file of words to accept, one per line, as names. Can be repeated. Optional
+    --list = print only the unknown words, once each and sorted. Optional
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --format = format of the input when the file extension doesn't tell. Optional
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate used to read frame-based formats (MicroDVD, EBU STL). Optional
+    -h, --help Display this help.
+";
+
+// Create a function to read the words of a user dictionary, skipping blank lines and # comments.
+fn read_user_dictionary(path: &Path) -> Result<Vec<String>, String> {
+    let contents =
+        fs::read(path).map_err(|error| format!("Unable to read {}: {}", path.display(), error))?;
+    let (text, _) = encoding::decode(&contents);
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+// Create a function that runs the spellcheck subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input_files = Vec::new();
+    let mut language = None;
+    let mut dictionary_file = None;
+    let mut user_dictionaries = Vec::new();
+    let mut list = false;
+    let mut member = None;
+    let mut format = None;
+    let mut input_encoding = None;
+    let mut options = ParseOptions::default();
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--lang" {
+            language = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--dict" {
+            dictionary_file = Some(PathBuf::from(value(i)?));
+            i += 1;
+        } else if args[i] == "--user-dict" {
+            user_dictionaries.push(PathBuf::from(value(i)?));
+            i += 1;
+        } else if args[i] == "--list" {
+            list = true;
+        } else if args[i] == "--member" {
+            member = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--format" {
+            format = Some(parse_format(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--input-encoding" {
+            input_encoding = Some(encoding::from_label(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--fps" {
+            options.framerate = Some(parse_fps(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--input" {
+            input_files.push(value(i)?);
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else if args[i] == "-" || !args[i].starts_with('-') {
+            input_files.push(args[i].clone());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if input_files.is_empty() {
+        return Err("No input file provided. Use --help for help.".to_string());
+    }
+    let mut user_words = Vec::new();
+    if let Some(path) = config::user_directory()
+        .map(|directory| directory.join("dictionary.txt"))
+        .filter(|path| path.is_file())
+    {
+        user_words.extend(read_user_dictionary(&path)?);
+    }
+    for path in &user_dictionaries {
+        user_words.extend(read_user_dictionary(path)?);
+    }
+
+    // Dictionaries by the path of their .dic file, as files in the same language share one.
+    let mut dictionaries: HashMap<PathBuf, Dictionary> = HashMap::new();
+    let mut unreadable = false;
+    let mut unknown = BTreeSet::new();
+    let mut misspellings = 0;
+    for input_file in &input_files {
+        let path = match (&dictionary_file, &language) {
+            (Some(path), _) => path.clone(),
+            (None, language) => {
+                let language = language
+                    .clone()
+                    .or_else(|| validate::language_from_name(input_file))
+                    .ok_or_else(|| {
+                        format!(
+                            "No language given for {}, use --lang or --dict. Use --help for help.",
+                            input_file
+                        )
+                    })?;
+                spelling::find_dictionary(&language).ok_or_else(|| {
+                    format!(
+                        "No Hunspell dictionary found for '{}', install it or give its .dic file with --dict",
+                        language
+                    )
+                })?
+            }
+        };
+        if !dictionaries.contains_key(&path) {
+            let mut dictionary = Dictionary::load(&path)?;
+            dictionary.add_words(user_words.iter().map(String::as_str));
+            dictionaries.insert(path.clone(), dictionary);
+        }
+        let dictionary = &dictionaries[&path];
+        let subtitles = match read_subtitles(
+            input_file,
+            member.as_deref(),
+            format,
+            &options,
+            input_encoding,
+        ) {
+            Ok((subtitles, _)) => subtitles,
+            Err(error) => {
+                eprintln!("{}: {}", input_file, error);
+                unreadable = true;
+                continue;
+            }
+        };
+        let mut count = 0;
+        for cue in &subtitles.cues {
+            let text = cue.plain_lines().join("\n");
+            for word in spelling::words(&text) {
+                if dictionary.check(word) {
+                    continue;
+                }
+                count += 1;
+                if list {
+                    unknown.insert(word.to_string());
+                    continue;
+                }
+                let suggestions = dictionary.suggest(word);
+                let suggestions = match suggestions.split_last() {
+                    None => String::new(),
+                    Some((last, [])) => format!("; did you mean {}?", last),
+                    Some((last, others)) => {
+                        format!("; did you mean {} or {}?", others.join(", "), last)
+                    }
+                };
+                println!(
+                    "{}: {} --> {}: Cue {}: unknown word '{}'{}",
+                    input_file,
+                    vtt::convert_to_time(cue.start_ms),
+                    vtt::convert_to_time(cue.end_ms),
+                    cue.index,
+                    word,
+                    suggestions
+                );
+            }
+        }
+        if !list {
+            println!(
+                "{}: {} unknown word{}",
+                input_file,
+                count,
+                if count == 1 { "" } else { "s" }
+            );
+        }
+        misspellings += count;
+    }
+    for word in &unknown {
+        println!("{}", word);
+    }
+    let code = if unreadable {
+        UNREADABLE
+    } else if misspellings > 0 {
+        MISSPELLED
+    } else {
+        0
+    };
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use super::{write_subtitles, FileArgs};
+use simple_sub_sync::subtitle_parser::{timing, vtt};
+
+const HELP: &str = "
+subsync split --input <file> --at <time> [--at <time>]... [--rebase] [--output <file>] [options]
+
+Splits a subtitle file in parts at the given times, to match a release on two CDs or split a double
+episode: each cue goes to the part it starts in, and ends at the end of that part at the latest. The
+parts are written next to the --output, or the input, as movie.part1.srt, movie.part2.srt... With
+--rebase, the times of every part are made relative to its start, as the second CD of a release
+starts again at zero.
+
+    --input = input file path, or - for standard input, or a .zip or .rar archive holding the subtitle. Mandatory
+    --at = time to split at, as 00:52:10, 3130s or 3130000ms, such as the length of the first CD. Can be
+        repeated to split in more parts. Mandatory
+    --rebase = make the times of every part relative to its start. Optional
+    --output = file path the parts are named after, movie.srt giving movie.part1.srt, movie.part2.srt...
+        Optional, defaults to the input file, mandatory when reading standard input
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --format = format of the input and output when the file extension doesn't tell. Optional
+    --output-encoding, --bom, --newline = how the output is written, see subsync shift --help. Optional
+    --overwrite = replace the output files if they exist. Optional, an existing file is never replaced otherwise
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    -h, --help Display this help.
+";
+
+// Create a function to name a part after the output file, movie.srt giving movie.part2.srt.
+fn part_name(output_file: &str, part: usize) -> String {
+    let path = Path::new(output_file);
+    let stem = path
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    let name = match path.extension() {
+        Some(extension) => format!("{}.part{}.{}", stem, part, extension.to_string_lossy()),
+        None => format!("{}.part{}", stem, part),
+    };
+    path.with_file_name(name).to_string_lossy().to_string()
+}
+
+// Create a function that runs the split subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut times = Vec::new();
+    let mut rebase = false;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--at" {
+            let time = timing::parse_duration(&value(i)?)?;
+            if time <= 0 {
+                return Err(format!("Invalid split time '{}'", args[i + 1]));
+            }
+            times.push(time);
+            i += 1;
+        } else if args[i] == "--rebase" {
+            rebase = true;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if files.input_file.is_empty() || times.is_empty() {
+        return Err("Both --input and --at are required. Use --help for help.".to_string());
+    }
+    let output_file = match files.output_file.as_str() {
+        "" if files.input_file == "-" => {
+            return Err(
+                "--output is required with standard input. Use --help for help.".to_string(),
+            )
+        }
+        "" => files.input_file.clone(),
+        "-" => return Err("The parts can't be written to standard output".to_string()),
+        output_file => output_file.to_string(),
+    };
+    times.sort_unstable();
+    times.dedup();
+
+    let (subtitles, input_newline) = files.read()?;
+    let starts = [0].into_iter().chain(times.iter().copied());
+    for (i, (mut part, start)) in subtitles
+        .split_at(&times)
+        .into_iter()
+        .zip(starts)
+        .enumerate()
+    {
+        if rebase {
+            part.shift(-start);
+        }
+        let name = part_name(&output_file, i + 1);
+        write_subtitles(
+            &part,
+            &name,
+            files.format,
+            &files.write_options(input_newline),
+        )?;
+        eprintln!(
+            "{}: {} cues from {}",
+            name,
+            part.cues.len(),
+            vtt::convert_to_time(start)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use super::speech::{parse_correlate_option, parse_vad_option};
+use super::{audio, FileArgs};
+use simple_sub_sync::subtitle_parser::correlate::{self, CorrelateOptions};
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::timing::TimeRange;
+use simple_sub_sync::subtitle_parser::vad::VadOptions;
+
+// Below this confidence, the offset found may well be a coincidence of the speech pattern.
+const LOW_CONFIDENCE: f64 = 0.2;
+
+const HELP: &str = "
+subsync sync --video <file> --input <file> --output <file> [options]
+
+Synchronizes subtitles against the soundtrack of a video in one go: finds where someone speaks by voice
+activity detection (see subsync speech --help), lays the cues over the speech to find the offset and
+framerate ratio under which they cover the most speech, and writes the subtitles with that ratio and
+offset applied. The offset, the ratio and a confidence score from 0 to 1 are printed; check a file
+synchronized with a low confidence before keeping it. WAV files (16-bit PCM) are read directly, other
+files are decoded with ffmpeg, which has to be installed.
+
+    --video = path of the video or audio file the subtitles should match. Mandatory
+    --input = input file path, or - for standard input, or an HLS playlist (.m3u8) or directory of WebVTT
+        segments, or a .zip or .rar archive holding the subtitle. Mandatory
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --output = output file path, or - for standard output, or an .m3u8 or .zip path. Mandatory
+    --max-offset = largest offset tried either way, as 90s or 1:30. Optional, defaults to 60s
+    --window = length of the parts of the file checked on their own for drift a single offset and ratio
+        can't fix, as 5:00. Optional, defaults to 10:00
+    --no-drift = only look for an offset, don't try the common framerate ratios. Optional
+    --margin = how far above the noise floor audio has to be to count as speech, in dB. Optional,
+        defaults to 10; raise it for noisy soundtracks, lower it for quiet dialogue
+    --min-speech = shortest speech interval kept, as 200ms or 0.2s. Optional, defaults to 200ms
+    --min-gap = pauses shorter than this are bridged, as 300ms or 0.3s. Optional, defaults to 300ms
+    --format = format of the input and output when the file extension doesn't tell. Optional, the input
+        format is otherwise detected from its contents and the output format defaults to the input format
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every start and end time
+        is rounded to, as broadcast QC tools require. Optional
+    --no-provenance = don't write the <output>.subsync.json file recording the retiming, which subsync revert
+        uses to undo it. Optional
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to the line endings of the input
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate of frame-based formats (MicroDVD, EBU STL), for the input and the output.
+        Optional, defaults to the framerate declared by the input file
+    --default-duration = duration of cues in formats without end times (TMPlayer), in miliseconds.
+        Optional, defaults to 3000
+    -h, --help Display this help.
+";
+
+// Create a function that runs the sync subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut video = None;
+    let mut vad_options = VadOptions::default();
+    let mut options = CorrelateOptions::default();
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)?
+            + parse_vad_option(args, i, &mut vad_options)?
+            + parse_correlate_option(args, i, &mut options)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--video" {
+            video = Some(
+                args.get(i + 1)
+                    .cloned()
+                    .ok_or_else(|| "Missing value for --video".to_string())?,
+            );
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    let video = video.ok_or_else(|| "No --video provided. Use --help for help.".to_string())?;
+    files.check()?;
+
+    let (mut subtitles, input_newline) = files.read()?;
+    let intervals = audio::speech_intervals(&video, &vad_options)?;
+    let cues: Vec<(i32, i32)> = subtitles
+        .cues
+        .iter()
+        .filter(|cue| cue.end_ms > cue.start_ms)
+        .map(|cue| (cue.start_ms, cue.end_ms))
+        .collect();
+    let correlation = correlate::correlate(&cues, &intervals, &options).ok_or_else(|| {
+        format!(
+            "Nothing to synchronize, {} has no cues or {} no speech",
+            files.input_file, video
+        )
+    })?;
+    eprintln!(
+        "Offset: {}ms, ratio: {:.6}, confidence: {:.2}, speech coverage: {:.1}%",
+        correlation.offset,
+        correlation.ratio,
+        correlation.confidence,
+        correlation.coverage * 100.0
+    );
+    if correlation.confidence < LOW_CONFIDENCE {
+        eprintln!("Warning: the confidence is low, check the result before keeping it");
+    }
+    if correlation.is_non_linear(500) {
+        eprintln!(
+            "Warning: the offset changes along the file, a single offset and ratio won't fix it; \
+             use subsync speech --input to see where"
+        );
+    }
+
+    subtitles.scale(correlation.ratio, 0);
+    let cut = subtitles.shift(correlation.offset);
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would start before 0:00, they were cut to start at 0:00 or removed",
+            cut
+        );
+    }
+    let transform = Transform::Linear {
+        factor: correlation.ratio,
+        origin: 0,
+        offset: correlation.offset,
+        keep_durations: false,
+    };
+    files.save(
+        &subtitles,
+        &files.write_options(input_newline),
+        vec![Step {
+            range: TimeRange::default(),
+            moved: None,
+            transform,
+            lost: cut,
+        }],
+    )
+}
@@ -0,0 +1,90 @@
+use super::whisper::{self, Whisper};
+use super::FileArgs;
+use simple_sub_sync::subtitle_parser::encoding::Newline;
+
+const HELP: &str = "
+subsync transcribe --video <file> --output <file> [--model <model>] [options]
+
+Transcribes the soundtrack of a video with whisper, as subtitles timed to when the words are spoken.
+The transcript is rough, but its timing can serve as a reference to align other subtitles to when no
+correctly timed subtitle exists. whisper.cpp (whisper-cli) or OpenAI's whisper has to be installed, as
+well as ffmpeg, which decodes the audio for it.
+
+    --video = path of the video or audio file. Mandatory
+    --output = output file path, or - for standard output, in the format of its extension. Mandatory
+    --whisper = name or path of the whisper binary. Optional, whisper-cli, whisper-cpp and whisper are
+        tried in this order; a binary named whisper is run as OpenAI's whisper, others as whisper.cpp
+    --model = path of the ggml model file for whisper.cpp (mandatory with it), or the model name for
+        OpenAI's whisper (tiny, base, small, medium, large). Optional with OpenAI's whisper
+    --language = spoken language, as a code such as en or fr. Optional, detected by whisper otherwise
+    --words = write one cue per word, for alignment at the word level. Optional, a cue per sentence otherwise
+    --format = format of the output when the file extension doesn't tell. Optional, defaults to SRT
+    --output-encoding = text encoding of the output: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252,
+        ... Optional, defaults to utf-8
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output. Optional
+    --newline = lf or crlf, the line endings of the output. Optional, defaults to lf
+    -h, --help Display this help.
+";
+
+// Create a function to read the whisper options of a command.
+// Returns how many arguments it used, like FileArgs::parse.
+pub fn parse_whisper_option(
+    args: &[String],
+    i: usize,
+    whisper: &mut Whisper,
+) -> Result<usize, String> {
+    let value = || {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    match args[i].as_str() {
+        "--whisper" => whisper.binary = Some(value()?),
+        "--model" => whisper.model = Some(value()?),
+        "--language" => whisper.language = Some(value()?),
+        "--words" => {
+            whisper.words = true;
+            return Ok(1);
+        }
+        _ => return Ok(0),
+    }
+    Ok(2)
+}
+
+// Create a function that runs the transcribe subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut files = FileArgs::default();
+    let mut video = None;
+    let mut options = Whisper::default();
+    let mut i = 0;
+    while i < args.len() {
+        let used = files.parse(args, i)? + parse_whisper_option(args, i, &mut options)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--video" {
+            video = Some(
+                args.get(i + 1)
+                    .cloned()
+                    .ok_or_else(|| "Missing value for --video".to_string())?,
+            );
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    let video = video.ok_or_else(|| "No --video provided. Use --help for help.".to_string())?;
+    if files.output_file.is_empty() {
+        return Err("No --output provided. Use --help for help.".to_string());
+    }
+
+    let subtitles = whisper::transcribe(&video, &options)?;
+    eprintln!("Transcribed {} cues from {}", subtitles.cues.len(), video);
+    files.input_file = video;
+    files.save(&subtitles, &files.write_options(Newline::Lf), Vec::new())
+}
@@ -0,0 +1,529 @@
+use serde::Serialize;
+
+use super::config;
+use super::{read_subtitles, write_subtitles, FileArgs};
+use simple_sub_sync::subtitle_parser::language;
+use simple_sub_sync::subtitle_parser::validate::{self, Limit, OverlapStrategy, Rules, Severity};
+use simple_sub_sync::subtitle_parser::vtt;
+
+// Exit codes, so scripts can tell why a check failed. 1 is also the exit code of every other failure,
+// such as a missing option.
+const UNREADABLE: i32 = 1;
+const ERRORS: i32 = 2;
+const WARNINGS: i32 = 3;
+
+// How long --extend-short makes short cues, in miliseconds, unless given --min-duration.
+const EXTENDED_DURATION: f64 = 1000.0;
+
+const HELP: &str = "
+subsync validate <file>... [--preset netflix|bbc|ebu] [--rules <file.toml>] [--strict] [--json] [options]
+subsync validate <file> --fix --output <file> [options]
+subsync validate <file> --renumber --output <file> [options]
+subsync validate <file> --drop-ads --output <file> [options]
+subsync validate <file> --fix-overlaps [--overlap-strategy trim|split] --output <file> [options]
+subsync validate <file> --extend-short [--min-duration <ms>] --output <file> [options]
+subsync validate <file> --fix-reading-speed [--max-cps <n>] [--max-wpm <n>] --output <file> [options]
+subsync validate <file> --split-long [--max-duration <ms>] [--max-chars <n>] --output <file> [options]
+
+Checks subtitle files for timing problems and prints every one with the time of its cue and a
+suggested fix. Errors break playback in most players: cues that end before they start, start before
+the start of the video, or start before the cue ahead of them. Warnings only make the subtitles harder
+to read: by default cues shorter than 100ms or longer than 10s, cues read faster than 25 characters
+per second, cues shown at the same time as another one, even out of order, cues without text, breaks
+in the numbering of the cues (an index used twice, indices missing, or a cue numbered lower than the
+one before it), and mojibake, text damaged by reading it from the wrong encoding (Ã© for é, â€™ for
+’), with the encoding it was likely read as, and ads and release credits rather than dialogue: web
+addresses, \"Downloaded from\" or \"Synced by\" credits, and promotional words in capitals in the
+first or last cues (XYZ TEAM PRESENTS). With --expect-lang, subtitles mostly in another language are
+an error, to catch a file downloaded in the wrong language. The rules file, or the [validate] table of
+the configuration file, sets the limits of these rules and of max_wpm (words per minute), max_lines,
+max_chars_per_line and min_gap, and whether breaking each one is an error, a warning or nothing:
+
+    max_cps = 17
+    max_chars_per_line = { limit = 42, severity = \"error\" }
+    min_duration = 833
+    overlap = \"off\"
+
+Durations and gaps are in miliseconds, and characters are counted as a reader sees them, without
+formatting tags, an accent or emoji made of several code points counting once. As CJK characters are
+wider, a [languages.ja] table (or [validate.languages.ja]) sets the max_lines and max_chars_per_line
+of subtitles in that language, given with --lang or in the file name (movie.ja.srt). A preset checks
+against the delivery specifications of a style guide instead, its reading speed, line length and
+duration limits being errors.
+
+With --fix, the problems that can be fixed mechanically are fixed and the result written to the
+--output, every change printed with its cue: cues out of order are sorted and every cue renumbered,
+then under the rules that are on, cues shorter than min_duration are made longer and cues that overlap
+the next one or end less than min_gap before it are cut, never beyond what leaves room for the next
+cue. What remains is then checked as usual. --renumber only numbers the cues 1, 2, 3... keeping them
+in the order of the file, as many players choke on duplicate indices. Without --fix or --renumber
+nothing is written.
+
+The exit code tells the result, to gate CI pipelines and batch scripts: 0 when no file has errors, 1
+when a file can't be read, 2 when a file has errors, and with --strict 3 when files only have warnings.
+
+    <file> = input file paths, or - for standard input, or .zip archives holding the subtitle, also given as
+        --input <file>. Mandatory
+    --preset = netflix, bbc or ebu, the style guide to check against: netflix for the Netflix English
+        timed text style guide (20 characters per second, 2 lines of 42 characters, 5/6s to 7s, 2 frames
+        apart at 24 fps, lines of 13 characters in Japanese and 16 in Chinese and Korean), bbc for the
+        BBC subtitle guidelines (180 words per minute, 2 lines of 37 characters, 1s to 7s, 1 frame apart
+        at 25 fps), ebu for EBU STL teletext subtitles (15 characters per second, 2 lines of 38
+        characters, 1s to 7s, 2 frames apart at 25 fps). Optional
+    --expect-lang = language the subtitles must be in, as de, deu or German: an error when their text is
+        mostly in another one, a warning when it is too short to tell. Optional
+    --lang = language of the subtitles, as ja or zh-TW, whose line limits replace the general ones.
+        Optional, taken from the file name (movie.ja.srt) otherwise
+    --max-cps = most characters per second a cue can be read at, without formatting tags and line breaks,
+        as 17. Optional, defaults to the rules, 25 otherwise
+    --max-wpm = most words per minute a cue can be read at, as 180. Optional, defaults to the rules, off
+        otherwise
+    --rules = TOML file of the rules to check, over the default ones or the --preset. Optional, defaults
+        to the [validate] table of the configuration file
+    --strict = exit with 3 when there are warnings but no errors. Optional, warnings exit with 0 otherwise
+    --quiet = print only the number of errors and warnings of every file. Optional
+    --json = print the report as JSON instead of text: for every file its errors, warnings, fixes and
+        issues, each issue with its rule, severity, cue, start and end times, message and suggested fix,
+        and the exit code. Optional
+    --fix = fix the problems that can be fixed mechanically and write the result to the --output. Optional
+    --renumber = number the cues 1, 2, 3... in the order of the file, changing nothing else, and write the
+        result to the --output. Optional
+    --drop-ads = remove the ads and release credits, renumber the cues left, and write the result to the
+        --output. Optional, can be given with --fix
+    --fix-overlaps = pull apart the cues that overlap, or that are closer than min_gap, and write the
+        result to the --output. Optional, can be given with --fix
+    --overlap-strategy = trim to end the earlier cue when the later one starts, split to end it and
+        start the later one in the middle of their overlap. Implies --fix-overlaps. Optional, defaults
+        to trim
+    --extend-short = make the cues shorter than the --min-duration longer, as far as the next cue lets
+        them, and write the result to the --output; the cues left too short are reported. Optional, can
+        be given with --fix
+    --min-duration = shortest duration of a cue in miliseconds, as 833, for --extend-short and the
+        min_duration rule. Optional, defaults to 1000 with --extend-short, to the rules otherwise
+    --fix-reading-speed = give the cues read faster than max_cps or max_wpm the time they need, ending
+        them later, then starting them earlier, as far as the cues around them let them, and write the
+        result to the --output; the cues left too fast are reported. Optional, can be given with --fix
+    --split-long = split the cues longer than the --max-duration or with more than --max-chars
+        characters in two, between their lines, after a sentence or a comma, or between words, each
+        half shown for a share of the time as long as its text, and write the result to the --output.
+        Optional, can be given with --fix
+    --max-duration = longest duration of a cue in miliseconds, as 7000, for --split-long and the
+        max_duration rule. Optional, defaults to the rules, 10000 otherwise
+    --max-chars = most characters a cue can hold, for --split-long. Optional, defaults to max_lines
+        times max_chars_per_line when the rules set both, no limit otherwise
+    --output = output file path, or - for standard output, with --fix, --renumber, --drop-ads,
+        --fix-overlaps, --extend-short, --split-long or --fix-reading-speed. Mandatory with them, which
+        take a single input file
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --output-encoding, --bom, --newline = how the output is written, see subsync shift --help. Optional
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single
+        subtitle file
+    --format = format of the input when the file extension doesn't tell. Optional
+    --input-encoding = text encoding of the input (shift_jis, gbk, euc-kr, windows-1256, ...). Optional,
+        detected otherwise
+    --fps = framerate used to read frame-based formats (MicroDVD, EBU STL). Optional, defaults to the
+        framerate declared by the input file
+    --default-duration = duration of cues in formats without end times (TMPlayer), in miliseconds.
+        Optional, defaults to 3000
+    -h, --help Display this help.
+";
+
+// The result of validate, as written by --json.
+#[derive(Debug, Serialize)]
+struct Report {
+    files: Vec<FileReport>,
+    errors: usize,
+    warnings: usize,
+    exit_code: i32,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct FileReport {
+    file: String,
+    // Why the file couldn't be read, in which case it has no issues.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    errors: usize,
+    warnings: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fixes: Vec<FixReport>,
+    issues: Vec<IssueReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct FixReport {
+    rule: &'static str,
+    cue: usize,
+    change: String,
+}
+
+#[derive(Debug, Serialize)]
+struct IssueReport {
+    rule: &'static str,
+    severity: &'static str,
+    cue: usize,
+    start_ms: i32,
+    end_ms: i32,
+    start: String,
+    end: String,
+    message: String,
+    suggestion: String,
+}
+
+// Create a function to write the report of a file as text: the fixes, the issues with what would fix
+// them, and how many errors and warnings are left, only the latter when quiet.
+fn to_text(report: &FileReport, quiet: bool) -> String {
+    let mut output = String::new();
+    for change in &report.fixes {
+        output.push_str(&format!(
+            "{}: fixed cue {} ({}): {}\n",
+            report.file, change.cue, change.rule, change.change
+        ));
+    }
+    if !quiet {
+        for issue in &report.issues {
+            output.push_str(&format!(
+                "{}: {}: {} --> {}: {}; {}\n",
+                report.file,
+                issue.severity,
+                issue.start,
+                issue.end,
+                issue.message,
+                issue.suggestion
+            ));
+        }
+    }
+    output.push_str(&format!(
+        "{}: {}, {}\n",
+        report.file,
+        count(report.errors, "error"),
+        count(report.warnings, "warning")
+    ));
+    output
+}
+
+// Create a function to count the issues of each severity in words, as 1 error, 3 warnings.
+fn count(issues: usize, name: &str) -> String {
+    format!("{} {}{}", issues, name, if issues == 1 { "" } else { "s" })
+}
+
+// Create a function that runs the validate subcommand.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input_files = Vec::new();
+    let mut files = FileArgs::default();
+    let mut strict = false;
+    let mut quiet = false;
+    let mut fix = false;
+    let mut renumber = false;
+    let mut drop_ads = false;
+    let mut fix_overlaps = None;
+    let mut extend_short = false;
+    let mut min_duration = None;
+    let mut split_long = false;
+    let mut fix_reading_speed = false;
+    let mut max_duration = None;
+    let mut max_chars = None;
+    let mut json = false;
+    let mut rules_file = None;
+    let mut preset = None;
+    let mut max_cps = None;
+    let mut max_wpm = None;
+    let mut language = None;
+    let mut expected_language = None;
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let mut i = 0;
+    while i < args.len() {
+        // Every --input is one more input file, in order with the others.
+        if args[i] == "--input" {
+            input_files.push(value(i)?);
+            i += 2;
+            continue;
+        }
+        let used = files.parse(args, i)?;
+        if used > 0 {
+            i += used;
+            continue;
+        } else if args[i] == "--strict" {
+            strict = true;
+        } else if args[i] == "--quiet" {
+            quiet = true;
+        } else if args[i] == "--fix" {
+            fix = true;
+        } else if args[i] == "--renumber" {
+            renumber = true;
+        } else if args[i] == "--drop-ads" {
+            drop_ads = true;
+        } else if args[i] == "--fix-overlaps" {
+            fix_overlaps = fix_overlaps.or(Some(OverlapStrategy::default()));
+        } else if args[i] == "--extend-short" {
+            extend_short = true;
+        } else if args[i] == "--fix-reading-speed" {
+            fix_reading_speed = true;
+        } else if args[i] == "--split-long" {
+            split_long = true;
+        } else if args[i] == "--min-duration" || args[i] == "--max-duration" {
+            let value = value(i)?;
+            let duration = value
+                .parse::<f64>()
+                .ok()
+                .filter(|duration| duration.is_finite() && *duration > 0.0)
+                .ok_or_else(|| format!("Invalid duration '{}'", value))?;
+            if args[i] == "--min-duration" {
+                min_duration = Some(duration);
+            } else {
+                max_duration = Some(duration);
+            }
+            i += 1;
+        } else if args[i] == "--max-chars" {
+            let value = value(i)?;
+            max_chars = Some(
+                value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|chars| *chars > 0)
+                    .ok_or_else(|| format!("Invalid number of characters '{}'", value))?,
+            );
+            i += 1;
+        } else if args[i] == "--overlap-strategy" {
+            fix_overlaps = Some(OverlapStrategy::from_name(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--json" {
+            json = true;
+        } else if args[i] == "--rules" {
+            rules_file = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--max-cps" || args[i] == "--max-wpm" {
+            let value = value(i)?;
+            let limit = value
+                .parse::<f64>()
+                .ok()
+                .filter(|limit| limit.is_finite() && *limit > 0.0)
+                .ok_or_else(|| format!("Invalid reading speed '{}'", value))?;
+            if args[i] == "--max-cps" {
+                max_cps = Some(limit);
+            } else {
+                max_wpm = Some(limit);
+            }
+            i += 1;
+        } else if args[i] == "--lang" {
+            language = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--expect-lang" {
+            let value = value(i)?;
+            expected_language = Some(
+                language::normalize(&value)
+                    .ok_or_else(|| format!("Unknown language '{}'", value))?,
+            );
+            i += 1;
+        } else if args[i] == "--preset" {
+            preset = Some(Rules::preset(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "-h" || args[i] == "--help" {
+            println!("{}", HELP);
+            return Ok(());
+        } else if args[i] == "-" || !args[i].starts_with('-') {
+            input_files.push(args[i].clone());
+        } else {
+            return Err(format!("Unknown option {}. Use --help for help.", args[i]));
+        }
+        i += 1;
+    }
+    if input_files.is_empty() {
+        return Err("No input file provided. Use --help for help.".to_string());
+    }
+    // The options that write the subtitles, the first one given named when they can't.
+    let writing = [
+        (fix, "--fix"),
+        (renumber, "--renumber"),
+        (drop_ads, "--drop-ads"),
+        (fix_overlaps.is_some(), "--fix-overlaps"),
+        (extend_short, "--extend-short"),
+        (split_long, "--split-long"),
+        (fix_reading_speed, "--fix-reading-speed"),
+    ]
+    .into_iter()
+    .find_map(|(given, option)| Some(option).filter(|_| given));
+    let write = writing.is_some();
+    if let Some(option) = writing.filter(|_| input_files.len() > 1 || files.output_file.is_empty())
+    {
+        return Err(format!(
+            "{} needs a single input file and the --output. Use --help for help.",
+            option
+        ));
+    }
+    let mut rules = match (&rules_file, preset) {
+        (Some(rules_file), preset) => config::load_rules(rules_file, preset.unwrap_or_default())?,
+        (None, Some(preset)) => preset,
+        (None, None) => config::load()?.validate,
+    };
+    rules.expected_language = expected_language.or(rules.expected_language);
+    // --extend-short makes cues at least a second long unless told otherwise, and checks that they are.
+    let min_duration = min_duration.or(Some(EXTENDED_DURATION).filter(|_| extend_short));
+    // The limits given on the command line keep the severity of the rules they change.
+    for (value, rule) in [
+        (max_cps, &mut rules.max_cps),
+        (max_wpm, &mut rules.max_wpm),
+        (min_duration, &mut rules.min_duration),
+        (max_duration, &mut rules.max_duration),
+    ] {
+        if let Some(value) = value {
+            *rule = Some(Limit {
+                value,
+                severity: rule.map_or(Severity::Warning, |rule| rule.severity),
+            });
+        }
+    }
+
+    let mut unreadable = false;
+    let mut errors = 0;
+    let mut warnings = 0;
+    let mut reports = Vec::new();
+    for input_file in &input_files {
+        let mut report = FileReport {
+            file: input_file.clone(),
+            ..FileReport::default()
+        };
+        let (mut subtitles, newline) = match read_subtitles(
+            input_file,
+            files.member.as_deref(),
+            files.format,
+            &files.options,
+            files.input_encoding,
+        ) {
+            Ok(read) => read,
+            Err(error) => {
+                if !json {
+                    eprintln!("{}: {}", input_file, error);
+                }
+                report.error = Some(error);
+                reports.push(report);
+                unreadable = true;
+                continue;
+            }
+        };
+        let language = language
+            .clone()
+            .or_else(|| validate::language_from_name(input_file));
+        let rules = rules.for_language(language.as_deref());
+        if write {
+            let mut fixes = Vec::new();
+            if drop_ads {
+                fixes.extend(validate::drop_ads(&mut subtitles));
+            }
+            let gap = rules.min_gap.map_or(0, |limit| limit.value.ceil() as i32);
+            if split_long {
+                // Without --max-chars, a cue holds as many characters as fit on its lines.
+                let max_chars = max_chars.or(rules
+                    .max_lines
+                    .zip(rules.max_chars_per_line)
+                    .map(|(lines, chars)| (lines.value * chars.value) as usize));
+                fixes.extend(validate::split_long(
+                    &mut subtitles,
+                    rules.max_duration.map(|limit| limit.value as i32),
+                    max_chars,
+                    gap,
+                ));
+            }
+            if let Some(strategy) = fix_overlaps {
+                fixes.extend(validate::fix_overlaps(&mut subtitles, strategy, gap));
+            }
+            if let Some(min_duration) = min_duration.filter(|_| extend_short) {
+                fixes.extend(validate::extend_short(
+                    &mut subtitles,
+                    min_duration.ceil() as i32,
+                    gap,
+                ));
+            }
+            if fix_reading_speed {
+                fixes.extend(validate::extend_fast(
+                    &mut subtitles,
+                    rules.max_cps.map(|limit| limit.value),
+                    rules.max_wpm.map(|limit| limit.value),
+                    gap,
+                ));
+            }
+            if fix {
+                fixes.extend(validate::fix(&mut subtitles, &rules));
+            } else if renumber {
+                fixes.extend(validate::renumber(&mut subtitles));
+            }
+            report.fixes = fixes
+                .into_iter()
+                .map(|change| FixReport {
+                    rule: change.rule,
+                    cue: change.cue,
+                    change: change.message,
+                })
+                .collect();
+            write_subtitles(
+                &subtitles,
+                &files.output_file,
+                files.format,
+                &files.write_options(newline),
+            )?;
+        }
+        report.issues = validate::check(&subtitles, &rules)
+            .into_iter()
+            .map(|issue| IssueReport {
+                rule: issue.rule(),
+                severity: issue.severity.name(),
+                cue: issue.cue,
+                start_ms: issue.start_ms,
+                end_ms: issue.end_ms,
+                start: vtt::convert_to_time(issue.start_ms),
+                end: vtt::convert_to_time(issue.end_ms),
+                message: issue.message(),
+                suggestion: issue.suggestion,
+            })
+            .collect();
+        report.errors = report
+            .issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error.name())
+            .count();
+        report.warnings = report.issues.len() - report.errors;
+        errors += report.errors;
+        warnings += report.warnings;
+        if !json {
+            // The report goes to standard error when the fixed subtitles go to standard output.
+            if write && files.output_file == "-" {
+                eprint!("{}", to_text(&report, quiet));
+            } else {
+                print!("{}", to_text(&report, quiet));
+            }
+        }
+        reports.push(report);
+    }
+    let code = if unreadable {
+        UNREADABLE
+    } else if errors > 0 {
+        ERRORS
+    } else if strict && warnings > 0 {
+        WARNINGS
+    } else {
+        0
+    };
+    if json {
+        let report = Report {
+            files: reports,
+            errors,
+            warnings,
+            exit_code: code,
+        };
+        let output = serde_json::to_string_pretty(&report)
+            .map_err(|error| format!("Unable to write the report: {}", error))?;
+        if write && files.output_file == "-" {
+            eprintln!("{}", output);
+        } else {
+            println!("{}", output);
+        }
+    }
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
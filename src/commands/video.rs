@@ -0,0 +1,350 @@
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use serde::Deserialize;
+
+// What ffprobe tells about a video file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoInfo {
+    // Framerate of the first video stream.
+    pub framerate: f64,
+    // Duration of the container, when it is known.
+    pub duration_ms: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    #[serde(default)]
+    index: usize,
+    codec_name: Option<String>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    #[serde(default)]
+    tags: ProbeTags,
+}
+
+#[derive(Default, Deserialize)]
+struct ProbeTags {
+    language: Option<String>,
+    title: Option<String>,
+}
+
+// A subtitle track of a video file, as listed by ffprobe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    // Index of the stream in the file, as ffmpeg and mkvmerge number tracks.
+    pub index: usize,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    // Whether the track holds text that can be extracted, rather than images that would need OCR.
+    pub text: bool,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+// Create a function to read a framerate as ffprobe writes it, a ratio like 24000/1001. Streams
+// without a framerate are written as 0/0.
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (numerator, denominator) = rate.split_once('/')?;
+    let framerate = numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?;
+    Some(framerate).filter(|framerate| framerate.is_finite() && *framerate > 0.0)
+}
+
+// Create a function to run one of the FFmpeg tools (ffprobe, ffmpeg), which have to be installed and on
+// the PATH, and return what it wrote to standard output.
+pub fn run_tool(tool: &str, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new(tool)
+        .args(args)
+        .output()
+        .map_err(|error| match error.kind() {
+            ErrorKind::NotFound => format!("{} was not found, install FFmpeg first", tool),
+            _ => format!("Unable to run {}: {}", tool, error),
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            tool,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+// Create a function to read the framerate and duration of a video file with ffprobe. The average
+// framerate is used, the nominal one when it is missing.
+pub fn probe_video(path: &str) -> Result<VideoInfo, String> {
+    let output = run_tool(
+        "ffprobe",
+        &[
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=avg_frame_rate,r_frame_rate:format=duration",
+            "-of",
+            "json",
+            path,
+        ],
+    )?;
+    let probe: ProbeOutput = serde_json::from_slice(&output)
+        .map_err(|error| format!("Unable to read the ffprobe output for {}: {}", path, error))?;
+    let stream = probe
+        .streams
+        .first()
+        .ok_or_else(|| format!("{} has no video stream", path))?;
+    let framerate = [&stream.avg_frame_rate, &stream.r_frame_rate]
+        .iter()
+        .find_map(|rate| rate.as_deref().and_then(parse_rate))
+        .ok_or_else(|| format!("ffprobe found no framerate in {}", path))?;
+    let duration_ms = probe
+        .format
+        .and_then(|format| format.duration)
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .map(|duration| (duration * 1000.0).round() as i32);
+    Ok(VideoInfo {
+        framerate,
+        duration_ms,
+    })
+}
+
+// Create a function to list the subtitle tracks of a video file with ffprobe.
+pub fn subtitle_tracks(path: &str) -> Result<Vec<Track>, String> {
+    let output = run_tool(
+        "ffprobe",
+        &[
+            "-v",
+            "error",
+            "-select_streams",
+            "s",
+            "-show_entries",
+            "stream=index,codec_name:stream_tags=language,title",
+            "-of",
+            "json",
+            path,
+        ],
+    )?;
+    let probe: ProbeOutput = serde_json::from_slice(&output)
+        .map_err(|error| format!("Unable to read the ffprobe output for {}: {}", path, error))?;
+    Ok(probe
+        .streams
+        .into_iter()
+        .map(|stream| {
+            let codec = stream.codec_name.unwrap_or_default();
+            Track {
+                index: stream.index,
+                text: matches!(
+                    codec.as_str(),
+                    "subrip" | "srt" | "ass" | "ssa" | "webvtt" | "mov_text" | "text" | "ttml"
+                ),
+                codec,
+                language: stream.tags.language,
+                title: stream.tags.title,
+            }
+        })
+        .collect())
+}
+
+// Where and how to mux converted subtitles into a copy of a video.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mux {
+    // The video to copy.
+    pub video: String,
+    // The new video, defaults to <video>.muxed.<ext> next to it.
+    pub output: Option<String>,
+    // Language of the new subtitle track, as an ISO 639-2 code (eng, fre, ...).
+    pub language: Option<String>,
+    pub track_name: Option<String>,
+}
+
+impl Mux {
+    // Create a function to get the path of the new video.
+    pub fn output_file(&self) -> String {
+        self.output.clone().unwrap_or_else(|| {
+            let path = Path::new(&self.video);
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("mkv");
+            format!("{}.muxed.{}", path.with_extension("").display(), extension)
+        })
+    }
+}
+
+// Create a function to tell whether a tool can be run.
+pub fn is_installed(tool: &str) -> bool {
+    Command::new(tool).arg("--version").output().is_ok()
+}
+
+// Create a function to write a copy of a video with a subtitle file added as a new track, with its
+// language and name set. Matroska files are written with mkvmerge when it is installed, others with
+// ffmpeg; the existing tracks are copied as they are.
+pub fn mux_subtitles(mux: &Mux, subtitle_file: &str, overwrite: bool) -> Result<String, String> {
+    let output_file = mux.output_file();
+    if !overwrite && Path::new(&output_file).exists() {
+        return Err(format!(
+            "{} already exists, use --overwrite to replace it",
+            output_file
+        ));
+    }
+    let extension = Path::new(&output_file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let matroska = matches!(extension.as_str(), "mkv" | "mka" | "webm");
+    let mut args: Vec<String> = Vec::new();
+    let tool = if matroska && is_installed("mkvmerge") {
+        args.extend(["-o".to_string(), output_file.clone(), mux.video.clone()]);
+        if let Some(language) = &mux.language {
+            args.extend(["--language".to_string(), format!("0:{}", language)]);
+        }
+        if let Some(name) = &mux.track_name {
+            args.extend(["--track-name".to_string(), format!("0:{}", name)]);
+        }
+        args.push(subtitle_file.to_string());
+        "mkvmerge"
+    } else {
+        // The new track comes after the subtitle tracks already in the video.
+        let track = format!("s:{}", subtitle_tracks(&mux.video)?.len());
+        let codec = if matches!(extension.as_str(), "mp4" | "m4v" | "mov") {
+            "mov_text"
+        } else {
+            "copy"
+        };
+        let codec_option = format!("-c:{}", track);
+        for arg in [
+            "-v",
+            "error",
+            "-y",
+            "-i",
+            &mux.video,
+            "-i",
+            subtitle_file,
+            "-map",
+            "0",
+            "-map",
+            "1",
+            "-c",
+            "copy",
+            &codec_option,
+            codec,
+        ] {
+            args.push(arg.to_string());
+        }
+        if let Some(language) = &mux.language {
+            args.push(format!("-metadata:s:{}", track));
+            args.push(format!("language={}", language));
+        }
+        if let Some(name) = &mux.track_name {
+            args.push(format!("-metadata:s:{}", track));
+            args.push(format!("title={}", name));
+        }
+        args.push(output_file.clone());
+        "ffmpeg"
+    };
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    run_tool(tool, &args)?;
+    Ok(output_file)
+}
+
+// How to hardcode subtitles into a copy of a video with ffmpeg's subtitles filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Burn {
+    // The video to copy.
+    pub video: String,
+    // The new video, defaults to <video>.burned.<ext> next to it.
+    pub output: Option<String>,
+    // Font family and size the text is drawn with, overriding the styles of ASS files.
+    pub font: Option<String>,
+    pub font_size: Option<u32>,
+    // Directory of font files to look for the fonts in, besides the installed fonts.
+    pub fonts_dir: Option<String>,
+}
+
+// Create a function to escape a value for an option of an ffmpeg filter, then for the filter graph it
+// is part of, so paths with colons, quotes or brackets (C:\Videos\...) pass through.
+fn escape_filter_value(value: &str) -> String {
+    let mut option = String::new();
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+    let mut graph = String::new();
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+// Create a function to quote an argument for a POSIX shell, when it needs it.
+pub fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+impl Burn {
+    // Create a function to get the path of the new video.
+    pub fn output_file(&self) -> String {
+        self.output.clone().unwrap_or_else(|| {
+            let path = Path::new(&self.video);
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("mkv");
+            format!("{}.burned.{}", path.with_extension("").display(), extension)
+        })
+    }
+
+    // Create a function to get the ffmpeg arguments that draw a subtitle file over the video. The video
+    // is encoded again, the audio copied as it is.
+    pub fn ffmpeg_args(&self, subtitle_file: &str, overwrite: bool) -> Vec<String> {
+        let mut filter = format!("subtitles=filename={}", escape_filter_value(subtitle_file));
+        let mut style = Vec::new();
+        if let Some(font) = &self.font {
+            style.push(format!("FontName={}", font));
+        }
+        if let Some(size) = self.font_size {
+            style.push(format!("FontSize={}", size));
+        }
+        if !style.is_empty() {
+            filter.push_str(&format!(
+                ":force_style={}",
+                escape_filter_value(&style.join(","))
+            ));
+        }
+        if let Some(directory) = &self.fonts_dir {
+            filter.push_str(&format!(":fontsdir={}", escape_filter_value(directory)));
+        }
+        let mut args: Vec<String> = Vec::new();
+        if overwrite {
+            args.push("-y".to_string());
+        }
+        args.extend([
+            "-i".to_string(),
+            self.video.clone(),
+            "-vf".to_string(),
+            filter,
+        ]);
+        args.extend(["-c:a".to_string(), "copy".to_string(), self.output_file()]);
+        args
+    }
+}
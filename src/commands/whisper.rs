@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::video::{is_installed, run_tool};
+use simple_sub_sync::subtitle_parser::{Format, ParseOptions, Registry, SubtitleFile};
+
+// The names whisper.cpp and OpenAI's whisper are installed under, tried in this order.
+const BINARIES: [&str; 3] = ["whisper-cli", "whisper-cpp", "whisper"];
+
+// How to run whisper to transcribe a soundtrack.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Whisper {
+    // Name or path of the binary, found on the PATH otherwise.
+    pub binary: Option<String>,
+    // Path of the ggml model file for whisper.cpp, or the model name (tiny, base, small, ...) for
+    // OpenAI's whisper.
+    pub model: Option<String>,
+    // Spoken language, as a code such as en or fr. Detected by whisper otherwise.
+    pub language: Option<String>,
+    // One cue per word instead of one per sentence.
+    pub words: bool,
+}
+
+impl Whisper {
+    // Create a function to tell whether the binary is OpenAI's Python whisper rather than whisper.cpp,
+    // from its name: their options differ.
+    fn is_openai(binary: &str) -> bool {
+        Path::new(binary)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem == "whisper")
+    }
+
+    // Create a function to run a binary.
+    fn run(binary: &str, args: &[String]) -> Result<(), String> {
+        let output = Command::new(binary)
+            .args(args)
+            .output()
+            .map_err(|error| format!("Unable to run {}: {}", binary, error))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed: {}",
+                binary,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    // Create a function to get the arguments that make a binary write the transcript of a WAV file as
+    // SRT next to it.
+    fn args(&self, binary: &str, audio: &Path) -> Result<Vec<String>, String> {
+        let audio_path = audio.display().to_string();
+        let mut args = Vec::new();
+        if Whisper::is_openai(binary) {
+            args.push(audio_path);
+            if let Some(model) = &self.model {
+                args.extend(["--model".to_string(), model.clone()]);
+            }
+            let directory = audio.parent().unwrap_or(Path::new("."));
+            args.extend([
+                "--output_format".to_string(),
+                "srt".to_string(),
+                "--output_dir".to_string(),
+                directory.display().to_string(),
+            ]);
+            if let Some(language) = &self.language {
+                args.extend(["--language".to_string(), language.clone()]);
+            }
+            if self.words {
+                args.extend(
+                    ["--word_timestamps", "True", "--max_words_per_line", "1"].map(String::from),
+                );
+            }
+        } else {
+            let model = self.model.clone().ok_or_else(|| {
+                "whisper.cpp needs --model, the path of a ggml model file such as ggml-base.bin"
+                    .to_string()
+            })?;
+            args.extend(["-m".to_string(), model, "-f".to_string(), audio_path]);
+            args.extend([
+                "-osrt".to_string(),
+                "-of".to_string(),
+                audio.with_extension("").display().to_string(),
+            ]);
+            if let Some(language) = &self.language {
+                args.extend(["-l".to_string(), language.clone()]);
+            }
+            if self.words {
+                args.extend(["-ml", "1", "-sow"].map(String::from));
+            }
+        }
+        Ok(args)
+    }
+
+    // Create a function to run whisper on a WAV file, with the first known binary name installed when
+    // none was given.
+    fn transcribe_wav(&self, audio: &Path) -> Result<(), String> {
+        let binary = match &self.binary {
+            Some(binary) => binary.clone(),
+            None => BINARIES
+                .iter()
+                .find(|binary| is_installed(binary))
+                .map(|binary| binary.to_string())
+                .ok_or_else(|| {
+                    format!(
+                        "None of {} was found, install whisper.cpp or OpenAI's whisper, or give its path with --whisper",
+                        BINARIES.join(", ")
+                    )
+                })?,
+        };
+        Whisper::run(&binary, &self.args(&binary, audio)?)
+    }
+}
+
+// Create a function to transcribe the soundtrack of a video or audio file with whisper, as a subtitle
+// file with the times whisper heard the words at. The audio is decoded to a 16 kHz WAV file with ffmpeg
+// first, as whisper.cpp requires, in a temporary directory removed afterwards.
+pub fn transcribe(path: &str, whisper: &Whisper) -> Result<SubtitleFile, String> {
+    let directory: PathBuf =
+        std::env::temp_dir().join(format!("subsync-whisper-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&directory)
+        .map_err(|error| format!("Unable to create {}: {}", directory.display(), error))?;
+    let result = (|| {
+        let audio = directory.join("audio.wav");
+        let audio_path = audio.display().to_string();
+        run_tool(
+            "ffmpeg",
+            &[
+                "-v",
+                "error",
+                "-i",
+                path,
+                "-vn",
+                "-ac",
+                "1",
+                "-ar",
+                "16000",
+                "-c:a",
+                "pcm_s16le",
+                &audio_path,
+            ],
+        )?;
+        whisper.transcribe_wav(&audio)?;
+        let transcript = audio.with_extension("srt");
+        let contents = fs::read(&transcript)
+            .map_err(|_| format!("whisper wrote no transcript of {}", path))?;
+        let mut subtitles = Registry::default()
+            .parse(&contents, Format::Srt, &ParseOptions::default())
+            .map_err(|error| format!("Unable to parse the transcript of {}: {}", path, error))?;
+        // whisper.cpp starts every line with the space that separates words in its tokens.
+        for cue in &mut subtitles.cues {
+            for line in &mut cue.lines {
+                *line = line.trim().to_string();
+            }
+        }
+        Ok(subtitles)
+    })();
+    let _ = fs::remove_dir_all(&directory);
+    result
+}
@@ -0,0 +1,4 @@
+// The subtitle parsing and conversion library behind the subsync command line tool.
+// Other programs can read and write subtitles through subtitle_parser::Registry, and add their own
+// formats by implementing subtitle_parser::SubtitleFormat.
+pub mod subtitle_parser;
@@ -1,117 +1,529 @@
-use regex::Regex;
-use std::fs::File;
-use std::io::prelude::*;
+use std::path::Path;
+
+mod commands;
+
+use commands::{
+    parse_format, parse_framerate, read_subtitles, save_subtitles, video, write_provenance,
+    write_subtitles, WriteOptions,
+};
+use encoding_rs::Encoding;
+use simple_sub_sync::subtitle_parser::encoding::{self, Newline, OutputEncoding};
+use simple_sub_sync::subtitle_parser::provenance::{Step, Transform};
+use simple_sub_sync::subtitle_parser::timing::{self, TimeRange};
+use simple_sub_sync::subtitle_parser::{compare, vtt, Format};
+use simple_sub_sync::subtitle_parser::{ParseOptions, SubtitleFile, DEFAULT_FRAMERATE};
 
 /*
 Create the main function, allowing us to run the program from the command line. The program will take four arguments:
 
-    -i = input_file.srt - Mandatory
+    -i = input_file.srt, .vtt, .sub (MicroDVD), .smi (SAMI), .ttml/.dfxp, .itt (iTT), .sbv (YouTube), .stl (EBU), .scc, .mpl/.txt (MPL2), .tmp/.txt (TMPlayer), .txt (Audacity labels), .xml (DCP Interop/SMPTE), .srv3/.ytt (YouTube), .ass/.ssa or .json - Mandatory
 
     -o = output_file.srt - Optional, defaults to Output.srt
     -if = input framerate - Optional, defaults to 29.97
     -of = output framerate - Optional, defaults to 29.97
+    -dur = default cue duration in miliseconds - Optional, defaults to 3000
+    -f = subtitle format name (srt, vtt, json, ...) - Optional
+    --member = name.srt, the subtitle to read in a .zip or .rar input - Optional
+    --input-encoding = shift_jis, gbk, windows-1256, ... - Optional, detected by default
+    -compare = comparison.ass - Optional
+    --output-encoding = utf-8, utf-8-bom, utf-16le, windows-1252, ... - Optional, defaults to utf-8
+    --overwrite - Optional
+    --bom - Optional
+    --newline = lf or crlf - Optional, defaults to the line endings of the input file
+    --after = 00:45:00, convert only the cues from this time - Optional
+    --before = 01:10:00, convert only the cues before this time - Optional
+    --snap-to-frames = 25, round times to frame boundaries - Optional
+    --pivot = 00:01:12.5 or first, the time kept in place by the conversion - Optional
+    --offset = -600ms, added after the conversion - Optional
+    --rounding = nearest, even, floor, ceil or frame, how converted times are rounded - Optional
+    --keep-durations, convert only the start times - Optional
+    --video = movie.mkv, read the output framerate and duration with ffprobe - Optional
+    --mux-into = movie.mkv, add the converted subtitles to a copy of the video - Optional
+    --mux-output, --language, --track-name = movie.subbed.mkv, eng, English - Optional
+    --no-provenance - Optional
 
     -h Display this help.
 
 */
 
-// Create a function to convert a hh:mm:ss,mmm string to miliseconds as an integer.
-fn convert_to_miliseconds(time: &str) -> i32 {
-    let re = Regex::new(r"(\d{2}):(\d{2}):(\d{2}),(\d{3})").unwrap();
-    let caps = re.captures(time).unwrap();
-    let hours = caps.get(1).unwrap().as_str().parse::<i32>().unwrap();
-    let minutes = caps.get(2).unwrap().as_str().parse::<i32>().unwrap();
-    let seconds = caps.get(3).unwrap().as_str().parse::<i32>().unwrap();
-    let miliseconds = caps.get(4).unwrap().as_str().parse::<i32>().unwrap();
-    let total_miliseconds = (hours * 3600000) + (minutes * 60000) + (seconds * 1000) + miliseconds;
-    total_miliseconds
+// Create a function to build the default output file name, next to the input file.
+fn default_output_file(input_file: &str, input_framerate: f32, output_framerate: f32) -> String {
+    let path = Path::new(input_file);
+    let output_file_name = path.with_extension("");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("srt");
+    format!(
+        "{}-{}-{}.{}",
+        output_file_name.display(),
+        input_framerate,
+        output_framerate,
+        extension
+    )
 }
 
-// Create a function to convert miliseconds to a hh:mm:ss,mmm string.
-fn convert_to_time(miliseconds: i32) -> String {
-    let hours = miliseconds / 3600000;
-    let minutes = (miliseconds - (hours * 3600000)) / 60000;
-    let seconds = (miliseconds - (hours * 3600000) - (minutes * 60000)) / 1000;
-    let miliseconds = miliseconds - (hours * 3600000) - (minutes * 60000) - (seconds * 1000);
-    let time = format!(
-        "{:02}:{:02}:{:02},{:03}",
-        hours, minutes, seconds, miliseconds
-    );
-    time
+// The time a framerate conversion is anchored at, which keeps its place while the other times are scaled.
+#[derive(Debug, Clone, Copy)]
+enum Pivot {
+    Time(i32),
+    // The start of the first cue converted, usually the first dialogue line.
+    FirstCue,
 }
 
-// Create a function to convert a timecode to a new framerate.
-fn convert_timecode(timecode: &str, input_framerate: f32, output_framerate: f32) -> String {
-    let miliseconds = convert_to_miliseconds(timecode);
-    let new_miliseconds = (miliseconds as f32 * input_framerate / output_framerate) as i32;
-    let new_timecode = convert_to_time(new_miliseconds);
-    new_timecode
+// How the legacy command writes its output, besides the output file name.
+#[derive(Debug, Default)]
+struct OutputOptions {
+    // Output framerate, defaults to 29.97.
+    framerate: Option<f32>,
+    // Path of an ASS file showing the original and converted timings.
+    compare_file: Option<String>,
+    encoding: OutputEncoding,
+    // Line endings, defaults to those of the input file.
+    newline: Option<Newline>,
+    // Replace existing output files.
+    overwrite: bool,
+    // Cues to convert, the others are left untouched.
+    range: TimeRange,
+    // Framerate whose frame boundaries the converted cues are snapped to.
+    snap_framerate: Option<f64>,
+    // Time the conversion is scaled around, defaults to the start of the range.
+    pivot: Option<Pivot>,
+    // Offset added to the converted times, in miliseconds.
+    offset: i32,
+    // Don't write the provenance of the output next to it.
+    no_provenance: bool,
+    // How converted times are rounded, by its name. Without it, times converted from the start of the
+    // video are truncated to the milisecond, others rounded to the nearest.
+    rounding: Option<String>,
+    // Scale only the start times, every cue keeping its duration.
+    keep_durations: bool,
+    // Framerate and duration of the video the subtitles are played with, as read by ffprobe.
+    video: Option<video::VideoInfo>,
+    // Video to mux the converted subtitles into a copy of.
+    mux: Option<video::Mux>,
 }
-// Create a function to regex replace all timecodes with converted timecodes in an input string.
-fn convert_timecodes(input: &str, input_framerate: f32, output_framerate: f32) -> String {
-    let re = Regex::new(r"(\d{2}):(\d{2}):(\d{2}),(\d{3})").unwrap();
-    let output = re.replace_all(input, |caps: &regex::Captures| {
-        let timecode = caps.get(0).unwrap().as_str();
-        let new_timecode = convert_timecode(timecode, input_framerate, output_framerate);
-        new_timecode
-    });
-    output.to_string()
+
+// Create a function to describe how much longer or shorter a video is than subtitles, from
+// DurationCheck::mismatch.
+fn describe_mismatch(mismatch: f64) -> String {
+    if mismatch < 0.0 {
+        format!("{:.2}% shorter", -mismatch * 100.0)
+    } else {
+        format!("{:.2}% longer", mismatch * 100.0)
+    }
 }
 
 // Create a function that Reads the input file, converts the timecodes, and writes the output file.
-fn convert_file(input_file: &str, output_file: &str, input_framerate: f32, output_framerate: f32) {
-    let mut file = File::open(input_file).expect("Unable to open file");
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .expect("Unable to read file");
-    let output = convert_timecodes(&contents, input_framerate, output_framerate);
-    let mut output_file = File::create(output_file).expect("Unable to create file");
-    output_file
-        .write_all(output.as_bytes())
-        .expect("Unable to write file");
+// Frame-based files that declare their own framerate use it unless -if is given.
+// When a comparison file is given, the original and converted timings are also written to it as ASS.
+fn convert_file(
+    input_file: &str,
+    member: Option<&str>,
+    input_encoding: Option<&'static Encoding>,
+    output_file: &str,
+    format: Option<Format>,
+    options: &ParseOptions,
+    output: &OutputOptions,
+) -> Result<(), String> {
+    let (mut subtitles, input_newline) =
+        read_subtitles(input_file, member, format, options, input_encoding)?;
+    let write_options = WriteOptions {
+        encoding: output.encoding,
+        newline: output.newline.unwrap_or(input_newline),
+        overwrite: output.overwrite,
+        snap_framerate: output.snap_framerate,
+        ..WriteOptions::default()
+    };
+    // Without a framerate from -if or the file, the length of the video tells what the subtitles were
+    // timed for: a video 4.27% longer than them is the film at 23.976 fps of subtitles timed at 25 fps.
+    let duration_check = |subtitles: &SubtitleFile| {
+        let video = output.video?;
+        let end = subtitles.cues.iter().map(|cue| cue.end_ms).max()?;
+        Some((
+            timing::DurationCheck::new(end, video.duration_ms?, video.framerate),
+            video,
+        ))
+    };
+    let input_framerate = match options.framerate.or(subtitles.framerate) {
+        Some(framerate) => framerate,
+        None => match duration_check(&subtitles) {
+            Some((check, _)) if check.conversion.is_some() => {
+                let (from, _) = check.conversion.unwrap();
+                eprintln!(
+                    "The video is {} than the subtitles, they were timed for {} fps",
+                    describe_mismatch(check.mismatch()),
+                    from
+                );
+                from
+            }
+            Some((check, video)) if check.fits() => video.framerate as f32,
+            _ => DEFAULT_FRAMERATE,
+        },
+    };
+    let output_framerate = output.framerate.unwrap_or(DEFAULT_FRAMERATE);
+    let output_file = if output_file.is_empty() {
+        default_output_file(input_file, input_framerate, output_framerate)
+    } else {
+        output_file.to_string()
+    };
+    let original = subtitles.clone();
+    // Cues inside a range are converted from its start, so they don't jump away from the cues before it.
+    let origin = match output.pivot {
+        Some(Pivot::Time(time)) => Some(time),
+        Some(Pivot::FirstCue) => subtitles
+            .cues
+            .iter()
+            .filter(|cue| output.range.contains(cue))
+            .map(|cue| cue.start_ms)
+            .min(),
+        None => output.range.after,
+    };
+    // The framerate ratio is applied first, then the offset.
+    let rounding = match &output.rounding {
+        Some(name) => Some(timing::Rounding::from_name(name, output_framerate as f64)?),
+        None => None,
+    };
+    let factor = input_framerate as f64 / output_framerate as f64;
+    subtitles.check_retiming(&output.range, factor, origin.unwrap_or(0), output.offset)?;
+    let mut shortened = 0;
+    let (cut, squeezed) = subtitles.within(&output.range, |part| {
+        if output.keep_durations {
+            let rounding = rounding.unwrap_or(timing::Rounding::Nearest);
+            shortened = part.scale_starts(factor, origin.unwrap_or(0), rounding);
+        } else {
+            match (origin, rounding) {
+                (origin, Some(rounding)) => {
+                    part.scale_rounded(factor, origin.unwrap_or(0), rounding)
+                }
+                (Some(origin), None) => part.scale(factor, origin),
+                (None, None) => part.convert_framerate(input_framerate, output_framerate),
+            }
+        }
+        part.shift(output.offset)
+    });
+    subtitles.framerate = Some(output_framerate);
+    if output.offset != 0 {
+        eprintln!(
+            "Converted from {} to {} fps, then shifted by {} ms",
+            input_framerate, output_framerate, output.offset
+        );
+    }
+    if cut > 0 {
+        eprintln!(
+            "Warning: {} cues would start before 0:00, they were cut to start at 0:00 or removed",
+            cut
+        );
+    }
+    if shortened > 0 {
+        eprintln!(
+            "Warning: {} cues would run into the next cue, they were cut at its start",
+            shortened
+        );
+    }
+    if squeezed > 0 {
+        eprintln!(
+            "Warning: {} retimed cues would overlap the cues around the range, they were cut at them or removed",
+            squeezed
+        );
+    }
+    if let Some((check, _)) = duration_check(&subtitles) {
+        if check.ends_after() {
+            eprintln!(
+                "Warning: the last cue ends at {}, after the end of the video at {}",
+                vtt::convert_to_time(check.end_ms),
+                vtt::convert_to_time(check.video_ms)
+            );
+        } else if check.ends_early() {
+            eprintln!(
+                "Warning: the last cue ends at {}, {:.1}% of the video before its end at {}",
+                vtt::convert_to_time(check.end_ms),
+                (1.0 - check.end_ms as f64 / check.video_ms as f64) * 100.0,
+                vtt::convert_to_time(check.video_ms)
+            );
+        }
+        if let Some((from, to)) = check.conversion {
+            eprintln!(
+                "Warning: the video is {} than the converted subtitles, the ratio of a {} to {} fps conversion; check the framerates",
+                describe_mismatch(check.mismatch()),
+                from,
+                to
+            );
+        }
+    }
+    if let Some(compare_file) = &output.compare_file {
+        write_subtitles(
+            &compare::comparison(&original, &subtitles),
+            compare_file,
+            Some(Format::Ass),
+            &write_options,
+        )?;
+    }
+    save_subtitles(&subtitles, &output_file, format, &write_options)?;
+    if let Some(mux) = &output.mux {
+        let muxed = video::mux_subtitles(mux, &output_file, output.overwrite)?;
+        eprintln!("Muxed {} into {}", output_file, muxed);
+    }
+    if output.no_provenance || (input_framerate == output_framerate && output.offset == 0) {
+        return Ok(());
+    }
+    let step = Step {
+        range: output.range,
+        transform: Transform::Linear {
+            factor,
+            origin: origin.unwrap_or(0),
+            offset: output.offset,
+            keep_durations: output.keep_durations,
+        },
+        moved: original.positions_within(&output.range),
+        lost: cut + shortened + squeezed,
+    };
+    write_provenance(input_file, &output_file, vec![step])
 }
 
 // Create the main function, which parses and validates arguments, and calls the convert_file function on the input file.
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+    let result = match args.get(1).map(|command| command.as_str()) {
+        Some("align") => commands::align::run(&args[2..]),
+        Some("analyze") => commands::analyze::run(&args[2..]),
+        Some("burn") => commands::burn::run(&args[2..]),
+        Some("chapters") => commands::chapters::run(&args[2..]),
+        Some("clean") => commands::clean::run(&args[2..]),
+        Some("convert-format") => commands::convert_format::run(&args[2..]),
+        Some("dropframe") => commands::dropframe::run(&args[2..]),
+        Some("edl") => commands::edl::run(&args[2..]),
+        Some("export-csv") => commands::export_csv::run(&args[2..]),
+        Some("extract") => commands::extract::run(&args[2..]),
+        Some("join") => commands::join::run(&args[2..]),
+        Some("merge-langs") => commands::merge_langs::run(&args[2..]),
+        Some("probe") => commands::probe::run(&args[2..]),
+        Some("renumber") => commands::renumber::run(&args[2..]),
+        Some("retime") => commands::retime::run(&args[2..]),
+        Some("revert") => commands::revert::run(&args[2..]),
+        Some("scale") => commands::scale::run(&args[2..]),
+        Some("shift") => commands::shift::run(&args[2..]),
+        Some("speech") => commands::speech::run(&args[2..]),
+        Some("split") => commands::split::run(&args[2..]),
+        Some("spellcheck") => commands::spellcheck::run(&args[2..]),
+        Some("sync") => commands::sync::run(&args[2..]),
+        Some("transcribe") => commands::transcribe::run(&args[2..]),
+        Some("validate") => commands::validate::run(&args[2..]),
+        _ => convert(&args),
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
+
+// Create a function that parses the framerate conversion arguments, and calls the convert_file function on the input file.
+fn convert(args: &[String]) -> Result<(), String> {
     let mut input_file = String::new();
     let mut output_file = String::new();
-    let mut input_framerate = 29.97;
-    let mut output_framerate = 29.97;
+    let mut options = ParseOptions::default();
+    let mut output = OutputOptions::default();
+    let mut member = None;
+    let mut bom = false;
+    let mut input_encoding = None;
+    let mut format = None;
     let mut help = false;
-    for i in 0..args.len() {
+    let mut after = None;
+    let mut before = None;
+    let mut video = None;
+    let mut mux = video::Mux::default();
+    let value = |i: usize| {
+        args.get(i + 1)
+            .cloned()
+            .ok_or_else(|| format!("Missing value for {}", args[i]))
+    };
+    let framerate = |i: usize| {
+        let value = value(i)?;
+        value
+            .parse::<f32>()
+            .ok()
+            .filter(|framerate| *framerate > 0.0 && framerate.is_finite())
+            .ok_or_else(|| format!("Invalid framerate '{}'", value))
+    };
+    // The first argument is the program itself.
+    let mut i = 1;
+    while i < args.len() {
         if args[i] == "-i" {
-            input_file = args[i + 1].clone();
+            input_file = value(i)?;
+            i += 1;
         } else if args[i] == "-o" {
-            output_file = args[i + 1].clone();
+            output_file = value(i)?;
+            i += 1;
         } else if args[i] == "-if" {
-            input_framerate = args[i + 1].parse::<f32>().unwrap();
+            options.framerate = Some(framerate(i)?);
+            i += 1;
         } else if args[i] == "-of" {
-            output_framerate = args[i + 1].parse::<f32>().unwrap();
+            output.framerate = Some(framerate(i)?);
+            i += 1;
+        } else if args[i] == "-dur" {
+            let duration = value(i)?;
+            options.default_duration = duration
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid duration '{}'", duration))?;
+            i += 1;
+        } else if args[i] == "--member" {
+            member = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--input-encoding" {
+            input_encoding = Some(encoding::from_label(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "-f" {
+            format = Some(parse_format(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "-compare" {
+            output.compare_file = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--output-encoding" {
+            output.encoding = OutputEncoding::from_name(&value(i)?)?;
+            i += 1;
+        } else if args[i] == "--overwrite" {
+            output.overwrite = true;
+        } else if args[i] == "--bom" {
+            bom = true;
+        } else if args[i] == "--newline" {
+            output.newline = Some(Newline::from_name(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--snap-to-frames" {
+            output.snap_framerate = Some(parse_framerate(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--no-provenance" {
+            output.no_provenance = true;
+        } else if args[i] == "--mux-into" {
+            mux.video = value(i)?;
+            i += 1;
+        } else if args[i] == "--mux-output" {
+            mux.output = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--language" {
+            mux.language = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--track-name" {
+            mux.track_name = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--video" {
+            video = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--keep-durations" {
+            output.keep_durations = true;
+        } else if args[i] == "--rounding" {
+            output.rounding = Some(value(i)?);
+            i += 1;
+        } else if args[i] == "--offset" {
+            output.offset = timing::parse_duration(&value(i)?)?;
+            i += 1;
+        } else if args[i] == "--pivot" {
+            output.pivot = Some(match value(i)?.as_str() {
+                "first" => Pivot::FirstCue,
+                time => Pivot::Time(timing::parse_duration(time)?),
+            });
+            i += 1;
+        } else if args[i] == "--after" {
+            after = Some(timing::parse_duration(&value(i)?)?);
+            i += 1;
+        } else if args[i] == "--before" {
+            before = Some(timing::parse_duration(&value(i)?)?);
+            i += 1;
         } else if args[i] == "-h" {
             help = true;
+        } else {
+            return Err(format!("Unknown option {}. Use -h for help.", args[i]));
+        }
+        i += 1;
+    }
+    output.range = TimeRange::new(after, before)?;
+    if !mux.video.is_empty() {
+        if output_file == "-" {
+            return Err("--mux-into needs an output file, not standard output".to_string());
         }
+        output.mux = Some(mux);
+    }
+    // The video the subtitles are played with is the authority on the output framerate.
+    if let Some(video) = video.filter(|_| !help) {
+        let info = video::probe_video(&video)?;
+        eprintln!(
+            "{}: {:.3} fps{}",
+            video,
+            info.framerate,
+            info.duration_ms.map_or(String::new(), |duration| format!(
+                ", {} long",
+                vtt::convert_to_time(duration)
+            ))
+        );
+        output.framerate = output.framerate.or(Some(info.framerate as f32));
+        output.video = Some(info);
     }
-    if help == true {
+    if bom {
+        output.encoding = output.encoding.with_bom();
+    }
+    if help {
         println!("
-    -i = input file path. Expect a string denoting a path to an .srt file.
-    -o = output file path. This is optional. If not provided, the program will write to a file named output.srt in the same directory as the input file.
-    -if = input framerate. Optional float, defaults to 29.97
+    -i = input file path. Expect a string denoting a path to an .srt, .vtt, .sub (MicroDVD), .smi (SAMI), .ttml/.dfxp, .itt (iTT), .sbv (YouTube), .stl (EBU), .scc, .mpl/.txt (MPL2), .tmp/.txt (TMPlayer), .txt (Audacity labels), .xml (DCP Interop/SMPTE), .srv3/.ytt (YouTube), .ass/.ssa or .json file, or an HLS playlist (.m3u8) or directory of WebVTT segments, or a .zip or .rar archive holding one of them.
+    --member = name of the subtitle file to read in an archive. Optional when the archive holds a single subtitle file
+    --input-encoding = text encoding of the input file (shift_jis, gbk, euc-kr, windows-1256, ...), for files whose encoding is guessed wrong. Optional, detected otherwise. Fails with the offsets of the bytes that aren't valid in that encoding
+    -o = output file path, or an .m3u8 path to write HLS WebVTT segments, or a .zip path to write a new archive holding the file. This is optional. If not provided, the program will write to a file named output.srt in the same directory as the input file.
+    -if = input framerate. Optional float, defaults to the framerate declared by a MicroDVD, EBU STL or SMPTE DCP file, or 29.97
     -of = output framerate. Optional float, defaults to 29.97
+    -dur = duration of cues in formats without end times (TMPlayer), in miliseconds. Optional, defaults to 3000
+    -f = format of the input and output when the file extension doesn't tell, for example json when using - for standard input or output. Optional
+    -compare = path of an .ass file to write with both timings, the original at the top in yellow and the converted one at the bottom in white, to check which is right by playing it over the video. Optional
+    --output-encoding = text encoding of the output file: utf-8, utf-8-bom, utf-16le, utf-16be, windows-1252, windows-1250, iso-8859-2, shift_jis, gbk, big5, ... Optional, defaults to utf-8. Fails if the text has characters the encoding can't represent
+    --overwrite = replace the output file if it exists. Optional, an existing file is never replaced otherwise
+    --bom = write a byte order mark at the start of UTF-8 output, which some players need. Optional
+    --newline = lf or crlf, the line endings of the output file. Optional, defaults to the line endings of the input file
+    --after = only convert the cues starting at or after this time, as 00:45:00, 2700s or 2700000ms, for example when only the part after a reel change drifts. Optional, the other cues are left untouched and the converted cues are stretched from this time
+    --before = only convert the cues starting before this time. Optional
+    --offset = offset added to every time after the framerate conversion, as -600ms, 1.5s, 1:02.5 or a timecode with its framerate (00:00:01:12@25). Optional, so \"scale by 25/23.976 then shift by -600 ms\" is -if 25 -of 23.976 --offset -600ms
+    --pivot = time the conversion is scaled around, which keeps its place: 00:01:12.5, or first for the start of the first cue (usually the first dialogue line). Optional, defaults to 00:00:00, or to --after when given, so no separate shift is needed when the first line is already in sync
+    --no-provenance = don't write the <output>.subsync.json file recording the conversion, which subsync revert uses to undo it. Optional
+    --snap-to-frames = framerate (25, 23.976 or 24000/1001) whose frame boundaries every converted start and end time is rounded to, as broadcast QC tools require. Optional
+    --rounding = how converted times are rounded to the milisecond: nearest, even (halves to the even milisecond, banker's rounding), floor, ceil, or frame for the nearest frame boundary of the output framerate, which keeps times that were on frames on frames so converting back and forth is lossless. Optional, times are otherwise truncated, or rounded to the nearest with --after or --pivot
+    --keep-durations = convert only the start times, every cue keeping its duration, for durations authored to reading-speed standards that mustn't shrink in a PAL to film conversion. Optional, cues that would run into the next one are cut at its start
+    --video = path of the video the subtitles are played with. Its framerate is read with ffprobe, which has to be installed, and used as the output framerate unless -of is given. Without -if, subtitles that end with the video are taken to be timed for its framerate, and subtitles that would once converted from or to 25 fps for that framerate. Subtitles running past the end of the video or ending well before it are reported. Optional
+    --mux-into = path of a video to add the converted subtitles to as a new track, in a copy of it written with mkvmerge for Matroska files when it is installed, or ffmpeg. Optional
+    --mux-output = path of the copy written by --mux-into. Optional, defaults to <video>.muxed.<ext> next to the video
+    --language = language of the new track, as an ISO 639-2 code such as eng or fre. Optional
+    --track-name = name of the new track, such as \"English (SDH)\". Optional
     -h Display help.
+
+    subsync convert-format --help shows how to translate between subtitle formats without retiming.
+    subsync export-csv --help shows how to export cue timings as CSV.
+    subsync probe --help shows how to report the format, encoding and contents of a subtitle file.
+    subsync analyze --help shows how to detect the framerate a subtitle file was timed for.
+    subsync validate --help shows how to check subtitle files for timing problems, with exit codes for scripts.
+    subsync spellcheck --help shows how to check the spelling of subtitles against a Hunspell dictionary.
+    subsync clean --help shows how to remove hearing-impaired annotations, correct OCR mistakes and merge duplicate or short cues.
+    subsync renumber --help shows how to number the cues one after the other, from 1 or another index.
+    subsync split --help shows how to split a subtitle file in parts at given times, for two-CD releases.
+    subsync join --help shows how to join the parts of a subtitle file, such as those of a two-CD release, with offsets.
+    subsync merge-langs --help shows how to interleave subtitles in two languages into one file, styled per language in .ass.
+    subsync shift --help shows how to move every cue earlier or later by a constant offset.
+    subsync scale --help shows how to stretch every cue time by an arbitrary factor.
+    subsync retime --help shows how to retime cues through several anchors, for releases with a different cut.
+    subsync edl --help shows how to remap subtitles across an EDL or comskip cut list.
+    subsync chapters --help shows how to shift each chapter by the offset between the chapters of two releases.
+    subsync dropframe --help shows how to convert times between drop-frame and non-drop-frame timecodes.
+    subsync extract --help shows how to dump and retime a subtitle track embedded in a video with ffmpeg.
+    subsync burn --help shows how to hardcode subtitles into a copy of a video with ffmpeg.
+    subsync speech --help shows how to find where someone speaks in the soundtrack of a video.
+    subsync sync --help shows how to synchronize subtitles against the soundtrack of a video in one go.
+    subsync transcribe --help shows how to transcribe the soundtrack of a video with whisper.
+    subsync revert --help shows how to undo a retiming done by subsync.
+    subsync align --help shows how to retime subtitles after a correctly timed file with the same text.
     ");
-    } else if input_file == "" {
+    } else if input_file.is_empty() {
         println!("No input file provided. Use -h for help.");
     } else {
-        if output_file == "" {
-            let re = Regex::new(r"(.*)\.srt").unwrap();
-            let caps = re.captures(&input_file).unwrap();
-            let output_file_name = caps.get(1).unwrap().as_str();
-            output_file = format!(
-                "{}-{}-{}.srt",
-                output_file_name, input_framerate, output_framerate
-            );
-        }
-        convert_file(&input_file, &output_file, input_framerate, output_framerate);
+        convert_file(
+            &input_file,
+            member.as_deref(),
+            input_encoding,
+            &output_file,
+            format,
+            &options,
+            &output,
+        )?;
     }
+    Ok(())
 }
@@ -0,0 +1,250 @@
+use regex::Regex;
+
+use super::dtw::DtwOptions;
+use super::provenance::Transform;
+use super::timing::warp_time;
+use super::{Cue, SubtitleFile};
+
+// Similarity two cues need to be matched by default, from 0 (nothing in common) to 1 (same text).
+pub const DEFAULT_THRESHOLD: f64 = 0.6;
+
+// How the timing of a reference file is carried over to the matched cues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignMode {
+    // Matched cues take the times of their reference cue, the others are retimed between them.
+    Transfer,
+    // Every cue is moved by the shift and scale that best fits the matched cues.
+    Fit,
+    // The text isn't compared, the timing of the cues is warped onto the reference, see dtw.
+    Timing,
+}
+
+impl AlignMode {
+    // Create a function to pick an alignment mode from its name on the command line.
+    pub fn from_name(name: &str) -> Option<AlignMode> {
+        match name {
+            "transfer" => Some(AlignMode::Transfer),
+            "fit" => Some(AlignMode::Fit),
+            "timing" => Some(AlignMode::Timing),
+            _ => None,
+        }
+    }
+}
+
+// Create a function to reduce the text of a cue to what is compared: lowercase words without
+// formatting tags, punctuation or line breaks.
+fn normalize(cue: &Cue) -> Vec<char> {
+    let tag = Regex::new(r"</?[a-zA-Z][^>]*>|\{[^}]*\}").unwrap();
+    let text = tag.replace_all(&cue.lines.join(" "), " ").to_lowercase();
+    let text: String = text
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    text.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .chars()
+        .collect()
+}
+
+// Create a function to list the character pairs of a text, sorted so two lists can be compared by merging.
+fn bigrams(text: &[char]) -> Vec<(char, char)> {
+    let mut pairs: Vec<(char, char)> = text.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    if pairs.is_empty() && text.len() == 1 {
+        pairs.push((text[0], ' '));
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+// Create a function to measure how similar two texts are, from the character pairs they share (the
+// Dice coefficient): 1 for the same text, 0 for texts without a pair in common. Reworded lines, typo
+// fixes and changed punctuation still score high.
+fn similarity(a: &[(char, char)], b: &[(char, char)]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    2.0 * shared as f64 / (a.len() + b.len()) as f64
+}
+
+// Create a function to match the cues of two files by text, keeping them in order: the matches are the
+// pairs at least as similar as the threshold that add up to the highest similarity, without two matches
+// crossing each other. Returns the positions of the matched cues in each file.
+pub fn match_cues(cues: &[Cue], reference: &[Cue], threshold: f64) -> Vec<(usize, usize)> {
+    let texts: Vec<Vec<(char, char)>> = cues.iter().map(|cue| bigrams(&normalize(cue))).collect();
+    let references: Vec<Vec<(char, char)>> = reference
+        .iter()
+        .map(|cue| bigrams(&normalize(cue)))
+        .collect();
+    let (n, m) = (texts.len(), references.len());
+    // score[i][j] is the best total similarity matching the first i cues with the first j references.
+    let mut score = vec![vec![0.0f64; m + 1]; n + 1];
+    for i in 1..=n {
+        for j in 1..=m {
+            let mut best = score[i - 1][j].max(score[i][j - 1]);
+            // Texts of very different lengths can't be similar enough, which saves comparing them.
+            let (a, b) = (texts[i - 1].len(), references[j - 1].len());
+            if 2.0 * a.min(b) as f64 >= threshold * (a + b) as f64 {
+                let pair = similarity(&texts[i - 1], &references[j - 1]);
+                if pair >= threshold {
+                    best = best.max(score[i - 1][j - 1] + pair);
+                }
+            }
+            score[i][j] = best;
+        }
+    }
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 && j > 0 {
+        if score[i][j] == score[i - 1][j] {
+            i -= 1;
+        } else if score[i][j] == score[i][j - 1] {
+            j -= 1;
+        } else {
+            matches.push((i - 1, j - 1));
+            i -= 1;
+            j -= 1;
+        }
+    }
+    matches.reverse();
+    matches
+}
+
+// Create a function to find the scale and offset that best turn a list of times into another, by
+// least squares. A single pair gives an offset alone.
+pub fn fit_linear(pairs: &[(i32, i32)]) -> (f64, f64) {
+    let count = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| *x as f64).sum::<f64>() / count;
+    let mean_y = pairs.iter().map(|(_, y)| *y as f64).sum::<f64>() / count;
+    let variance: f64 = pairs
+        .iter()
+        .map(|(x, _)| (*x as f64 - mean_x).powi(2))
+        .sum();
+    if variance == 0.0 {
+        return (1.0, mean_y - mean_x);
+    }
+    let covariance: f64 = pairs
+        .iter()
+        .map(|(x, y)| (*x as f64 - mean_x) * (*y as f64 - mean_y))
+        .sum();
+    let factor = covariance / variance;
+    (factor, mean_y - factor * mean_x)
+}
+
+impl SubtitleFile {
+    // Create a function to find the cue holding a piece of dialogue, ignoring case, punctuation,
+    // formatting and line breaks. Fails when no cue or several cues hold it.
+    pub fn find_cue(&self, text: &str) -> Result<&Cue, String> {
+        let search = |cue: &Cue| normalize(cue).into_iter().collect::<String>();
+        let needle = search(&Cue {
+            lines: vec![text.to_string()],
+            ..Default::default()
+        });
+        if needle.is_empty() {
+            return Err(format!("Nothing to search for in '{}'", text));
+        }
+        let found: Vec<&Cue> = self
+            .cues
+            .iter()
+            .filter(|cue| search(cue).contains(&needle))
+            .collect();
+        match found.as_slice() {
+            [cue] => Ok(cue),
+            [] => Err(format!("No cue says '{}'", text)),
+            _ => Err(format!(
+                "Several cues say '{}' (cues {}), give more of the line",
+                text,
+                found
+                    .iter()
+                    .map(|cue| cue.index.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    // Create a function to retime the cues after a correctly timed file with the same text, matched with
+    // match_cues. Returns the positions of the cues that didn't match any reference cue, and the
+    // transform that was fitted with AlignMode::Fit, or an error when no cue matched. Cues can end up
+    // before zero, see clamp_to_zero. AlignMode::Timing ignores the text, see align_timing_to.
+    pub fn align_to(
+        &mut self,
+        reference: &SubtitleFile,
+        mode: AlignMode,
+        threshold: f64,
+    ) -> Result<(Vec<usize>, Option<Transform>), String> {
+        if mode == AlignMode::Timing {
+            let transform = self.align_timing_to(reference, &DtwOptions::default())?;
+            return Ok((Vec::new(), Some(transform)));
+        }
+        let matches = match_cues(&self.cues, &reference.cues, threshold);
+        if matches.is_empty() {
+            return Err("No cue matches the reference, lower the threshold or check the reference is the same language".to_string());
+        }
+        let unmatched = (0..self.cues.len())
+            .filter(|i| !matches.iter().any(|(cue, _)| cue == i))
+            .collect();
+        let mut transform = None;
+        match mode {
+            AlignMode::Fit => {
+                let pairs: Vec<(i32, i32)> = matches
+                    .iter()
+                    .map(|(cue, other)| (self.cues[*cue].start_ms, reference.cues[*other].start_ms))
+                    .collect();
+                let (factor, offset) = fit_linear(&pairs);
+                let offset = offset.round() as i32;
+                self.scale(factor, 0);
+                for cue in self.cues.iter_mut() {
+                    cue.start_ms += offset;
+                    cue.end_ms += offset;
+                }
+                transform = Some(Transform::Linear {
+                    factor,
+                    origin: 0,
+                    offset,
+                    keep_durations: false,
+                });
+            }
+            AlignMode::Timing => unreachable!(),
+            AlignMode::Transfer => {
+                // The matches anchor the cues in between, as long as they keep the cues in order.
+                let mut anchors: Vec<(i32, i32)> = Vec::new();
+                for (cue, other) in &matches {
+                    let anchor = (self.cues[*cue].start_ms, reference.cues[*other].start_ms);
+                    if anchors
+                        .last()
+                        .is_none_or(|last| anchor.0 > last.0 && anchor.1 > last.1)
+                    {
+                        anchors.push(anchor);
+                    }
+                }
+                let original = self.cues.clone();
+                for (i, cue) in self.cues.iter_mut().enumerate() {
+                    match matches.iter().find(|(matched, _)| *matched == i) {
+                        Some((_, other)) => {
+                            cue.start_ms = reference.cues[*other].start_ms;
+                            cue.end_ms = reference.cues[*other].end_ms;
+                        }
+                        None => {
+                            cue.start_ms = warp_time(&anchors, original[i].start_ms);
+                            cue.end_ms = warp_time(&anchors, original[i].end_ms);
+                        }
+                    }
+                }
+            }
+        }
+        Ok((unmatched, transform))
+    }
+}
@@ -0,0 +1,382 @@
+use regex::Regex;
+
+use super::{
+    decode_text, sniff_text, tags, Capabilities, Cue, Format, ParseOptions, SubtitleFile,
+    SubtitleFormat, TagDialect,
+};
+
+const DEFAULT_HEADER: &str = "[Script Info]
+; Script generated by subsync
+ScriptType: v4.00+
+WrapStyle: 0
+ScaledBorderAndShadow: yes
+PlayResX: 384
+PlayResY: 288
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1";
+
+const EVENTS_FORMAT: &str =
+    "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+// Event fields kept in the cue settings, in the order they are written.
+const EXTRA_FIELDS: [&str; 6] = ["Layer", "Name", "MarginL", "MarginR", "MarginV", "Effect"];
+const DEFAULT_SETTINGS: &str = "0,,0,0,0,";
+
+// Create a function to convert a h:mm:ss.cc string to miliseconds as an integer.
+pub fn convert_to_miliseconds(time: &str) -> Option<i32> {
+    let re = Regex::new(r"^(\d+):(\d{2}):(\d{2})\.(\d{2})$").unwrap();
+    let caps = re.captures(time.trim())?;
+    let hours = caps.get(1)?.as_str().parse::<i32>().ok()?;
+    let minutes = caps.get(2)?.as_str().parse::<i32>().ok()?;
+    let seconds = caps.get(3)?.as_str().parse::<i32>().ok()?;
+    let centiseconds = caps.get(4)?.as_str().parse::<i32>().ok()?;
+    Some((hours * 3600000) + (minutes * 60000) + (seconds * 1000) + centiseconds * 10)
+}
+
+// Create a function to convert miliseconds to a h:mm:ss.cc string, rounding to the nearest centisecond.
+pub fn convert_to_time(miliseconds: i32) -> String {
+    let centiseconds = (miliseconds.max(0) + 5) / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centiseconds / 360000,
+        centiseconds / 6000 % 60,
+        centiseconds / 100 % 60,
+        centiseconds % 100
+    )
+}
+
+// Create a function to get the alignment of a style from the styles section of the header, as a
+// numeric keypad position. SSA (v4) alignments are converted to their ASS (v4+) equivalent.
+pub fn style_alignment(header: &str, style: &str) -> Option<u8> {
+    let mut format: Vec<&str> = Vec::new();
+    let mut legacy = false;
+    for line in header.lines() {
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("[v4 styles]") {
+            legacy = true;
+        } else if let Some(fields) = line.strip_prefix("Format:") {
+            format = fields.split(',').map(|field| field.trim()).collect();
+        } else if let Some(values) = line.strip_prefix("Style:") {
+            let values: Vec<&str> = values.split(',').map(|value| value.trim()).collect();
+            if values.first() != Some(&style) {
+                continue;
+            }
+            let position = format
+                .iter()
+                .position(|field| field.eq_ignore_ascii_case("Alignment"))?;
+            let alignment = values.get(position)?.parse::<u8>().ok()?;
+            return Some(match alignment {
+                9..=11 if legacy => alignment - 5,
+                5..=7 if legacy => alignment + 2,
+                _ => alignment,
+            });
+        }
+    }
+    None
+}
+
+// A style of the header, the fields not given being those of the default style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub name: String,
+    pub font: String,
+    pub size: f64,
+    // Color of the text as RGB.
+    pub color: u32,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+impl Style {
+    // Create a function to read a style given as font,size,color followed by bold or italic, as
+    // Arial,20,white or "Noto Sans CJK SC,16,#ffff00,italic". Fields left empty keep their default.
+    pub fn parse(name: &str, spec: &str) -> Result<Style, String> {
+        let mut style = Style {
+            name: name.to_string(),
+            font: "Arial".to_string(),
+            size: 20.0,
+            color: 0xffffff,
+            bold: false,
+            italic: false,
+        };
+        let mut fields = spec.split(',').map(str::trim);
+        if let Some(font) = fields.next().filter(|font| !font.is_empty()) {
+            style.font = font.to_string();
+        }
+        if let Some(size) = fields.next().filter(|size| !size.is_empty()) {
+            style.size = size
+                .parse::<f64>()
+                .ok()
+                .filter(|size| size.is_finite() && *size > 0.0)
+                .ok_or_else(|| format!("Invalid font size '{}'", size))?;
+        }
+        if let Some(color) = fields.next().filter(|color| !color.is_empty()) {
+            style.color =
+                tags::parse_color(color).ok_or_else(|| format!("Unknown color '{}'", color))?;
+        }
+        for flag in fields {
+            match flag.to_lowercase().as_str() {
+                "bold" => style.bold = true,
+                "italic" => style.italic = true,
+                _ => return Err(format!("Unknown style '{}', expected bold or italic", flag)),
+            }
+        }
+        Ok(style)
+    }
+
+    // Create a function to write the style as a Style line of the [V4+ Styles] section.
+    fn line(&self) -> String {
+        let (r, g, b) = (
+            self.color >> 16,
+            (self.color >> 8) & 0xff,
+            self.color & 0xff,
+        );
+        let flag = |set: bool| if set { -1 } else { 0 };
+        format!(
+            "Style: {},{},{},&H00{:02X}{:02X}{:02X},&H000000FF,&H00000000,&H00000000,{},{},0,0,100,100,0,0,1,2,2,2,10,10,10,1",
+            self.name,
+            self.font,
+            self.size,
+            b,
+            g,
+            r,
+            flag(self.bold),
+            flag(self.italic)
+        )
+    }
+}
+
+// Create a function to write the header subsync writes .ass files with, with the given styles instead
+// of the default one.
+pub fn header(styles: &[Style]) -> String {
+    let (header, _) = DEFAULT_HEADER.rsplit_once('\n').unwrap_or_default();
+    let mut header = header.to_string();
+    for style in styles {
+        header.push('\n');
+        header.push_str(&style.line());
+    }
+    header
+}
+
+// Create a function to parse the contents of an .ass or .ssa file.
+// The sections before [Events] are kept as the header, so styles survive a round trip.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let events = lines
+        .iter()
+        .position(|line| line.trim().eq_ignore_ascii_case("[events]"))
+        .ok_or_else(|| "Missing [Events] section".to_string())?;
+    let header = lines[..events].join("\n").trim_end().to_string();
+    let mut format: Vec<String> = EVENTS_FORMAT["Format:".len()..]
+        .split(',')
+        .map(|field| field.trim().to_string())
+        .collect();
+    let mut cues = Vec::new();
+    let mut comments = Vec::new();
+    let mut trailer = Vec::new();
+    for (i, line) in lines.iter().enumerate().skip(events + 1) {
+        if line.trim().starts_with('[') {
+            trailer.push(lines[i..].join("\n").trim_end().to_string());
+            break;
+        }
+        let Some((kind, values)) = line.split_once(':') else {
+            continue;
+        };
+        match kind.trim() {
+            "Format" => {
+                format = values
+                    .split(',')
+                    .map(|field| field.trim().to_string())
+                    .collect();
+            }
+            "Dialogue" => {
+                let index = cues.len() + 1;
+                let values: Vec<&str> = values.trim_start().splitn(format.len(), ',').collect();
+                let field = |name: &str| {
+                    format
+                        .iter()
+                        .position(|field| field.eq_ignore_ascii_case(name))
+                        .and_then(|position| values.get(position))
+                        .map(|value| value.trim())
+                };
+                let time = |name: &str| {
+                    let value = field(name).unwrap_or("");
+                    convert_to_miliseconds(value)
+                        .ok_or_else(|| format!("Cue {}: invalid timestamp '{}'", index, value))
+                };
+                let text = values.last().copied().unwrap_or("");
+                let settings: Vec<&str> = EXTRA_FIELDS
+                    .iter()
+                    .map(|name| match field(name) {
+                        Some(value) => value,
+                        None if *name == "Name" || *name == "Effect" => "",
+                        None => "0",
+                    })
+                    .collect();
+                cues.push(Cue {
+                    index,
+                    start_ms: time("Start")?,
+                    end_ms: time("End")?,
+                    settings: Some(settings.join(",")),
+                    style: field("Style").map(|style| style.to_string()),
+                    lines: text.split("\\N").map(|line| line.to_string()).collect(),
+                    comments: std::mem::take(&mut comments),
+                    ..Default::default()
+                });
+            }
+            _ => comments.push(line.to_string()),
+        }
+    }
+    trailer.splice(0..0, comments);
+    Ok(SubtitleFile {
+        format: Format::Ass,
+        framerate: None,
+        header: vec![header],
+        cues,
+        trailer,
+    })
+}
+
+// Create a function to write the cues out as .ass.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let is_ass = file.format == Format::Ass;
+    let mut output = match file.header.first() {
+        Some(header) if is_ass => header.clone(),
+        _ => DEFAULT_HEADER.to_string(),
+    };
+    output.push_str("\n\n[Events]\n");
+    output.push_str(EVENTS_FORMAT);
+    output.push('\n');
+    for cue in &file.cues {
+        if is_ass {
+            for comment in &cue.comments {
+                output.push_str(comment);
+                output.push('\n');
+            }
+        }
+        let settings = match &cue.settings {
+            Some(settings) if is_ass => settings.as_str(),
+            _ => DEFAULT_SETTINGS,
+        };
+        let (layer, rest) = settings.split_once(',').unwrap_or(("0", ",0,0,0,"));
+        let mut lines = cue.lines.clone();
+        if let (Format::Vtt, Some(alignment)) = (file.format, file.cue_alignment(cue)) {
+            if let Some(first) = lines.first_mut() {
+                *first = format!("{{\\an{}}}{}", alignment, first);
+            }
+        }
+        output.push_str(&format!(
+            "Dialogue: {},{},{},{},{},{}\n",
+            layer,
+            convert_to_time(cue.start_ms),
+            convert_to_time(cue.end_ms),
+            cue.style.as_deref().filter(|_| is_ass).unwrap_or("Default"),
+            rest,
+            lines.join("\\N")
+        ));
+    }
+    for block in file.trailer.iter().filter(|_| is_ass) {
+        // Trailing Comment lines follow the events, other sections ([Fonts], ...) get a blank line.
+        if block.starts_with('[') {
+            output.push('\n');
+        }
+        output.push_str(block);
+        output.push('\n');
+    }
+    output
+}
+
+// The Advanced SubStation Alpha format, as registered in the format registry.
+pub struct Ass;
+
+impl SubtitleFormat for Ass {
+    fn format(&self) -> Format {
+        Format::Ass
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["ass", "ssa"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["ass", "ssa"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: true,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn tag_dialect(&self) -> TagDialect {
+        TagDialect::Ass
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        sniff_text(contents).starts_with("[Script Info]")
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASS: &str = "[Script Info]\nScriptType: v4.00+\n\n[V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\nStyle: Top,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,8,10,10,10,1\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nComment: 0,0:00:00.00,0:00:00.00,Top,,0,0,0,,A note\nDialogue: 1,0:00:01.00,0:00:02.50,Top,Bob,0,0,0,,{\\i1}Hello{\\i0}, there\\NSecond, line\nDialogue: 0,1:00:00.00,1:00:01.99,Default,,0,0,0,,Later\n\n[Fonts]\nfontname: x.ttf\n";
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(ASS).unwrap();
+        assert_eq!(file.cues.len(), 2);
+        assert_eq!(file.cues[0].style.as_deref(), Some("Top"));
+        assert_eq!(file.cues[0].settings.as_deref(), Some("1,Bob,0,0,0,"));
+        assert_eq!(
+            file.cues[0].lines,
+            ["{\\i1}Hello{\\i0}, there", "Second, line"]
+        );
+        assert_eq!(
+            (file.cues[1].start_ms, file.cues[1].end_ms),
+            (3600000, 3601990)
+        );
+        assert_eq!(serialize(&file), ASS);
+    }
+
+    #[test]
+    fn styles_give_their_alignment() {
+        let file = parse(ASS).unwrap();
+        assert_eq!(style_alignment(&file.header[0], "Top"), Some(8));
+        assert_eq!(style_alignment(&file.header[0], "Missing"), None);
+        // SSA numbers the top row 5 to 7, ASS 7 to 9.
+        let ssa = "[V4 Styles]\nFormat: Name, Alignment\nStyle: Top, 6\n";
+        assert_eq!(style_alignment(ssa, "Top"), Some(8));
+    }
+
+    #[test]
+    fn times_are_rounded_to_centiseconds() {
+        assert_eq!(convert_to_time(1004), "0:00:01.00");
+        assert_eq!(convert_to_time(1005), "0:00:01.01");
+        assert_eq!(convert_to_time(36000000), "10:00:00.00");
+        assert_eq!(convert_to_miliseconds("0:00:01.5"), None);
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        assert!(
+            parse("[Script Info]\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Text\n")
+                .is_err()
+        );
+        assert!(parse("[Events]\nDialogue: 0,0:00:01,0:00:02.00,Default,,0,0,0,,Text\n").is_err());
+    }
+}
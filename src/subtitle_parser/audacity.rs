@@ -0,0 +1,133 @@
+use regex::Regex;
+
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+// Create a function to convert a time in seconds, as written by Audacity, to miliseconds.
+fn convert_to_miliseconds(time: &str) -> Option<i32> {
+    let seconds = time.trim().parse::<f64>().ok()?;
+    Some((seconds * 1000.0).round() as i32)
+}
+
+// Create a function to parse an Audacity label track: start, end and text separated by tabs, with times
+// in seconds. Lines starting with '\' hold the frequency range of spectral labels and are skipped.
+// Labels only have one line of text, so '|' separates the lines of a cue.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let mut cues = Vec::new();
+    for line in contents.lines() {
+        if line.trim().is_empty() || line.starts_with('\\') {
+            continue;
+        }
+        let mut fields = line.splitn(3, '\t');
+        let start = fields.next().unwrap_or("");
+        let end = fields
+            .next()
+            .ok_or_else(|| format!("Invalid label line '{}'", line))?;
+        let text = fields.next().unwrap_or("");
+        let index = cues.len() + 1;
+        let start_ms = convert_to_miliseconds(start)
+            .ok_or_else(|| format!("Label {}: invalid time '{}'", index, start))?;
+        let end_ms = convert_to_miliseconds(end)
+            .ok_or_else(|| format!("Label {}: invalid time '{}'", index, end))?;
+        cues.push(Cue {
+            index,
+            start_ms,
+            end_ms,
+            lines: text.split('|').map(|line| line.to_string()).collect(),
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::Audacity,
+        framerate: None,
+        header: Vec::new(),
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to write the cues out as an Audacity label track.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let mut output = String::new();
+    for cue in &file.cues {
+        output.push_str(&format!(
+            "{:.6}\t{:.6}\t{}\n",
+            cue.start_ms as f64 / 1000.0,
+            cue.end_ms as f64 / 1000.0,
+            cue.lines.join("|")
+        ));
+    }
+    output
+}
+
+// The Audacity label track format, as registered in the format registry.
+pub struct Audacity;
+
+impl SubtitleFormat for Audacity {
+    fn format(&self) -> Format {
+        Format::Audacity
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["audacity", "labels"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: false,
+            positions: false,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        Regex::new(r"^\d+(\.\d+)?\t\d+(\.\d+)?(\t|\r?\n|$)")
+            .unwrap()
+            .is_match(&sniff_text(contents))
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS: &str = "1.000000\t2.500000\tHello.\n3600.123000\t3601.000000\tTwo|lines\n";
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(LABELS).unwrap();
+        assert_eq!(
+            (file.cues[1].start_ms, file.cues[1].end_ms),
+            (3600123, 3601000)
+        );
+        assert_eq!(file.cues[1].lines, ["Two", "lines"]);
+        assert_eq!(serialize(&file), LABELS);
+    }
+
+    #[test]
+    fn frequency_lines_are_skipped() {
+        let file = parse("1.5\t2\tA\n\\\t100.0\t2000.0\n").unwrap();
+        assert_eq!(file.cues.len(), 1);
+        assert_eq!((file.cues[0].start_ms, file.cues[0].end_ms), (1500, 2000));
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert!(parse("1.0 2.0 Text\n").is_err());
+        assert!(parse("1.0\tlater\tText\n").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use regex::Regex;
+
+use super::segments::Segment;
+use super::timing::{parse_duration, TimeRange};
+
+// A chapter of a release, as read from its chapter file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub name: Option<String>,
+    pub start_ms: i32,
+}
+
+// Create a function to read Matroska chapters XML (mkvextract chapters): the ChapterTimeStart and first
+// ChapterString of every ChapterAtom, nested chapters included.
+fn parse_xml(text: &str) -> Result<Vec<Chapter>, String> {
+    let start = Regex::new(r"<ChapterTimeStart>\s*([^<]*?)\s*</ChapterTimeStart>").unwrap();
+    let name = Regex::new(r"<ChapterString>([^<]*)</ChapterString>").unwrap();
+    text.split("<ChapterAtom")
+        .skip(1)
+        .filter_map(|atom| {
+            let time = start.captures(atom)?;
+            Some(parse_duration(&time[1]).map(|start_ms| Chapter {
+                name: name.captures(atom).map(|name| name[1].trim().to_string()),
+                start_ms,
+            }))
+        })
+        .collect()
+}
+
+// Create a function to read OGM chapters text: CHAPTER01=00:00:00.000 lines, each followed by an
+// optional CHAPTER01NAME=Intro line.
+fn parse_ogm(text: &str) -> Result<Vec<Chapter>, String> {
+    let start = Regex::new(r"^CHAPTER(\d+)=(.+)$").unwrap();
+    let name = Regex::new(r"^CHAPTER(\d+)NAME=(.*)$").unwrap();
+    let mut chapters: Vec<(String, Chapter)> = Vec::new();
+    for line in text.lines().map(|line| line.trim()) {
+        if let Some(caps) = name.captures(line) {
+            if let Some((_, chapter)) = chapters.iter_mut().find(|(number, _)| *number == caps[1]) {
+                chapter.name = Some(caps[2].trim().to_string());
+            }
+        } else if let Some(caps) = start.captures(line) {
+            let chapter = Chapter {
+                name: None,
+                start_ms: parse_duration(&caps[2])?,
+            };
+            chapters.push((caps[1].to_string(), chapter));
+        }
+    }
+    Ok(chapters.into_iter().map(|(_, chapter)| chapter).collect())
+}
+
+// Create a function to read a chapter file, Matroska chapters XML or OGM chapters text, as a list of
+// chapters in time order.
+pub fn parse_chapters(text: &str) -> Result<Vec<Chapter>, String> {
+    let mut chapters = if text.contains("<Chapters") || text.contains("<ChapterAtom") {
+        parse_xml(text)?
+    } else {
+        parse_ogm(text)?
+    };
+    if chapters.is_empty() {
+        return Err("No chapter found".to_string());
+    }
+    chapters.sort_by_key(|chapter| chapter.start_ms);
+    Ok(chapters)
+}
+
+// Create a function to pair the chapters of two releases: by name when every chapter has a distinct
+// name found in both, by position otherwise, which needs the same number of chapters.
+fn pair_chapters<'a>(
+    source: &'a [Chapter],
+    target: &'a [Chapter],
+) -> Result<Vec<(&'a Chapter, &'a Chapter)>, String> {
+    let named = |chapters: &[Chapter]| {
+        let names: Vec<&str> = chapters
+            .iter()
+            .filter_map(|chapter| chapter.name.as_deref())
+            .collect();
+        names.len() == chapters.len()
+            && names
+                .iter()
+                .enumerate()
+                .all(|(i, name)| !names[..i].contains(name))
+    };
+    if named(source) && named(target) {
+        let pairs: Vec<(&Chapter, &Chapter)> = source
+            .iter()
+            .filter_map(|chapter| {
+                target
+                    .iter()
+                    .find(|other| other.name == chapter.name)
+                    .map(|other| (chapter, other))
+            })
+            .collect();
+        if pairs.len() == source.len() {
+            return Ok(pairs);
+        }
+    }
+    if source.len() != target.len() {
+        return Err(format!(
+            "The source has {} chapters and the target {}, and their names don't match",
+            source.len(),
+            target.len()
+        ));
+    }
+    Ok(source.iter().zip(target.iter()).collect())
+}
+
+// Create a function to compute the segments that move each chapter of a source release to where the
+// same chapter starts in the target release: the cues of a chapter all move by the difference between
+// its two start times.
+pub fn chapter_segments(source: &[Chapter], target: &[Chapter]) -> Result<Vec<Segment>, String> {
+    let pairs = pair_chapters(source, target)?;
+    Ok(pairs
+        .iter()
+        .enumerate()
+        .map(|(i, (chapter, other))| Segment {
+            range: TimeRange {
+                after: if i == 0 { None } else { Some(chapter.start_ms) },
+                before: pairs.get(i + 1).map(|(next, _)| next.start_ms),
+            },
+            offset: other.start_ms - chapter.start_ms,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = "<?xml version=\"1.0\"?>
+<Chapters>
+  <EditionEntry>
+    <ChapterAtom>
+      <ChapterTimeStart>00:00:00.000000000</ChapterTimeStart>
+      <ChapterDisplay><ChapterString>Intro</ChapterString></ChapterDisplay>
+    </ChapterAtom>
+    <ChapterAtom>
+      <ChapterTimeStart>00:05:30.500000000</ChapterTimeStart>
+      <ChapterDisplay><ChapterString>Heist</ChapterString></ChapterDisplay>
+    </ChapterAtom>
+  </EditionEntry>
+</Chapters>
+";
+
+    const OGM: &str = "CHAPTER01=00:00:00.000
+CHAPTER01NAME=Intro
+CHAPTER02=00:06:00.500
+CHAPTER02NAME=Heist
+";
+
+    fn chapter(name: Option<&str>, start_ms: i32) -> Chapter {
+        Chapter {
+            name: name.map(str::to_string),
+            start_ms,
+        }
+    }
+
+    #[test]
+    fn xml_and_ogm_chapters_are_read() {
+        assert_eq!(
+            parse_chapters(XML).unwrap(),
+            [chapter(Some("Intro"), 0), chapter(Some("Heist"), 330500)]
+        );
+        assert_eq!(
+            parse_chapters(OGM).unwrap(),
+            [chapter(Some("Intro"), 0), chapter(Some("Heist"), 360500)]
+        );
+    }
+
+    #[test]
+    fn each_chapter_moves_to_its_start_in_the_target() {
+        let source = parse_chapters(XML).unwrap();
+        let target = parse_chapters(OGM).unwrap();
+        let segments = chapter_segments(&source, &target).unwrap();
+        assert_eq!(
+            segments,
+            [
+                Segment {
+                    range: TimeRange {
+                        after: None,
+                        before: Some(330500)
+                    },
+                    offset: 0
+                },
+                Segment {
+                    range: TimeRange {
+                        after: Some(330500),
+                        before: None
+                    },
+                    offset: 30000
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn chapters_are_paired_by_name_then_by_position() {
+        let source = [chapter(Some("Intro"), 0), chapter(Some("Heist"), 100000)];
+        let target = [chapter(Some("Heist"), 130000), chapter(Some("Intro"), 0)];
+        assert_eq!(chapter_segments(&source, &target).unwrap()[1].offset, 30000);
+        let unnamed = [chapter(None, 0), chapter(None, 130000)];
+        assert_eq!(
+            chapter_segments(&source, &unnamed).unwrap()[1].offset,
+            30000
+        );
+    }
+
+    #[test]
+    fn malformed_and_unmatched_chapters_are_errors() {
+        assert!(parse_chapters("").is_err());
+        assert!(parse_chapters("CHAPTER01=later\n").is_err());
+        assert!(parse_chapters("<Chapters><ChapterAtom><ChapterTimeStart>x</ChapterTimeStart></ChapterAtom></Chapters>").is_err());
+        let source = [chapter(None, 0)];
+        let target = [chapter(None, 0), chapter(None, 1000)];
+        assert!(chapter_segments(&source, &target).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use regex::Regex;
+
+use super::{tags, Registry, SubtitleFile, TagDialect};
+
+// The colors a teletext decoder can show, white being the default text color.
+const TELETEXT_COLORS: [&str; 7] = ["white", "yellow", "cyan", "lime", "magenta", "red", "blue"];
+
+// What to do with <font color> tags, as found on nearly every line of subtitles ripped from teletext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorPolicy {
+    // Leave the colors as they are.
+    Keep,
+    // Replace each color by the closest teletext color name, and drop white, the default color.
+    Normalize,
+    // Remove the colors.
+    Strip,
+    // Remove the colors, and label lines with the speaker their color stands for (SDH).
+    Speakers,
+}
+
+impl ColorPolicy {
+    // Create a function to pick a color policy from its name on the command line.
+    pub fn from_name(name: &str) -> Option<ColorPolicy> {
+        match name {
+            "keep" => Some(ColorPolicy::Keep),
+            "normalize" => Some(ColorPolicy::Normalize),
+            "strip" => Some(ColorPolicy::Strip),
+            "speakers" => Some(ColorPolicy::Speakers),
+            _ => None,
+        }
+    }
+}
+
+// Create a function to find the teletext color closest to an RGB color.
+fn nearest_teletext_color(rgb: u32) -> &'static str {
+    let channels = |rgb: u32| {
+        [
+            (rgb >> 16) as i32,
+            ((rgb >> 8) & 0xff) as i32,
+            (rgb & 0xff) as i32,
+        ]
+    };
+    let target = channels(rgb);
+    TELETEXT_COLORS
+        .iter()
+        .min_by_key(|name| {
+            let color = channels(tags::parse_color(name).unwrap());
+            (0..3).map(|i| (color[i] - target[i]).pow(2)).sum::<i32>()
+        })
+        .unwrap()
+}
+
+// Create a function to read a speaker mapping given on the command line, such as yellow=ANNA.
+// The color can be a name or a #rrggbb value, and matches every color closest to the same teletext color.
+pub fn parse_speaker(mapping: &str) -> Result<(&'static str, String), String> {
+    let (color, name) = mapping
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid speaker '{}', expected <color>=<name>", mapping))?;
+    let rgb = tags::parse_color(color).ok_or_else(|| format!("Unknown color '{}'", color))?;
+    Ok((nearest_teletext_color(rgb), name.trim().to_string()))
+}
+
+// Create a function to rewrite the color of every <font> tag of a line. The callback gets the tag's
+// color and returns the color to write, or None to remove it. Font tags left without attributes are
+// removed along with their closing tag.
+fn rewrite_fonts(line: &str, mut rewrite: impl FnMut(Option<u32>) -> Option<String>) -> String {
+    let tag = Regex::new(r"(?i)<(/?)font\b([^>]*)>").unwrap();
+    let color = Regex::new(r#"(?i)\s*color\s*=\s*("[^"]*"|'[^']*'|[#\w]+)"#).unwrap();
+    let mut kept = Vec::new();
+    tag.replace_all(line, |caps: &regex::Captures| {
+        if &caps[1] == "/" {
+            return match kept.pop() {
+                Some(false) => String::new(),
+                _ => caps[0].to_string(),
+            };
+        }
+        let value = color
+            .captures(&caps[2])
+            .and_then(|value| tags::parse_color(value[1].trim_matches('\'')));
+        let attributes = color.replace_all(&caps[2], "").to_string();
+        let attributes = match rewrite(value) {
+            Some(value) => format!(" color=\"{}\"{}", value, attributes),
+            None => attributes,
+        };
+        kept.push(!attributes.trim().is_empty());
+        if attributes.trim().is_empty() {
+            String::new()
+        } else {
+            format!("<font{}>", attributes)
+        }
+    })
+    .to_string()
+}
+
+impl SubtitleFile {
+    // Create a function to apply a color policy to the inline colors of the cues. With
+    // ColorPolicy::Speakers, a line whose color maps to a speaker is prefixed with "NAME: " when the
+    // speaker changes; colors without a speaker lose their color without a label.
+    pub fn apply_color_policy(&mut self, policy: ColorPolicy, speakers: &[(&'static str, String)]) {
+        if policy == ColorPolicy::Keep {
+            return;
+        }
+        // Colors are handled as <font color> tags, other dialects are translated back and forth.
+        let dialect = Registry::default()
+            .get(self.format)
+            .map_or(TagDialect::Html, |format| format.tag_dialect());
+        let mut current: Option<&str> = None;
+        for cue in self.cues.iter_mut() {
+            for line in cue.lines.iter_mut() {
+                let html = tags::translate(line, dialect, TagDialect::Html);
+                let mut first = None;
+                let mut text = rewrite_fonts(&html, |rgb| {
+                    let name = rgb.map(nearest_teletext_color);
+                    if first.is_none() {
+                        first = Some(name.unwrap_or("white"));
+                    }
+                    match policy {
+                        ColorPolicy::Normalize => {
+                            name.filter(|name| *name != "white").map(String::from)
+                        }
+                        _ => None,
+                    }
+                });
+                if policy == ColorPolicy::Speakers && !text.trim().is_empty() {
+                    let color = first.unwrap_or("white");
+                    let speaker = speakers
+                        .iter()
+                        .find(|(speaker, _)| *speaker == color)
+                        .map(|(_, name)| name.as_str());
+                    if let Some(name) = speaker.filter(|_| speaker != current) {
+                        text = format!("{}: {}", name, text.trim_start());
+                    }
+                    current = speaker;
+                }
+                *line = tags::translate(&text, TagDialect::Html, dialect);
+            }
+        }
+    }
+}
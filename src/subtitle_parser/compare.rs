@@ -0,0 +1,54 @@
+use super::{Cue, Format, SubtitleFile};
+
+// Styles of the comparison file: the original timing at the top in yellow, the converted timing at the
+// bottom in white.
+const HEADER: &str = "[Script Info]
+; Timing comparison generated by subsync
+ScriptType: v4.00+
+WrapStyle: 0
+ScaledBorderAndShadow: yes
+PlayResX: 384
+PlayResY: 288
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Original,Arial,18,&H0000FFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,2,1,8,10,10,10,1
+Style: Converted,Arial,18,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,2,1,2,10,10,10,1";
+
+// Create a function to copy the cues of a file as ASS events in the given style. Inline styling and
+// positioning are removed, so the style alone tells the two timings apart.
+fn styled_cues(file: &SubtitleFile, style: &str) -> Vec<Cue> {
+    let mut file = file.clone();
+    file.strip_styles();
+    file.strip_positions();
+    file.cues
+        .into_iter()
+        .map(|cue| Cue {
+            start_ms: cue.start_ms,
+            end_ms: cue.end_ms,
+            style: Some(style.to_string()),
+            settings: Some("0,,0,0,0,".to_string()),
+            lines: cue.lines,
+            ..Default::default()
+        })
+        .collect()
+}
+
+// Create a function to build an ASS file showing two timings of the same subtitles at once: the
+// original cues at the top of the screen in yellow, the converted cues at the bottom in white. Playing
+// it over the video shows which timing matches the speech.
+pub fn comparison(original: &SubtitleFile, converted: &SubtitleFile) -> SubtitleFile {
+    let mut cues = styled_cues(original, "Original");
+    cues.extend(styled_cues(converted, "Converted"));
+    cues.sort_by_key(|cue| cue.start_ms);
+    for (i, cue) in cues.iter_mut().enumerate() {
+        cue.index = i + 1;
+    }
+    SubtitleFile {
+        format: Format::Ass,
+        framerate: None,
+        header: vec![HEADER.to_string()],
+        cues,
+        trailer: Vec::new(),
+    }
+}
@@ -0,0 +1,362 @@
+// Finds how subtitles are off from a soundtrack, by sliding the cues over the speech intervals found by
+// voice activity detection (see vad) and keeping the offset and framerate ratio under which the cues
+// cover the most speech and the least silence.
+
+// Resolution of the search, in miliseconds.
+const STEP_MS: i32 = 20;
+
+// The framerate ratios tried besides 1, both ways: PAL speedup (25/23.976, 25/24), 24/23.976 and
+// 30/25 conversions.
+const RATIOS: [f64; 4] = [
+    25.0 / (24000.0 / 1001.0),
+    25.0 / 24.0,
+    24.0 / (24000.0 / 1001.0),
+    1.2,
+];
+
+// Offsets smaller than this are taken for subtitles already in sync, in miliseconds.
+const IN_SYNC_MS: i32 = 100;
+
+// Windows whose offsets spread over more than this are taken for a different cut, in miliseconds.
+const JUMP_MS: i32 = 500;
+
+// How far the search goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorrelateOptions {
+    // Largest offset tried either way, in miliseconds.
+    pub max_offset_ms: i32,
+    // Try the common framerate ratios, not only 1.
+    pub drift: bool,
+    // Length of the windows of the per-window breakdown, in miliseconds.
+    pub window_ms: i32,
+}
+
+impl Default for CorrelateOptions {
+    fn default() -> CorrelateOptions {
+        CorrelateOptions {
+            max_offset_ms: 60_000,
+            drift: true,
+            window_ms: 600_000,
+        }
+    }
+}
+
+// The best offset of the cues of one window of the file, with the ratio of the whole file applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub start_ms: i32,
+    pub end_ms: i32,
+    pub cues: usize,
+    // None when the window has too few cues to tell.
+    pub offset: Option<i32>,
+    pub coverage: f64,
+}
+
+// What the correlation found: times should become time * ratio + offset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correlation {
+    pub ratio: f64,
+    pub offset: i32,
+    // Share of the cue time that falls on speech once corrected, from 0 to 1.
+    pub coverage: f64,
+    // How much better the best offset is than the best one more than 2 seconds away, from 0 to 1.
+    pub confidence: f64,
+    pub windows: Vec<Window>,
+}
+
+impl Correlation {
+    // Create a function to tell whether the windows disagree on the offset by more than a tolerance,
+    // which a single offset and ratio can't fix (a different cut, or a reel change).
+    pub fn is_non_linear(&self, tolerance_ms: i32) -> bool {
+        let offsets: Vec<i32> = self
+            .windows
+            .iter()
+            .filter_map(|window| window.offset)
+            .collect();
+        match (offsets.iter().min(), offsets.iter().max()) {
+            (Some(min), Some(max)) => max - min > tolerance_ms,
+            _ => false,
+        }
+    }
+
+    // Create a function to classify how the cues are off: a constant offset, a drift from a framerate
+    // mismatch, or jumps between parts of the file, which get an anchor in the middle of every window
+    // with an offset of its own.
+    pub fn misalignment(&self) -> Misalignment {
+        if self.is_non_linear(JUMP_MS) {
+            let anchors = self
+                .windows
+                .iter()
+                .filter_map(|window| {
+                    let middle = (window.start_ms + window.end_ms) / 2;
+                    window.offset.map(|offset| {
+                        ((middle as f64 / self.ratio).round() as i32, middle + offset)
+                    })
+                })
+                .collect();
+            Misalignment::Jumps(anchors)
+        } else if self.ratio != 1.0 {
+            Misalignment::Drift {
+                ratio: self.ratio,
+                offset: self.offset,
+            }
+        } else if self.offset.abs() >= IN_SYNC_MS {
+            Misalignment::Offset(self.offset)
+        } else {
+            Misalignment::InSync
+        }
+    }
+}
+
+// One point of a drift curve: the best offset of the cues starting in a window around a time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftPoint {
+    // Middle of the window, in the time of the subtitles.
+    pub time_ms: i32,
+    pub cues: usize,
+    // None when the window has too few cues to tell.
+    pub offset: Option<i32>,
+    pub coverage: f64,
+}
+
+// The kind of misalignment a correlation found, with the parameters of its fix.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Misalignment {
+    // The cues already fall on the speech.
+    InSync,
+    // Every cue is off by the same offset, in miliseconds: a shift fixes it.
+    Offset(i32),
+    // The cues drift apart from the speech along the file, from a framerate mismatch: times should become
+    // time * ratio + offset.
+    Drift { ratio: f64, offset: i32 },
+    // The offset jumps between parts of the file (a different cut, a reel change): times in the
+    // subtitles and the time each should be at, to retime through.
+    Jumps(Vec<(i32, i32)>),
+}
+
+// The speech intervals as a running sum over steps of +1 for speech and -1 for silence, so the score of
+// any stretch of time is a difference of two values. Time outside the soundtrack counts as silence.
+struct SpeechSum {
+    sums: Vec<i32>,
+}
+
+impl SpeechSum {
+    fn new(speech: &[(i32, i32)]) -> SpeechSum {
+        let length = speech
+            .iter()
+            .map(|(_, end)| end / STEP_MS + 1)
+            .max()
+            .unwrap_or(0) as usize;
+        let mut values = vec![-1; length];
+        for (start, end) in speech {
+            for value in values
+                .iter_mut()
+                .take((*end / STEP_MS).max(0) as usize)
+                .skip((*start / STEP_MS).max(0) as usize)
+            {
+                *value = 1;
+            }
+        }
+        let mut sums = vec![0; length + 1];
+        for (i, value) in values.iter().enumerate() {
+            sums[i + 1] = sums[i] + value;
+        }
+        SpeechSum { sums }
+    }
+
+    // Create a function to get the running sum up to a step, which can be outside the soundtrack.
+    fn at(&self, step: i64) -> i64 {
+        let length = self.sums.len() as i64 - 1;
+        if step < 0 {
+            -step
+        } else if step > length {
+            self.sums[length as usize] as i64 - (step - length)
+        } else {
+            self.sums[step as usize] as i64
+        }
+    }
+
+    // Create a function to score cues, in steps, moved by an offset in steps: the steps of speech they
+    // cover minus the steps of silence.
+    fn score(&self, cues: &[(i64, i64)], offset: i64) -> i64 {
+        cues.iter()
+            .map(|(start, end)| self.at(end + offset) - self.at(start + offset))
+            .sum()
+    }
+}
+
+// Create a function to find the best offset of cues given in steps, within a range of offsets. Returns
+// the score of every offset tried, from the lowest offset.
+fn scores(speech: &SpeechSum, cues: &[(i64, i64)], from: i64, to: i64) -> Vec<i64> {
+    (from..=to)
+        .map(|offset| speech.score(cues, offset))
+        .collect()
+}
+
+// Create a function to turn cue times into steps after scaling them by a ratio.
+fn to_steps(cues: &[(i32, i32)], ratio: f64) -> Vec<(i64, i64)> {
+    cues.iter()
+        .map(|(start, end)| {
+            (
+                (*start as f64 * ratio / STEP_MS as f64).round() as i64,
+                (*end as f64 * ratio / STEP_MS as f64).round() as i64,
+            )
+        })
+        .collect()
+}
+
+// Create a function to get the share of the cue time that falls on speech from the score of cues.
+fn coverage(score: i64, total: i64) -> f64 {
+    if total > 0 {
+        ((score + total) as f64 / (2 * total) as f64).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+// Create a function to find the best offset of the cues starting within a window, in steps, when it has
+// enough of them to tell. Returns the number of cues, their offset in miliseconds and their coverage,
+// at the fallback offset when they are too few.
+fn window_offset(
+    sum: &SpeechSum,
+    steps: &[(i64, i64)],
+    from: i64,
+    window: i64,
+    max_offset: i64,
+    fallback: i64,
+) -> (usize, Option<i32>, f64) {
+    let part: Vec<(i64, i64)> = steps
+        .iter()
+        .filter(|(start, _)| *start >= from && *start < from + window)
+        .copied()
+        .collect();
+    let total: i64 = part.iter().map(|(start, end)| end - start).sum();
+    if part.len() < 10 {
+        return (
+            part.len(),
+            None,
+            coverage(sum.score(&part, fallback), total),
+        );
+    }
+    let scored = scores(sum, &part, -max_offset, max_offset);
+    let top = *scored.iter().max().unwrap();
+    let index = scored.iter().position(|score| *score == top).unwrap() as i64;
+    (
+        part.len(),
+        Some(((index - max_offset) * STEP_MS as i64) as i32),
+        coverage(top, total),
+    )
+}
+
+// Create a function to find the offset and framerate ratio that best lay the cues (start and end times)
+// over the speech intervals, with a breakdown of the best offset of every window of the file so that
+// drift a single offset and ratio can't fix shows. Returns None without cues or speech.
+pub fn correlate(
+    cues: &[(i32, i32)],
+    speech: &[(i32, i32)],
+    options: &CorrelateOptions,
+) -> Option<Correlation> {
+    if cues.is_empty() || speech.is_empty() {
+        return None;
+    }
+    let sum = SpeechSum::new(speech);
+    let max_offset = (options.max_offset_ms / STEP_MS) as i64;
+    let mut ratios = vec![1.0];
+    if options.drift {
+        for ratio in RATIOS {
+            ratios.push(ratio);
+            ratios.push(1.0 / ratio);
+        }
+    }
+    let mut best: Option<(i64, f64, Vec<i64>)> = None;
+    for ratio in ratios {
+        let steps = to_steps(cues, ratio);
+        let scored = scores(&sum, &steps, -max_offset, max_offset);
+        let top = *scored.iter().max().unwrap();
+        if best.as_ref().is_none_or(|(score, _, _)| top > *score) {
+            best = Some((top, ratio, scored));
+        }
+    }
+    let (top, ratio, scored) = best?;
+    let index = scored.iter().position(|score| *score == top).unwrap();
+    let offset = index as i64 - max_offset;
+    // The runner-up has to be more than 2 seconds away, next to the peak the score barely changes.
+    let margin = (2000 / STEP_MS) as usize;
+    let runner_up = scored
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i.abs_diff(index) > margin)
+        .map(|(_, score)| *score)
+        .max()
+        .unwrap_or(-top.abs());
+    let steps = to_steps(cues, ratio);
+    let total: i64 = steps.iter().map(|(start, end)| end - start).sum();
+    let confidence = if top > 0 {
+        ((top - runner_up.max(0)) as f64 / top as f64).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    let mut windows = Vec::new();
+    let window = (options.window_ms.max(STEP_MS) / STEP_MS) as i64;
+    let last = steps.iter().map(|(start, _)| *start).max().unwrap_or(0);
+    let mut from = 0;
+    while from <= last {
+        let (part, window_offset, window_coverage) =
+            window_offset(&sum, &steps, from, window, max_offset, offset);
+        windows.push(Window {
+            start_ms: (from * STEP_MS as i64) as i32,
+            end_ms: ((from + window) * STEP_MS as i64) as i32,
+            cues: part,
+            offset: window_offset,
+            coverage: window_coverage,
+        });
+        from += window;
+    }
+
+    Some(Correlation {
+        ratio,
+        offset: (offset * STEP_MS as i64) as i32,
+        coverage: coverage(top, total),
+        confidence,
+        windows,
+    })
+}
+
+// Create a function to follow the offset of the cues along the file: the best offset of every window of
+// the file, with windows starting every quarter of their length so they overlap. The windows are laid
+// over the speech with the ratio of the whole file (see correlate), which their offsets are turned back
+// from, so a constant error gives a flat curve, a framerate mismatch a sloped line and a different cut
+// steps.
+pub fn drift_curve(
+    cues: &[(i32, i32)],
+    speech: &[(i32, i32)],
+    options: &CorrelateOptions,
+) -> Vec<DriftPoint> {
+    let Some(correlation) = correlate(cues, speech, options) else {
+        return Vec::new();
+    };
+    let ratio = correlation.ratio;
+    let sum = SpeechSum::new(speech);
+    let steps = to_steps(cues, ratio);
+    let max_offset = (options.max_offset_ms / STEP_MS) as i64;
+    let fallback = (correlation.offset / STEP_MS) as i64;
+    let window = (options.window_ms.max(STEP_MS) / STEP_MS) as i64;
+    let last = steps.iter().map(|(start, _)| *start).max().unwrap_or(0);
+    let mut points = Vec::new();
+    let mut from = 0;
+    while from <= last {
+        let (cues, offset, coverage) =
+            window_offset(&sum, &steps, from, window, max_offset, fallback);
+        let middle = ((from + window / 2) * STEP_MS as i64) as f64;
+        let time_ms = (middle / ratio).round() as i32;
+        points.push(DriftPoint {
+            time_ms,
+            cues,
+            offset: offset.map(|offset| middle as i32 + offset - time_ms),
+            coverage,
+        });
+        from += (window / 4).max(1);
+    }
+    points
+}
@@ -0,0 +1,486 @@
+use regex::Regex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+// Interop timecodes count ticks of 4 miliseconds after the seconds.
+const INTEROP_TICK_RATE: i32 = 250;
+
+// Edit rate written to SMPTE files when the subtitles don't have a framerate.
+const DEFAULT_EDIT_RATE: i32 = 24;
+
+const INTEROP_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<DCSubtitle Version=\"1.0\">
+  <SubtitleID>{id}</SubtitleID>
+  <MovieTitle>subtitles</MovieTitle>
+  <ReelNumber>1</ReelNumber>
+  <Language>English</Language>
+  <Font Size=\"42\">";
+
+const INTEROP_TRAILER: &str = "  </Font>
+</DCSubtitle>";
+
+const SMPTE_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<SubtitleReel xmlns=\"http://www.smpte-ra.org/schemas/428-7/2010/DCST\" xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">
+  <Id>urn:uuid:{id}</Id>
+  <ContentTitleText>subtitles</ContentTitleText>
+  <IssueDate>{date}</IssueDate>
+  <ReelNumber>1</ReelNumber>
+  <Language>en</Language>
+  <EditRate>24 1</EditRate>
+  <TimeCodeRate>24</TimeCodeRate>
+  <StartTime>00:00:00:00</StartTime>
+  <SubtitleList>
+    <Font Size=\"42\">";
+
+const SMPTE_TRAILER: &str = "    </Font>
+  </SubtitleList>
+</SubtitleReel>";
+
+// Create a function to read a single element's text from the document header.
+fn element(header: &str, name: &str) -> Option<String> {
+    Regex::new(&format!(r"<{}>\s*([^<]*?)\s*</{}>", name, name))
+        .unwrap()
+        .captures(header)
+        .map(|caps| caps[1].to_string())
+}
+
+// Create a function to convert a DCP timecode to miliseconds. The last field is a count of ticks
+// (Interop) or edit units (SMPTE) at the given rate. Interop files may also use hh:mm:ss.sss.
+fn convert_to_miliseconds(time: &str, rate: f64) -> Option<i32> {
+    let re = Regex::new(r"^(\d+):(\d{2}):(\d{2})(?::(\d+)|(\.\d+))$").unwrap();
+    let caps = re.captures(time.trim())?;
+    let hours = caps[1].parse::<f64>().ok()?;
+    let minutes = caps[2].parse::<f64>().ok()?;
+    let seconds = caps[3].parse::<f64>().ok()?;
+    let fraction = match (caps.get(4), caps.get(5)) {
+        (Some(units), _) => units.as_str().parse::<f64>().ok()? / rate,
+        (None, Some(fraction)) => fraction.as_str().parse::<f64>().ok()?,
+        _ => 0.0,
+    };
+    Some(((hours * 3600.0 + minutes * 60.0 + seconds + fraction) * 1000.0).round() as i32)
+}
+
+// Create a function to convert miliseconds to a hh:mm:ss:uu timecode at the given rate.
+fn convert_to_time(miliseconds: i32, rate: i32, digits: usize) -> String {
+    let units = (miliseconds.max(0) as f64 * rate as f64 / 1000.0).round() as i32;
+    let seconds = units / rate;
+    format!(
+        "{:02}:{:02}:{:02}:{:0digits$}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60,
+        units % rate,
+        digits = digits
+    )
+}
+
+// Create a function to get the SMPTE timecode rate, from TimeCodeRate or else EditRate.
+fn timecode_rate(header: &str) -> Option<f64> {
+    element(header, "TimeCodeRate")
+        .and_then(|rate| rate.parse::<f64>().ok())
+        .or_else(|| {
+            let rate = element(header, "EditRate")?;
+            let parts: Vec<f64> = rate
+                .split_whitespace()
+                .filter_map(|part| part.parse::<f64>().ok())
+                .collect();
+            match parts.as_slice() {
+                [numerator, denominator] if *denominator != 0.0 => Some(numerator / denominator),
+                [rate] => Some(*rate),
+                _ => None,
+            }
+        })
+}
+
+// Create a function to turn the markup of a <Text> element into a subtitle line.
+// Italic and bold fonts become <i> and <b> tags, other markup is dropped.
+fn parse_text(text: &str) -> String {
+    let tag = Regex::new(r"<(/?)(\w+)([^>]*?)/?>").unwrap();
+    let mut line = String::new();
+    let mut fonts = Vec::new();
+    let mut position = 0;
+    for caps in tag.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        line.push_str(&text[position..whole.start()]);
+        position = whole.end();
+        match (&caps[1], &caps[2]) {
+            ("/", "Font") => line.push_str(fonts.pop().flatten().unwrap_or("")),
+            ("", "Font") => {
+                let attributes = &caps[3];
+                if attributes.contains("Italic=\"yes\"") {
+                    line.push_str("<i>");
+                    fonts.push(Some("</i>"));
+                } else if attributes.contains("Weight=\"bold\"") {
+                    line.push_str("<b>");
+                    fonts.push(Some("</b>"));
+                } else {
+                    fonts.push(None);
+                }
+            }
+            _ => {}
+        }
+    }
+    line.push_str(&text[position..]);
+    line.trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Create a function to turn a subtitle line back into <Text> markup.
+fn serialize_text(line: &str) -> String {
+    let escaped = line
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let tag = Regex::new(r"&lt;(/?)(\w+)[^&]*?&gt;").unwrap();
+    tag.replace_all(&escaped, |caps: &regex::Captures| {
+        match (&caps[1], caps[2].to_lowercase().as_str()) {
+            ("", "i") => "<Font Italic=\"yes\">".to_string(),
+            ("", "b") => "<Font Weight=\"bold\">".to_string(),
+            ("/", "i") | ("/", "b") => "</Font>".to_string(),
+            _ => String::new(),
+        }
+    })
+    .to_string()
+}
+
+// Create a function to parse a DCP subtitle file, either Interop (<DCSubtitle>) or SMPTE (<SubtitleReel>).
+// SMPTE times are read at the TimeCodeRate unless a framerate is given, relative to the StartTime.
+// The attributes of each <Subtitle> and its <Text> elements are kept in the cue settings, separated by '|'.
+pub fn parse(contents: &str, framerate: Option<f32>) -> Result<SubtitleFile, String> {
+    let subtitle = Regex::new(r"(?s)<Subtitle\s([^>]*)>(.*?)</Subtitle>").unwrap();
+    let text = Regex::new(r"(?s)<Text(\s[^>]*)?>(.*?)</Text>").unwrap();
+    let attribute = Regex::new(r#"(\w+)\s*=\s*"([^"]*)""#).unwrap();
+    let format = if contents.contains("<SubtitleReel") {
+        Format::DcpSmpte
+    } else if contents.contains("<DCSubtitle") {
+        Format::DcpInterop
+    } else {
+        return Err("Missing <DCSubtitle> or <SubtitleReel> element".to_string());
+    };
+    let first = subtitle
+        .find(contents)
+        .ok_or_else(|| "No <Subtitle> elements found".to_string())?;
+    let header = contents[..first.start()].trim_end().to_string();
+    let (rate, offset) = if format == Format::DcpSmpte {
+        let rate = framerate
+            .map(|framerate| framerate as f64)
+            .or_else(|| timecode_rate(&header))
+            .unwrap_or(DEFAULT_EDIT_RATE as f64);
+        let offset = element(&header, "StartTime")
+            .and_then(|time| convert_to_miliseconds(&time, rate))
+            .unwrap_or(0);
+        (rate, offset)
+    } else {
+        (INTEROP_TICK_RATE as f64, 0)
+    };
+    let mut cues = Vec::new();
+    let mut last_end = first.start();
+    for caps in subtitle.captures_iter(contents) {
+        last_end = caps.get(0).unwrap().end();
+        let index = cues.len() + 1;
+        if caps[2].contains("<Image") {
+            return Err(format!("Cue {}: image subtitles are not supported", index));
+        }
+        let mut start_ms = None;
+        let mut end_ms = None;
+        let mut settings = Vec::new();
+        for attribute in attribute.captures_iter(&caps[1]) {
+            let time = || {
+                convert_to_miliseconds(&attribute[2], rate)
+                    .map(|time| time - offset)
+                    .ok_or_else(|| format!("Cue {}: invalid timecode '{}'", index, &attribute[2]))
+            };
+            match &attribute[1] {
+                "TimeIn" => start_ms = Some(time()?),
+                "TimeOut" => end_ms = Some(time()?),
+                "SpotNumber" => {}
+                _ => settings.push(attribute[0].to_string()),
+            }
+        }
+        let mut settings = vec![settings.join(" ")];
+        let mut lines = Vec::new();
+        for text in text.captures_iter(&caps[2]) {
+            settings.push(text.get(1).map_or("", |m| m.as_str()).trim().to_string());
+            lines.push(parse_text(&text[2]));
+        }
+        cues.push(Cue {
+            index,
+            start_ms: start_ms.ok_or_else(|| format!("Cue {}: missing TimeIn", index))?,
+            end_ms: end_ms.ok_or_else(|| format!("Cue {}: missing TimeOut", index))?,
+            settings: Some(settings.join("|")),
+            lines,
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format,
+        framerate: if format == Format::DcpSmpte {
+            Some(rate as f32)
+        } else {
+            None
+        },
+        header: vec![header],
+        cues,
+        trailer: vec![contents[last_end..]
+            .trim_start_matches(['\r', '\n'])
+            .trim_end()
+            .to_string()],
+    })
+}
+
+// Create a function to get today's date as an xs:dateTime, for the IssueDate of new SMPTE files.
+fn issue_date() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    // Days since 1970-01-01 to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = seconds / 86400 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T00:00:00", year, month, day)
+}
+
+// Create a function to write the cues out as a DCP subtitle file, Interop or SMPTE.
+// SMPTE files are written at the subtitles' framerate (rounded, as DCPs only use whole rates),
+// and their EditRate and TimeCodeRate are updated to match.
+pub fn serialize(file: &SubtitleFile, format: Format) -> String {
+    let smpte = format == Format::DcpSmpte;
+    let same_format = file.format == format;
+    let id = uuid::Uuid::new_v4().to_string();
+    let mut header = match file.header.first() {
+        Some(header) if same_format => header.clone(),
+        _ if smpte => SMPTE_HEADER
+            .replace("{id}", &id)
+            .replace("{date}", &issue_date()),
+        _ => INTEROP_HEADER.replace("{id}", &id),
+    };
+    let trailer = match file.trailer.first() {
+        Some(trailer) if same_format => trailer.as_str(),
+        _ if smpte => SMPTE_TRAILER,
+        _ => INTEROP_TRAILER,
+    };
+    let (rate, offset) = if smpte {
+        let rate = file
+            .framerate
+            .map(|framerate| framerate as f64)
+            .or_else(|| timecode_rate(&header))
+            .unwrap_or(DEFAULT_EDIT_RATE as f64)
+            .round() as i32;
+        header = Regex::new(r"<EditRate>[^<]*</EditRate>")
+            .unwrap()
+            .replace(&header, format!("<EditRate>{} 1</EditRate>", rate).as_str())
+            .to_string();
+        header = Regex::new(r"<TimeCodeRate>[^<]*</TimeCodeRate>")
+            .unwrap()
+            .replace(
+                &header,
+                format!("<TimeCodeRate>{}</TimeCodeRate>", rate).as_str(),
+            )
+            .to_string();
+        let offset = element(&header, "StartTime")
+            .and_then(|time| convert_to_miliseconds(&time, rate as f64))
+            .unwrap_or(0);
+        (rate, offset)
+    } else {
+        (INTEROP_TICK_RATE, 0)
+    };
+    let (digits, valign, vposition) = if smpte {
+        (2, "Valign", "Vposition")
+    } else {
+        (3, "VAlign", "VPosition")
+    };
+    let mut output = format!("{}\n", header);
+    for (i, cue) in file.cues.iter().enumerate() {
+        let settings: Vec<&str> = match &cue.settings {
+            Some(settings) if same_format => settings.split('|').collect(),
+            _ => Vec::new(),
+        };
+        let attributes = match settings.first() {
+            Some(attributes) if !attributes.is_empty() => format!(" {}", attributes),
+            _ => String::new(),
+        };
+        output.push_str(&format!(
+            "      <Subtitle SpotNumber=\"{}\" TimeIn=\"{}\" TimeOut=\"{}\"{}>\n",
+            i + 1,
+            convert_to_time(cue.start_ms + offset, rate, digits),
+            convert_to_time(cue.end_ms + offset, rate, digits),
+            attributes
+        ));
+        for (j, line) in cue.lines.iter().enumerate() {
+            let attributes = if settings.len() == cue.lines.len() + 1 {
+                settings[j + 1].to_string()
+            } else {
+                // Stack the lines up from the bottom of the screen, 6.5% apart.
+                let position = 10.0 + (cue.lines.len() - 1 - j) as f32 * 6.5;
+                format!("{}=\"bottom\" {}=\"{}\"", valign, vposition, position)
+            };
+            let attributes = if attributes.is_empty() {
+                attributes
+            } else {
+                format!(" {}", attributes)
+            };
+            output.push_str(&format!(
+                "        <Text{}>{}</Text>\n",
+                attributes,
+                serialize_text(line)
+            ));
+        }
+        output.push_str("      </Subtitle>\n");
+    }
+    output.push_str(trailer);
+    output.push('\n');
+    output
+}
+
+// The DCP subtitle formats, as registered in the format registry: Format::DcpInterop or Format::DcpSmpte.
+pub struct Dcp(pub Format);
+
+impl SubtitleFormat for Dcp {
+    fn format(&self) -> Format {
+        self.0
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        if self.0 == Format::DcpSmpte {
+            &["smpte", "dcp"]
+        } else {
+            &["interop"]
+        }
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: true,
+            frame_based: self.0 == Format::DcpSmpte,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        let root = if self.0 == Format::DcpSmpte {
+            "<SubtitleReel"
+        } else {
+            "<DCSubtitle"
+        };
+        sniff_text(contents).contains(root)
+    }
+
+    fn parse(&self, contents: &[u8], options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?, options.framerate)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file, self.0).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTEROP: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<DCSubtitle Version=\"1.0\">
+  <SubtitleID>b2f3c0a4-1c1e-4f3e-9a57-2d1a6a0b6c11</SubtitleID>
+  <MovieTitle>Test</MovieTitle>
+  <ReelNumber>1</ReelNumber>
+  <Language>English</Language>
+  <Font Size=\"42\">
+      <Subtitle SpotNumber=\"1\" TimeIn=\"00:00:01:125\" TimeOut=\"00:00:02:125\" FadeUpTime=\"20\">
+        <Text VAlign=\"bottom\" VPosition=\"10\"><Font Italic=\"yes\">Hello</Font> &amp; bye</Text>
+      </Subtitle>
+      <Subtitle SpotNumber=\"2\" TimeIn=\"01:00:00:000\" TimeOut=\"01:00:01:100\">
+        <Text VAlign=\"bottom\" VPosition=\"16.5\">Two</Text>
+        <Text VAlign=\"bottom\" VPosition=\"10\">lines</Text>
+      </Subtitle>
+  </Font>
+</DCSubtitle>
+";
+
+    const SMPTE: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<SubtitleReel xmlns=\"http://www.smpte-ra.org/schemas/428-7/2010/DCST\">
+  <EditRate>25 1</EditRate>
+  <TimeCodeRate>25</TimeCodeRate>
+  <StartTime>10:00:00:00</StartTime>
+  <SubtitleList>
+    <Font>
+      <Subtitle SpotNumber=\"1\" TimeIn=\"10:00:01:12\" TimeOut=\"10:00:03:00\">
+        <Text Valign=\"bottom\" Vposition=\"10\">Hello</Text>
+      </Subtitle>
+    </Font>
+  </SubtitleList>
+</SubtitleReel>
+";
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(INTEROP, None).unwrap();
+        assert_eq!(file.format, Format::DcpInterop);
+        assert_eq!((file.cues[0].start_ms, file.cues[0].end_ms), (1500, 2500));
+        assert_eq!(file.cues[0].lines, ["<i>Hello</i> & bye"]);
+        assert_eq!(
+            (file.cues[1].start_ms, file.cues[1].end_ms),
+            (3600000, 3601400)
+        );
+        assert_eq!(file.cues[1].lines, ["Two", "lines"]);
+        assert_eq!(serialize(&file, Format::DcpInterop), INTEROP);
+    }
+
+    #[test]
+    fn smpte_times_are_relative_to_the_start_time() {
+        let file = parse(SMPTE, None).unwrap();
+        assert_eq!(file.format, Format::DcpSmpte);
+        assert_eq!(file.framerate, Some(25.0));
+        assert_eq!((file.cues[0].start_ms, file.cues[0].end_ms), (1480, 3000));
+        assert_eq!(serialize(&file, Format::DcpSmpte), SMPTE);
+    }
+
+    #[test]
+    fn interop_converts_to_smpte() {
+        let file = parse(INTEROP, None).unwrap();
+        let smpte = parse(&serialize(&file, Format::DcpSmpte), None).unwrap();
+        assert_eq!(smpte.format, Format::DcpSmpte);
+        let times: Vec<_> = smpte.cues.iter().map(|c| (c.start_ms, c.end_ms)).collect();
+        // New SMPTE files default to 24 frames per second, so times are rounded to frames.
+        assert_eq!(times, [(1500, 2500), (3600000, 3601417)]);
+        assert_eq!(smpte.cues[1].lines, ["Two", "lines"]);
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        assert!(parse("<tt></tt>", None).is_err());
+        assert!(parse("<DCSubtitle Version=\"1.0\"></DCSubtitle>", None).is_err());
+        let missing = INTEROP.replace(" TimeOut=\"00:00:02:125\"", "");
+        assert!(parse(&missing, None).is_err());
+        let invalid = INTEROP.replace("00:00:02:125", "00:00:2:250");
+        assert!(parse(&invalid, None).is_err());
+        let image = INTEROP.replace(
+            "<Text VAlign=\"bottom\" VPosition=\"10\">",
+            "<Image>x.png</Image><Text>",
+        );
+        assert!(parse(&image, None).is_err());
+    }
+}
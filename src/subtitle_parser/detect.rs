@@ -0,0 +1,1301 @@
+// Framerate detection: which framerate subtitles were timed for, from several independent methods. Each
+// method scores every candidate framerate from 0 to 1 and has a weight, how much a full score from it
+// can be trusted. The confidence in a candidate is the chance that at least one method is right about
+// it (1 - (1 - weight * score) for every method, multiplied), so agreeing methods add up and a single
+// weak method stays weak. Priors, such as the region a release comes from, are only weighed in when no
+// evidence from the file itself is strong, so they can break a tie but never outrank evidence.
+
+use regex::Regex;
+
+use super::align::{self, fit_linear};
+use super::dtw::{self, DtwOptions};
+use super::timecode::TimecodeRate;
+use super::timing::DurationCheck;
+use super::{Cue, SubtitleFile};
+
+// The framerates considered by default.
+pub const COMMON_FRAMERATES: [f64; 5] = [23.976, 24.0, 25.0, 29.97, 30.0];
+
+// Evidence at least this confident makes the priors irrelevant.
+pub const STRONG_EVIDENCE: f64 = 0.5;
+
+// Weight of a framerate declared by the file itself (MicroDVD, EBU STL, DCP).
+const DECLARED_WEIGHT: f64 = 0.95;
+
+// Weight of the frame grid of the cue times, reached with this many intervals.
+const INTERVALS_WEIGHT: f64 = 0.9;
+const INTERVALS_SAMPLE: f64 = 100.0;
+
+// Weight of the remainders of the cue times, reached with this many times.
+const RESIDUES_WEIGHT: f64 = 0.9;
+const RESIDUES_SAMPLE: f64 = 100.0;
+
+// Weight of the 3:2 pulldown cadence, reached with this many times.
+const TELECINE_WEIGHT: f64 = 0.9;
+const TELECINE_SAMPLE: f64 = 100.0;
+
+// 3:2 pulldown shows 4 film frames over 10 fields of 29.97 fps video, for 3, 2, 3 and 2 fields: the
+// fields each film frame starts at, in a cycle of 10 fields of 1001/60 miliseconds.
+const PULLDOWN_FIELDS: [f64; 4] = [0.0, 3.0, 5.0, 8.0];
+const PULLDOWN_CYCLE: f64 = 10.0;
+
+// An earlier conversion is only looked for with this many cue times, and reported from this score.
+const CONVERSION_SAMPLE: usize = 50;
+const CONVERSION_SCORE: f64 = 0.5;
+
+// Weight of the duration of the video, see DurationCheck.
+const DURATION_WEIGHT: f64 = 0.8;
+
+// Weight of a known runtime, and the share of it the credits usually take after the last cue.
+const RUNTIME_WEIGHT: f64 = 0.7;
+const CREDITS: f64 = 0.015;
+
+// A runtime more than this share away from the last cue, once converted, matches no conversion.
+const RUNTIME_SLACK: f64 = 0.03;
+
+// Weight of the regression against a correctly timed reference file, reached with this many matched
+// cues, and how far the slope can be from the ratio of two framerates, which are at least 0.001 apart.
+const REFERENCE_WEIGHT: f64 = 0.95;
+const REFERENCE_SAMPLE: f64 = 50.0;
+const SLOPE_TOLERANCE: f64 = 0.0005;
+
+// Matched cues this far from the line fitted through all of them are left out of the regression, in
+// miliseconds: a mismatch or a scene of a different cut.
+const OUTLIER_MS: f64 = 1000.0;
+
+// Weight of the region prior.
+const PRIOR_WEIGHT: f64 = 0.3;
+
+// Weight of the hints in file names: a framerate written out (23.976, 25fps), a region (PAL, NTSC) or
+// the source of a release (BluRay, WEB-DL, DVDRip), which only tells the framerate it usually has.
+const FILENAME_WEIGHT: f64 = 0.35;
+const SOURCE_HINT: f64 = 0.6;
+
+// Files with this many cues are also detected half by half, and the confidence of their candidates is
+// multiplied by the penalty when the halves confidently disagree.
+const CROSS_CHECK_CUES: usize = 40;
+const DISAGREEMENT_PENALTY: f64 = 0.5;
+
+// A best candidate less confident than STRONG_EVIDENCE, or ahead of the next one by less than this, is
+// borderline by default.
+pub const BORDERLINE: f64 = 0.15;
+
+// Scores and confidences below this are noise, not a lead.
+const NOISE: f64 = 0.01;
+
+// Number of most common intervals kept for the explanation of a detection.
+const HISTOGRAM_SIZE: usize = 10;
+
+// Intervals this close to a whole number of frames, in miliseconds, count as on the frame grid by
+// default: times are rounded to the milisecond, and some tools truncate instead.
+pub const GRID_TOLERANCE: f64 = 1.0;
+
+// How a detection method reached its scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    // The framerate the file declares.
+    Declared,
+    // The intervals between cue times are whole numbers of frames.
+    Intervals,
+    // The remainders of the cue times divided by the frame length gather at one point.
+    Residues,
+    // The cue times follow the 3:2 pulldown cadence of telecined film, see pulldown_score.
+    Telecine,
+    // The subtitles end with the video at its framerate, or once converted, see DurationCheck.
+    Duration,
+    // The framerates usual in a region, a weak prior.
+    Prior,
+    // Hints in the names of the subtitle and video files, a weak prior.
+    Filename,
+    // The slope of the cue times against those of a correctly timed reference file.
+    Reference,
+    // The speed factor from the last cue to a known runtime, see runtime_match.
+    Runtime,
+}
+
+impl Method {
+    // Create a function to get the name of a method, as reports show it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Method::Declared => "declared",
+            Method::Intervals => "intervals",
+            Method::Residues => "residues",
+            Method::Telecine => "telecine",
+            Method::Duration => "duration",
+            Method::Prior => "prior",
+            Method::Filename => "filename",
+            Method::Reference => "reference",
+            Method::Runtime => "runtime",
+        }
+    }
+
+    // Create a function to tell whether a method is a prior rather than evidence from the file.
+    pub fn is_prior(&self) -> bool {
+        matches!(self, Method::Prior | Method::Filename)
+    }
+}
+
+// The region a release comes from, whose usual framerates are the prior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    // European television and DVDs: 25 fps.
+    Pal,
+    // American television and DVDs: 29.97 fps, or film at 23.976 fps.
+    Ntsc,
+    // Cinema releases, Blu-rays and web releases: 23.976 or 24 fps.
+    Film,
+}
+
+impl Region {
+    // Create a function to pick a region from its name on the command line.
+    pub fn from_name(name: &str) -> Result<Region, String> {
+        match name.to_lowercase().as_str() {
+            "pal" => Ok(Region::Pal),
+            "ntsc" => Ok(Region::Ntsc),
+            "film" => Ok(Region::Film),
+            _ => Err(format!(
+                "Invalid region '{}', expected pal, ntsc or film",
+                name
+            )),
+        }
+    }
+
+    // Create a function to score a framerate by how usual it is in the region.
+    fn score(&self, framerate: f64) -> f64 {
+        let usual: &[(f64, f64)] = match self {
+            Region::Pal => &[(25.0, 1.0)],
+            Region::Ntsc => &[(29.97, 1.0), (23.976, 0.8)],
+            Region::Film => &[(23.976, 1.0), (24.0, 0.8)],
+        };
+        usual
+            .iter()
+            .find(|(usual, _)| same_framerate(*usual, framerate))
+            .map_or(0.0, |(_, score)| *score)
+    }
+}
+
+// The scores one method gave the candidates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evidence {
+    pub method: Method,
+    // How much a full score can be trusted, from 0 to 1.
+    pub weight: f64,
+    // Every candidate framerate and its score, from 0 to 1.
+    pub scores: Vec<(f64, f64)>,
+    // What the method saw, for reports.
+    pub detail: String,
+    // How many samples the scores come from (intervals, cue times, matched cues), 1 for a single fact
+    // such as a declared framerate.
+    pub samples: usize,
+    // Share of the samples that fit every candidate, before what chance gives is taken out, for the
+    // methods that count samples.
+    pub ratios: Vec<(f64, f64)>,
+    // The most common intervals between cue times, in miliseconds, and how many there are.
+    pub histogram: Vec<(i32, usize)>,
+}
+
+impl Evidence {
+    // Create a function to get the score a method gave a framerate, 0 when it isn't a candidate.
+    pub fn score(&self, framerate: f64) -> f64 {
+        self.scores
+            .iter()
+            .find(|(candidate, _)| same_framerate(*candidate, framerate))
+            .map_or(0.0, |(_, score)| *score)
+    }
+}
+
+// A candidate framerate and how confident the detection is in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    pub framerate: f64,
+    pub confidence: f64,
+}
+
+// An earlier framerate conversion found in subtitles: their times were multiplied by the factor after
+// being timed on the frame grid of the original framerate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conversion {
+    pub original: f64,
+    pub factor: f64,
+    // How well the times fit the converted grid, from 0 to 1, see grid_score.
+    pub score: f64,
+}
+
+// The framerate conversion a known runtime implies: the runtime divided by the end of the last cue, and
+// the pair of framerates (from the one of the subtitles to the one of the film) whose ratio it is
+// nearest, None when it is nearest no speed change at all.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuntimeMatch {
+    pub factor: f64,
+    pub conversion: Option<(f64, f64)>,
+    // How clearly the factor points to that pair rather than the next nearest ratio, from 0 to 1.
+    pub confidence: f64,
+}
+
+// What the detection found: the candidates from the most to the least likely, and the evidence of
+// every method that had something to say.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub candidates: Vec<Candidate>,
+    pub evidence: Vec<Evidence>,
+    // Whether the priors were weighed in, because no evidence was strong.
+    pub used_priors: bool,
+    // An earlier conversion the subtitles went through, see detect_conversion.
+    pub conversion: Option<Conversion>,
+    // The conversion a known runtime implies, see runtime_match.
+    pub runtime: Option<RuntimeMatch>,
+    // The detection of each half of the file, see cross_check.
+    pub halves: Option<Halves>,
+    // How far ahead of the next candidate the best one has to be, see DetectOptions.
+    pub borderline: f64,
+}
+
+// The most likely framerate of the first and second half of a file, detected on their own from the cue
+// times alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Halves {
+    // Where the second half starts, in miliseconds.
+    pub split_ms: i32,
+    pub first: Option<Candidate>,
+    pub second: Option<Candidate>,
+}
+
+impl Halves {
+    // Create a function to tell whether both halves confidently point to different framerates: drift a
+    // single framerate doesn't explain, or a file partly edited or converted.
+    pub fn disagree(&self) -> bool {
+        match (self.first, self.second) {
+            (Some(first), Some(second)) => {
+                first.confidence >= STRONG_EVIDENCE
+                    && second.confidence >= STRONG_EVIDENCE
+                    && !same_framerate(first.framerate, second.framerate)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Detection {
+    // Create a function to get the framerate a method scores highest, when it scores any.
+    pub fn favoured(evidence: &Evidence) -> Option<(f64, f64)> {
+        evidence
+            .scores
+            .iter()
+            .copied()
+            .filter(|(_, score)| *score >= NOISE)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    // Create a function to tell whether the best candidate is too close to call: unsure, or barely ahead
+    // of the next one, so the candidates are worth comparing over the video.
+    pub fn is_borderline(&self) -> bool {
+        match (self.candidates.first(), self.candidates.get(1)) {
+            (Some(best), next) => {
+                best.confidence < STRONG_EVIDENCE
+                    || next.is_some_and(|next| best.confidence - next.confidence < self.borderline)
+            }
+            (None, _) => false,
+        }
+    }
+
+    // Create a function to get the most likely framerate, when any method had something to say.
+    pub fn best(&self) -> Option<Candidate> {
+        self.candidates
+            .first()
+            .copied()
+            .filter(|candidate| candidate.confidence >= NOISE)
+    }
+}
+
+// What detection is given besides the subtitles, and how strict it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectOptions {
+    // The framerates considered, COMMON_FRAMERATES when empty.
+    pub candidates: Vec<f64>,
+    // Region whose usual framerates are the prior, none by default.
+    pub region: Option<Region>,
+    // Duration and framerate of the video the subtitles are played with.
+    pub video: Option<(i32, f64)>,
+    // The known runtime of the film, in miliseconds, see runtime_match.
+    pub runtime_ms: Option<i32>,
+    // Subtitles correctly timed for the video, the input is regressed against, see reference_evidence.
+    pub reference: Option<SubtitleFile>,
+    // Paths of the subtitle and video files, whose names can hint at the framerate.
+    pub names: Vec<String>,
+    // How far from the frame grid a time can be and still count as on it, in miliseconds: larger for
+    // sloppy fan subtitles or centisecond formats (ASS), 1 for frame-exact broadcast subtitles.
+    pub tolerance_ms: f64,
+    // Similarity cues need to be matched with the reference, see align::match_cues.
+    pub match_threshold: f64,
+    // How far ahead of the next candidate the best one has to be not to be borderline.
+    pub borderline: f64,
+}
+
+impl Default for DetectOptions {
+    fn default() -> DetectOptions {
+        DetectOptions {
+            candidates: Vec::new(),
+            region: None,
+            video: None,
+            runtime_ms: None,
+            reference: None,
+            names: Vec::new(),
+            tolerance_ms: GRID_TOLERANCE,
+            match_threshold: align::DEFAULT_THRESHOLD,
+            borderline: BORDERLINE,
+        }
+    }
+}
+
+// Create a function to weigh a method by the samples its scores come from: a score from a handful of
+// intervals can be luck, so the weight grows with the samples up to its full value at the full sample.
+fn sampled_weight(weight: f64, samples: usize, full: f64) -> f64 {
+    weight * (samples as f64 / full).min(1.0)
+}
+
+// Create a function to tell whether two framerates are the same, 23.976 and 24000/1001 included.
+fn same_framerate(a: f64, b: f64) -> bool {
+    (a - b).abs() < 0.005
+}
+
+// Create a function to get the length of a frame in miliseconds, reading 23.976 as 24000/1001.
+fn frame_ms(framerate: f64) -> f64 {
+    let exact = TimecodeRate::new(framerate, false).map_or(framerate, |rate| rate.framerate);
+    1000.0 / exact
+}
+
+// Create a function to score the candidates by how many intervals between consecutive cue times are a
+// whole number of their frames, beyond what chance gives: files timed on a frame grid hit nearly every
+// time, files timed by ear as often as the tolerance covers of a frame.
+fn intervals_evidence(
+    subtitles: &SubtitleFile,
+    candidates: &[f64],
+    tolerance: f64,
+) -> Option<Evidence> {
+    let mut times: Vec<i32> = subtitles
+        .cues
+        .iter()
+        .flat_map(|cue| [cue.start_ms, cue.end_ms])
+        .collect();
+    times.sort_unstable();
+    times.dedup();
+    let intervals: Vec<f64> = times
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]) as f64)
+        .filter(|interval| *interval < 60_000.0)
+        .collect();
+    if intervals.len() < 10 {
+        return None;
+    }
+    let mut ratios = Vec::new();
+    let scores = candidates
+        .iter()
+        .map(|framerate| {
+            let frame = frame_ms(*framerate);
+            let hits = intervals
+                .iter()
+                .filter(|interval| {
+                    let frames = (*interval / frame).round();
+                    (*interval - frames * frame).abs() <= tolerance
+                })
+                .count() as f64
+                / intervals.len() as f64;
+            let chance = ((2.0 * tolerance + 1.0) / frame).min(1.0);
+            ratios.push((*framerate, hits));
+            let score = if chance >= 1.0 {
+                0.0
+            } else {
+                ((hits - chance) / (1.0 - chance)).clamp(0.0, 1.0)
+            };
+            (*framerate, score)
+        })
+        .collect();
+    Some(Evidence {
+        method: Method::Intervals,
+        weight: sampled_weight(INTERVALS_WEIGHT, intervals.len(), INTERVALS_SAMPLE),
+        samples: intervals.len(),
+        ratios,
+        histogram: common_intervals(&intervals),
+        scores,
+        detail: "intervals between cue times".to_string(),
+    })
+}
+
+// Create a function to count the most common intervals, to the milisecond, the most common first.
+fn common_intervals(intervals: &[f64]) -> Vec<(i32, usize)> {
+    let mut counts: Vec<(i32, usize)> = Vec::new();
+    for interval in intervals {
+        let interval = interval.round() as i32;
+        match counts.iter_mut().find(|(value, _)| *value == interval) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((interval, 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts.truncate(HISTOGRAM_SIZE);
+    counts
+}
+
+// Create a function to list the distinct start and end times of the cues, sorted.
+fn cue_times(subtitles: &SubtitleFile) -> Vec<i32> {
+    let mut times: Vec<i32> = subtitles
+        .cues
+        .iter()
+        .flat_map(|cue| [cue.start_ms, cue.end_ms])
+        .collect();
+    times.sort_unstable();
+    times.dedup();
+    times
+}
+
+// Create a function to score how the remainders of times divided by a frame length gather: a histogram
+// of the remainders in bins about as wide as the tolerance, and the share of the times in the fullest
+// three bins beyond what chance puts there, from 0 to 1.
+fn grid_score(times: &[i32], frame: f64, tolerance: f64) -> f64 {
+    let (peak, chance) = grid_share(times, frame, tolerance);
+    ((peak - chance) / (1.0 - chance)).clamp(0.0, 1.0)
+}
+
+// Create a function to get the share of times in the fullest three bins of the remainders, and the share
+// chance puts there, see grid_score.
+fn grid_share(times: &[i32], frame: f64, tolerance: f64) -> (f64, f64) {
+    let bins = (frame / tolerance.max(0.1)).round().max(4.0) as usize;
+    let mut histogram = vec![0usize; bins];
+    for time in times {
+        let residue = (*time as f64).rem_euclid(frame) / frame;
+        histogram[((residue * bins as f64) as usize).min(bins - 1)] += 1;
+    }
+    let peak = (0..bins)
+        .map(|i| histogram[(i + bins - 1) % bins] + histogram[i] + histogram[(i + 1) % bins])
+        .max()
+        .unwrap_or(0) as f64
+        / times.len().max(1) as f64;
+    // The fullest bins of times spread at random still hold a little more than their share.
+    let share = 3.0 / bins as f64;
+    let chance = share + 3.0 * (share * (1.0 - share) / times.len().max(1) as f64).sqrt();
+    (peak, chance)
+}
+
+// Create a function to score the candidates by how the remainders of every cue time divided by their
+// frame length gather, see grid_score. Times on a frame grid all fall in the same bins, wherever the
+// grid starts, so files shifted by a fraction of a frame still score; times on the grid of a close
+// framerate (24 for 23.976) drift through every bin over a film.
+fn residues_evidence(
+    subtitles: &SubtitleFile,
+    candidates: &[f64],
+    tolerance: f64,
+) -> Option<Evidence> {
+    let times = cue_times(subtitles);
+    if times.len() < 10 {
+        return None;
+    }
+    let scores = candidates
+        .iter()
+        .map(|framerate| {
+            (
+                *framerate,
+                grid_score(&times, frame_ms(*framerate), tolerance),
+            )
+        })
+        .collect();
+    Some(Evidence {
+        method: Method::Residues,
+        weight: sampled_weight(RESIDUES_WEIGHT, times.len(), RESIDUES_SAMPLE),
+        samples: times.len(),
+        ratios: candidates
+            .iter()
+            .map(|framerate| {
+                (
+                    *framerate,
+                    grid_share(&times, frame_ms(*framerate), tolerance).0,
+                )
+            })
+            .collect(),
+        histogram: Vec::new(),
+        scores,
+        detail: "cue times divided by the frame length".to_string(),
+    })
+}
+
+// Create a function to score how the cue times follow the cadence subtitles timed against telecined film
+// leave behind: changes of picture at the start of each film frame of the 3:2 pulldown, 50 and 33 ms
+// apart in turn. The remainders of the times divided by the cycle of 4 film frames gather at the 4
+// points film frames start at, wherever the cycle starts; the score is the share of times on them beyond
+// what chance puts there, from 0 to 1.
+fn pulldown_score(times: &[i32], tolerance: f64) -> f64 {
+    let field = 1001.0 / 60.0;
+    let cycle = PULLDOWN_CYCLE * field;
+    let near = |distance: f64| {
+        let distance = distance.rem_euclid(cycle);
+        distance.min(cycle - distance) <= tolerance + 0.5
+    };
+    let hits = (0..cycle.ceil() as i32)
+        .map(|start| {
+            times
+                .iter()
+                .filter(|time| {
+                    PULLDOWN_FIELDS
+                        .iter()
+                        .any(|phase| near(**time as f64 - start as f64 - phase * field))
+                })
+                .count()
+        })
+        .max()
+        .unwrap_or(0) as f64
+        / times.len().max(1) as f64;
+    let share = (PULLDOWN_FIELDS.len() as f64 * (2.0 * tolerance + 2.0) / cycle).min(1.0);
+    let chance = share + 3.0 * (share * (1.0 - share) / times.len().max(1) as f64).sqrt();
+    if chance >= 1.0 {
+        return 0.0;
+    }
+    ((hits - chance) / (1.0 - chance)).clamp(0.0, 1.0)
+}
+
+// Create a function to score 23.976 fps from the 3:2 pulldown cadence, when it fits the times better
+// than the frame grid of any candidate: such subtitles were timed against 29.97 fps telecined video of
+// 23.976 fps film, and play unchanged over the film, while their times land on a 30 fps or 29.97 fps
+// grid only half of the time.
+fn telecine_evidence(
+    subtitles: &SubtitleFile,
+    candidates: &[f64],
+    tolerance: f64,
+) -> Option<Evidence> {
+    let times = cue_times(subtitles);
+    if times.len() < 10 {
+        return None;
+    }
+    let score = pulldown_score(&times, tolerance);
+    let best_grid = candidates
+        .iter()
+        .map(|framerate| grid_score(&times, frame_ms(*framerate), tolerance))
+        .fold(0.0, f64::max);
+    if score < NOISE || score < best_grid + BORDERLINE {
+        return None;
+    }
+    Some(Evidence {
+        method: Method::Telecine,
+        weight: sampled_weight(TELECINE_WEIGHT, times.len(), TELECINE_SAMPLE),
+        samples: times.len(),
+        ratios: Vec::new(),
+        histogram: Vec::new(),
+        scores: candidates
+            .iter()
+            .map(|candidate| {
+                (
+                    *candidate,
+                    score * f64::from(same_framerate(*candidate, 23.976) as u8),
+                )
+            })
+            .collect(),
+        detail: format!(
+            "cue times on the 3:2 pulldown cadence of telecined film ({:.2}), 29.97 fps video of 23.976 fps film",
+            score
+        ),
+    })
+}
+
+// Create a function to find an earlier framerate conversion applied to subtitles timed on a frame grid,
+// once or twice, the wrong way or on top of another: their times then sit on the grid of an unusual
+// framerate, the original one scaled by the ratio of two common framerates or the product of two such
+// ratios (25 to 23.976 applied twice). A conversion that leads to a candidate framerate can't be told
+// apart from subtitles timed for it, so only unusual grids are reported, when they fit better than any
+// candidate.
+pub fn detect_conversion(
+    subtitles: &SubtitleFile,
+    candidates: &[f64],
+    tolerance: f64,
+) -> Option<Conversion> {
+    let times = cue_times(subtitles);
+    if times.len() < CONVERSION_SAMPLE {
+        return None;
+    }
+    let exact: Vec<f64> = candidates
+        .iter()
+        .map(|rate| 1000.0 / frame_ms(*rate))
+        .collect();
+    let mut ratios: Vec<f64> = Vec::new();
+    for a in &exact {
+        for b in &exact {
+            if (a / b - 1.0).abs() > 0.0005 {
+                ratios.push(a / b);
+            }
+        }
+    }
+    let mut twice = Vec::new();
+    for a in &ratios {
+        for b in &ratios {
+            twice.push(a * b);
+        }
+    }
+    let best_candidate = exact
+        .iter()
+        .map(|rate| grid_score(&times, 1000.0 / rate, tolerance))
+        .fold(0.0, f64::max);
+    let mut best: Option<Conversion> = None;
+    // A grid reached by a single conversion is reached by two from another framerate too, the simpler
+    // explanation wins.
+    for factors in [&ratios, &twice] {
+        if best.is_some() {
+            break;
+        }
+        for (original, rate) in candidates.iter().zip(exact.iter()) {
+            for factor in factors.iter() {
+                let frame = 1000.0 / rate * factor;
+                let usual = (factor - 1.0).abs() < 0.0005
+                    || exact
+                        .iter()
+                        .any(|other| (1000.0 / other - frame).abs() < 0.01);
+                if usual {
+                    continue;
+                }
+                let score = grid_score(&times, frame, tolerance);
+                if score >= CONVERSION_SCORE
+                    && score > best_candidate + BORDERLINE
+                    && best.is_none_or(|best| score > best.score)
+                {
+                    best = Some(Conversion {
+                        original: *original,
+                        factor: *factor,
+                        score,
+                    });
+                }
+            }
+        }
+    }
+    best
+}
+
+// Create a function to score the candidates from the duration of the video: subtitles that end with it
+// were timed for its framerate, subtitles that do once converted for the framerate converted from.
+fn duration_evidence(
+    subtitles: &SubtitleFile,
+    candidates: &[f64],
+    video_ms: i32,
+    video_framerate: f64,
+) -> Option<Evidence> {
+    let end = subtitles.cues.iter().map(|cue| cue.end_ms).max()?;
+    let check = DurationCheck::new(end, video_ms, video_framerate);
+    let (framerate, detail) = match check.conversion {
+        Some((from, _)) => (
+            from as f64,
+            format!(
+                "the video is {:.2}% longer than the subtitles",
+                check.mismatch() * 100.0
+            ),
+        ),
+        None if check.fits() => (
+            video_framerate,
+            "the subtitles end with the video".to_string(),
+        ),
+        None => return None,
+    };
+    Some(Evidence {
+        method: Method::Duration,
+        weight: DURATION_WEIGHT,
+        samples: 1,
+        ratios: Vec::new(),
+        histogram: Vec::new(),
+        scores: candidates
+            .iter()
+            .map(|candidate| {
+                (
+                    *candidate,
+                    f64::from(same_framerate(*candidate, framerate) as u8),
+                )
+            })
+            .collect(),
+        detail,
+    })
+}
+
+// Create a function to score the candidates from a correctly timed reference file: cues matched by text
+// (see align::match_cues), or by timing when too few match, give pairs of times whose slope, fitted by
+// least squares, is the ratio of the framerate the input was timed for to the one the reference was.
+// The framerate of the reference is the one of the video, or else the one detected from the reference
+// itself; without either, only a slope away from 1 tells which pair of framerates it is.
+fn reference_evidence(
+    subtitles: &SubtitleFile,
+    reference: &SubtitleFile,
+    candidates: &[f64],
+    video_framerate: Option<f64>,
+    threshold: f64,
+) -> Option<Evidence> {
+    let mut pairs: Vec<(i32, i32)> = align::match_cues(&subtitles.cues, &reference.cues, threshold)
+        .iter()
+        .map(|(cue, other)| {
+            (
+                subtitles.cues[*cue].start_ms,
+                reference.cues[*other].start_ms,
+            )
+        })
+        .collect();
+    let mut matched_by = "text";
+    if pairs.len() < 10 {
+        let times = |subtitles: &SubtitleFile| -> Vec<(i32, i32)> {
+            subtitles
+                .cues
+                .iter()
+                .filter(|cue| cue.end_ms > cue.start_ms)
+                .map(|cue| (cue.start_ms, cue.end_ms))
+                .collect()
+        };
+        pairs = dtw::warp_anchors(&times(subtitles), &times(reference), &DtwOptions::default())?;
+        matched_by = "timing";
+    }
+    if pairs.len() < 2 {
+        return None;
+    }
+    let (factor, offset) = fit_linear(&pairs);
+    pairs.retain(|(x, y)| (*x as f64 * factor + offset - *y as f64).abs() <= OUTLIER_MS);
+    if pairs.len() < 2 {
+        return None;
+    }
+    let (slope, _) = fit_linear(&pairs);
+    let exact = |framerate: f64| 1000.0 / frame_ms(framerate);
+    let reference_framerate = video_framerate.or_else(|| {
+        detect_framerate(reference, &DetectOptions::default())
+            .best()
+            .filter(|best| best.confidence >= STRONG_EVIDENCE)
+            .map(|best| best.framerate)
+    });
+    let fits = |framerate: f64, reference: f64| {
+        (1.0 - (slope - exact(framerate) / exact(reference)).abs() / SLOPE_TOLERANCE).max(0.0)
+    };
+    let scores: Vec<(f64, f64)> = match reference_framerate {
+        Some(reference) => candidates
+            .iter()
+            .map(|candidate| (*candidate, fits(*candidate, reference)))
+            .collect(),
+        // Any framerate fits a slope of 1 when the reference framerate is unknown.
+        None if (slope - 1.0).abs() < SLOPE_TOLERANCE => return None,
+        None => candidates
+            .iter()
+            .map(|candidate| {
+                let score = candidates
+                    .iter()
+                    .map(|reference| fits(*candidate, *reference))
+                    .fold(0.0, f64::max);
+                (*candidate, score)
+            })
+            .collect(),
+    };
+    Some(Evidence {
+        method: Method::Reference,
+        weight: sampled_weight(REFERENCE_WEIGHT, pairs.len(), REFERENCE_SAMPLE),
+        samples: pairs.len(),
+        ratios: Vec::new(),
+        histogram: Vec::new(),
+        scores,
+        detail: format!(
+            "slope {:.6} of the cues matched by {}{}",
+            slope,
+            matched_by,
+            reference_framerate.map_or(String::new(), |framerate| format!(
+                ", reference timed for {} fps",
+                framerate
+            ))
+        ),
+    })
+}
+
+// Create a function to find the framerate conversion a known runtime implies: the runtime divided by the
+// end of the last cue is the ratio of the framerate the subtitles were timed for to the one of the film,
+// once the credits after the last cue are allowed for. Every pair of candidates is tried, or only those
+// ending at the framerate of the video when it is known. The confidence falls as the factor gets as
+// close to the next nearest ratio, and is 0 when no ratio is within RUNTIME_SLACK.
+pub fn runtime_match(
+    subtitles: &SubtitleFile,
+    candidates: &[f64],
+    runtime_ms: i32,
+    video_framerate: Option<f64>,
+) -> Option<RuntimeMatch> {
+    let end = subtitles.cues.iter().map(|cue| cue.end_ms).max()?;
+    if end <= 0 || runtime_ms <= 0 {
+        return None;
+    }
+    let factor = runtime_ms as f64 / end as f64;
+    let exact = |framerate: f64| 1000.0 / frame_ms(framerate);
+    let distance = |ratio: f64| ((factor / ratio).ln() - CREDITS).abs();
+    let mut pairs: Vec<(f64, f64, f64)> = Vec::new();
+    for from in candidates {
+        for to in candidates {
+            if video_framerate.is_some_and(|video| !same_framerate(video, *to)) {
+                continue;
+            }
+            // 24 and 23.976 fps differ too little for a runtime to tell them apart.
+            let ratio = exact(*from) / exact(*to);
+            if (ratio - 1.0).abs() < 0.002 && !same_framerate(*from, *to) {
+                continue;
+            }
+            pairs.push((*from, *to, ratio));
+        }
+    }
+    pairs.sort_by(|a, b| distance(a.2).total_cmp(&distance(b.2)));
+    let (from, to, ratio) = *pairs.first()?;
+    let best = distance(ratio);
+    let next = pairs
+        .iter()
+        .map(|(_, _, other)| *other)
+        .find(|other| (other / ratio - 1.0).abs() > 0.002)
+        .map_or(f64::INFINITY, distance);
+    let confidence = if best > RUNTIME_SLACK {
+        0.0
+    } else {
+        (1.0 - best / next).clamp(0.0, 1.0)
+    };
+    Some(RuntimeMatch {
+        factor,
+        conversion: ((ratio - 1.0).abs() > 0.0005).then_some((from, to)),
+        confidence,
+    })
+}
+
+// Create a function to score the candidates from the conversion a known runtime implies: the framerate
+// converted from, or without a conversion the framerate of the video when it is known.
+fn runtime_evidence(
+    found: &RuntimeMatch,
+    candidates: &[f64],
+    video_framerate: Option<f64>,
+) -> Option<Evidence> {
+    let framerate = match found.conversion {
+        Some((from, _)) => from,
+        None => video_framerate?,
+    };
+    Some(Evidence {
+        method: Method::Runtime,
+        weight: RUNTIME_WEIGHT,
+        samples: 1,
+        ratios: Vec::new(),
+        histogram: Vec::new(),
+        scores: candidates
+            .iter()
+            .map(|candidate| {
+                (
+                    *candidate,
+                    found.confidence * f64::from(same_framerate(*candidate, framerate) as u8),
+                )
+            })
+            .collect(),
+        detail: format!(
+            "the runtime is {:.4}x the end of the last cue",
+            found.factor
+        ),
+    })
+}
+
+// Create a function to read the framerate hints in file names, as the hints found and the score they give
+// each candidate: a framerate written out (23.976 or 23.98, 29.97, 25fps, 24p) scores it fully, a
+// region (PAL, NTSC) scores its usual framerates, and the source of a release (BluRay and web releases
+// are film, DVDRip is PAL or NTSC) scores them less. Plain numbers aren't read, they are more often
+// episode numbers or years than framerates.
+fn filename_evidence(names: &[String], candidates: &[f64]) -> Option<Evidence> {
+    let framerate =
+        Regex::new(r"^(\d+(?:\.\d+)?)(?:fps|p)$|^(23\.976?|23\.98|29\.97|59\.94)$").unwrap();
+    // Dots separate words in release names, but are also decimal points.
+    let words = Regex::new(r"\d+\.\d+(?:fps|p)?|[a-z0-9]+(?:-[a-z]+)?").unwrap();
+    let mut hints: Vec<String> = Vec::new();
+    let mut scores = vec![0.0f64; candidates.len()];
+    let mut hint = |name: &str, score: &dyn Fn(f64) -> f64| {
+        if !hints.iter().any(|hint| hint == name) {
+            hints.push(name.to_string());
+        }
+        for (i, candidate) in candidates.iter().enumerate() {
+            scores[i] = scores[i].max(score(*candidate));
+        }
+    };
+    for name in names {
+        let file = std::path::Path::new(name)
+            .file_name()
+            .map_or(name.to_lowercase(), |file| {
+                file.to_string_lossy().to_lowercase()
+            });
+        for word in words.find_iter(&file).map(|word| word.as_str()) {
+            if let Some(caps) = framerate.captures(word) {
+                let value: f64 = caps
+                    .get(1)
+                    .or(caps.get(2))
+                    .unwrap()
+                    .as_str()
+                    .parse()
+                    .unwrap();
+                // 720p and 1080p are resolutions, not framerates.
+                if value <= 120.0 {
+                    hint(word, &|candidate| {
+                        f64::from(same_framerate(candidate, value) as u8)
+                    });
+                }
+                continue;
+            }
+            match word {
+                "pal" => hint(word, &|candidate| Region::Pal.score(candidate)),
+                "ntsc" => hint(word, &|candidate| Region::Ntsc.score(candidate)),
+                "bluray" | "blu-ray" | "bdrip" | "brrip" | "bdremux" | "web-dl" | "webdl"
+                | "webrip" => hint(word, &|candidate| {
+                    SOURCE_HINT * Region::Film.score(candidate)
+                }),
+                "dvdrip" => hint(word, &|candidate| {
+                    SOURCE_HINT
+                        * 0.5
+                        * Region::Pal
+                            .score(candidate)
+                            .max(Region::Ntsc.score(candidate))
+                }),
+                _ => {}
+            }
+        }
+    }
+    if hints.is_empty() {
+        return None;
+    }
+    Some(Evidence {
+        method: Method::Filename,
+        weight: FILENAME_WEIGHT,
+        samples: hints.len(),
+        ratios: Vec::new(),
+        histogram: Vec::new(),
+        scores: candidates.iter().copied().zip(scores).collect(),
+        detail: format!("the file names say {}", hints.join(", ")),
+    })
+}
+
+// Create a function to detect the framerate of each half of a file on its own, from the cue times alone
+// (the framerate the file declares is the same for both).
+fn cross_check(
+    subtitles: &SubtitleFile,
+    candidates: &[f64],
+    options: &DetectOptions,
+) -> Option<Halves> {
+    if subtitles.cues.len() < CROSS_CHECK_CUES {
+        return None;
+    }
+    let mut cues = subtitles.cues.clone();
+    cues.sort_by_key(|cue| cue.start_ms);
+    let second = cues.split_off(cues.len() / 2);
+    let split_ms = second[0].start_ms;
+    let options = DetectOptions {
+        candidates: candidates.to_vec(),
+        tolerance_ms: options.tolerance_ms,
+        ..Default::default()
+    };
+    let detect_half = |cues: Vec<Cue>| {
+        let half = SubtitleFile {
+            cues,
+            framerate: None,
+            ..subtitles.clone()
+        };
+        detect(&half, &options, false).best()
+    };
+    Some(Halves {
+        split_ms,
+        first: detect_half(cues),
+        second: detect_half(second),
+    })
+}
+
+// Create a function to detect the framerate subtitles were timed for, see the top of this file. When
+// both halves of the file confidently point to different framerates, every candidate is less likely.
+pub fn detect_framerate(subtitles: &SubtitleFile, options: &DetectOptions) -> Detection {
+    detect(subtitles, options, true)
+}
+
+// Create a function to detect the framerate of subtitles, of a whole file or of one half of it.
+fn detect(subtitles: &SubtitleFile, options: &DetectOptions, whole_file: bool) -> Detection {
+    let candidates: Vec<f64> = if options.candidates.is_empty() {
+        COMMON_FRAMERATES.to_vec()
+    } else {
+        options.candidates.clone()
+    };
+    let mut evidence = Vec::new();
+    if let Some(declared) = subtitles.framerate {
+        evidence.push(Evidence {
+            method: Method::Declared,
+            weight: DECLARED_WEIGHT,
+            samples: 1,
+            ratios: Vec::new(),
+            histogram: Vec::new(),
+            scores: candidates
+                .iter()
+                .map(|candidate| {
+                    (
+                        *candidate,
+                        f64::from(same_framerate(*candidate, declared as f64) as u8),
+                    )
+                })
+                .collect(),
+            detail: format!("the file declares {} fps", declared),
+        });
+    }
+    evidence.extend(intervals_evidence(
+        subtitles,
+        &candidates,
+        options.tolerance_ms,
+    ));
+    evidence.extend(residues_evidence(
+        subtitles,
+        &candidates,
+        options.tolerance_ms,
+    ));
+    evidence.extend(telecine_evidence(
+        subtitles,
+        &candidates,
+        options.tolerance_ms,
+    ));
+    if let Some(reference) = &options.reference {
+        evidence.extend(reference_evidence(
+            subtitles,
+            reference,
+            &candidates,
+            options.video.map(|(_, framerate)| framerate),
+            options.match_threshold,
+        ));
+    }
+    let video_framerate = options.video.map(|(_, framerate)| framerate);
+    let runtime = options
+        .runtime_ms
+        .and_then(|runtime_ms| runtime_match(subtitles, &candidates, runtime_ms, video_framerate));
+    if let Some(runtime) = &runtime {
+        evidence.extend(runtime_evidence(runtime, &candidates, video_framerate));
+    }
+    if let Some((video_ms, video_framerate)) = options.video {
+        evidence.extend(duration_evidence(
+            subtitles,
+            &candidates,
+            video_ms,
+            video_framerate,
+        ));
+    }
+
+    let combine = |evidence: &[Evidence], i: usize| {
+        1.0 - evidence
+            .iter()
+            .map(|evidence| 1.0 - evidence.weight * evidence.scores[i].1)
+            .product::<f64>()
+    };
+    let strongest = (0..candidates.len())
+        .map(|i| combine(&evidence, i))
+        .fold(0.0, f64::max);
+    let mut priors = Vec::new();
+    if let Some(region) = options.region {
+        priors.push(Evidence {
+            method: Method::Prior,
+            weight: PRIOR_WEIGHT,
+            samples: 1,
+            ratios: Vec::new(),
+            histogram: Vec::new(),
+            scores: candidates
+                .iter()
+                .map(|candidate| (*candidate, region.score(*candidate)))
+                .collect(),
+            detail: "usual framerates of the region".to_string(),
+        });
+    }
+    priors.extend(filename_evidence(&options.names, &candidates));
+    let used_priors = strongest < STRONG_EVIDENCE && !priors.is_empty();
+    for mut prior in priors {
+        if !used_priors {
+            prior.detail = format!("{}, ignored as the evidence is strong", prior.detail);
+        }
+        evidence.push(prior);
+    }
+    let counted: Vec<Evidence> = evidence
+        .iter()
+        .filter(|evidence| used_priors || !evidence.method.is_prior())
+        .cloned()
+        .collect();
+    let mut ranked: Vec<Candidate> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, framerate)| Candidate {
+            framerate: *framerate,
+            confidence: combine(&counted, i),
+        })
+        .collect();
+    let halves = whole_file
+        .then(|| cross_check(subtitles, &candidates, options))
+        .flatten();
+    if halves.is_some_and(|halves| halves.disagree()) {
+        for candidate in ranked.iter_mut() {
+            candidate.confidence *= DISAGREEMENT_PENALTY;
+        }
+    }
+    ranked.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    Detection {
+        candidates: ranked,
+        evidence,
+        used_priors,
+        conversion: whole_file
+            .then(|| detect_conversion(subtitles, &candidates, options.tolerance_ms))
+            .flatten(),
+        halves,
+        borderline: options.borderline,
+        runtime,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::Format;
+
+    // Create a function to make subtitles from frame numbers, the start and end of each cue, at the time
+    // time_ms gives every frame.
+    fn file(frames: &[(i64, i64)], time_ms: impl Fn(i64) -> f64) -> SubtitleFile {
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: frames
+                .iter()
+                .enumerate()
+                .map(|(i, (start, end))| Cue {
+                    index: i + 1,
+                    start_ms: time_ms(*start).round() as i32,
+                    end_ms: time_ms(*end).round() as i32,
+                    lines: vec![format!("Cue {}", i + 1)],
+                    ..Default::default()
+                })
+                .collect(),
+            trailer: Vec::new(),
+        }
+    }
+
+    // Create a function to make count cues of 20 to 99 frames, 3 to 40 frames apart, as a subtitler
+    // working on a frame grid would, the same ones every time.
+    fn cue_frames(count: usize) -> Vec<(i64, i64)> {
+        let mut seed: u64 = 12345;
+        let mut next = |range: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % range
+        };
+        let mut frame = 240;
+        (0..count)
+            .map(|_| {
+                let start = frame + 3 + next(38) as i64;
+                let end = start + 20 + next(80) as i64;
+                frame = end;
+                (start, end)
+            })
+            .collect()
+    }
+
+    fn best(subtitles: &SubtitleFile, options: &DetectOptions) -> Candidate {
+        detect_framerate(subtitles, options).best().unwrap()
+    }
+
+    #[test]
+    fn frame_grids_are_detected() {
+        let frames = cue_frames(200);
+        for (framerate, frame) in [
+            (25.0, 40.0),
+            (23.976, 1001.0 / 24.0),
+            (29.97, 1001.0 / 30.0),
+        ] {
+            let subtitles = file(&frames, |frame_number| frame_number as f64 * frame);
+            let candidate = best(&subtitles, &DetectOptions::default());
+            assert_eq!(candidate.framerate, framerate);
+            assert!(candidate.confidence >= STRONG_EVIDENCE);
+        }
+    }
+
+    #[test]
+    fn field_accurate_pulldown_is_23_976() {
+        // Each film frame starts on the field of the 3:2 cadence it is shown from.
+        let field = |frame: i64| {
+            (frame / 4) as f64 * PULLDOWN_CYCLE + PULLDOWN_FIELDS[(frame % 4) as usize]
+        };
+        let subtitles = file(&cue_frames(200), |frame| field(frame) * 1001.0 / 60.0);
+        let detection = detect_framerate(&subtitles, &DetectOptions::default());
+        assert_eq!(detection.best().unwrap().framerate, 23.976);
+        assert!(detection
+            .evidence
+            .iter()
+            .any(|evidence| evidence.method == Method::Telecine));
+    }
+
+    #[test]
+    fn double_conversion_is_reported() {
+        // PAL subtitles slowed down from 24 to 23.976 fps twice. 23.976 fps subtitles converted from 24
+        // to 25 fps, then from 24 to 23.976, land on the same grid, so only the grid is certain.
+        let factor = (24.0 * 1001.0 / 24000.0_f64).powi(2);
+        let subtitles = file(&cue_frames(300), |frame| frame as f64 * 40.0 * factor);
+        let conversion = detect_conversion(&subtitles, &COMMON_FRAMERATES, GRID_TOLERANCE).unwrap();
+        assert!((frame_ms(conversion.original) * conversion.factor - 40.0 * factor).abs() < 0.001);
+        assert!(conversion.score >= CONVERSION_SCORE);
+        // Subtitles timed for a common framerate aren't conversions.
+        let pal = file(&cue_frames(300), |frame| frame as f64 * 40.0);
+        assert_eq!(
+            detect_conversion(&pal, &COMMON_FRAMERATES, GRID_TOLERANCE),
+            None
+        );
+    }
+
+    #[test]
+    fn a_declared_framerate_outweighs_priors() {
+        let mut subtitles = file(&cue_frames(3), |frame| frame as f64 * 40.0);
+        let options = DetectOptions {
+            region: Some(Region::Ntsc),
+            names: vec!["Movie.1080p.BluRay.srt".to_string()],
+            ..DetectOptions::default()
+        };
+        let detection = detect_framerate(&subtitles, &options);
+        assert!(detection.used_priors);
+        assert_eq!(detection.best().unwrap().framerate, 23.976);
+        subtitles.framerate = Some(25.0);
+        let detection = detect_framerate(&subtitles, &options);
+        assert!(!detection.used_priors);
+        assert_eq!(detection.best().unwrap().framerate, 25.0);
+    }
+
+    #[test]
+    fn file_names_hint_at_framerates_but_not_resolutions() {
+        let evidence = filename_evidence(
+            &[
+                "Show.S01E02.PAL.DVDRip.srt".to_string(),
+                "Film.1080p.25fps.mkv".to_string(),
+            ],
+            &COMMON_FRAMERATES,
+        )
+        .unwrap();
+        assert_eq!(evidence.score(25.0), 1.0);
+        assert_eq!(evidence.score(29.97), SOURCE_HINT * 0.5);
+        assert_eq!(evidence.detail, "the file names say pal, dvdrip, 25fps");
+        assert_eq!(
+            filename_evidence(&["Film.1080p.srt".to_string()], &COMMON_FRAMERATES),
+            None
+        );
+    }
+
+    #[test]
+    fn a_runtime_tells_the_conversion() {
+        let subtitles = file(&cue_frames(200), |frame| frame as f64 * 40.0);
+        let end = subtitles.cues.last().unwrap().end_ms as f64;
+        // The film runs 4.27% longer at 23.976 fps, plus its credits.
+        let runtime = (end * 25.0 * 1001.0 / 24000.0 * CREDITS.exp()).round() as i32;
+        let found = runtime_match(&subtitles, &COMMON_FRAMERATES, runtime, Some(23.976)).unwrap();
+        assert_eq!(found.conversion, Some((25.0, 23.976)));
+        assert!(found.confidence > 0.5);
+        let found = runtime_match(&subtitles, &COMMON_FRAMERATES, runtime * 2, None).unwrap();
+        assert_eq!(found.confidence, 0.0);
+    }
+
+    #[test]
+    fn halves_on_different_grids_disagree() {
+        let frames = cue_frames(200);
+        let split = frames[100].0;
+        let subtitles = file(&frames, |frame| {
+            if frame < split {
+                frame as f64 * 40.0
+            } else {
+                split as f64 * 40.0 + (frame - split) as f64 * 1001.0 / 24.0
+            }
+        });
+        let detection = detect_framerate(&subtitles, &DetectOptions::default());
+        let halves = detection.halves.unwrap();
+        assert!(halves.disagree());
+        assert_eq!(halves.first.unwrap().framerate, 25.0);
+        assert_eq!(halves.second.unwrap().framerate, 23.976);
+        assert!(detection.best().unwrap().confidence <= DISAGREEMENT_PENALTY);
+    }
+}
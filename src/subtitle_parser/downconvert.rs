@@ -0,0 +1,213 @@
+use regex::Regex;
+
+use super::{tags, Format, SubtitleFile};
+
+// What to do with an ASS feature when converting to a simpler format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Keep,
+    Strip,
+    Approximate,
+}
+
+// The ASS features a downconversion policy can be set for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Italics,
+    Bold,
+    Underline,
+    Colors,
+    Newlines,
+    Karaoke,
+    Signs,
+    Positions,
+}
+
+impl Feature {
+    // Create a function to pick a feature from its name on the command line.
+    pub fn from_name(name: &str) -> Option<Feature> {
+        match name.trim().to_lowercase().as_str() {
+            "italics" | "italic" => Some(Feature::Italics),
+            "bold" => Some(Feature::Bold),
+            "underline" => Some(Feature::Underline),
+            "colors" | "colours" | "color" | "colour" => Some(Feature::Colors),
+            "newlines" | "newline" => Some(Feature::Newlines),
+            "karaoke" => Some(Feature::Karaoke),
+            "signs" | "sign" => Some(Feature::Signs),
+            "positions" | "position" => Some(Feature::Positions),
+            _ => None,
+        }
+    }
+
+    // Create a function to tell whether a feature can be approximated.
+    // Colors become the closest basic color, signs are moved to the top of the screen without
+    // their other positioning and drawing overrides.
+    fn can_approximate(self) -> bool {
+        matches!(self, Feature::Colors | Feature::Signs)
+    }
+}
+
+// What to do with each ASS feature when converting to a simpler format. Everything is kept by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownconvertPolicy {
+    pub italics: Action,
+    pub bold: Action,
+    pub underline: Action,
+    pub colors: Action,
+    pub newlines: Action,
+    pub karaoke: Action,
+    pub signs: Action,
+    pub positions: Action,
+}
+
+impl Default for DownconvertPolicy {
+    fn default() -> DownconvertPolicy {
+        DownconvertPolicy {
+            italics: Action::Keep,
+            bold: Action::Keep,
+            underline: Action::Keep,
+            colors: Action::Keep,
+            newlines: Action::Keep,
+            karaoke: Action::Keep,
+            signs: Action::Keep,
+            positions: Action::Keep,
+        }
+    }
+}
+
+impl DownconvertPolicy {
+    // Create a function to set the action for a comma separated list of features (italics,karaoke).
+    pub fn set(&mut self, features: &str, action: Action) -> Result<(), String> {
+        for name in features.split(',').filter(|name| !name.trim().is_empty()) {
+            let feature = Feature::from_name(name)
+                .ok_or_else(|| format!("Unknown feature '{}'", name.trim()))?;
+            if action == Action::Approximate && !feature.can_approximate() {
+                return Err(format!("'{}' can only be kept or stripped", name.trim()));
+            }
+            let field = match feature {
+                Feature::Italics => &mut self.italics,
+                Feature::Bold => &mut self.bold,
+                Feature::Underline => &mut self.underline,
+                Feature::Colors => &mut self.colors,
+                Feature::Newlines => &mut self.newlines,
+                Feature::Karaoke => &mut self.karaoke,
+                Feature::Signs => &mut self.signs,
+                Feature::Positions => &mut self.positions,
+            };
+            *field = action;
+        }
+        Ok(())
+    }
+}
+
+// Create a function to tell whether an ASS event is a sign rather than dialogue: it is positioned with
+// \pos, \move or \clip, is a vector drawing, or its style or name mentions signs.
+fn is_sign(text: &str, style: Option<&str>, name: &str) -> bool {
+    let overrides = Regex::new(r"\{[^}]*\\(pos|move|clip|iclip|org|p[1-9])[^}]*\}").unwrap();
+    let mentions_sign = |value: &str| {
+        let value = value.to_lowercase();
+        value.contains("sign") || value.contains("title")
+    };
+    overrides.is_match(text) || style.is_some_and(mentions_sign) || mentions_sign(name)
+}
+
+// Create a function to remove the override tags matched by a regex, and the blocks they leave empty.
+fn remove_overrides(line: &str, tag: &Regex) -> String {
+    let block = Regex::new(r"\{([^}]*)\}").unwrap();
+    block
+        .replace_all(line, |caps: &regex::Captures| {
+            if !tag.is_match(&caps[1]) {
+                return caps[0].to_string();
+            }
+            let rest = tag.replace_all(&caps[1], "");
+            if rest.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", rest)
+            }
+        })
+        .to_string()
+}
+
+// Create a function to replace ASS colors by the closest basic color.
+fn approximate_colors(line: &str) -> String {
+    let color = Regex::new(r"\\(1?c)&H([0-9a-fA-F]+)&?").unwrap();
+    color
+        .replace_all(line, |caps: &regex::Captures| {
+            let bgr = u32::from_str_radix(&caps[2], 16).unwrap_or(0) & 0xffffff;
+            let rgb = ((bgr & 0xff) << 16) | (bgr & 0xff00) | (bgr >> 16);
+            let rgb = tags::parse_color(tags::nearest_vtt_color(rgb)).unwrap_or(rgb);
+            let bgr = ((rgb & 0xff) << 16) | (rgb & 0xff00) | (rgb >> 16);
+            format!("\\{}&H{:06X}&", &caps[1], bgr)
+        })
+        .to_string()
+}
+
+impl SubtitleFile {
+    // Create a function to apply a downconversion policy to the events of an ASS file.
+    // Files in other formats are left unchanged.
+    pub fn downconvert(&mut self, policy: &DownconvertPolicy) {
+        if self.format != Format::Ass {
+            return;
+        }
+        let italics = Regex::new(r"\\i\d+").unwrap();
+        let bold = Regex::new(r"\\b\d+").unwrap();
+        let underline = Regex::new(r"\\u\d+").unwrap();
+        let colors = Regex::new(r"\\1?c(&H[0-9a-fA-F]+&?)?").unwrap();
+        let karaoke = Regex::new(r"\\(k|K|kf|ko)\d+").unwrap();
+        let positions = Regex::new(r"\\(an?\d+|pos\([^)]*\)|move\([^)]*\))").unwrap();
+        let sign_overrides =
+            Regex::new(r"\\(an?\d+|pos\([^)]*\)|move\([^)]*\)|i?clip\([^)]*\)|org\([^)]*\)|p\d+)")
+                .unwrap();
+        let drawing = Regex::new(r"\{[^}]*\\p[1-9]").unwrap();
+        self.cues.retain_mut(|cue| {
+            let name = cue
+                .settings
+                .as_deref()
+                .and_then(|settings| settings.split(',').nth(1))
+                .unwrap_or("");
+            let sign = is_sign(&cue.lines.join("\\N"), cue.style.as_deref(), name);
+            if sign && policy.signs == Action::Strip {
+                return false;
+            }
+            // Vector drawings can't be shown as text.
+            let is_drawing = cue.lines.iter().any(|line| drawing.is_match(line));
+            if sign && policy.signs == Action::Approximate && is_drawing {
+                return false;
+            }
+            for line in cue.lines.iter_mut() {
+                for (action, tag) in [
+                    (policy.italics, &italics),
+                    (policy.bold, &bold),
+                    (policy.underline, &underline),
+                    (policy.colors, &colors),
+                    (policy.karaoke, &karaoke),
+                    (policy.positions, &positions),
+                ] {
+                    if action == Action::Strip {
+                        *line = remove_overrides(line, tag);
+                    }
+                }
+                if policy.colors == Action::Approximate {
+                    *line = approximate_colors(line);
+                }
+            }
+            if sign && policy.signs == Action::Approximate {
+                for line in cue.lines.iter_mut() {
+                    *line = remove_overrides(line, &sign_overrides);
+                }
+                if let Some(first) = cue.lines.first_mut() {
+                    *first = format!("{{\\an8}}{}", first);
+                }
+            }
+            if policy.newlines == Action::Strip && cue.lines.len() > 1 {
+                let text: Vec<&str> = cue.lines.iter().map(|line| line.trim()).collect();
+                cue.lines = vec![text.join(" ")];
+            }
+            true
+        });
+        for (i, cue) in self.cues.iter_mut().enumerate() {
+            cue.index = i + 1;
+        }
+    }
+}
@@ -0,0 +1,208 @@
+// Dynamic time warping of the timing of two subtitle files, for a translation timed to another cut of
+// a video than a reference such as a whisper transcript: their text can't be compared, but where the
+// cues fall in time can. Both files become signals of how much of each step of time is covered by
+// cues, and the warp that best lays one over the other is found step by step, so offsets can change
+// at every cut.
+
+use super::correlate::{self, CorrelateOptions};
+use super::provenance::Transform;
+use super::SubtitleFile;
+
+// Resolution of the warp, in miliseconds.
+const STEP_MS: i32 = 100;
+
+// Cost of a step of the warp that moves in one file only, against 1 for a step covered in one file
+// and not the other: small enough to follow a cut, large enough not to chase every pause.
+const PENALTY: f32 = 0.05;
+
+// Signals are smoothed over this many steps either way, so cues near each other still line up.
+const SMOOTHING: usize = 3;
+
+// How far the warp goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DtwOptions {
+    // Largest distance between the warp and the offset and ratio that best fit the whole file, in
+    // miliseconds. Cuts longer than this aren't followed.
+    pub band_ms: i32,
+}
+
+impl Default for DtwOptions {
+    fn default() -> DtwOptions {
+        DtwOptions { band_ms: 20_000 }
+    }
+}
+
+// Create a function to turn intervals into how much of every step of time they cover, smoothed.
+fn activity(intervals: &[(f64, f64)], length: usize) -> Vec<f32> {
+    let mut covered = vec![0.0f32; length];
+    let step = STEP_MS as f64;
+    for (start, end) in intervals {
+        let first = (start / step).floor().max(0.0) as usize;
+        let last = ((end / step).ceil().max(0.0) as usize).min(length);
+        for (i, value) in covered.iter_mut().enumerate().take(last).skip(first) {
+            let from = start.max(i as f64 * step);
+            let to = end.min((i + 1) as f64 * step);
+            *value += ((to - from) / step).max(0.0) as f32;
+        }
+    }
+    (0..length)
+        .map(|i| {
+            let window = &covered[i.saturating_sub(SMOOTHING)..(i + SMOOTHING + 1).min(length)];
+            window.iter().sum::<f32>() / window.len() as f32
+        })
+        .collect()
+}
+
+// Create a function to find anchors that warp the times of cues (start and end times) onto the times
+// of reference cues: the best offset and ratio of the whole file first (see correlate), then the warp
+// within a band around it. Returns one anchor per cue that keeps the cues in order, or None without
+// cues on either side.
+pub fn warp_anchors(
+    cues: &[(i32, i32)],
+    reference: &[(i32, i32)],
+    options: &DtwOptions,
+) -> Option<Vec<(i32, i32)>> {
+    if cues.is_empty() || reference.is_empty() {
+        return None;
+    }
+    let (ratio, offset) = correlate::correlate(cues, reference, &CorrelateOptions::default())
+        .map(|correlation| (correlation.ratio, correlation.offset as f64))
+        .unwrap_or((1.0, 0.0));
+    let moved: Vec<(f64, f64)> = cues
+        .iter()
+        .map(|(start, end)| (*start as f64 * ratio + offset, *end as f64 * ratio + offset))
+        .collect();
+    let target: Vec<(f64, f64)> = reference
+        .iter()
+        .map(|(start, end)| (*start as f64, *end as f64))
+        .collect();
+    let band = (options.band_ms.max(STEP_MS) / STEP_MS) as usize;
+    // Both files are padded by the band at the start, so cues moved before zero still take part.
+    let pad = (band * STEP_MS as usize) as f64;
+    let moved: Vec<(f64, f64)> = moved
+        .iter()
+        .map(|(start, end)| (start + pad, end + pad))
+        .collect();
+    let target: Vec<(f64, f64)> = target
+        .iter()
+        .map(|(start, end)| (start + pad, end + pad))
+        .collect();
+    let end = moved
+        .iter()
+        .chain(target.iter())
+        .map(|(_, end)| *end)
+        .fold(0.0, f64::max);
+    let length = (end / STEP_MS as f64).ceil() as usize + band + 1;
+    let a = activity(&moved, length);
+    let b = activity(&target, length);
+
+    // Costs of the previous and current rows of the band, and the move that reached every cell:
+    // 0 from the diagonal, 1 from the previous step of the cues, 2 from the previous step of the
+    // reference. Cell k of row i is step i + k - band of the reference.
+    let width = 2 * band + 1;
+    let mut previous = vec![f32::INFINITY; width];
+    let mut current = vec![f32::INFINITY; width];
+    let mut moves = vec![0u8; length * width];
+    for i in 0..length {
+        for k in 0..width {
+            let j = i + k;
+            if j < band || j - band >= length {
+                current[k] = f32::INFINITY;
+                continue;
+            }
+            let j = j - band;
+            let cost = (a[i] - b[j]).abs();
+            if i == 0 && j == 0 {
+                current[k] = cost;
+                continue;
+            }
+            let diagonal = previous[k];
+            let from_cues = if k + 1 < width {
+                previous[k + 1] + PENALTY
+            } else {
+                f32::INFINITY
+            };
+            let from_reference = if k > 0 {
+                current[k - 1] + PENALTY
+            } else {
+                f32::INFINITY
+            };
+            let (best, step) = if diagonal <= from_cues && diagonal <= from_reference {
+                (diagonal, 0)
+            } else if from_cues <= from_reference {
+                (from_cues, 1)
+            } else {
+                (from_reference, 2)
+            };
+            current[k] = cost + best;
+            moves[i * width + k] = step;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    // Walk the warp back from the end, keeping the average reference step of every step of the cues.
+    let mut sums = vec![0usize; length];
+    let mut counts = vec![0usize; length];
+    let (mut i, mut k) = (length - 1, band);
+    loop {
+        sums[i] += i + k - band;
+        counts[i] += 1;
+        if i == 0 && k == band {
+            break;
+        }
+        match moves[i * width + k] {
+            0 if i > 0 => i -= 1,
+            1 if i > 0 => {
+                i -= 1;
+                k += 1;
+            }
+            _ if k > 0 => k -= 1,
+            _ => break,
+        }
+    }
+    let map: Vec<f64> = sums
+        .iter()
+        .zip(counts.iter())
+        .map(|(sum, count)| *sum as f64 / (*count).max(1) as f64)
+        .collect();
+
+    let mut anchors: Vec<(i32, i32)> = Vec::new();
+    for ((start, _), (moved_start, _)) in cues.iter().zip(moved.iter()) {
+        let step = ((moved_start / STEP_MS as f64).floor().max(0.0) as usize).min(length - 1);
+        let to = (map[step] * STEP_MS as f64 + moved_start - step as f64 * STEP_MS as f64 - pad)
+            .round() as i32;
+        if anchors
+            .last()
+            .is_none_or(|last| *start > last.0 && to > last.1)
+        {
+            anchors.push((*start, to));
+        }
+    }
+    Some(anchors)
+}
+
+impl SubtitleFile {
+    // Create a function to retime the cues after reference subtitles in another language, or a
+    // transcript, by warping their timing onto the reference with warp_anchors. Returns the warp
+    // applied, or an error when either file has no cues. Cues can end up before zero, see clamp_to_zero.
+    pub fn align_timing_to(
+        &mut self,
+        reference: &SubtitleFile,
+        options: &DtwOptions,
+    ) -> Result<Transform, String> {
+        let timings = |file: &SubtitleFile| -> Vec<(i32, i32)> {
+            file.cues
+                .iter()
+                .filter(|cue| cue.end_ms > cue.start_ms)
+                .map(|cue| (cue.start_ms, cue.end_ms))
+                .collect()
+        };
+        let anchors = warp_anchors(&timings(self), &timings(reference), options)
+            .ok_or_else(|| "Both files need cues to align their timing".to_string())?;
+        for cue in self.cues.iter_mut() {
+            cue.start_ms = super::timing::warp_time(&anchors, cue.start_ms);
+            cue.end_ms = super::timing::warp_time(&anchors, cue.end_ms);
+        }
+        Ok(Transform::Warp { anchors })
+    }
+}
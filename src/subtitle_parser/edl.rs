@@ -0,0 +1,228 @@
+use regex::Regex;
+
+use super::segments::{check_segments, Segment};
+use super::timecode::TimecodeRate;
+use super::timing::TimeRange;
+
+// Create a function to convert an EDL timecode to miliseconds: HH:MM:SS:FF at the given framerate, or
+// HH:MM:SS;FF, which is always 29.97 fps drop-frame.
+fn timecode_to_ms(timecode: &str, framerate: f64) -> Option<i32> {
+    if timecode.contains(';') {
+        return TimecodeRate::ntsc(true).parse(timecode);
+    }
+    TimecodeRate::new(framerate, false).ok()?.parse(timecode)
+}
+
+// Create a function to read the events of a CMX3600 EDL as kept segments: each event plays a source
+// range at a record time. Record times are counted from the first event, so the cut starts at zero.
+// Audio events that repeat a video event are only counted once.
+fn parse_cmx3600(text: &str, framerate: f64) -> Result<Vec<Segment>, String> {
+    let timecode = r"(\d{2}:\d{2}:\d{2}[:;]\d{2})";
+    let event = Regex::new(&format!(
+        r"^\s*\d+\s+.*?{0}\s+{0}\s+{0}\s+{0}\s*$",
+        timecode
+    ))
+    .unwrap();
+    let mut events: Vec<(i32, i32, i32)> = Vec::new();
+    for line in text.lines() {
+        let Some(caps) = event.captures(line) else {
+            continue;
+        };
+        let time = |i: usize| {
+            timecode_to_ms(&caps[i], framerate)
+                .ok_or_else(|| format!("Invalid timecode '{}'", &caps[i]))
+        };
+        let (source_in, source_out, record_in) = (time(1)?, time(2)?, time(3)?);
+        if source_out > source_in && !events.contains(&(source_in, source_out, record_in)) {
+            events.push((source_in, source_out, record_in));
+        }
+    }
+    let start = events
+        .iter()
+        .map(|(_, _, record_in)| *record_in)
+        .min()
+        .ok_or("No event found in the EDL")?;
+    Ok(events
+        .iter()
+        .map(|(source_in, source_out, record_in)| Segment {
+            range: TimeRange {
+                after: Some(*source_in),
+                before: Some(*source_out),
+            },
+            offset: record_in - start - source_in,
+        })
+        .collect())
+}
+
+// Create a function to turn a list of removed ranges (commercials) into the kept segments between them,
+// each moved back by the length removed before it.
+fn kept_segments(mut removed: Vec<(i32, i32)>) -> Result<Vec<Segment>, String> {
+    if removed.is_empty() {
+        return Err("No cut found in the cut list".to_string());
+    }
+    removed.sort();
+    let mut segments = Vec::new();
+    let mut kept_from = 0;
+    let mut offset = 0;
+    for (start, end) in removed {
+        if start > kept_from {
+            segments.push(Segment {
+                range: TimeRange {
+                    after: Some(kept_from),
+                    before: Some(start),
+                },
+                offset,
+            });
+        }
+        if end > kept_from {
+            offset -= end - start.max(kept_from);
+            kept_from = end;
+        }
+    }
+    segments.push(Segment {
+        range: TimeRange {
+            after: Some(kept_from),
+            before: None,
+        },
+        offset,
+    });
+    Ok(segments)
+}
+
+// Create a function to read the commercials of a comskip .txt cut list: a "FILE PROCESSING COMPLETE
+// 53999 FRAMES AT 2997" header giving the framerate in hundredths, then a start and end frame per line.
+fn parse_comskip(text: &str) -> Result<Vec<Segment>, String> {
+    let header = Regex::new(r"FRAMES AT\s+(\d+)").unwrap();
+    let framerate = header
+        .captures(text)
+        .and_then(|caps| caps[1].parse::<f64>().ok())
+        .filter(|framerate| *framerate > 0.0)
+        .map(|framerate| framerate / 100.0)
+        .ok_or("Missing framerate in the comskip header")?;
+    let pair = Regex::new(r"^\s*(\d+)\s+(\d+)\s*$").unwrap();
+    let frame =
+        |value: &str| (value.parse::<f64>().unwrap_or(0.0) * 1000.0 / framerate).round() as i32;
+    let removed = text
+        .lines()
+        .filter_map(|line| pair.captures(line))
+        .map(|caps| (frame(&caps[1]), frame(&caps[2])))
+        .collect();
+    kept_segments(removed)
+}
+
+// Create a function to read the commercials of an MPlayer EDL, as written by comskip (.edl): a start
+// and end time in seconds and an action per line. Cuts (0) and commercial breaks (3) are removed, mutes
+// (1) are ignored.
+fn parse_mplayer(text: &str) -> Result<Vec<Segment>, String> {
+    let mut removed = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let seconds = |field: Option<&&str>| {
+            field
+                .and_then(|field| field.parse::<f64>().ok())
+                .map(|seconds| (seconds * 1000.0).round() as i32)
+                .ok_or_else(|| format!("Line {}: expected <start> <end> <action>", number + 1))
+        };
+        let (start, end) = (seconds(fields.first())?, seconds(fields.get(1))?);
+        if matches!(fields.get(2), None | Some(&"0") | Some(&"3")) && end > start {
+            removed.push((start, end));
+        }
+    }
+    kept_segments(removed)
+}
+
+// Create a function to read an edit decision list as the segments of the input that are kept, each with
+// the offset that moves it to its place in the edited cut. CMX3600 EDLs list the kept events, with
+// timecodes at the given framerate; comskip cut lists (.txt) and MPlayer EDLs (.edl with a start, end
+// and action per line) list the removed commercials.
+pub fn parse_cut_list(text: &str, framerate: f64) -> Result<Vec<Segment>, String> {
+    let timecodes = Regex::new(r"\d{2}:\d{2}:\d{2}[:;]\d{2}").unwrap();
+    let mut segments = if text.contains("FILE PROCESSING COMPLETE") {
+        parse_comskip(text)?
+    } else if text
+        .lines()
+        .any(|line| timecodes.find_iter(line).count() == 4)
+    {
+        parse_cmx3600(text, framerate)?
+    } else {
+        parse_mplayer(text)?
+    };
+    check_segments(&mut segments)?;
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CMX3600: &str = "TITLE: Cut
+FCM: NON-DROP FRAME
+
+001  AX       V     C        00:00:00:00 00:10:00:00 01:00:00:00 01:10:00:00
+002  AX       V     C        00:12:00:00 00:20:00:00 01:10:00:00 01:18:00:00
+002  AX       A     C        00:12:00:00 00:20:00:00 01:10:00:00 01:18:00:00
+";
+
+    fn segment(after: i32, before: Option<i32>, offset: i32) -> Segment {
+        Segment {
+            range: TimeRange {
+                after: Some(after),
+                before,
+            },
+            offset,
+        }
+    }
+
+    #[test]
+    fn cmx3600_events_are_kept_segments() {
+        assert_eq!(
+            parse_cut_list(CMX3600, 25.0).unwrap(),
+            [
+                segment(0, Some(600000), 0),
+                segment(720000, Some(1200000), -120000)
+            ]
+        );
+    }
+
+    #[test]
+    fn drop_frame_timecodes_are_29_97() {
+        let edl = "001  AX  V  C  00:01:00;02 00:10:00;00 00:00:00;00 00:08:59;28\n";
+        let segments = parse_cut_list(edl, 25.0).unwrap();
+        // 00:01:00;02 is frame 1800 and 00:10:00;00 frame 17982, each 1001/30 ms long: 60060 ms and
+        // 599999.4 ms, drop-frame labels running 0.6 ms behind the clock after ten minutes.
+        assert_eq!(segments, [segment(60060, Some(599999), -60060)]);
+        // Frame 12 at 24000/1001 fps is 500.5 ms in, rounded up.
+        let edl = "001  AX  V  C  00:00:00:12 00:00:01:00 00:00:00:00 00:00:00:12\n";
+        let segments = parse_cut_list(edl, 24000.0 / 1001.0).unwrap();
+        assert_eq!(segments, [segment(501, Some(1001), -501)]);
+    }
+
+    #[test]
+    fn commercials_are_removed_segments() {
+        let comskip = "FILE PROCESSING COMPLETE 53999 FRAMES AT 2500
+-------------------
+750\t1500
+3000\t3750
+";
+        let mplayer = "30.0 60.0 3\n90 95 1\n120 150 0\n";
+        let kept = [
+            segment(0, Some(30000), 0),
+            segment(60000, Some(120000), -30000),
+            segment(150000, None, -60000),
+        ];
+        assert_eq!(parse_cut_list(comskip, 29.97).unwrap(), kept);
+        assert_eq!(parse_cut_list(mplayer, 29.97).unwrap(), kept);
+    }
+
+    #[test]
+    fn malformed_cut_lists_are_errors() {
+        assert!(parse_cut_list("", 29.97).is_err());
+        assert!(parse_cut_list("FILE PROCESSING COMPLETE\n1 2\n", 29.97).is_err());
+        assert!(parse_cut_list("30.0 sixty 3\n", 29.97).is_err());
+        let overlapping = CMX3600.replace("00:12:00:00 00:20:00:00", "00:05:00:00 00:20:00:00");
+        assert!(parse_cut_list(&overlapping, 25.0).is_err());
+    }
+}
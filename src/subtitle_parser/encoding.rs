@@ -0,0 +1,335 @@
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{
+    DecoderResult, Encoding, ISO_8859_2, UTF_8, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252,
+};
+use std::borrow::Cow;
+
+// Create a function to detect the text encoding of a file: from its byte order mark, then UTF-8 if
+// it is valid UTF-8, then a guess from the byte frequencies (Windows-1252, Windows-1250, Shift_JIS,
+// GBK, Big5, ...). Returns the encoding and the length of the byte order mark.
+pub fn detect(contents: &[u8]) -> (&'static Encoding, usize) {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(contents) {
+        return (encoding, bom_length);
+    }
+    if std::str::from_utf8(contents).is_ok() {
+        return (UTF_8, 0);
+    }
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(contents, true);
+    (detector.guess(None, Utf8Detection::Allow), 0)
+}
+
+// Create a function to decode a text file to UTF-8, whatever its encoding. The byte order mark is
+// removed. Returns the text and the encoding it was decoded from.
+pub fn decode(contents: &[u8]) -> (Cow<'_, str>, &'static Encoding) {
+    let (encoding, bom_length) = detect(contents);
+    let (text, _) = encoding.decode_without_bom_handling(&contents[bom_length..]);
+    (text, encoding)
+}
+
+// Create a function to pick an input encoding from its WHATWG label: utf-8, utf-16le, shift_jis, gbk,
+// euc-kr, windows-1256, ...
+pub fn from_label(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("Unknown encoding '{}'", label))
+}
+
+// Create a function to decode a text file from a given encoding, skipping its byte order mark. Fails
+// with the offsets of the bytes that aren't valid in that encoding.
+pub fn decode_as(contents: &[u8], encoding: &'static Encoding) -> Result<String, String> {
+    let contents = match Encoding::for_bom(contents) {
+        Some((bom, length)) if bom == encoding => &contents[length..],
+        _ => contents,
+    };
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::new();
+    let mut invalid = Vec::new();
+    let mut position = 0;
+    loop {
+        text.reserve(contents.len() - position + 16);
+        let (result, read) =
+            decoder.decode_to_string_without_replacement(&contents[position..], &mut text, true);
+        position += read;
+        match result {
+            DecoderResult::InputEmpty => break,
+            DecoderResult::OutputFull => {}
+            DecoderResult::Malformed(length, consumed) => {
+                invalid.push(position - length as usize - consumed as usize);
+            }
+        }
+    }
+    if invalid.is_empty() {
+        return Ok(text);
+    }
+    let offsets: Vec<String> = invalid
+        .iter()
+        .take(10)
+        .map(|offset| offset.to_string())
+        .collect();
+    let more = match invalid.len() {
+        count if count > 10 => format!(" and {} more", count - 10),
+        _ => String::new(),
+    };
+    Err(format!(
+        "Not valid {}: undecodable bytes at offsets {}{}",
+        encoding.name(),
+        offsets.join(", "),
+        more
+    ))
+}
+
+// Create a function to name an encoding the way people know it: ASCII, UTF-8, UTF-8 with BOM,
+// UTF-16LE, windows-1252, ...
+pub fn describe(contents: &[u8]) -> String {
+    let (encoding, bom_length) = detect(contents);
+    if encoding == UTF_8 && bom_length > 0 {
+        "UTF-8 with BOM".to_string()
+    } else if encoding == UTF_8 && contents.is_ascii() {
+        "ASCII".to_string()
+    } else {
+        encoding.name().to_string()
+    }
+}
+
+// The legacy encodings UTF-8 text is most often read as by mistake, turning é into Ã© with
+// windows-1252, or п into Рї with windows-1251. ISO-8859-2 goes before windows-1250, which has most
+// of its letters at other places.
+const MISREAD_AS: [&Encoding; 4] = [WINDOWS_1252, ISO_8859_2, WINDOWS_1250, WINDOWS_1251];
+
+// UTF-8 text read as a legacy encoding: the encoding it was read as, and the damaged sequences found
+// with the characters they were, as (Ã©, é).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mojibake {
+    pub read_as: &'static Encoding,
+    pub sequences: Vec<(String, String)>,
+}
+
+// Create a function to find mojibake in a text: runs of characters which, written back in the legacy
+// encoding they were read as, make a UTF-8 sequence. Text encoded to UTF-8 twice looks the same.
+pub fn find_mojibake(text: &str) -> Option<Mojibake> {
+    let characters: Vec<char> = text.chars().collect();
+    for read_as in MISREAD_AS {
+        let byte = |character: char| {
+            let mut buffer = [0; 4];
+            let (bytes, _, unmappable) = read_as.encode(character.encode_utf8(&mut buffer));
+            match (unmappable, &bytes[..]) {
+                (false, [byte]) if *byte >= 0x80 => Some(*byte),
+                _ => None,
+            }
+        };
+        let mut sequences: Vec<(String, String)> = Vec::new();
+        let mut i = 0;
+        while i < characters.len() {
+            // The length of the UTF-8 sequence a lead byte starts.
+            let length = match byte(characters[i]) {
+                Some(0xc2..=0xdf) => 2,
+                Some(0xe0..=0xef) => 3,
+                Some(0xf0..=0xf4) => 4,
+                _ => 0,
+            };
+            let run = characters.get(i..i + length).filter(|_| length > 0);
+            let decoded = run
+                .and_then(|run| run.iter().map(|character| byte(*character)).collect())
+                .and_then(|bytes: Vec<u8>| String::from_utf8(bytes).ok());
+            match (run, decoded) {
+                (Some(run), Some(decoded)) => {
+                    let damaged: String = run.iter().collect();
+                    if !sequences.iter().any(|(seen, _)| *seen == damaged) {
+                        sequences.push((damaged, decoded));
+                    }
+                    i += length;
+                }
+                _ => i += 1,
+            }
+        }
+        if !sequences.is_empty() {
+            return Some(Mojibake { read_as, sequences });
+        }
+    }
+    None
+}
+
+// The text encoding a file is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    // UTF-8 with a byte order mark, which some hardware players need to recognize UTF-8.
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    // A legacy single or multi-byte encoding, such as windows-1252 or Shift_JIS.
+    Legacy(&'static Encoding),
+}
+
+impl OutputEncoding {
+    // Create a function to pick an output encoding from its name: utf-8, utf-8-bom, utf-16le,
+    // utf-16be, or any WHATWG encoding label (windows-1252, iso-8859-2, shift_jis, gbk, big5, ...).
+    pub fn from_name(name: &str) -> Result<OutputEncoding, String> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(OutputEncoding::Utf8),
+            "utf-8-bom" | "utf8-bom" | "utf-8-sig" => Ok(OutputEncoding::Utf8Bom),
+            "utf-16le" | "utf-16" | "utf16le" => Ok(OutputEncoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(OutputEncoding::Utf16Be),
+            label => match Encoding::for_label(label.as_bytes()) {
+                Some(encoding) if encoding.output_encoding() == encoding => {
+                    Ok(OutputEncoding::Legacy(encoding))
+                }
+                _ => Err(format!("Unknown or unsupported output encoding '{}'", name)),
+            },
+        }
+    }
+
+    // Create a function to add a byte order mark to UTF-8 output. UTF-16 output always has one, and
+    // legacy encodings have none.
+    pub fn with_bom(self) -> OutputEncoding {
+        match self {
+            OutputEncoding::Utf8 => OutputEncoding::Utf8Bom,
+            encoding => encoding,
+        }
+    }
+
+    // Create a function to encode UTF-8 text. Fails with the characters the encoding can't represent,
+    // rather than writing them as numeric character references.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            OutputEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            OutputEncoding::Utf8Bom => Ok([&[0xef, 0xbb, 0xbf], text.as_bytes()].concat()),
+            OutputEncoding::Utf16Le => Ok([0xfeff]
+                .into_iter()
+                .chain(text.encode_utf16())
+                .flat_map(|unit| unit.to_le_bytes())
+                .collect()),
+            OutputEncoding::Utf16Be => Ok([0xfeff]
+                .into_iter()
+                .chain(text.encode_utf16())
+                .flat_map(|unit| unit.to_be_bytes())
+                .collect()),
+            OutputEncoding::Legacy(encoding) => {
+                let (bytes, _, had_errors) = encoding.encode(text);
+                if !had_errors {
+                    return Ok(bytes.into_owned());
+                }
+                let mut missing: Vec<char> = Vec::new();
+                for character in text.chars() {
+                    let (_, _, unmappable) = encoding.encode(character.encode_utf8(&mut [0; 4]));
+                    if unmappable && !missing.contains(&character) {
+                        missing.push(character);
+                    }
+                }
+                let missing: Vec<String> = missing
+                    .iter()
+                    .map(|character| format!("'{}' (U+{:04X})", character, *character as u32))
+                    .collect();
+                Err(format!(
+                    "Characters not representable in {}: {}",
+                    encoding.name(),
+                    missing.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+// The line endings a text file is written with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Newline {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl Newline {
+    // Create a function to pick line endings from their name on the command line: lf or crlf.
+    pub fn from_name(name: &str) -> Result<Newline, String> {
+        match name.to_lowercase().as_str() {
+            "lf" | "unix" => Ok(Newline::Lf),
+            "crlf" | "windows" | "dos" => Ok(Newline::Crlf),
+            _ => Err(format!("Invalid newline '{}', expected lf or crlf", name)),
+        }
+    }
+
+    // Create a function to tell the line endings most lines of a text use.
+    pub fn detect(text: &str) -> Newline {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            Newline::Crlf
+        } else {
+            Newline::Lf
+        }
+    }
+
+    // Create a function to rewrite every line ending of a text with these line endings.
+    pub fn apply(&self, text: &str) -> String {
+        let text = text.replace("\r\n", "\n");
+        match self {
+            Newline::Lf => text,
+            Newline::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, UTF_16BE, UTF_16LE};
+
+    const TEXT: &str = "1\n00:00:01,000 --> 00:00:02,000\nCafé, déjà vu.\n";
+
+    #[test]
+    fn encodings_are_detected() {
+        assert_eq!(detect(TEXT.as_bytes()), (UTF_8, 0));
+        let (bytes, _, _) = WINDOWS_1252.encode(TEXT);
+        assert_eq!(detect(&bytes).0, WINDOWS_1252);
+        assert_eq!(describe(b"plain"), "ASCII");
+        assert_eq!(
+            describe(&OutputEncoding::Utf8Bom.encode(TEXT).unwrap()),
+            "UTF-8 with BOM"
+        );
+    }
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        for (output, encoding) in [
+            (OutputEncoding::Utf8Bom, UTF_8),
+            (OutputEncoding::Utf16Le, UTF_16LE),
+            (OutputEncoding::Utf16Be, UTF_16BE),
+        ] {
+            let bytes = output.encode(TEXT).unwrap();
+            assert_eq!(decode(&bytes), (Cow::Borrowed(TEXT), encoding));
+        }
+        let legacy = OutputEncoding::from_name("windows-1252").unwrap();
+        let bytes = legacy.encode(TEXT).unwrap();
+        assert_eq!(decode_as(&bytes, WINDOWS_1252).unwrap(), TEXT);
+    }
+
+    #[test]
+    fn unrepresentable_and_undecodable_text_are_errors() {
+        let error = OutputEncoding::Legacy(WINDOWS_1252)
+            .encode("日本")
+            .unwrap_err();
+        assert!(error.contains("U+65E5"));
+        assert!(OutputEncoding::from_name("klingon").is_err());
+        assert!(from_label("klingon").is_err());
+        let error = decode_as(b"ok \x82\xa0 \xff", SHIFT_JIS).unwrap_err();
+        assert!(error.contains("offsets 6"));
+    }
+
+    #[test]
+    fn mojibake_is_found() {
+        let mojibake = find_mojibake("CafÃ© dÃ©jÃ  vu").unwrap();
+        assert_eq!(mojibake.read_as, WINDOWS_1252);
+        assert_eq!(mojibake.sequences[0], ("Ã©".to_string(), "é".to_string()));
+        assert_eq!(find_mojibake(TEXT), None);
+    }
+
+    #[test]
+    fn newlines_are_detected_and_applied() {
+        assert_eq!(Newline::detect("a\r\nb\r\nc\n"), Newline::Crlf);
+        assert_eq!(Newline::detect(TEXT), Newline::Lf);
+        assert_eq!(Newline::Crlf.apply("a\nb\r\n"), "a\r\nb\r\n");
+        assert_eq!(Newline::Lf.apply("a\r\nb\n"), "a\nb\n");
+        assert!(Newline::from_name("cr").is_err());
+    }
+}
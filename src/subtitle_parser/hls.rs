@@ -0,0 +1,280 @@
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{decode_text, vtt, Cue, Format, SubtitleFile};
+
+// Length of the segments written when re-segmenting, in miliseconds.
+pub const DEFAULT_SEGMENT_DURATION: i32 = 6000;
+
+// MPEG transport stream timestamps count a 90 kHz clock.
+const MPEGTS_CLOCK: i64 = 90;
+
+// Create a function to tell whether a path is an HLS playlist.
+pub fn is_playlist(path: &str) -> bool {
+    path.to_lowercase().ends_with(".m3u8")
+}
+
+// Create a function to list the segments of a playlist, or the .vtt files of a directory in name order.
+// A directory that holds an .m3u8 playlist is read through that playlist.
+fn segment_paths(path: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(path);
+    let playlist = if path.is_dir() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)
+            .map_err(|error| format!("Unable to read {}: {}", path.display(), error))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        entries.sort_by_key(|entry| (entry.as_os_str().len(), entry.clone()));
+        let extension = |entry: &PathBuf, name: &str| {
+            entry
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case(name))
+        };
+        match entries.iter().find(|entry| extension(entry, "m3u8")) {
+            Some(playlist) => playlist.clone(),
+            None => {
+                return Ok(entries
+                    .into_iter()
+                    .filter(|entry| extension(entry, "vtt"))
+                    .collect())
+            }
+        }
+    } else {
+        path.to_path_buf()
+    };
+    let contents = fs::read(&playlist)
+        .map_err(|error| format!("Unable to read {}: {}", playlist.display(), error))?;
+    let contents = decode_text(&contents)?;
+    let directory = playlist.parent().unwrap_or(Path::new(""));
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|uri| {
+            if uri.contains("://") {
+                Err(format!("Remote segment '{}' is not supported", uri))
+            } else {
+                Ok(directory.join(uri))
+            }
+        })
+        .collect()
+}
+
+// Create a function to read the X-TIMESTAMP-MAP of a segment header, as (MPEGTS, LOCAL in miliseconds).
+fn timestamp_map(header: &str) -> Option<(i64, i32)> {
+    let re = Regex::new(r"X-TIMESTAMP-MAP=([^\r\n]*)").unwrap();
+    let map = re.captures(header)?;
+    let mut mpegts = 0;
+    let mut local = 0;
+    for field in map[1].split(',') {
+        match field.trim().split_once(':') {
+            Some(("MPEGTS", value)) => mpegts = value.trim().parse::<i64>().ok()?,
+            Some(("LOCAL", value)) => local = vtt::convert_to_miliseconds(value)?,
+            _ => {}
+        }
+    }
+    Some((mpegts, local))
+}
+
+// Create a function to replace the X-TIMESTAMP-MAP of a header with one mapping local time 0 to the given MPEGTS.
+fn with_timestamp_map(header: &[String], mpegts: i64) -> Vec<String> {
+    let map = Regex::new(r"\n?X-TIMESTAMP-MAP=[^\r\n]*").unwrap();
+    let mut header = header.to_vec();
+    header[0] = format!(
+        "{}\nX-TIMESTAMP-MAP=MPEGTS:{},LOCAL:00:00:00.000",
+        map.replace(&header[0], ""),
+        mpegts
+    );
+    header
+}
+
+// Create a function to stitch the WebVTT segments of an HLS playlist (or a directory of segments) into
+// one file. Each segment's X-TIMESTAMP-MAP is honored, so cue times become relative to the first
+// segment's MPEGTS, which is kept in the header. Cues repeated across segment boundaries are merged.
+pub fn read_playlist(path: &str) -> Result<SubtitleFile, String> {
+    let segments = segment_paths(path)?;
+    let mut stitched: Option<SubtitleFile> = None;
+    let mut base = 0;
+    for segment in &segments {
+        let contents = fs::read(segment)
+            .map_err(|error| format!("Unable to read {}: {}", segment.display(), error))?;
+        let file = decode_text(&contents)
+            .and_then(vtt::parse)
+            .map_err(|error| format!("Unable to parse {}: {}", segment.display(), error))?;
+        let (mpegts, local) = timestamp_map(&file.header[0]).unwrap_or((base, 0));
+        let stitched = stitched.get_or_insert_with(|| {
+            base = mpegts;
+            SubtitleFile {
+                format: Format::Vtt,
+                framerate: None,
+                header: with_timestamp_map(&file.header, base),
+                cues: Vec::new(),
+                trailer: Vec::new(),
+            }
+        });
+        let offset = ((mpegts - base) / MPEGTS_CLOCK) as i32 - local;
+        for mut cue in file.cues {
+            cue.start_ms += offset;
+            cue.end_ms += offset;
+            let repeated = stitched.cues.iter_mut().rev().find(|previous| {
+                previous.lines == cue.lines
+                    && previous.start_ms <= cue.start_ms
+                    && previous.end_ms >= cue.start_ms
+            });
+            match repeated {
+                Some(previous) => previous.end_ms = previous.end_ms.max(cue.end_ms),
+                None => stitched.cues.push(cue),
+            }
+        }
+        stitched.trailer.extend(file.trailer);
+    }
+    let mut stitched = stitched.ok_or_else(|| format!("No WebVTT segments found in {}", path))?;
+    stitched.cues.sort_by_key(|cue| cue.start_ms);
+    for (i, cue) in stitched.cues.iter_mut().enumerate() {
+        cue.index = i + 1;
+    }
+    Ok(stitched)
+}
+
+// Create a function to split the cues into WebVTT segments of the given length, written next to the
+// playlist as <playlist name>_<n>.vtt, and write the playlist. Cues that span a segment boundary are
+// repeated in every segment they overlap, as HLS expects.
+pub fn write_playlist(
+    file: &SubtitleFile,
+    path: &str,
+    segment_duration: i32,
+) -> Result<(), String> {
+    let playlist = Path::new(path);
+    let directory = playlist.parent().unwrap_or(Path::new(""));
+    let stem = playlist
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("subtitles");
+    let (header, base) = match (file.format, file.header.first()) {
+        (Format::Vtt, Some(header)) => (
+            file.header.clone(),
+            timestamp_map(header).map_or(0, |(mpegts, local)| mpegts - local as i64 * MPEGTS_CLOCK),
+        ),
+        _ => (vec!["WEBVTT".to_string()], 0),
+    };
+    let header = with_timestamp_map(&header, base);
+    let last_end = file.cues.iter().map(|cue| cue.end_ms).max().unwrap_or(0);
+    let count = ((last_end + segment_duration - 1) / segment_duration).max(1);
+    let mut output = format!(
+        "#EXTM3U\n#EXT-X-TARGETDURATION:{}\n#EXT-X-VERSION:3\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n",
+        (segment_duration + 999) / 1000
+    );
+    for n in 0..count {
+        let start = n * segment_duration;
+        let end = (start + segment_duration).min(last_end.max(start + 1));
+        let cues: Vec<Cue> = file
+            .cues
+            .iter()
+            .filter(|cue| cue.start_ms < end && cue.end_ms > start)
+            .cloned()
+            .collect();
+        let segment = SubtitleFile {
+            format: Format::Vtt,
+            framerate: file.framerate,
+            header: header.clone(),
+            cues,
+            trailer: Vec::new(),
+        };
+        let name = format!("{}_{}.vtt", stem, n);
+        let segment_path = directory.join(&name);
+        fs::write(&segment_path, vtt::serialize(&segment))
+            .map_err(|error| format!("Unable to write {}: {}", segment_path.display(), error))?;
+        output.push_str(&format!(
+            "#EXTINF:{:.3},\n{}\n",
+            (end - start) as f32 / 1000.0,
+            name
+        ));
+    }
+    output.push_str("#EXT-X-ENDLIST\n");
+    fs::write(playlist, output).map_err(|error| format!("Unable to write {}: {}", path, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Create a function to make an empty directory for a test's playlist and segments.
+    fn directory() -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("subsync-hls-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn cue(index: usize, start_ms: i32, end_ms: i32, text: &str) -> Cue {
+        Cue {
+            index,
+            start_ms,
+            end_ms,
+            lines: vec![text.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let directory = directory();
+        let file = SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: vec![
+                cue(1, 1000, 2500, "First"),
+                cue(2, 5000, 7000, "Across the boundary"),
+                cue(3, 13000, 14000, "Last"),
+            ],
+            trailer: Vec::new(),
+        };
+        let playlist = directory.join("subs.m3u8");
+        write_playlist(&file, playlist.to_str().unwrap(), DEFAULT_SEGMENT_DURATION).unwrap();
+        assert!(directory.join("subs_2.vtt").exists());
+        let stitched = read_playlist(playlist.to_str().unwrap()).unwrap();
+        let cues: Vec<_> = stitched
+            .cues
+            .iter()
+            .map(|cue| (cue.index, cue.start_ms, cue.end_ms, cue.lines[0].as_str()))
+            .collect();
+        assert_eq!(
+            cues,
+            [
+                (1, 1000, 2500, "First"),
+                (2, 5000, 7000, "Across the boundary"),
+                (3, 13000, 14000, "Last")
+            ]
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn timestamp_maps_are_relative_to_the_first_segment() {
+        let directory = directory();
+        fs::write(
+            directory.join("a.vtt"),
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n00:00:01.000 --> 00:00:02.000\nOne\n",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("b.vtt"),
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:1440000,LOCAL:00:00:00.000\n\n00:00:01.000 --> 00:00:02.000\nTwo\n",
+        )
+        .unwrap();
+        let stitched = read_playlist(directory.to_str().unwrap()).unwrap();
+        assert_eq!(stitched.cues[1].start_ms, 7000);
+        assert!(stitched.header[0].contains("MPEGTS:900000,LOCAL:00:00:00.000"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn missing_and_remote_segments_are_errors() {
+        let directory = directory();
+        assert!(read_playlist(directory.to_str().unwrap()).is_err());
+        let playlist = directory.join("subs.m3u8");
+        fs::write(&playlist, "#EXTM3U\nhttps://example.com/0.vtt\n").unwrap();
+        assert!(read_playlist(playlist.to_str().unwrap()).is_err());
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+// The cue model as exchanged with other tools.
+#[derive(Serialize, Deserialize)]
+struct JsonCue {
+    #[serde(default)]
+    index: usize,
+    start_ms: i32,
+    end_ms: i32,
+    #[serde(default)]
+    lines: Vec<String>,
+}
+
+// Create a function to parse a JSON array of {index, start_ms, end_ms, lines} objects.
+// Cues without an index are numbered by their position.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let json_cues: Vec<JsonCue> =
+        serde_json::from_str(contents).map_err(|error| format!("Invalid JSON: {}", error))?;
+    let cues = json_cues
+        .into_iter()
+        .enumerate()
+        .map(|(i, cue)| Cue {
+            index: if cue.index == 0 { i + 1 } else { cue.index },
+            start_ms: cue.start_ms,
+            end_ms: cue.end_ms,
+            lines: cue.lines,
+            ..Default::default()
+        })
+        .collect();
+    Ok(SubtitleFile {
+        format: Format::Json,
+        framerate: None,
+        header: Vec::new(),
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to write the cues out as a JSON array.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let json_cues: Vec<JsonCue> = file
+        .cues
+        .iter()
+        .map(|cue| JsonCue {
+            index: cue.index,
+            start_ms: cue.start_ms,
+            end_ms: cue.end_ms,
+            lines: cue.lines.clone(),
+        })
+        .collect();
+    let mut output = serde_json::to_string_pretty(&json_cues).unwrap();
+    output.push('\n');
+    output
+}
+
+// The JSON cue list format, as registered in the format registry.
+pub struct Json;
+
+impl SubtitleFormat for Json {
+    fn format(&self) -> Format {
+        Format::Json
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: false,
+            positions: false,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        Regex::new(r"^\[\s*[\{\]]")
+            .unwrap()
+            .is_match(&sniff_text(contents))
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let json = "[\n  {\n    \"index\": 1,\n    \"start_ms\": 1000,\n    \"end_ms\": 2500,\n    \"lines\": [\n      \"Hello\",\n      \"\\\"there\\\"\"\n    ]\n  }\n]\n";
+        let file = parse(json).unwrap();
+        assert_eq!(file.cues[0].lines, ["Hello", "\"there\""]);
+        assert_eq!(serialize(&file), json);
+    }
+
+    #[test]
+    fn missing_indices_and_lines_have_defaults() {
+        let file =
+            parse(r#"[{"start_ms": 0, "end_ms": 1}, {"start_ms": 2, "end_ms": 3}]"#).unwrap();
+        let indices: Vec<usize> = file.cues.iter().map(|cue| cue.index).collect();
+        assert_eq!(indices, [1, 2]);
+        assert!(file.cues[0].lines.is_empty());
+    }
+
+    #[test]
+    fn malformed_json_is_an_error() {
+        assert!(parse(r#"[{"start_ms": 0}]"#).is_err());
+        assert!(parse(r#"[{"start_ms": "0:01", "end_ms": 1}]"#).is_err());
+        assert!(parse(r#"{"cues": []}"#).is_err());
+        assert!(parse("[").is_err());
+    }
+}
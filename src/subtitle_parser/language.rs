@@ -0,0 +1,164 @@
+use whatlang::{Detector, Lang};
+
+use super::SubtitleFile;
+
+// The languages that can be identified, by their ISO 639-1 code.
+const LANGUAGES: [(&str, Lang); 69] = [
+    ("af", Lang::Afr),
+    ("ak", Lang::Aka),
+    ("am", Lang::Amh),
+    ("ar", Lang::Ara),
+    ("az", Lang::Aze),
+    ("be", Lang::Bel),
+    ("bg", Lang::Bul),
+    ("bn", Lang::Ben),
+    ("ca", Lang::Cat),
+    ("cs", Lang::Ces),
+    ("da", Lang::Dan),
+    ("de", Lang::Deu),
+    ("el", Lang::Ell),
+    ("en", Lang::Eng),
+    ("eo", Lang::Epo),
+    ("es", Lang::Spa),
+    ("et", Lang::Est),
+    ("fa", Lang::Pes),
+    ("fi", Lang::Fin),
+    ("fr", Lang::Fra),
+    ("gu", Lang::Guj),
+    ("he", Lang::Heb),
+    ("hi", Lang::Hin),
+    ("hr", Lang::Hrv),
+    ("hu", Lang::Hun),
+    ("hy", Lang::Hye),
+    ("id", Lang::Ind),
+    ("it", Lang::Ita),
+    ("ja", Lang::Jpn),
+    ("jv", Lang::Jav),
+    ("ka", Lang::Kat),
+    ("km", Lang::Khm),
+    ("kn", Lang::Kan),
+    ("ko", Lang::Kor),
+    ("la", Lang::Lat),
+    ("lt", Lang::Lit),
+    ("lv", Lang::Lav),
+    ("mk", Lang::Mkd),
+    ("ml", Lang::Mal),
+    ("mr", Lang::Mar),
+    ("my", Lang::Mya),
+    ("nb", Lang::Nob),
+    ("ne", Lang::Nep),
+    ("nl", Lang::Nld),
+    ("or", Lang::Ori),
+    ("pa", Lang::Pan),
+    ("pl", Lang::Pol),
+    ("pt", Lang::Por),
+    ("ro", Lang::Ron),
+    ("ru", Lang::Rus),
+    ("si", Lang::Sin),
+    ("sk", Lang::Slk),
+    ("sl", Lang::Slv),
+    ("sn", Lang::Sna),
+    ("sr", Lang::Srp),
+    ("sv", Lang::Swe),
+    ("ta", Lang::Tam),
+    ("te", Lang::Tel),
+    ("th", Lang::Tha),
+    ("tk", Lang::Tuk),
+    ("tl", Lang::Tgl),
+    ("tr", Lang::Tur),
+    ("uk", Lang::Ukr),
+    ("ur", Lang::Urd),
+    ("uz", Lang::Uzb),
+    ("vi", Lang::Vie),
+    ("yi", Lang::Yid),
+    ("zh", Lang::Cmn),
+    ("zu", Lang::Zul),
+];
+
+// Other codes for these languages: ISO 639-2 bibliographic codes as found in Matroska files, and
+// codes of macrolanguages or old ones.
+const ALIASES: [(&str, &str); 20] = [
+    ("arm", "hy"),
+    ("bur", "my"),
+    ("chi", "zh"),
+    ("cze", "cs"),
+    ("dut", "nl"),
+    ("fas", "fa"),
+    ("fil", "tl"),
+    ("fre", "fr"),
+    ("geo", "ka"),
+    ("ger", "de"),
+    ("gre", "el"),
+    ("in", "id"),
+    ("iw", "he"),
+    ("nn", "nb"),
+    ("no", "nb"),
+    ("nor", "nb"),
+    ("per", "fa"),
+    ("rum", "ro"),
+    ("slo", "sk"),
+    ("zho", "zh"),
+];
+
+// The language subtitles are mostly written in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identification {
+    // ISO 639-1 code, as de.
+    pub language: &'static str,
+    // English name, as German.
+    pub name: &'static str,
+    // From 0 to 1, how sure the guess is.
+    pub confidence: f64,
+    // Whether there was enough text to trust the guess.
+    pub reliable: bool,
+}
+
+// Create a function to read a language as its ISO 639-1 code, from that code, a regional variant
+// (de-AT), an ISO 639-2 or 639-3 code (ger, deu) or its English name (German).
+pub fn normalize(language: &str) -> Option<&'static str> {
+    let language = language.trim().to_lowercase();
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    let primary = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == primary)
+        .map_or(primary, |(_, code)| *code);
+    let lang = Lang::from_code(primary).or_else(|| {
+        Lang::all()
+            .iter()
+            .copied()
+            .find(|lang| lang.eng_name().to_lowercase() == language)
+    });
+    LANGUAGES
+        .iter()
+        .find(|(code, known)| *code == primary || Some(*known) == lang)
+        .map(|(code, _)| *code)
+}
+
+// Create a function to name a language in English from its ISO 639-1 code, as German for de.
+pub fn name(language: &str) -> &'static str {
+    LANGUAGES
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map_or("an unknown language", |(_, lang)| lang.eng_name())
+}
+
+// Create a function to identify the language of subtitles from the text of all their cues, without
+// formatting tags. None when they have no text to tell.
+pub fn identify(subtitles: &SubtitleFile) -> Option<Identification> {
+    let text: Vec<String> = subtitles
+        .cues
+        .iter()
+        .flat_map(|cue| cue.plain_lines())
+        .collect();
+    let info = Detector::new().detect(&text.join("\n"))?;
+    let language = LANGUAGES
+        .iter()
+        .find(|(_, lang)| *lang == info.lang())
+        .map_or(info.lang().code(), |(code, _)| *code);
+    Some(Identification {
+        language,
+        name: info.lang().eng_name(),
+        confidence: info.confidence(),
+        reliable: info.is_reliable(),
+    })
+}
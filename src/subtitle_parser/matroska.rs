@@ -0,0 +1,631 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+
+use flate2::read::ZlibDecoder;
+
+use super::{ass, Cue, Format, ParseOptions, SubtitleFile};
+
+// The EBML element IDs read from a Matroska file, with their length marker.
+const SEGMENT: u32 = 0x18538067;
+const INFO: u32 = 0x1549A966;
+const TIMECODE_SCALE: u32 = 0x2AD7B1;
+const TRACKS: u32 = 0x1654AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const LANGUAGE: u32 = 0x22B59C;
+const NAME: u32 = 0x536E;
+const DEFAULT_DURATION: u32 = 0x23E383;
+const CONTENT_ENCODINGS: u32 = 0x6D80;
+const CONTENT_ENCODING: u32 = 0x6240;
+const CONTENT_COMPRESSION: u32 = 0x5034;
+const CONTENT_COMP_ALGO: u32 = 0x4254;
+const CONTENT_COMP_SETTINGS: u32 = 0x4255;
+const CONTENT_ENCRYPTION: u32 = 0x5035;
+const CLUSTER: u32 = 0x1F43B675;
+const CLUSTER_TIMECODE: u32 = 0xE7;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_DURATION: u32 = 0x9B;
+
+// Matroska's TrackType of subtitle tracks.
+const SUBTITLE_TRACK: u64 = 17;
+
+// Create a function to tell whether a path is a Matroska file (.mkv, .mka, .mks, .webm).
+pub fn is_matroska(path: &str) -> bool {
+    let path = path.to_lowercase();
+    [".mkv", ".mka", ".mks", ".webm"]
+        .iter()
+        .any(|extension| path.ends_with(extension))
+}
+
+// A track of a Matroska file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Track {
+    // Position of the track in the file from 0, as ffmpeg and mkvmerge number tracks.
+    pub index: usize,
+    // Number the blocks of the track refer to it by.
+    pub number: u64,
+    pub track_type: u64,
+    pub codec: String,
+    pub language: Option<String>,
+    pub name: Option<String>,
+    codec_private: Vec<u8>,
+    default_duration_ns: Option<u64>,
+    // ContentCompAlgo of the track: 0 for zlib, 3 for header stripping.
+    compression: Option<u64>,
+    stripped_header: Vec<u8>,
+    encrypted: bool,
+}
+
+impl Track {
+    pub fn is_subtitle(&self) -> bool {
+        self.track_type == SUBTITLE_TRACK
+    }
+
+    // Create a function to tell whether the track is a text subtitle track that can be read.
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self.codec.as_str(),
+            "S_TEXT/UTF8" | "S_TEXT/ASCII" | "S_TEXT/ASS" | "S_TEXT/SSA" | "S_TEXT/WEBVTT"
+        )
+    }
+
+    // Create a function to undo the compression of a block of the track.
+    fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>, String> {
+        match self.compression {
+            None => Ok(data),
+            Some(0) => {
+                let mut decoded = Vec::new();
+                ZlibDecoder::new(data.as_slice())
+                    .read_to_end(&mut decoded)
+                    .map_err(|error| format!("Unable to decompress a block: {}", error))?;
+                Ok(decoded)
+            }
+            Some(3) => Ok([self.stripped_header.clone(), data].concat()),
+            Some(algorithm) => Err(format!(
+                "Track {} uses an unsupported compression ({})",
+                self.index, algorithm
+            )),
+        }
+    }
+}
+
+// A block of the track being read, in timecode units.
+struct Block {
+    time: i64,
+    duration: Option<u64>,
+    data: Vec<u8>,
+}
+
+// A reader of EBML elements that counts its position, to know where master elements end.
+struct Reader<R> {
+    inner: BufReader<R>,
+    position: u64,
+}
+
+impl<R: Read + Seek> Reader<R> {
+    // Create a function to read a byte, or None at the end of the file.
+    fn byte(&mut self) -> Result<Option<u8>, String> {
+        let mut byte = [0u8];
+        match self.inner.read(&mut byte) {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                self.position += 1;
+                Ok(Some(byte[0]))
+            }
+            Err(error) => Err(format!("Unable to read the file: {}", error)),
+        }
+    }
+
+    // Create a function to read a variable length integer: its value without the length marker (or with
+    // it, for IDs), and its length. The value is None at the end of the file.
+    fn vint(&mut self, keep_marker: bool) -> Result<Option<(u64, u32)>, String> {
+        let first = match self.byte()? {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        let length = first.leading_zeros() + 1;
+        if length > 8 {
+            return Err(format!("Invalid element at byte {}", self.position - 1));
+        }
+        let mut value = if keep_marker {
+            first as u64
+        } else {
+            (first as u64) & (0xFF >> length)
+        };
+        for _ in 1..length {
+            let byte = self
+                .byte()?
+                .ok_or_else(|| "The file ends in the middle of an element".to_string())?;
+            value = (value << 8) | byte as u64;
+        }
+        Ok(Some((value, length)))
+    }
+
+    // Create a function to read the size of an element, None when the size is unknown.
+    fn size(&mut self) -> Result<Option<u64>, String> {
+        let (size, length) = self
+            .vint(false)?
+            .ok_or_else(|| "The file ends in the middle of an element".to_string())?;
+        Ok(Some(size).filter(|size| *size != (1 << (7 * length)) - 1))
+    }
+
+    fn bytes(&mut self, size: u64) -> Result<Vec<u8>, String> {
+        if size > 64 << 20 {
+            return Err(format!("Element too large at byte {}", self.position));
+        }
+        let mut bytes = vec![0u8; size as usize];
+        self.inner
+            .read_exact(&mut bytes)
+            .map_err(|error| format!("Unable to read the file: {}", error))?;
+        self.position += size;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, size: u64) -> Result<(), String> {
+        self.inner
+            .seek_relative(size as i64)
+            .map_err(|error| format!("Unable to read the file: {}", error))?;
+        self.position += size;
+        Ok(())
+    }
+}
+
+// Create a function to read an unsigned integer element.
+fn uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+// Create a function to read a string element, which can be padded with zeros.
+fn string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+// Create a function to walk the elements of a Matroska file, reading its tracks and, when a track is
+// given by its index, the blocks of that track. Without a track, reading stops at the first cluster.
+// Returns the tracks, the timecode scale in nanoseconds and the blocks.
+fn walk<R: Read + Seek>(
+    reader: R,
+    wanted: Option<usize>,
+) -> Result<(Vec<Track>, u64, Vec<Block>), String> {
+    let mut reader = Reader {
+        inner: BufReader::new(reader),
+        position: 0,
+    };
+    let mut tracks: Vec<Track> = Vec::new();
+    let mut timecode_scale = 1_000_000;
+    let mut blocks = Vec::new();
+    let mut cluster_time = 0i64;
+    // Master elements being read, with where they end.
+    let mut open: Vec<(u32, u64)> = Vec::new();
+    let mut group: Option<Block> = None;
+    let mut group_duration = None;
+    let mut seen_segment = false;
+    loop {
+        while let Some((id, end)) = open.last().copied() {
+            if reader.position < end {
+                break;
+            }
+            open.pop();
+            if id == BLOCK_GROUP {
+                if let Some(mut block) = group.take() {
+                    block.duration = group_duration.take().or(block.duration);
+                    blocks.push(block);
+                }
+            }
+        }
+        let id = match reader.vint(true)? {
+            Some((id, _)) => id as u32,
+            None => break,
+        };
+        let size = reader.size()?;
+        let end = size.map_or(u64::MAX, |size| reader.position + size);
+        match id {
+            SEGMENT | INFO | TRACKS | TRACK_ENTRY | CONTENT_ENCODINGS | CONTENT_ENCODING
+            | CONTENT_COMPRESSION | CLUSTER | BLOCK_GROUP => {
+                if id == SEGMENT {
+                    seen_segment = true;
+                } else if id == TRACK_ENTRY {
+                    tracks.push(Track {
+                        index: tracks.len(),
+                        ..Default::default()
+                    });
+                } else if id == CONTENT_COMPRESSION {
+                    if let Some(track) = tracks.last_mut() {
+                        track.compression = Some(0);
+                    }
+                } else if id == CLUSTER && wanted.is_none() {
+                    break;
+                } else if id == BLOCK_GROUP {
+                    group = None;
+                    group_duration = None;
+                }
+                // Elements of unknown size run until their parent ends.
+                let end = if size.is_none() {
+                    open.last().map_or(u64::MAX, |(_, end)| *end)
+                } else {
+                    end
+                };
+                open.push((id, end));
+                continue;
+            }
+            _ => {}
+        }
+        let size = size.ok_or_else(|| {
+            format!(
+                "Element {:X} of unknown size at byte {}",
+                id, reader.position
+            )
+        })?;
+        if id == SIMPLE_BLOCK || id == BLOCK {
+            let wanted = wanted.and_then(|index| tracks.get(index));
+            let (number, length) = reader
+                .vint(false)?
+                .ok_or_else(|| "The file ends in the middle of a block".to_string())?;
+            // The size of a malformed block can be smaller than the track number read from it.
+            let rest = size
+                .checked_sub(length as u64)
+                .ok_or_else(|| format!("Malformed block at byte {}", reader.position))?;
+            let track = match wanted.filter(|track| track.number == number) {
+                Some(track) => track,
+                None => {
+                    reader.skip(rest)?;
+                    continue;
+                }
+            };
+            let remaining = rest
+                .checked_sub(3)
+                .ok_or_else(|| format!("Invalid block at byte {}", reader.position))?;
+            let header = reader.bytes(3)?;
+            let data = reader.bytes(remaining)?;
+            if header[2] & 0x06 != 0 {
+                return Err(format!(
+                    "Track {} uses laced blocks, which aren't supported",
+                    track.index
+                ));
+            }
+            let block = Block {
+                time: cluster_time + i16::from_be_bytes([header[0], header[1]]) as i64,
+                duration: None,
+                data: track.decode(data)?,
+            };
+            if id == BLOCK {
+                group = Some(block);
+            } else {
+                blocks.push(block);
+            }
+            continue;
+        }
+        let interesting = matches!(
+            id,
+            TIMECODE_SCALE
+                | TRACK_NUMBER
+                | TRACK_TYPE
+                | CODEC_ID
+                | CODEC_PRIVATE
+                | LANGUAGE
+                | NAME
+                | DEFAULT_DURATION
+                | CONTENT_COMP_ALGO
+                | CONTENT_COMP_SETTINGS
+                | CONTENT_ENCRYPTION
+                | CLUSTER_TIMECODE
+                | BLOCK_DURATION
+        );
+        if !interesting {
+            reader.skip(size)?;
+            continue;
+        }
+        let bytes = reader.bytes(size)?;
+        if id == TIMECODE_SCALE {
+            timecode_scale = uint(&bytes).max(1);
+        } else if id == CLUSTER_TIMECODE {
+            cluster_time = uint(&bytes) as i64;
+        } else if id == BLOCK_DURATION {
+            group_duration = Some(uint(&bytes));
+        } else if let Some(track) = tracks.last_mut() {
+            match id {
+                TRACK_NUMBER => track.number = uint(&bytes),
+                TRACK_TYPE => track.track_type = uint(&bytes),
+                CODEC_ID => track.codec = string(&bytes),
+                CODEC_PRIVATE => track.codec_private = bytes,
+                LANGUAGE => track.language = Some(string(&bytes)),
+                NAME => track.name = Some(string(&bytes)),
+                DEFAULT_DURATION => track.default_duration_ns = Some(uint(&bytes)),
+                CONTENT_COMP_ALGO => track.compression = Some(uint(&bytes)),
+                CONTENT_COMP_SETTINGS => track.stripped_header = bytes,
+                _ => track.encrypted = true,
+            }
+        }
+    }
+    if let Some(mut block) = group {
+        block.duration = group_duration.or(block.duration);
+        blocks.push(block);
+    }
+    if !seen_segment {
+        return Err("Not a Matroska file".to_string());
+    }
+    Ok((tracks, timecode_scale, blocks))
+}
+
+// Create a function to list the subtitle tracks of a Matroska file.
+pub fn subtitle_tracks(path: &str) -> Result<Vec<Track>, String> {
+    let file = File::open(path).map_err(|error| format!("Unable to open {}: {}", path, error))?;
+    let (tracks, _, _) = walk(file, None).map_err(|error| format!("{}: {}", path, error))?;
+    Ok(tracks
+        .into_iter()
+        .filter(|track| track.is_subtitle())
+        .collect())
+}
+
+// Create a function to read a text subtitle track of a Matroska file, given by its index. SRT
+// (S_TEXT/UTF8) and WebVTT tracks become SubRip and WebVTT cues, ASS and SSA tracks an ASS file with
+// the styles of the track. Blocks without a duration last the track's default duration, or the
+// default duration of the options.
+pub fn read_track(
+    path: &str,
+    index: usize,
+    options: &ParseOptions,
+) -> Result<SubtitleFile, String> {
+    let file = File::open(path).map_err(|error| format!("Unable to open {}: {}", path, error))?;
+    let (tracks, scale, blocks) =
+        walk(file, Some(index)).map_err(|error| format!("{}: {}", path, error))?;
+    let track = tracks
+        .get(index)
+        .filter(|track| track.is_subtitle())
+        .ok_or_else(|| format!("{} has no subtitle track {}", path, index))?;
+    if track.encrypted {
+        return Err(format!("Track {} is encrypted", index));
+    }
+    if !track.is_text() {
+        return Err(format!(
+            "Track {} is a {} track, only S_TEXT/UTF8, S_TEXT/ASS, S_TEXT/SSA and S_TEXT/WEBVTT tracks can be read",
+            index, track.codec
+        ));
+    }
+    let to_ms = |time: i64| (time as f64 * scale as f64 / 1_000_000.0).round() as i32;
+    let default_duration = track
+        .default_duration_ns
+        .map_or(options.default_duration, |duration| {
+            (duration as f64 / 1_000_000.0).round() as i32
+        });
+    let mut cues: Vec<Cue> = blocks
+        .iter()
+        .map(|block| {
+            let start_ms = to_ms(block.time);
+            let end_ms = match block.duration {
+                Some(duration) => to_ms(block.time + duration as i64),
+                None => start_ms + default_duration,
+            };
+            let text = String::from_utf8_lossy(&block.data).replace("\r\n", "\n");
+            Cue {
+                start_ms,
+                end_ms,
+                lines: text.lines().map(|line| line.to_string()).collect(),
+                ..Default::default()
+            }
+        })
+        .collect();
+    cues.sort_by_key(|cue| cue.start_ms);
+
+    if track.codec == "S_TEXT/ASS" || track.codec == "S_TEXT/SSA" {
+        // Blocks hold the fields of a Dialogue line after its times, led by a read order:
+        // ReadOrder, Layer, Style, Name, MarginL, MarginR, MarginV, Effect, Text.
+        let mut contents = string(&track.codec_private).replace("\r\n", "\n");
+        if !contents.contains("[Events]") {
+            contents.push_str(
+                "\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+            );
+        }
+        for cue in &cues {
+            let event = cue.lines.join("\\N");
+            let fields: Vec<&str> = event.splitn(3, ',').collect();
+            if fields.len() < 3 {
+                continue;
+            }
+            contents.push_str(&format!(
+                "\nDialogue: {},{},{},{}",
+                fields[1],
+                ass::convert_to_time(cue.start_ms),
+                ass::convert_to_time(cue.end_ms),
+                fields[2]
+            ));
+        }
+        contents.push('\n');
+        return ass::parse(&contents);
+    }
+    for (i, cue) in cues.iter_mut().enumerate() {
+        cue.index = i + 1;
+    }
+    Ok(SubtitleFile {
+        format: if track.codec == "S_TEXT/WEBVTT" {
+            Format::Vtt
+        } else {
+            Format::Srt
+        },
+        framerate: None,
+        header: Vec::new(),
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::DEFAULT_DURATION;
+    use std::io::Cursor;
+
+    // Create a function to write an EBML element, with its size always on 8 bytes.
+    fn element(id: u32, body: &[u8]) -> Vec<u8> {
+        let id: Vec<u8> = id
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        let size = (body.len() as u64 | 1 << 56).to_be_bytes();
+        [id, size.to_vec(), body.to_vec()].concat()
+    }
+
+    fn track_entry(number: u8, track_type: u8, codec: &str, extra: &[u8]) -> Vec<u8> {
+        element(
+            TRACK_ENTRY,
+            &[
+                element(TRACK_NUMBER, &[number]),
+                element(TRACK_TYPE, &[track_type]),
+                element(CODEC_ID, codec.as_bytes()),
+                extra.to_vec(),
+            ]
+            .concat(),
+        )
+    }
+
+    // Create a function to write a block of a track, at a time relative to its cluster.
+    fn block(id: u32, number: u8, time: i16, data: &str) -> Vec<u8> {
+        let time = time.to_be_bytes();
+        element(
+            id,
+            &[&[0x80 | number, time[0], time[1], 0x80], data.as_bytes()].concat(),
+        )
+    }
+
+    // Create a function to write a Matroska file with a video track and a subtitle track of the given
+    // codec, and a cluster at one second holding a simple block and a block group of that track.
+    fn matroska(codec: &str, extra: &[u8], first: &str, second: &str) -> Vec<u8> {
+        let segment = [
+            element(INFO, &element(TIMECODE_SCALE, &[0x0F, 0x42, 0x40])),
+            element(
+                TRACKS,
+                &[
+                    track_entry(1, 1, "V_MPEG4/ISO/AVC", &[]),
+                    track_entry(2, 17, codec, extra),
+                ]
+                .concat(),
+            ),
+            element(
+                CLUSTER,
+                &[
+                    element(CLUSTER_TIMECODE, &[0x03, 0xE8]),
+                    block(SIMPLE_BLOCK, 1, 0, "video"),
+                    block(SIMPLE_BLOCK, 2, 0, first),
+                    element(
+                        BLOCK_GROUP,
+                        &[
+                            block(BLOCK, 2, 500, second),
+                            element(BLOCK_DURATION, &[0x05, 0xDC]),
+                        ]
+                        .concat(),
+                    ),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        [element(0x1A45DFA3, &[]), element(SEGMENT, &segment)].concat()
+    }
+
+    // Create a function to read a track of a Matroska file written to a temporary path.
+    fn read(contents: &[u8], index: usize) -> Result<SubtitleFile, String> {
+        let path = std::env::temp_dir().join(format!("subsync-{}.mkv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        let file = read_track(path.to_str().unwrap(), index, &ParseOptions::default());
+        std::fs::remove_file(&path).unwrap();
+        file
+    }
+
+    #[test]
+    fn subtitle_tracks_are_listed() {
+        let language = element(LANGUAGE, b"eng\0");
+        let contents = matroska("S_TEXT/UTF8", &language, "Hello", "Two\r\nlines");
+        let (tracks, scale, _) = walk(Cursor::new(contents), None).unwrap();
+        assert_eq!(scale, 1_000_000);
+        let subtitles: Vec<&Track> = tracks.iter().filter(|track| track.is_subtitle()).collect();
+        assert_eq!(subtitles.len(), 1);
+        assert_eq!(subtitles[0].index, 1);
+        assert_eq!(subtitles[0].language.as_deref(), Some("eng"));
+        assert!(subtitles[0].is_text());
+    }
+
+    #[test]
+    fn text_tracks_are_read() {
+        let file = read(&matroska("S_TEXT/UTF8", &[], "Hello", "Two\r\nlines"), 1).unwrap();
+        assert_eq!(file.format, Format::Srt);
+        let cues: Vec<(usize, i32, i32, Vec<String>)> = file
+            .cues
+            .into_iter()
+            .map(|cue| (cue.index, cue.start_ms, cue.end_ms, cue.lines))
+            .collect();
+        assert_eq!(
+            cues,
+            [
+                (1, 1000, 1000 + DEFAULT_DURATION, vec!["Hello".to_string()]),
+                (2, 1500, 3000, vec!["Two".to_string(), "lines".to_string()])
+            ]
+        );
+    }
+
+    #[test]
+    fn stripped_headers_are_restored() {
+        let encoding = element(
+            CONTENT_ENCODINGS,
+            &element(
+                CONTENT_ENCODING,
+                &element(
+                    CONTENT_COMPRESSION,
+                    &[
+                        element(CONTENT_COMP_ALGO, &[3]),
+                        element(CONTENT_COMP_SETTINGS, b"Hel"),
+                    ]
+                    .concat(),
+                ),
+            ),
+        );
+        let file = read(&matroska("S_TEXT/UTF8", &encoding, "lo", "p"), 1).unwrap();
+        assert_eq!(file.cues[0].lines, ["Hello"]);
+        assert_eq!(file.cues[1].lines, ["Help"]);
+    }
+
+    #[test]
+    fn ass_tracks_keep_their_styles() {
+        let private = element(
+            CODEC_PRIVATE,
+            b"[Script Info]\nScriptType: v4.00+\n\n[V4+ Styles]\nFormat: Name, Fontname, Fontsize\nStyle: Top,Arial,20\n",
+        );
+        let contents = matroska(
+            "S_TEXT/ASS",
+            &private,
+            "0,0,Top,,0,0,0,,Hello",
+            "1,0,Top,,0,0,0,,Two\\Nlines",
+        );
+        let file = read(&contents, 1).unwrap();
+        assert_eq!(file.format, Format::Ass);
+        assert_eq!((file.cues[1].start_ms, file.cues[1].end_ms), (1500, 3000));
+        assert_eq!(file.cues[1].lines, ["Two", "lines"]);
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        let contents = matroska("S_TEXT/UTF8", &[], "Hello", "Bye");
+        assert!(walk(Cursor::new(element(0x1A45DFA3, &[])), None).is_err());
+        assert!(walk(Cursor::new(&contents[..contents.len() - 2]), Some(1)).is_err());
+        assert!(read(&contents, 0).is_err());
+        assert!(read(&contents, 2).is_err());
+        assert!(read(&matroska("S_HDMV/PGS", &[], "", ""), 1).is_err());
+        // A block too short to hold the track number it starts with.
+        let malformed = [
+            element(TRACKS, &track_entry(2, 17, "S_TEXT/UTF8", &[])),
+            element(CLUSTER, &[SIMPLE_BLOCK as u8, 0x81, 0x40, 0x02, 0]),
+        ]
+        .concat();
+        let error = walk(Cursor::new(element(SEGMENT, &malformed)), Some(0)).err();
+        assert!(error.unwrap().starts_with("Malformed block"));
+    }
+}
@@ -0,0 +1,263 @@
+use super::{Cue, SubtitleFile};
+
+// Two cues made into one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    // Index of the cue kept, and of the cue merged into it, as numbered before the merge.
+    pub cue: usize,
+    pub merged: usize,
+    // Times of the cue made.
+    pub start_ms: i32,
+    pub end_ms: i32,
+    pub lines: Vec<String>,
+}
+
+// Create a function to merge neighbouring cues of a file, in the order of the file: merged returns the
+// lines of the cue made of two cues at most max_gap miliseconds apart, or None to keep them apart.
+// The cue made runs from the start of the first to the end of the last, and is merged again with the
+// next one if it can, and keeps the number of the first. Returns every merge made.
+fn merge_with(
+    subtitles: &mut SubtitleFile,
+    max_gap: i32,
+    merged: impl Fn(&Cue, &Cue) -> Option<Vec<String>>,
+) -> Vec<Merge> {
+    let mut merges = Vec::new();
+    let mut cues: Vec<Cue> = Vec::new();
+    for cue in subtitles.cues.drain(..) {
+        if let Some(last) = cues.last_mut() {
+            let gap = cue.start_ms - last.end_ms;
+            if cue.start_ms >= last.start_ms && gap <= max_gap {
+                if let Some(lines) = merged(last, &cue) {
+                    last.end_ms = last.end_ms.max(cue.end_ms);
+                    last.lines = lines;
+                    last.comments.extend(cue.comments);
+                    merges.push(Merge {
+                        cue: last.index,
+                        merged: cue.index,
+                        start_ms: last.start_ms,
+                        end_ms: last.end_ms,
+                        lines: last.lines.clone(),
+                    });
+                    continue;
+                }
+            }
+        }
+        cues.push(cue);
+    }
+    subtitles.cues = cues;
+    merges
+}
+
+// Create a function to merge cues with the same text that follow each other at most max_gap
+// miliseconds apart, or overlap, as rips often split one cue in two. Text is the same when it reads the
+// same, whatever its formatting tags and spaces.
+pub fn merge_duplicates(subtitles: &mut SubtitleFile, max_gap: i32) -> Vec<Merge> {
+    let text = |cue: &Cue| {
+        let text = cue.plain_lines().join(" ");
+        text.split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    merge_with(subtitles, max_gap, |first, second| {
+        let same = !first.plain_lines().concat().trim().is_empty() && text(first) == text(second);
+        Some(first.lines.clone()).filter(|_| same)
+    })
+}
+
+// How short a cue must be to be merged, and how big the cue made of two can get.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeLimits {
+    // Cues shorter than this, in miliseconds, are merged with a neighbour.
+    pub min_duration: i32,
+    // Longest duration of the cue made, in miliseconds.
+    pub max_duration: i32,
+    pub max_lines: usize,
+    pub max_chars_per_line: usize,
+    // Most time between the two cues, in miliseconds.
+    pub max_gap: i32,
+}
+
+impl Default for MergeLimits {
+    fn default() -> MergeLimits {
+        MergeLimits {
+            min_duration: 1000,
+            max_duration: 7000,
+            max_lines: 2,
+            max_chars_per_line: 42,
+            max_gap: 500,
+        }
+    }
+}
+
+// Create a function to merge the cues shorter than the min_duration of the limits with a neighbour, as
+// machine-generated captions flash a few words at a time, when the cue made still reads comfortably: no
+// longer than max_duration, and its text fitting on max_lines lines of max_chars_per_line characters.
+// The last line of the first cue and the first line of the second are joined when they fit on one.
+pub fn merge_short(subtitles: &mut SubtitleFile, limits: &MergeLimits) -> Vec<Merge> {
+    merge_with(subtitles, limits.max_gap, |first, second| {
+        let short = |cue: &Cue| cue.end_ms - cue.start_ms < limits.min_duration;
+        if !short(first) && !short(second)
+            || second.end_ms.max(first.end_ms) - first.start_ms > limits.max_duration
+        {
+            return None;
+        }
+        let fits = |lines: &Vec<String>| {
+            let cue = Cue {
+                lines: lines.clone(),
+                ..first.clone()
+            };
+            lines.len() <= limits.max_lines
+                && cue
+                    .line_lengths()
+                    .iter()
+                    .all(|length| *length <= limits.max_chars_per_line)
+        };
+        let mut joined = first.lines.clone();
+        if let (Some(last), Some(next)) = (joined.last_mut(), second.lines.first()) {
+            last.push(' ');
+            last.push_str(next);
+        }
+        joined.extend(second.lines.iter().skip(1).cloned());
+        let stacked = [first.lines.clone(), second.lines.clone()].concat();
+        [joined, stacked].into_iter().find(fits)
+    })
+}
+
+// Create a function to interleave subtitles in two languages into one file, the secondary text stacked
+// under the primary: a secondary cue is shown with every primary cue it overlaps for at least half of
+// the shorter of the two, and on its own when there is none. Cues keep the times of the primary ones,
+// and are numbered from 1. With styles, the names of the primary and secondary .ass styles, cues get
+// the style of their language, the secondary text of a stacked cue switching to its style with \r.
+// Both files must use the same tag dialect.
+pub fn merge_languages(
+    primary: &SubtitleFile,
+    secondary: &SubtitleFile,
+    styles: Option<(&str, &str)>,
+) -> SubtitleFile {
+    let overlap = |first: &Cue, second: &Cue| {
+        let overlap = first.end_ms.min(second.end_ms) - first.start_ms.max(second.start_ms);
+        let shorter = (first.end_ms - first.start_ms).min(second.end_ms - second.start_ms);
+        overlap > 0 && overlap * 2 >= shorter
+    };
+    let mut shown = vec![false; secondary.cues.len()];
+    let mut cues = Vec::new();
+    for cue in &primary.cues {
+        let mut lines = cue.lines.clone();
+        for (i, other) in secondary.cues.iter().enumerate() {
+            if !overlap(cue, other) {
+                continue;
+            }
+            shown[i] = true;
+            let mut other_lines = other.lines.clone();
+            if let (Some((_, style)), Some(first)) = (styles, other_lines.first_mut()) {
+                first.insert_str(0, &format!("{{\\r{}}}", style));
+            }
+            lines.extend(other_lines);
+        }
+        cues.push(Cue {
+            style: styles.map_or(cue.style.clone(), |(style, _)| Some(style.to_string())),
+            settings: cue.settings.clone().filter(|_| styles.is_none()),
+            lines,
+            ..cue.clone()
+        });
+    }
+    for (cue, _) in secondary.cues.iter().zip(shown).filter(|(_, shown)| !shown) {
+        cues.push(Cue {
+            identifier: None,
+            style: styles.map(|(_, style)| style.to_string()),
+            settings: None,
+            comments: Vec::new(),
+            ..cue.clone()
+        });
+    }
+    cues.sort_by_key(|cue| cue.start_ms);
+    for (i, cue) in cues.iter_mut().enumerate() {
+        cue.index = i + 1;
+    }
+    SubtitleFile {
+        cues,
+        ..primary.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::Format;
+
+    fn file(cues: &[(usize, i32, i32, &str)]) -> SubtitleFile {
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: cues
+                .iter()
+                .map(|(index, start_ms, end_ms, text)| Cue {
+                    index: *index,
+                    start_ms: *start_ms,
+                    end_ms: *end_ms,
+                    lines: text.lines().map(str::to_string).collect(),
+                    ..Default::default()
+                })
+                .collect(),
+            trailer: Vec::new(),
+        }
+    }
+
+    fn merged(merges: &[Merge]) -> Vec<(usize, usize, String)> {
+        merges
+            .iter()
+            .map(|merge| (merge.cue, merge.merged, merge.lines.join(" / ")))
+            .collect()
+    }
+
+    #[test]
+    fn merges_number_cues_as_in_the_input() {
+        let mut subtitles = file(&[
+            (3, 1000, 1500, "Same."),
+            (4, 1600, 2000, "Same."),
+            (5, 2100, 2600, "Ok."),
+            (6, 5000, 8000, "Go."),
+        ]);
+        let duplicates = merge_duplicates(&mut subtitles, 500);
+        assert_eq!(merged(&duplicates), [(3, 4, "Same.".to_string())]);
+        let short = merge_short(&mut subtitles, &MergeLimits::default());
+        assert_eq!(merged(&short), [(3, 5, "Same. Ok.".to_string())]);
+        let indices: Vec<usize> = subtitles.cues.iter().map(|cue| cue.index).collect();
+        assert_eq!(indices, [3, 6]);
+    }
+
+    #[test]
+    fn duplicates_ignore_tags_and_spaces_but_not_gaps() {
+        let mut subtitles = file(&[
+            (1, 0, 1000, "<i>Run!</i>"),
+            (2, 1200, 2000, "Run! "),
+            (3, 4000, 5000, "Run!"),
+        ]);
+        let merges = merge_duplicates(&mut subtitles, 500);
+        assert_eq!(merged(&merges), [(1, 2, "<i>Run!</i>".to_string())]);
+        assert_eq!(subtitles.cues[0].end_ms, 2000);
+        assert_eq!(subtitles.cues.len(), 2);
+    }
+
+    #[test]
+    fn short_cues_stack_when_their_lines_dont_join() {
+        let limits = MergeLimits {
+            max_chars_per_line: 20,
+            ..MergeLimits::default()
+        };
+        let mut subtitles = file(&[
+            (1, 0, 500, "So I was thinking"),
+            (2, 600, 1100, "that we could go"),
+            (3, 1200, 1700, "to the beach today"),
+        ]);
+        let merges = merge_short(&mut subtitles, &limits);
+        assert_eq!(
+            merged(&merges),
+            [(1, 2, "So I was thinking / that we could go".to_string())]
+        );
+        // A third line doesn't fit, so the last cue stays on its own.
+        assert_eq!(subtitles.cues.len(), 2);
+        assert_eq!(subtitles.cues[1].index, 3);
+    }
+}
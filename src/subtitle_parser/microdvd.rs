@@ -0,0 +1,158 @@
+use regex::Regex;
+
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+    DEFAULT_FRAMERATE,
+};
+
+// Create a function to convert a frame number to miliseconds at the given framerate.
+pub fn frames_to_miliseconds(frame: i32, framerate: f32) -> i32 {
+    (frame as f64 * 1000.0 / framerate as f64).round() as i32
+}
+
+// Create a function to convert miliseconds to the nearest frame number at the given framerate.
+pub fn miliseconds_to_frames(miliseconds: i32, framerate: f32) -> i32 {
+    (miliseconds as f64 * framerate as f64 / 1000.0).round() as i32
+}
+
+// Create a function to parse the contents of a MicroDVD .sub file.
+// Frame numbers are turned into miliseconds using the given framerate, or the framerate declared
+// by a {1}{1}fps first line when no framerate is given.
+pub fn parse(contents: &str, framerate: Option<f32>) -> Result<SubtitleFile, String> {
+    let re = Regex::new(r"^\{(\d+)\}\{(\d+)\}(.*)$").unwrap();
+    let mut lines = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .peekable();
+    let mut declared_framerate = None;
+    if let Some(caps) = lines.peek().and_then(|line| re.captures(line.trim())) {
+        if &caps[1] == "1" && &caps[2] == "1" {
+            declared_framerate = caps[3].trim().parse::<f32>().ok();
+            if declared_framerate.is_some() {
+                lines.next();
+            }
+        }
+    }
+    let framerate = framerate
+        .or(declared_framerate)
+        .unwrap_or(DEFAULT_FRAMERATE);
+    let mut cues = Vec::new();
+    for line in lines {
+        let caps = re
+            .captures(line.trim())
+            .ok_or_else(|| format!("Invalid MicroDVD line '{}'", line))?;
+        let start = caps[1].parse::<i32>().map_err(|e| e.to_string())?;
+        let end = caps[2].parse::<i32>().map_err(|e| e.to_string())?;
+        cues.push(Cue {
+            index: cues.len() + 1,
+            start_ms: frames_to_miliseconds(start, framerate),
+            end_ms: frames_to_miliseconds(end, framerate),
+            lines: caps[3].split('|').map(|text| text.to_string()).collect(),
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::MicroDvd,
+        framerate: Some(framerate),
+        header: Vec::new(),
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to write the cues out as MicroDVD, using the file's framerate.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let framerate = file.framerate.unwrap_or(DEFAULT_FRAMERATE);
+    let mut output = format!("{{1}}{{1}}{}\n", framerate);
+    for cue in &file.cues {
+        output.push_str(&format!(
+            "{{{}}}{{{}}}{}\n",
+            miliseconds_to_frames(cue.start_ms, framerate),
+            miliseconds_to_frames(cue.end_ms, framerate),
+            cue.lines.join("|")
+        ));
+    }
+    output
+}
+
+// The MicroDVD format, as registered in the format registry.
+pub struct MicroDvd;
+
+impl SubtitleFormat for MicroDvd {
+    fn format(&self) -> Format {
+        Format::MicroDvd
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["microdvd", "sub"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["sub"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: false,
+            frame_based: true,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        Regex::new(r"^\{\d+\}\{\d+\}")
+            .unwrap()
+            .is_match(&sniff_text(contents))
+    }
+
+    fn parse(&self, contents: &[u8], options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?, options.framerate)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUB: &str = "{1}{1}25\n{25}{75}Hello.\n{100}{150}{y:i}Two|lines.\n";
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(SUB, None).unwrap();
+        assert_eq!(file.framerate, Some(25.0));
+        assert_eq!(file.cues.len(), 2);
+        assert_eq!((file.cues[0].start_ms, file.cues[0].end_ms), (1000, 3000));
+        assert_eq!(file.cues[1].lines, ["{y:i}Two", "lines."]);
+        assert_eq!(serialize(&file), SUB);
+    }
+
+    #[test]
+    fn given_framerate_overrides_the_declared_one() {
+        let file = parse(SUB, Some(23.976)).unwrap();
+        assert_eq!(file.framerate, Some(23.976));
+        assert_eq!(file.cues[0].start_ms, 1043);
+        let file = parse("{25}{75}No header.\n", None).unwrap();
+        assert_eq!(file.framerate, Some(DEFAULT_FRAMERATE));
+        assert_eq!(file.cues.len(), 1);
+    }
+
+    #[test]
+    fn frames_convert_both_ways() {
+        for frame in [0, 1, 1799, 1800, 17982, 107892] {
+            let miliseconds = frames_to_miliseconds(frame, 29.97);
+            assert_eq!(miliseconds_to_frames(miliseconds, 29.97), frame);
+        }
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert!(parse("{1}{1}25\n{25}75}Text\n", None).is_err());
+        assert!(parse("{25}{75}Text\nText without frames\n", None).is_err());
+        assert!(parse("{99999999999}{1}Text\n", None).is_err());
+    }
+}
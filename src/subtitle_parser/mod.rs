@@ -0,0 +1,332 @@
+use regex::Regex;
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+
+pub mod align;
+pub mod ass;
+pub mod audacity;
+pub mod chapters;
+pub mod colors;
+pub mod compare;
+pub mod correlate;
+pub mod dcp;
+pub mod detect;
+pub mod downconvert;
+pub mod dtw;
+pub mod edl;
+pub mod encoding;
+pub mod hls;
+pub mod json;
+pub mod language;
+pub mod matroska;
+pub mod merge;
+pub mod microdvd;
+pub mod mpl2;
+pub mod ocr;
+pub mod provenance;
+pub mod registry;
+pub mod sami;
+pub mod sbv;
+pub mod scc;
+pub mod sdh;
+pub mod segments;
+pub mod spelling;
+pub mod srt;
+pub mod srv3;
+pub mod stl;
+pub mod tags;
+pub mod timecode;
+pub mod timing;
+pub mod tmp;
+pub mod ttml;
+pub mod vad;
+pub mod validate;
+pub mod vtt;
+
+pub use registry::{Capabilities, Registry, SubtitleFormat};
+pub use tags::TagDialect;
+
+// Framerate assumed when none is given and the file doesn't declare one.
+pub const DEFAULT_FRAMERATE: f32 = 29.97;
+
+// Duration given to cues of formats that don't store end times, in miliseconds.
+pub const DEFAULT_DURATION: i32 = 3000;
+
+// Options that control how files are parsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseOptions {
+    // Framerate used by frame-based formats, overriding any framerate declared in the file.
+    pub framerate: Option<f32>,
+    // Duration of cues in formats without end times (TMPlayer), in miliseconds.
+    pub default_duration: i32,
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            framerate: None,
+            default_duration: DEFAULT_DURATION,
+        }
+    }
+}
+
+// The subtitle formats the parser knows how to read and write. See Registry for their implementations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Srt,
+    Vtt,
+    MicroDvd,
+    Sami,
+    Ttml,
+    // iTT, Apple's TTML profile with SMPTE timecodes.
+    Itt,
+    Sbv,
+    Stl,
+    Scc,
+    Mpl2,
+    Tmp,
+    Ass,
+    Json,
+    Audacity,
+    DcpInterop,
+    DcpSmpte,
+    Srv3,
+    // A format added to a Registry outside of this crate, by name.
+    Custom(&'static str),
+}
+
+// A single subtitle cue. Times are stored in miliseconds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cue {
+    pub index: usize,
+    // Cue identifier, as used by WebVTT. SRT cues only have a numeric index.
+    pub identifier: Option<String>,
+    pub start_ms: i32,
+    pub end_ms: i32,
+    // Cue settings that follow the timing line in WebVTT (position, align, ...) or SRT (X1:.. Y1:..),
+    // or the extra attributes of a TTML <p> element, the rows of an EBU STL subtitle, the caption data
+    // that loaded an SCC caption, or the layer, name, margins and effect of an ASS event.
+    pub settings: Option<String>,
+    // Style or class name the cue is displayed with (the SAMI P class, the ASS style).
+    pub style: Option<String>,
+    pub lines: Vec<String>,
+    // Comment blocks (WebVTT NOTE blocks, ASS Comment lines) found directly before this cue.
+    pub comments: Vec<String>,
+}
+
+impl Cue {
+    // Create a function to count the characters a viewer reads, ignoring formatting tags and line breaks.
+    pub fn text_length(&self) -> usize {
+        self.line_lengths().iter().sum()
+    }
+
+    // Create a function to count the characters a viewer reads on every line, ignoring formatting tags.
+    // Characters are grapheme clusters, so an accent written as a combining mark or an emoji made of
+    // several code points counts once.
+    pub fn line_lengths(&self) -> Vec<usize> {
+        self.plain_lines()
+            .iter()
+            .map(|line| line.graphemes(true).count())
+            .collect()
+    }
+
+    // Create a function to get the lines as a viewer reads them, without formatting tags.
+    pub fn plain_lines(&self) -> Vec<String> {
+        let tag = Regex::new(r"</?[a-zA-Z][^>]*>|\{[^}]*\}").unwrap();
+        self.lines
+            .iter()
+            .map(|line| tag.replace_all(line, "").to_string())
+            .collect()
+    }
+
+    // Create a function to count the words a viewer reads, ignoring formatting tags.
+    pub fn word_count(&self) -> usize {
+        self.plain_lines()
+            .iter()
+            .map(|line| line.split_whitespace().count())
+            .sum()
+    }
+
+    // Create a function to compute the reading speed of the cue in characters per second.
+    pub fn chars_per_second(&self) -> f32 {
+        let duration = self.end_ms - self.start_ms;
+        if duration <= 0 {
+            return 0.0;
+        }
+        self.text_length() as f32 * 1000.0 / duration as f32
+    }
+
+    // Create a function to compute the reading speed of the cue in words per minute.
+    pub fn words_per_minute(&self) -> f32 {
+        let duration = self.end_ms - self.start_ms;
+        if duration <= 0 {
+            return 0.0;
+        }
+        self.word_count() as f32 * 60000.0 / duration as f32
+    }
+}
+
+// A parsed subtitle file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleFile {
+    pub format: Format,
+    // The framerate the cue times belong to, for frame-based formats or after a framerate conversion.
+    pub framerate: Option<f32>,
+    // Format specific blocks found before the first cue (the WEBVTT header, STYLE and REGION blocks, ...).
+    pub header: Vec<String>,
+    pub cues: Vec<Cue>,
+    // Comment blocks found after the last cue.
+    pub trailer: Vec<String>,
+}
+
+impl SubtitleFile {
+    // Create a function to convert every cue to a new framerate.
+    pub fn convert_framerate(&mut self, input_framerate: f32, output_framerate: f32) {
+        for cue in self.cues.iter_mut() {
+            cue.start_ms = convert_timestamp(cue.start_ms, input_framerate, output_framerate);
+            cue.end_ms = convert_timestamp(cue.end_ms, input_framerate, output_framerate);
+        }
+        self.framerate = Some(output_framerate);
+    }
+
+    // Create a function to translate the inline styling tags of every line to another dialect.
+    pub fn translate_tags(&mut self, from: TagDialect, to: TagDialect) {
+        for cue in self.cues.iter_mut() {
+            for line in cue.lines.iter_mut() {
+                *line = tags::translate(line, from, to);
+            }
+        }
+    }
+
+    // Create a function to find where a cue is placed on screen, as a numeric keypad position like
+    // {\an8}: from an override in its text, its WebVTT cue settings or its ASS style.
+    pub fn cue_alignment(&self, cue: &Cue) -> Option<u8> {
+        if let Some(alignment) = cue.lines.iter().find_map(|line| tags::alignment(line)) {
+            return Some(alignment);
+        }
+        match (self.format, &cue.settings, &cue.style) {
+            (Format::Vtt, Some(settings), _) => tags::vtt_alignment(settings),
+            (Format::Ass, _, Some(style)) => ass::style_alignment(self.header.first()?, style),
+            _ => None,
+        }
+        .filter(|alignment| *alignment != 2)
+    }
+
+    // Create a function to remove inline formatting (HTML-like tags, ASS override tags) and style names.
+    // ASS positioning overrides are kept, they are handled by strip_positions.
+    pub fn strip_styles(&mut self) {
+        let html_tag = Regex::new(r"</?[a-zA-Z][^>]*>").unwrap();
+        let override_block = Regex::new(r"\{([^}]*)\}").unwrap();
+        let position_tag = Regex::new(r"\\(an?\d+|pos\([^)]*\)|move\([^)]*\))").unwrap();
+        for cue in self.cues.iter_mut() {
+            cue.style = None;
+            for line in cue.lines.iter_mut() {
+                let text = html_tag.replace_all(line, "");
+                let text = override_block.replace_all(&text, |caps: &regex::Captures| {
+                    let positions: Vec<&str> = position_tag
+                        .find_iter(&caps[1])
+                        .map(|tag| tag.as_str())
+                        .collect();
+                    if positions.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{{{}}}", positions.join(""))
+                    }
+                });
+                *line = text.to_string();
+            }
+        }
+    }
+
+    // Create a function to remove positioning: cue settings, TTML regions, EBU STL rows, ASS margins
+    // and ASS positioning overrides ({\an8}, {\pos(x,y)}, {\move(...)}).
+    pub fn strip_positions(&mut self) {
+        let position_tag = Regex::new(r"\\(an?\d+|pos\([^)]*\)|move\([^)]*\))").unwrap();
+        let empty_block = Regex::new(r"\{\}").unwrap();
+        let region = Regex::new(
+            r#"\s*(region|tts:origin|tts:extent|tts:textAlign|tts:displayAlign)="[^"]*""#,
+        )
+        .unwrap();
+        for cue in self.cues.iter_mut() {
+            cue.settings = match (self.format, cue.settings.take()) {
+                (Format::Ass, Some(settings)) => {
+                    let fields: Vec<&str> = settings.split(',').collect();
+                    Some(format!(
+                        "{},{},0,0,0,{}",
+                        fields[0],
+                        fields.get(1).unwrap_or(&""),
+                        fields[5..].join(",")
+                    ))
+                }
+                (Format::Ttml | Format::Itt, Some(settings)) => {
+                    Some(region.replace_all(&settings, "").trim().to_string())
+                        .filter(|s| !s.is_empty())
+                }
+                _ => None,
+            };
+            for line in cue.lines.iter_mut() {
+                let text = position_tag.replace_all(line, "");
+                *line = empty_block.replace_all(&text, "").to_string();
+            }
+        }
+    }
+
+    // Create a function to check the cues for common timing problems, returning the issues found with
+    // their kind, so callers can pick the ones they care about; ValidationIssue::message describes one.
+    // See validate::Rules for the problems checked by default and how bad each one is; the reading speed
+    // is left to subsync validate.
+    pub fn validate(&self) -> Vec<validate::ValidationIssue> {
+        let rules = validate::Rules {
+            max_cps: None,
+            ..validate::Rules::default()
+        };
+        validate::check(self, &rules)
+    }
+}
+
+// Create a function to convert a timestamp in miliseconds to a new framerate.
+pub fn convert_timestamp(miliseconds: i32, input_framerate: f32, output_framerate: f32) -> i32 {
+    (miliseconds as f32 * input_framerate / output_framerate) as i32
+}
+
+// Create a function to split a file into blocks of non-empty lines separated by blank lines.
+pub fn split_blocks(contents: &str) -> Vec<Vec<&str>> {
+    let mut blocks = Vec::new();
+    let mut block = Vec::new();
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            if !block.is_empty() {
+                blocks.push(block);
+                block = Vec::new();
+            }
+        } else {
+            block.push(line);
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+// Create a function to remove a UTF-8 byte order mark from the start of a file.
+pub fn strip_bom(contents: &[u8]) -> &[u8] {
+    contents.strip_prefix(b"\xef\xbb\xbf").unwrap_or(contents)
+}
+
+// Create a function to decode the contents of a text format. A byte order mark is skipped, so it
+// doesn't end up in the first line.
+pub fn decode_text(contents: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(strip_bom(contents))
+        .map_err(|error| format!("File is not valid UTF-8: {}", error))
+}
+
+// Create a function to get the start of a file as text, to detect its format.
+pub fn sniff_text(contents: &[u8]) -> Cow<'_, str> {
+    let contents = strip_bom(contents);
+    let start = &contents[..contents.len().min(65536)];
+    match String::from_utf8_lossy(start) {
+        Cow::Borrowed(text) => Cow::Borrowed(text.trim_start()),
+        Cow::Owned(text) => Cow::Owned(text.trim_start().to_string()),
+    }
+}
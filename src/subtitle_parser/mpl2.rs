@@ -0,0 +1,137 @@
+use regex::Regex;
+
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+// Create a function to parse the contents of an MPL2 file: [start][end]text, with times in deciseconds.
+// Lines are separated by '|', and a line starting with '/' is italic.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let re = Regex::new(r"^\[(\d+)\]\[(\d+)\](.*)$").unwrap();
+    let mut cues = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let caps = re
+            .captures(line.trim())
+            .ok_or_else(|| format!("Invalid MPL2 line '{}'", line))?;
+        let start = caps[1].parse::<i32>().map_err(|e| e.to_string())?;
+        let end = caps[2].parse::<i32>().map_err(|e| e.to_string())?;
+        let lines = caps[3]
+            .split('|')
+            .map(|text| match text.strip_prefix('/') {
+                Some(text) => format!("<i>{}</i>", text.trim_start()),
+                None => text.to_string(),
+            })
+            .collect();
+        cues.push(Cue {
+            index: cues.len() + 1,
+            start_ms: start * 100,
+            end_ms: end * 100,
+            lines,
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::Mpl2,
+        framerate: None,
+        header: Vec::new(),
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to write the cues out as MPL2, rounding times to the nearest decisecond.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let mut output = String::new();
+    for cue in &file.cues {
+        let lines: Vec<String> = cue
+            .lines
+            .iter()
+            .map(|line| {
+                match line
+                    .strip_prefix("<i>")
+                    .and_then(|line| line.strip_suffix("</i>"))
+                {
+                    Some(line) if !line.contains("<i>") => format!("/{}", line),
+                    _ => line.to_string(),
+                }
+            })
+            .collect();
+        output.push_str(&format!(
+            "[{}][{}]{}\n",
+            (cue.start_ms.max(0) + 50) / 100,
+            (cue.end_ms.max(0) + 50) / 100,
+            lines.join("|")
+        ));
+    }
+    output
+}
+
+// The MPL2 format, as registered in the format registry.
+pub struct Mpl2;
+
+impl SubtitleFormat for Mpl2 {
+    fn format(&self) -> Format {
+        Format::Mpl2
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["mpl2", "mpl"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["mpl"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: false,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        Regex::new(r"^\[\d+\]\[\d+\]")
+            .unwrap()
+            .is_match(&sniff_text(contents))
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MPL2: &str = "[10][25]Hello.\n[30][45]/In italics|Not in italics\n";
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(MPL2).unwrap();
+        assert_eq!((file.cues[0].start_ms, file.cues[0].end_ms), (1000, 2500));
+        assert_eq!(file.cues[1].lines, ["<i>In italics</i>", "Not in italics"]);
+        assert_eq!(serialize(&file), MPL2);
+    }
+
+    #[test]
+    fn times_are_rounded_to_deciseconds() {
+        let mut file = parse(MPL2).unwrap();
+        file.cues[0].start_ms = 1049;
+        file.cues[0].end_ms = 2550;
+        assert!(serialize(&file).starts_with("[10][26]"));
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert!(parse("[10]Hello.\n").is_err());
+        assert!(parse("{10}{25}Hello.\n").is_err());
+        assert!(parse("[99999999999][1]Hello.\n").is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+use super::{vtt, SubtitleFile};
+
+// Words OCR misreads in English subtitles ripped from DVD and Blu-ray images, and what they should be:
+// a lowercase l read for I, rn read for m.
+const WORDS: [(&str, &str); 44] = [
+    ("l", "I"),
+    ("l'm", "I'm"),
+    ("l'll", "I'll"),
+    ("l've", "I've"),
+    ("l'd", "I'd"),
+    ("lt", "It"),
+    ("lt's", "It's"),
+    ("lt'll", "It'll"),
+    ("lf", "If"),
+    ("ln", "In"),
+    ("ls", "Is"),
+    ("lsn't", "Isn't"),
+    ("lt'II", "It'll"),
+    ("I'II", "I'll"),
+    ("rne", "me"),
+    ("rny", "my"),
+    ("rnan", "man"),
+    ("rnen", "men"),
+    ("rnake", "make"),
+    ("rnany", "many"),
+    ("rnaybe", "maybe"),
+    ("rnean", "mean"),
+    ("rnight", "might"),
+    ("rnind", "mind"),
+    ("rnoney", "money"),
+    ("rnore", "more"),
+    ("rnorning", "morning"),
+    ("rnother", "mother"),
+    ("rnuch", "much"),
+    ("rnust", "must"),
+    ("corne", "come"),
+    ("frorn", "from"),
+    ("horne", "home"),
+    ("narne", "name"),
+    ("sarne", "same"),
+    ("sorne", "some"),
+    ("sornething", "something"),
+    ("sornetimes", "sometimes"),
+    ("tirne", "time"),
+    ("tirnes", "times"),
+    ("whorn", "whom"),
+    ("becorne", "become"),
+    ("rnyself", "myself"),
+    ("ernpty", "empty"),
+];
+
+// Replacements of whole words, the built-in ones and those of the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrRules {
+    words: HashMap<String, String>,
+}
+
+impl Default for OcrRules {
+    fn default() -> OcrRules {
+        OcrRules {
+            words: WORDS
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl OcrRules {
+    // Create a function to add replacements, which replace the built-in ones of the same words.
+    pub fn add(&mut self, replacements: Vec<(String, String)>) {
+        self.words.extend(replacements);
+    }
+
+    // Create a function to correct a word: pipes read for l between letters when one of them is
+    // lowercase, and for I between uppercase letters, at the start of a word or on their own, an
+    // uppercase I read for l inside a lowercase word (wiII), then the replacements. A pipe ending a word
+    // is left alone, as it can't be told apart from a stray mark.
+    fn correct(&self, word: &str) -> String {
+        if let Some(replacement) = self.words.get(word) {
+            return replacement.clone();
+        }
+        let mut corrected = String::new();
+        let characters: Vec<char> = word.chars().collect();
+        let lowercase = characters.iter().any(|character| character.is_lowercase())
+            && characters
+                .iter()
+                .enumerate()
+                .all(|(i, character)| *character == 'I' && i > 0 || !character.is_uppercase());
+        for (i, character) in characters.iter().enumerate() {
+            // The letters on each side of a pipe, past the pipes next to it.
+            let before = characters[..i].iter().rev().find(|c| **c != '|').copied();
+            let after = characters[i + 1..].iter().find(|c| **c != '|').copied();
+            let letter = |c: Option<char>| c.filter(|c| c.is_alphabetic());
+            corrected.push(match (character, letter(before), letter(after)) {
+                ('|', Some(before), Some(after))
+                    if before.is_lowercase() || after.is_lowercase() =>
+                {
+                    'l'
+                }
+                ('|', Some(_), Some(_)) => 'I',
+                ('|', None, _) if before.is_none() => 'I',
+                ('I', _, _) if i > 0 && lowercase => 'l',
+                (character, _, _) => *character,
+            });
+        }
+        self.words.get(&corrected).cloned().unwrap_or(corrected)
+    }
+}
+
+// A correction made to a cue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    pub cue: usize,
+    pub start_ms: i32,
+    pub end_ms: i32,
+    pub from: String,
+    pub to: String,
+}
+
+impl Correction {
+    // Create a function to describe a correction, as printed in reports.
+    pub fn message(&self) -> String {
+        format!(
+            "{} --> {}: Cue {}: '{}' -> '{}'",
+            vtt::convert_to_time(self.start_ms),
+            vtt::convert_to_time(self.end_ms),
+            self.cue,
+            self.from,
+            self.to
+        )
+    }
+}
+
+// Create a function to read a replacement table: one replacement per line as `wrong -> right`, blank
+// lines and lines starting with # skipped.
+pub fn parse_table(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut replacements = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (from, to) = line
+            .split_once("->")
+            .map(|(from, to)| (from.trim(), to.trim()))
+            .filter(|(from, _)| !from.is_empty())
+            .ok_or_else(|| {
+                format!(
+                    "Invalid replacement on line {}, expected 'wrong -> right': {}",
+                    number + 1,
+                    line
+                )
+            })?;
+        replacements.push((from.to_string(), to.to_string()));
+    }
+    Ok(replacements)
+}
+
+// Create a function to correct the OCR mistakes of every cue, leaving formatting tags alone: two
+// apostrophes read for a double quote, and every word corrected by the rules. Returns every
+// correction made, in the order of the cues.
+pub fn fix_ocr(subtitles: &mut SubtitleFile, rules: &OcrRules) -> Vec<Correction> {
+    let tag = Regex::new(r"</?[a-zA-Z][^>]*>|\{[^}]*\}").unwrap();
+    let word = Regex::new(r"''|[\p{L}\p{N}|]+(?:['’][\p{L}\p{N}|]+)*").unwrap();
+    let mut corrections = Vec::new();
+    for cue in &mut subtitles.cues {
+        for line in &mut cue.lines {
+            let mut corrected = String::new();
+            let mut last = 0;
+            // The text between tags, and the tag after it.
+            let tags = tag.find_iter(line).map(|tag| (tag.start(), tag.end()));
+            for (start, end) in tags.chain([(line.len(), line.len())]) {
+                let text = &line[last..start];
+                let text = word.replace_all(text, |found: &regex::Captures| {
+                    let from = &found[0];
+                    let to = if from == "''" {
+                        "\"".to_string()
+                    } else {
+                        rules.correct(from)
+                    };
+                    if to != from {
+                        corrections.push(Correction {
+                            cue: cue.index,
+                            start_ms: cue.start_ms,
+                            end_ms: cue.end_ms,
+                            from: from.to_string(),
+                            to: to.clone(),
+                        });
+                    }
+                    to
+                });
+                corrected.push_str(&text);
+                corrected.push_str(&line[start..end]);
+                last = end;
+            }
+            *line = corrected;
+        }
+    }
+    corrections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::{Cue, Format};
+
+    fn file(lines: &[&str]) -> SubtitleFile {
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: vec![Cue {
+                index: 7,
+                start_ms: 1000,
+                end_ms: 2000,
+                lines: lines.iter().map(|line| line.to_string()).collect(),
+                ..Default::default()
+            }],
+            trailer: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn misread_letters_are_corrected() {
+        let rules = OcrRules::default();
+        for (word, corrected) in [
+            ("l", "I"),
+            ("lt'II", "It'll"),
+            ("rnore", "more"),
+            ("wiII", "will"),
+            ("He||o", "Hello"),
+            ("|t's", "It's"),
+            ("|", "I"),
+            ("W|LL", "WILL"),
+            // Acronyms and words starting with I, and pipes ending a word, are left alone.
+            ("CIA", "CIA"),
+            ("Ice", "Ice"),
+            ("no|", "no|"),
+            ("love", "love"),
+        ] {
+            assert_eq!(rules.correct(word), corrected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn tags_are_left_alone() {
+        let mut subtitles = file(&[
+            "{\\an8}<i>l'm</i> ''here''",
+            "<font color=\"lime\">rnore</font>",
+        ]);
+        let corrections = fix_ocr(&mut subtitles, &OcrRules::default());
+        assert_eq!(
+            subtitles.cues[0].lines,
+            [
+                "{\\an8}<i>I'm</i> \"here\"",
+                "<font color=\"lime\">more</font>"
+            ]
+        );
+        let changes: Vec<(&str, &str)> = corrections
+            .iter()
+            .map(|correction| (correction.from.as_str(), correction.to.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("l'm", "I'm"),
+                ("''", "\""),
+                ("''", "\""),
+                ("rnore", "more")
+            ]
+        );
+        assert_eq!(
+            corrections[0].message(),
+            "00:00:01.000 --> 00:00:02.000: Cue 7: 'l'm' -> 'I'm'"
+        );
+    }
+
+    #[test]
+    fn user_replacements_come_before_the_built_in_ones() {
+        let table = "# Names\n\nlan -> Ian\nl -> l\n";
+        let mut rules = OcrRules::default();
+        rules.add(parse_table(table).unwrap());
+        let mut subtitles = file(&["lan said l"]);
+        fix_ocr(&mut subtitles, &rules);
+        assert_eq!(subtitles.cues[0].lines, ["Ian said l"]);
+        assert_eq!(
+            parse_table("lan -> Ian\n-> right\n"),
+            Err("Invalid replacement on line 2, expected 'wrong -> right': -> right".to_string())
+        );
+        assert!(parse_table("lan = Ian").is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use super::segments::Segment;
+use super::timecode::TimecodeRate;
+use super::timing::{Rounding, TimeRange};
+use super::SubtitleFile;
+
+// A retiming as recorded in the provenance of a file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Transform {
+    // time = origin + (time - origin) * factor + offset, as done by framerate conversions, scale and shift.
+    // With keep_durations, only start times are moved, see SubtitleFile::scale_starts.
+    Linear {
+        factor: f64,
+        origin: i32,
+        offset: i32,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        keep_durations: bool,
+    },
+    // A piecewise-linear warp through anchors, see SubtitleFile::warp.
+    Warp {
+        anchors: Vec<(i32, i32)>,
+    },
+    // Time ranges moved by their own offsets, see SubtitleFile::apply_segments.
+    Segments {
+        segments: Vec<Segment>,
+    },
+    // Timecodes reinterpreted from one counting to another, see SubtitleFile::relabel_timecodes.
+    Relabel {
+        from: TimecodeRate,
+        to: TimecodeRate,
+    },
+}
+
+// A retiming applied to the cues of a range of a file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    #[serde(default)]
+    pub range: TimeRange,
+    #[serde(flatten)]
+    pub transform: Transform,
+    // Positions of the cues a step limited to a range retimed, from 0, in the file it wrote. Cues
+    // outside the range can land inside it once retimed, so these cues are the ones reverted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moved: Option<Range<usize>>,
+    // Number of cues the step cut or removed, whose original times weren't kept.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub lost: usize,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+impl Step {
+    // Create a function to record a retiming of every cue.
+    pub fn new(transform: Transform) -> Step {
+        Step {
+            range: TimeRange::default(),
+            transform,
+            moved: None,
+            lost: 0,
+        }
+    }
+
+    // Create a function to tell whether the cues the step retimed are known: every cue, or those at the
+    // positions recorded for a step limited to a range, which older versions didn't record.
+    pub fn knows_moved_cues(&self) -> bool {
+        self.range.is_all() || self.moved.is_some()
+    }
+
+    // Create a function to record the number of cues the step cut or removed.
+    pub fn losing(mut self, lost: usize) -> Step {
+        self.lost = lost;
+        self
+    }
+
+    // Create a function to build the step that undoes this one, retiming the cues the step moved. Cues
+    // cut or removed by the step can't be brought back, see lost, and times can be a milisecond off from
+    // rounding.
+    pub fn inverse(&self) -> Step {
+        let transform = match &self.transform {
+            Transform::Linear {
+                factor,
+                origin,
+                offset,
+                keep_durations,
+            } => Transform::Linear {
+                factor: 1.0 / factor,
+                origin: origin + offset,
+                offset: -offset,
+                keep_durations: *keep_durations,
+            },
+            Transform::Warp { anchors } => Transform::Warp {
+                anchors: anchors.iter().map(|(from, to)| (*to, *from)).collect(),
+            },
+            Transform::Segments { segments } => Transform::Segments {
+                segments: segments
+                    .iter()
+                    .map(|segment| Segment {
+                        range: TimeRange {
+                            after: segment.range.after.map(|after| after + segment.offset),
+                            before: segment.range.before.map(|before| before + segment.offset),
+                        },
+                        offset: -segment.offset,
+                    })
+                    .collect(),
+            },
+            Transform::Relabel { from, to } => Transform::Relabel {
+                from: *to,
+                to: *from,
+            },
+        };
+        Step {
+            range: TimeRange::default(),
+            transform,
+            moved: self.moved.clone(),
+            lost: 0,
+        }
+    }
+}
+
+impl SubtitleFile {
+    // Create a function to apply a recorded retiming to the cues.
+    // A step with the positions of the cues it moved only retimes those.
+    pub fn apply_step(&mut self, step: &Step) {
+        let retime = |part: &mut SubtitleFile| match &step.transform {
+            Transform::Linear {
+                factor,
+                origin,
+                offset,
+                keep_durations,
+            } => {
+                if *keep_durations {
+                    part.scale_starts(*factor, *origin, Rounding::Nearest);
+                } else {
+                    part.scale(*factor, *origin);
+                }
+                part.shift(*offset);
+            }
+            Transform::Warp { anchors } => {
+                part.warp(anchors);
+            }
+            Transform::Segments { segments } => {
+                part.apply_segments(segments);
+            }
+            Transform::Relabel { from, to } => part.relabel_timecodes(from, to),
+        };
+        match &step.moved {
+            Some(positions) => self.at_positions(positions, retime),
+            None => {
+                self.within(&step.range, retime);
+            }
+        }
+    }
+}
+
+// What was done to a file written by subsync: the file it was made from and the retimings applied to
+// it, in order. It is written next to the file, see sidecar_path, so the retiming can be reverted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    // Version of subsync that wrote the file.
+    pub version: String,
+    // The original file, before any of the steps.
+    pub input: String,
+    pub steps: Vec<Step>,
+}
+
+impl Provenance {
+    // Create a function to record the retimings applied to an input file.
+    pub fn new(input: &str, steps: Vec<Step>) -> Provenance {
+        Provenance {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            input: input.to_string(),
+            steps,
+        }
+    }
+
+    // Create a function to read a provenance file.
+    pub fn parse(contents: &str) -> Result<Provenance, String> {
+        serde_json::from_str(contents).map_err(|error| format!("Invalid provenance: {}", error))
+    }
+
+    // Create a function to write a provenance file.
+    pub fn serialize(&self) -> String {
+        let mut output = serde_json::to_string_pretty(self).unwrap();
+        output.push('\n');
+        output
+    }
+
+    // Create a function to add the retimings of a file made from this one, so the provenance still
+    // leads back to the original file.
+    pub fn then(mut self, steps: Vec<Step>) -> Provenance {
+        self.steps.extend(steps);
+        self.version = env!("CARGO_PKG_VERSION").to_string();
+        self
+    }
+
+    // Create a function to count the cues cut or removed by the retimings, which make reverting them
+    // inexact.
+    pub fn lost(&self) -> usize {
+        self.steps.iter().map(|step| step.lost).sum()
+    }
+
+    // Create a function to build the steps that undo every retiming, last one first.
+    pub fn inverse(&self) -> Vec<Step> {
+        self.steps.iter().rev().map(Step::inverse).collect()
+    }
+}
+
+// Create a function to name the provenance file written next to an output file.
+pub fn sidecar_path(output_file: &str) -> String {
+    format!("{}.subsync.json", output_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::{Cue, Format};
+
+    fn file(times: &[(i32, i32)]) -> SubtitleFile {
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: times
+                .iter()
+                .enumerate()
+                .map(|(i, (start_ms, end_ms))| Cue {
+                    index: i + 1,
+                    start_ms: *start_ms,
+                    end_ms: *end_ms,
+                    lines: vec![format!("Cue {}", i + 1)],
+                    ..Default::default()
+                })
+                .collect(),
+            trailer: Vec::new(),
+        }
+    }
+
+    fn times(file: &SubtitleFile) -> Vec<(usize, i32, i32)> {
+        file.cues
+            .iter()
+            .map(|cue| (cue.index, cue.start_ms, cue.end_ms))
+            .collect()
+    }
+
+    #[test]
+    fn reverting_a_range_leaves_the_cues_outside_it() {
+        // A 25 to 23.976 fps conversion of 1:00 to 2:00 stretches the range past the cue at 2:00.720.
+        let original = file(&[(30000, 32000), (90000, 92000), (120720, 122000)]);
+        let range = TimeRange::new(Some(60000), Some(120000)).unwrap();
+        let step = Step {
+            range,
+            transform: Transform::Linear {
+                factor: 25.0 / (24000.0 / 1001.0),
+                origin: 60000,
+                offset: 0,
+                keep_durations: false,
+            },
+            moved: original.positions_within(&range),
+            lost: 0,
+        };
+        let mut subtitles = original.clone();
+        subtitles.apply_step(&step);
+        assert_eq!(
+            times(&subtitles),
+            [(1, 30000, 32000), (2, 91281, 93367), (3, 120720, 122000)]
+        );
+        let provenance = Provenance::parse(&Provenance::new("p.srt", vec![step]).serialize());
+        for step in provenance.unwrap().inverse() {
+            subtitles.apply_step(&step);
+        }
+        assert_eq!(times(&subtitles), times(&original));
+    }
+
+    #[test]
+    fn reverting_segments_moves_every_cue_back() {
+        let original = file(&[(1000, 2000), (10000, 11000), (20000, 21000)]);
+        let segments = vec![
+            Segment {
+                range: TimeRange::new(None, Some(15000)).unwrap(),
+                offset: 0,
+            },
+            Segment {
+                range: TimeRange::new(Some(15000), None).unwrap(),
+                offset: -4000,
+            },
+        ];
+        let provenance = Provenance::new(
+            "p.srt",
+            vec![
+                Step::new(Transform::Segments { segments }),
+                Step::new(Transform::Linear {
+                    factor: 1.0,
+                    origin: 0,
+                    offset: 500,
+                    keep_durations: false,
+                }),
+            ],
+        );
+        let mut subtitles = original.clone();
+        for step in &provenance.steps {
+            subtitles.apply_step(step);
+        }
+        assert_eq!(subtitles.cues[2].start_ms, 16500);
+        for step in provenance.inverse() {
+            subtitles.apply_step(&step);
+        }
+        assert_eq!(times(&subtitles), times(&original));
+    }
+
+    #[test]
+    fn lossy_and_unrecorded_ranges_are_not_revertible() {
+        let step = Step::new(Transform::Linear {
+            factor: 1.0,
+            origin: 0,
+            offset: -5000,
+            keep_durations: false,
+        });
+        assert_eq!(
+            Provenance::new("p.srt", vec![step.clone().losing(2)]).lost(),
+            2
+        );
+        // Written by a version that didn't record the cues a range-limited step moved.
+        let older = Provenance::parse(
+            r#"{"version": "0.1.0", "input": "p.srt", "steps": [{"range": {"after": 60000}, "type": "linear", "factor": 1.0, "origin": 0, "offset": 500}]}"#,
+        )
+        .unwrap();
+        assert!(!older.steps[0].knows_moved_cues());
+        assert!(step.knows_moved_cues());
+        assert!(Provenance::parse("{}").is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use std::path::Path;
+
+use super::{
+    ass, audacity, dcp, json, microdvd, mpl2, sami, sbv, scc, srt, srv3, stl, tmp, ttml, vtt,
+    Format, ParseOptions, SubtitleFile, TagDialect,
+};
+
+// What a subtitle format can store, so conversions know what will be kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    // Inline formatting (italics, bold, colors) or style names.
+    pub styles: bool,
+    // Cue positions (cue settings, regions, rows, margins).
+    pub positions: bool,
+    // Times are stored as frames or timecodes, which need a framerate.
+    pub frame_based: bool,
+    // Cue end times. Formats without them get a default duration when read.
+    pub end_times: bool,
+}
+
+// A subtitle format that can be read and written. Implement it and register it in a Registry to
+// add a format; the file's format should be a Format::Custom with the same name as the first of names().
+pub trait SubtitleFormat {
+    // The Format that identifies files read with this format.
+    fn format(&self) -> Format;
+
+    // Names the format can be picked with on the command line. The first one is its display name.
+    fn names(&self) -> &'static [&'static str];
+
+    // File extensions, lowercase and without the dot.
+    fn extensions(&self) -> &'static [&'static str];
+
+    fn capabilities(&self) -> Capabilities;
+
+    // How the format writes inline styling. Lines are translated when converting between dialects.
+    fn tag_dialect(&self) -> TagDialect {
+        TagDialect::Html
+    }
+
+    // Whether files are binary rather than text, so they are never transcoded.
+    fn is_binary(&self) -> bool {
+        false
+    }
+
+    // Create a function to tell whether a file's contents look like this format.
+    fn detect(&self, _contents: &[u8]) -> bool {
+        false
+    }
+
+    fn parse(&self, contents: &[u8], options: &ParseOptions) -> Result<SubtitleFile, String>;
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8>;
+}
+
+// The known subtitle formats. Formats are tried in registration order when detecting a format from
+// the contents of a file, and files that match none of them are read as SubRip.
+pub struct Registry {
+    formats: Vec<Box<dyn SubtitleFormat>>,
+}
+
+impl Default for Registry {
+    // Create a function to build a registry of the built-in formats.
+    fn default() -> Registry {
+        let mut registry = Registry::empty();
+        registry.register(Box::new(stl::Stl));
+        registry.register(Box::new(vtt::Vtt));
+        registry.register(Box::new(json::Json));
+        registry.register(Box::new(ass::Ass));
+        registry.register(Box::new(scc::Scc));
+        registry.register(Box::new(sami::Sami));
+        registry.register(Box::new(srv3::Srv3));
+        registry.register(Box::new(dcp::Dcp(Format::DcpInterop)));
+        registry.register(Box::new(dcp::Dcp(Format::DcpSmpte)));
+        registry.register(Box::new(ttml::Ttml(Format::Itt)));
+        registry.register(Box::new(ttml::Ttml(Format::Ttml)));
+        registry.register(Box::new(microdvd::MicroDvd));
+        registry.register(Box::new(mpl2::Mpl2));
+        registry.register(Box::new(audacity::Audacity));
+        registry.register(Box::new(tmp::Tmp));
+        registry.register(Box::new(sbv::Sbv));
+        registry.register(Box::new(srt::Srt));
+        registry
+    }
+}
+
+impl Registry {
+    // Create a function to build a registry without any formats.
+    pub fn empty() -> Registry {
+        Registry {
+            formats: Vec::new(),
+        }
+    }
+
+    // Create a function to add a format. A format registered for the same Format replaces the old one.
+    pub fn register(&mut self, format: Box<dyn SubtitleFormat>) {
+        self.formats
+            .retain(|existing| existing.format() != format.format());
+        self.formats.push(format);
+    }
+
+    // Create a function to get the implementation of a format.
+    pub fn get(&self, format: Format) -> Result<&dyn SubtitleFormat, String> {
+        self.formats
+            .iter()
+            .find(|existing| existing.format() == format)
+            .map(|existing| existing.as_ref())
+            .ok_or_else(|| format!("Unsupported subtitle format {:?}", format))
+    }
+
+    // Create a function to pick a format from a file extension.
+    pub fn from_extension(&self, path: &str) -> Option<Format> {
+        let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+        self.formats
+            .iter()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+            .map(|format| format.format())
+    }
+
+    // Create a function to pick a format from its name or extension, as given on the command line.
+    pub fn from_name(&self, name: &str) -> Option<Format> {
+        let name = name.to_lowercase();
+        self.formats
+            .iter()
+            .find(|format| {
+                format.names().contains(&name.as_str())
+                    || format.extensions().contains(&name.as_str())
+            })
+            .map(|format| format.format())
+    }
+
+    // Create a function to guess the format from the file contents, for files with unknown extensions.
+    pub fn detect(&self, contents: &[u8]) -> Format {
+        self.formats
+            .iter()
+            .find(|format| format.detect(contents))
+            .map_or(Format::Srt, |format| format.format())
+    }
+
+    // Create a function to parse a file's contents in the given format.
+    pub fn parse(
+        &self,
+        contents: &[u8],
+        format: Format,
+        options: &ParseOptions,
+    ) -> Result<SubtitleFile, String> {
+        self.get(format)?.parse(contents, options)
+    }
+
+    // Create a function to write the cues out in the given format, translating inline styling tags
+    // when the file was read from a format with a different tag dialect.
+    pub fn serialize(&self, file: &SubtitleFile, format: Format) -> Result<Vec<u8>, String> {
+        let target = self.get(format)?;
+        let from = self
+            .get(file.format)
+            .map_or(TagDialect::Html, |source| source.tag_dialect());
+        if from == target.tag_dialect() {
+            return Ok(target.serialize(file));
+        }
+        let mut file = file.clone();
+        file.translate_tags(from, target.tag_dialect());
+        Ok(target.serialize(&file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::Cue;
+
+    fn file() -> SubtitleFile {
+        let cue = |index, start_ms, end_ms, lines: &[&str]| Cue {
+            index,
+            start_ms,
+            end_ms,
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+            ..Default::default()
+        };
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: Some(25.0),
+            header: Vec::new(),
+            cues: vec![
+                cue(1, 1000, 2480, &["Hello"]),
+                cue(2, 3600000, 3602000, &["Two", "lines"]),
+            ],
+            trailer: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn every_format_is_detected_and_read_back() {
+        let registry = Registry::default();
+        let options = ParseOptions {
+            framerate: Some(25.0),
+            ..Default::default()
+        };
+        for format in &registry.formats {
+            let name = format.names()[0];
+            let contents = registry.serialize(&file(), format.format()).unwrap();
+            // SubRip isn't detected, it is what files no format detects are read as.
+            assert_eq!(registry.detect(&contents), format.format(), "{}", name);
+            let read = registry
+                .parse(&contents, format.format(), &options)
+                .unwrap();
+            assert_eq!(read.cues.len(), 2, "{}", name);
+            for (read, written) in read.cues.iter().zip(file().cues) {
+                // Frame-based formats keep times to the nearest frame, SCC to a 29.97 fps frame.
+                assert!((read.start_ms - written.start_ms).abs() <= 40, "{}", name);
+                if format.capabilities().end_times {
+                    assert!((read.end_ms - written.end_ms).abs() <= 40, "{}", name);
+                }
+                assert_eq!(read.lines, written.lines, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn formats_are_found_by_extension_and_name() {
+        let registry = Registry::default();
+        assert_eq!(registry.from_extension("Movie.en.SRT"), Some(Format::Srt));
+        assert_eq!(registry.from_extension("movie.ytt"), Some(Format::Srv3));
+        assert_eq!(registry.from_name("WebVTT"), registry.from_name("vtt"));
+        assert_eq!(registry.from_extension("movie"), None);
+        assert_eq!(registry.from_name("klingon"), None);
+        assert_eq!(registry.detect(b"not subtitles"), Format::Srt);
+    }
+
+    #[test]
+    fn unregistered_formats_are_errors() {
+        let registry = Registry::empty();
+        assert!(registry
+            .parse(b"", Format::Srt, &ParseOptions::default())
+            .is_err());
+        assert!(registry.serialize(&file(), Format::Vtt).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+// How long the last cue of a language stays up when no closing SYNC follows it.
+const LAST_CUE_DURATION: i32 = 2000;
+
+const DEFAULT_HEADER: &str = "<SAMI>
+<HEAD>
+<STYLE TYPE=\"text/css\">
+<!--
+P { margin-left:8pt; margin-right:8pt; margin-bottom:2pt; margin-top:2pt;
+    text-align:center; font-size:20pt; font-family:Arial, Sans-serif;
+    font-weight:normal; color:white; }
+.ENCC { Name:English; lang:en-US; }
+-->
+</STYLE>
+</HEAD>";
+
+// Create a function to turn the markup inside a SYNC block into subtitle lines.
+fn parse_text(text: &str) -> Vec<String> {
+    let p_tag = Regex::new(r"(?i)</?p[^>]*>").unwrap();
+    let br_tag = Regex::new(r"(?i)<br\s*/?>").unwrap();
+    let text = p_tag.replace_all(text, "");
+    let text = text.replace(['\r', '\n'], "");
+    br_tag
+        .split(&text)
+        .map(|line| {
+            line.replace("&nbsp;", " ")
+                .replace("&amp;", "&")
+                .trim()
+                .to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+// Create a function to parse the contents of a SAMI .smi file.
+// Every SYNC opens a cue which is closed by the next SYNC of the same class (language).
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let body = Regex::new(r"(?i)<body[^>]*>").unwrap();
+    let sync = Regex::new(r#"(?i)<sync\s+start\s*=\s*"?(\d+)"?[^>]*>"#).unwrap();
+    let class = Regex::new(r#"(?i)<p[^>]*\sclass\s*=\s*"?([\w-]+)"?"#).unwrap();
+    let body_end = Regex::new(r"(?i)</body>").unwrap();
+    let body_start = body
+        .find(contents)
+        .ok_or_else(|| "Missing <BODY> tag".to_string())?;
+    let header = contents[..body_start.start()].trim().to_string();
+
+    let syncs: Vec<regex::Captures> = sync.captures_iter(contents).collect();
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut open: HashMap<Option<String>, usize> = HashMap::new();
+    for (i, caps) in syncs.iter().enumerate() {
+        let tag = caps.get(0).unwrap();
+        let block_end = match syncs.get(i + 1) {
+            Some(next) => next.get(0).unwrap().start(),
+            None => body_end
+                .find_at(contents, tag.end())
+                .map(|m| m.start())
+                .unwrap_or(contents.len()),
+        };
+        let block = &contents[tag.end()..block_end];
+        let start_ms = caps[1]
+            .parse::<i32>()
+            .map_err(|_| format!("Invalid SYNC start '{}'", &caps[1]))?;
+        let style = class.captures(block).map(|caps| caps[1].to_string());
+        if let Some(previous) = open.remove(&style) {
+            cues[previous].end_ms = start_ms;
+        }
+        let lines = parse_text(block);
+        if !lines.is_empty() {
+            open.insert(style.clone(), cues.len());
+            cues.push(Cue {
+                start_ms,
+                end_ms: start_ms + LAST_CUE_DURATION,
+                style,
+                lines,
+                ..Default::default()
+            });
+        }
+    }
+    cues.sort_by_key(|cue| cue.start_ms);
+    for (i, cue) in cues.iter_mut().enumerate() {
+        cue.index = i + 1;
+    }
+    Ok(SubtitleFile {
+        format: Format::Sami,
+        framerate: None,
+        header: vec![header],
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to write the cues out as SAMI.
+// A cue is closed with an empty &nbsp; SYNC unless the next cue of the same class starts right when it ends.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let mut output = String::new();
+    if file.format == Format::Sami && !file.header.is_empty() {
+        output.push_str(&file.header.join("\n"));
+    } else {
+        output.push_str(DEFAULT_HEADER);
+    }
+    output.push_str("\n<BODY>\n");
+    let mut syncs = Vec::new();
+    for (i, cue) in file.cues.iter().enumerate() {
+        let class = cue.style.as_deref().unwrap_or("ENCC");
+        let text: Vec<String> = cue
+            .lines
+            .iter()
+            .map(|line| line.replace('&', "&amp;"))
+            .collect();
+        syncs.push((cue.start_ms, class, text.join("<br>")));
+        let next_start = file.cues[i + 1..]
+            .iter()
+            .find(|next| next.style == cue.style)
+            .map(|next| next.start_ms);
+        if next_start != Some(cue.end_ms) {
+            syncs.push((cue.end_ms, class, "&nbsp;".to_string()));
+        }
+    }
+    syncs.sort_by_key(|(start, _, _)| *start);
+    for (start, class, text) in syncs {
+        output.push_str(&format!(
+            "<SYNC Start={}><P Class={}>{}\n",
+            start, class, text
+        ));
+    }
+    output.push_str("</BODY>\n</SAMI>\n");
+    output
+}
+
+// The SAMI format, as registered in the format registry.
+pub struct Sami;
+
+impl SubtitleFormat for Sami {
+    fn format(&self) -> Format {
+        Format::Sami
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["sami", "smi"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["smi", "sami"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: false,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        sniff_text(contents).to_lowercase().starts_with("<sami")
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMI: &str = "<SAMI>\n<HEAD>\n<TITLE>Test</TITLE>\n</HEAD>\n<BODY>\n<SYNC Start=1000><P Class=ENCC>Hello &amp; welcome<br>Two lines\n<SYNC Start=1000><P Class=FRCC>Bonjour\n<SYNC Start=2500><P Class=ENCC>Next\n<SYNC Start=3000><P Class=FRCC>&nbsp;\n<SYNC Start=4000><P Class=ENCC>&nbsp;\n</BODY>\n</SAMI>\n";
+
+    #[test]
+    fn cues_last_until_the_next_sync_of_their_class() {
+        let file = parse(SMI).unwrap();
+        let cues: Vec<(i32, i32, Option<&str>)> = file
+            .cues
+            .iter()
+            .map(|cue| (cue.start_ms, cue.end_ms, cue.style.as_deref()))
+            .collect();
+        assert_eq!(
+            cues,
+            [
+                (1000, 2500, Some("ENCC")),
+                (1000, 3000, Some("FRCC")),
+                (2500, 4000, Some("ENCC")),
+            ]
+        );
+        assert_eq!(file.cues[0].lines, ["Hello & welcome", "Two lines"]);
+    }
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(SMI).unwrap();
+        assert_eq!(serialize(&file), SMI);
+    }
+
+    #[test]
+    fn last_cue_without_closing_sync_gets_a_duration() {
+        let file = parse("<SAMI><BODY><SYNC Start=500><P>Alone</BODY></SAMI>").unwrap();
+        assert_eq!(file.cues[0].end_ms, 500 + LAST_CUE_DURATION);
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        assert!(parse("<SAMI><SYNC Start=500><P>No body</SAMI>").is_err());
+        assert!(parse("<SAMI><BODY><SYNC Start=99999999999><P>Text</BODY></SAMI>").is_err());
+    }
+}
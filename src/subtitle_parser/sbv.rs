@@ -0,0 +1,147 @@
+use regex::Regex;
+
+use super::{
+    decode_text, sniff_text, split_blocks, Capabilities, Cue, Format, ParseOptions, SubtitleFile,
+    SubtitleFormat,
+};
+
+// Create a function to convert a h:mm:ss.mmm string to miliseconds as an integer.
+pub fn convert_to_miliseconds(time: &str) -> Option<i32> {
+    let re = Regex::new(r"^(\d+):(\d{2}):(\d{2})\.(\d{3})$").unwrap();
+    let caps = re.captures(time.trim())?;
+    let hours = caps.get(1)?.as_str().parse::<i32>().ok()?;
+    let minutes = caps.get(2)?.as_str().parse::<i32>().ok()?;
+    let seconds = caps.get(3)?.as_str().parse::<i32>().ok()?;
+    let miliseconds = caps.get(4)?.as_str().parse::<i32>().ok()?;
+    Some((hours * 3600000) + (minutes * 60000) + (seconds * 1000) + miliseconds)
+}
+
+// Create a function to convert miliseconds to a h:mm:ss.mmm string.
+pub fn convert_to_time(miliseconds: i32) -> String {
+    let time = super::vtt::convert_to_time(miliseconds);
+    match time.strip_prefix('0') {
+        Some(time) if !time.starts_with(':') => time.to_string(),
+        _ => time,
+    }
+}
+
+// Create a function to parse the contents of a YouTube .sbv file.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let mut cues = Vec::new();
+    for block in split_blocks(contents) {
+        let index = cues.len() + 1;
+        let (start, end) = block[0]
+            .split_once(',')
+            .ok_or_else(|| format!("Cue {}: invalid timing line '{}'", index, block[0]))?;
+        let start_ms = convert_to_miliseconds(start)
+            .ok_or_else(|| format!("Cue {}: invalid timestamp '{}'", index, start.trim()))?;
+        let end_ms = convert_to_miliseconds(end)
+            .ok_or_else(|| format!("Cue {}: invalid timestamp '{}'", index, end.trim()))?;
+        cues.push(Cue {
+            index,
+            start_ms,
+            end_ms,
+            lines: block[1..].iter().map(|line| line.to_string()).collect(),
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::Sbv,
+        framerate: None,
+        header: Vec::new(),
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to write the cues out as .sbv.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let mut output = String::new();
+    for cue in &file.cues {
+        output.push_str(&format!(
+            "{},{}\n",
+            convert_to_time(cue.start_ms),
+            convert_to_time(cue.end_ms)
+        ));
+        for line in &cue.lines {
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// The YouTube SBV format, as registered in the format registry.
+pub struct Sbv;
+
+impl SubtitleFormat for Sbv {
+    fn format(&self) -> Format {
+        Format::Sbv
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["sbv"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["sbv"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: false,
+            positions: false,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        Regex::new(r"^\d+:\d{2}:\d{2}\.\d{3},\d+:\d{2}:\d{2}\.\d{3}")
+            .unwrap()
+            .is_match(&sniff_text(contents))
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SBV: &str =
+        "0:00:01.000,0:00:02.500\nHello.\n\n0:01:02.003,12:00:00.000\nTwo\nlines.\n\n";
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(SBV).unwrap();
+        assert_eq!(file.cues.len(), 2);
+        assert_eq!(
+            (file.cues[1].start_ms, file.cues[1].end_ms),
+            (62003, 43200000)
+        );
+        assert_eq!(file.cues[1].lines, ["Two", "lines."]);
+        assert_eq!(serialize(&file), SBV);
+    }
+
+    #[test]
+    fn hours_are_written_without_a_leading_zero() {
+        assert_eq!(convert_to_time(1000), "0:00:01.000");
+        assert_eq!(convert_to_time(36000000), "10:00:00.000");
+        assert_eq!(convert_to_miliseconds("00:00:01.000"), Some(1000));
+    }
+
+    #[test]
+    fn malformed_cues_are_errors() {
+        assert!(parse("0:00:01.000 0:00:02.000\nText\n").is_err());
+        assert!(parse("0:00:01.000,0:00:02\nText\n").is_err());
+        assert!(parse("0:00:01,000,0:00:02,000\nText\n").is_err());
+    }
+}
@@ -0,0 +1,607 @@
+use regex::Regex;
+
+use super::timecode::TimecodeRate;
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+const HEADER: &str = "Scenarist_SCC V1.0";
+
+// Miscellaneous control codes (channel 1), without parity.
+const RESUME_CAPTION_LOADING: u8 = 0x20;
+const BACKSPACE: u8 = 0x21;
+const ROLL_UP_2: u8 = 0x25;
+const ROLL_UP_4: u8 = 0x27;
+const RESUME_DIRECT_CAPTIONING: u8 = 0x29;
+const ERASE_DISPLAYED_MEMORY: u8 = 0x2C;
+const CARRIAGE_RETURN: u8 = 0x2D;
+const ERASE_NON_DISPLAYED_MEMORY: u8 = 0x2E;
+const END_OF_CAPTION: u8 = 0x2F;
+
+// Characters of the basic set that differ from ASCII.
+const BASIC_CHARACTERS: [(u8, char); 9] = [
+    (0x2A, 'á'),
+    (0x5C, 'é'),
+    (0x5E, 'í'),
+    (0x5F, 'ó'),
+    (0x60, 'ú'),
+    (0x7B, 'ç'),
+    (0x7C, '÷'),
+    (0x7D, 'Ñ'),
+    (0x7E, 'ñ'),
+];
+
+// Special characters (0x11 0x30-0x3F) and extended characters (0x12/0x13 0x20-0x3F), in code order.
+const SPECIAL_CHARACTERS: &str = "®°½¿™¢£♪à èâêîôû";
+const EXTENDED_CHARACTERS_12: &str = "ÁÉÓÚÜü‘¡*'—©℠•“”ÀÂÇÈÊËëÎÏïÔÙùÛ«»";
+const EXTENDED_CHARACTERS_13: &str = "ÃãÍÌìÒòÕõ{}\\^_|~ÄäÖöß¥¤¦ÅåØø┌┐└┘";
+
+// Preamble address code first and second bytes for rows 1 to 15.
+const ROWS: [(u8, u8); 15] = [
+    (0x11, 0x40),
+    (0x11, 0x60),
+    (0x12, 0x40),
+    (0x12, 0x60),
+    (0x15, 0x40),
+    (0x15, 0x60),
+    (0x16, 0x40),
+    (0x16, 0x60),
+    (0x17, 0x40),
+    (0x17, 0x60),
+    (0x10, 0x40),
+    (0x13, 0x40),
+    (0x13, 0x60),
+    (0x14, 0x40),
+    (0x14, 0x60),
+];
+
+// Create a function to convert an SCC timecode to a frame count at 29.97 fps. Timecodes with a ';' or
+// '.' before the frames are drop-frame, ':' is non-drop-frame.
+pub fn timecode_to_frames(timecode: &str) -> Option<i64> {
+    let re = Regex::new(r"^(\d{2}):(\d{2}):(\d{2})([:;.,])(\d{2})$").unwrap();
+    let caps = re.captures(timecode.trim())?;
+    let field = |i: usize| caps[i].parse::<i64>().ok();
+    let rate = TimecodeRate::ntsc(&caps[4] != ":");
+    Some(rate.label_to_frames(field(1)?, field(2)?, field(3)?, field(5)?))
+}
+
+// Create a function to write a frame count as a 29.97 fps drop-frame timecode.
+pub fn frames_to_timecode(frames: i64) -> String {
+    let (hours, minutes, seconds, frames) = TimecodeRate::ntsc(true).frames_to_label(frames);
+    format!("{:02}:{:02}:{:02};{:02}", hours, minutes, seconds, frames)
+}
+
+// Create a function to add odd parity to a 7 bit value.
+fn with_parity(byte: u8) -> u8 {
+    if byte.count_ones().is_multiple_of(2) {
+        byte | 0x80
+    } else {
+        byte
+    }
+}
+
+// Caption decoder state while reading the file.
+#[derive(Default)]
+struct Decoder {
+    roll_up: bool,
+    // Lines being loaded into non-displayed memory (pop-on), or being written on screen (roll-up).
+    lines: Vec<String>,
+    italics: bool,
+    // The caption on screen: start frame, lines and the words that loaded it.
+    displayed: Option<(i64, Vec<String>, Option<String>)>,
+    // Words received since the last end of caption, kept so pop-on captions can be written back unchanged.
+    words: Vec<String>,
+    line_start: Option<i64>,
+    cues: Vec<Cue>,
+}
+
+impl Decoder {
+    fn push_char(&mut self, character: char, frame: i64) {
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        if self.roll_up && self.line_start.is_none() {
+            self.line_start = Some(frame);
+        }
+        self.lines.last_mut().unwrap().push(character);
+    }
+
+    fn new_line(&mut self) {
+        self.close_italics();
+        if self
+            .lines
+            .last()
+            .is_some_and(|line| !line.trim().is_empty())
+        {
+            self.lines.push(String::new());
+        }
+    }
+
+    fn close_italics(&mut self) {
+        if self.italics {
+            if let Some(line) = self.lines.last_mut() {
+                line.push_str("</i>");
+            }
+            self.italics = false;
+        }
+    }
+
+    fn set_italics(&mut self, italics: bool, frame: i64) {
+        if italics && !self.italics {
+            self.push_char(' ', frame);
+            self.lines.last_mut().unwrap().push_str("<i>");
+        } else if !italics && self.italics {
+            self.lines.last_mut().unwrap().push_str("</i>");
+            self.push_char(' ', frame);
+        }
+        self.italics = italics;
+    }
+
+    fn end_displayed(&mut self, frame: i64) {
+        if let Some((start, lines, words)) = self.displayed.take() {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty() && line != "<i></i>")
+                .collect();
+            if !lines.is_empty() {
+                self.cues.push(Cue {
+                    index: self.cues.len() + 1,
+                    start_ms: TimecodeRate::ntsc(true).frames_to_ms(start),
+                    end_ms: TimecodeRate::ntsc(true).frames_to_ms(frame),
+                    settings: words,
+                    lines,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    // Roll-up and paint-on captions are split into one cue per line.
+    fn carriage_return(&mut self, frame: i64) {
+        self.close_italics();
+        self.end_displayed(frame);
+        if let Some(start) = self.line_start.take() {
+            self.displayed = Some((start, std::mem::take(&mut self.lines), None));
+        }
+        self.lines.clear();
+    }
+
+    fn control(&mut self, first: u8, second: u8, frame: i64) {
+        match (first, second) {
+            (0x14, RESUME_CAPTION_LOADING) => self.roll_up = false,
+            (0x14, BACKSPACE) => {
+                if let Some(line) = self.lines.last_mut() {
+                    line.pop();
+                }
+            }
+            (0x14, ROLL_UP_2..=ROLL_UP_4) | (0x14, RESUME_DIRECT_CAPTIONING) => {
+                if !self.roll_up {
+                    self.lines.clear();
+                }
+                self.roll_up = true;
+            }
+            (0x14, ERASE_DISPLAYED_MEMORY) => {
+                if self.roll_up {
+                    self.carriage_return(frame);
+                }
+                self.end_displayed(frame);
+                // An erase sent while the next caption is loaded is not part of it.
+                if self.roll_up {
+                    self.words.clear();
+                } else {
+                    self.words.pop();
+                }
+            }
+            (0x14, CARRIAGE_RETURN) if self.roll_up => self.carriage_return(frame),
+            (0x14, ERASE_NON_DISPLAYED_MEMORY) if !self.roll_up => {
+                self.lines.clear();
+                self.italics = false;
+            }
+            (0x14, END_OF_CAPTION) => {
+                self.close_italics();
+                self.end_displayed(frame);
+                let words = self.words.join(" ");
+                self.displayed = Some((frame, std::mem::take(&mut self.lines), Some(words)));
+                self.words.clear();
+                self.roll_up = false;
+            }
+            (0x11, 0x20..=0x2F) => self.set_italics(second >= 0x2E, frame),
+            (0x11, 0x30..=0x3F) => {
+                let character = SPECIAL_CHARACTERS.chars().nth((second - 0x30) as usize);
+                self.push_char(character.unwrap_or(' '), frame);
+            }
+            (0x12, 0x20..=0x3F) | (0x13, 0x20..=0x3F) => {
+                let table = if first == 0x12 {
+                    EXTENDED_CHARACTERS_12
+                } else {
+                    EXTENDED_CHARACTERS_13
+                };
+                // Extended characters replace the basic fallback character sent before them.
+                if let Some(line) = self.lines.last_mut() {
+                    line.pop();
+                }
+                let character = table.chars().nth((second - 0x20) as usize);
+                self.push_char(character.unwrap_or(' '), frame);
+            }
+            (0x10..=0x17, 0x40..=0x7F) => {
+                self.new_line();
+                let attribute = second & 0x1F;
+                self.italics = false;
+                if attribute == 0x0E || attribute == 0x0F {
+                    self.push_char(' ', frame);
+                    self.lines.last_mut().unwrap().push_str("<i>");
+                    self.italics = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Create a function to parse the contents of an .scc file, decoding the CEA-608 channel 1 captions.
+// Pop-on captions keep the words that loaded them, so they can be written back unchanged.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+    match lines.next() {
+        Some(line) if line.trim().starts_with("Scenarist_SCC") => {}
+        _ => return Err(format!("Missing {} header", HEADER)),
+    }
+    let mut decoder = Decoder::default();
+    let mut last_frame = 0;
+    let mut channel_one = true;
+    let mut previous_control = None;
+    for line in lines {
+        let mut parts = line.split_whitespace();
+        let timecode = parts.next().unwrap_or("");
+        let packet_start = timecode_to_frames(timecode)
+            .ok_or_else(|| format!("Invalid timecode '{}'", timecode))?;
+        for (i, word) in parts.enumerate() {
+            let frame = packet_start + i as i64;
+            last_frame = frame;
+            let value = u16::from_str_radix(word, 16)
+                .map_err(|_| format!("{}: invalid caption data '{}'", timecode, word))?;
+            decoder.words.push(word.to_lowercase());
+            let first = (value >> 8) as u8 & 0x7F;
+            let second = value as u8 & 0x7F;
+            if (0x10..=0x1F).contains(&first) {
+                // Control codes are sent twice, the repeat is ignored.
+                if previous_control == Some(value) {
+                    previous_control = None;
+                    // Keep the repeat of an end of caption with the caption it ended.
+                    let word = decoder.words.pop();
+                    if (first & 0xF7, second) == (0x14, END_OF_CAPTION) {
+                        if let (Some((_, _, Some(words))), Some(word)) =
+                            (&mut decoder.displayed, word)
+                        {
+                            words.push(' ');
+                            words.push_str(&word);
+                        }
+                    } else if second != ERASE_DISPLAYED_MEMORY || first & 0xF7 != 0x14 {
+                        decoder.words.extend(word);
+                    }
+                    continue;
+                }
+                previous_control = Some(value);
+                channel_one = first & 0x08 == 0;
+                if channel_one {
+                    decoder.control(first & 0xF7, second, frame);
+                }
+                continue;
+            }
+            previous_control = None;
+            if !channel_one {
+                continue;
+            }
+            for byte in [first, second] {
+                if byte >= 0x20 {
+                    let character = BASIC_CHARACTERS
+                        .iter()
+                        .find(|(code, _)| *code == byte)
+                        .map_or(byte as char, |(_, character)| *character);
+                    decoder.push_char(character, frame);
+                }
+            }
+        }
+    }
+    if decoder.roll_up {
+        decoder.carriage_return(last_frame + 1);
+    }
+    decoder.end_displayed(last_frame + 1);
+    Ok(SubtitleFile {
+        format: Format::Scc,
+        framerate: None,
+        header: vec![HEADER.to_string()],
+        cues: decoder.cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to encode a cue as a pop-on caption: erase and load the buffer, then end of caption.
+fn encode_caption(lines: &[String]) -> Vec<String> {
+    let control = |first: u8, second: u8| {
+        let word = format!("{:02x}{:02x}", with_parity(first), with_parity(second));
+        [word.clone(), word]
+    };
+    let tag = Regex::new(r"</?[^>]*>").unwrap();
+    let mut words = Vec::new();
+    words.extend(control(0x14, ERASE_NON_DISPLAYED_MEMORY));
+    words.extend(control(0x14, RESUME_CAPTION_LOADING));
+    let lines: Vec<&String> = lines.iter().rev().take(4).rev().collect();
+    for (i, line) in lines.iter().enumerate() {
+        let text = tag.replace_all(line, "");
+        let text = text.trim();
+        // Center the line using the 4 column indents of the preamble address codes.
+        let indent = ((32 - text.chars().count().min(32)) / 2 / 4) as u8;
+        let (first, second) = ROWS[15 - lines.len() + i];
+        let line = line.trim();
+        let (attribute, line) = match line.strip_prefix("<i>") {
+            Some(rest) => (0x0E, rest),
+            None => (0x10 + indent * 2, line),
+        };
+        words.extend(control(first, second + attribute));
+        let mut bytes: Vec<u8> = Vec::new();
+        let mid_row = |bytes: &mut Vec<u8>, code: u8| {
+            // Mid-row codes take the place of a space.
+            if bytes.last() == Some(&b' ') {
+                bytes.pop();
+            }
+            if !bytes.len().is_multiple_of(2) {
+                bytes.push(0);
+            }
+            bytes.extend([0x11, code, 0x11, code]);
+        };
+        let mut rest = line;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("<i>") {
+                mid_row(&mut bytes, 0x2E);
+                rest = after.trim_start();
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix("</i>") {
+                if !after.is_empty() {
+                    mid_row(&mut bytes, 0x20);
+                }
+                rest = after.trim_start();
+                continue;
+            }
+            if let Some(found) = tag.find(rest).filter(|found| found.start() == 0) {
+                rest = &rest[found.end()..];
+                continue;
+            }
+            let character = rest.chars().next().unwrap();
+            rest = &rest[character.len_utf8()..];
+            if let Some((code, _)) = BASIC_CHARACTERS.iter().find(|(_, c)| *c == character) {
+                bytes.push(*code);
+            } else if (' '..='z').contains(&character)
+                && !BASIC_CHARACTERS
+                    .iter()
+                    .any(|(code, _)| *code == character as u8)
+            {
+                bytes.push(character as u8);
+            } else if let Some(position) = SPECIAL_CHARACTERS.chars().position(|c| c == character) {
+                if !bytes.len().is_multiple_of(2) {
+                    bytes.push(0);
+                }
+                bytes.extend([0x11, 0x30 + position as u8, 0x11, 0x30 + position as u8]);
+            } else {
+                bytes.push(b' ');
+            }
+        }
+        if !bytes.len().is_multiple_of(2) {
+            bytes.push(0);
+        }
+        for pair in bytes.chunks(2) {
+            words.push(format!(
+                "{:02x}{:02x}",
+                with_parity(pair[0]),
+                with_parity(pair[1])
+            ));
+        }
+    }
+    words.extend(control(0x14, END_OF_CAPTION));
+    words
+}
+
+// Create a function to find where the words of a caption can be cut without separating a control code
+// from its repeat, as the number of words before each cut.
+fn word_boundaries(words: &[String]) -> Vec<usize> {
+    let mut boundaries = vec![0];
+    let mut i = 0;
+    while i < words.len() {
+        let control = u16::from_str_radix(&words[i], 16)
+            .is_ok_and(|value| (0x10..=0x1F).contains(&((value >> 8) as u8 & 0x7F)));
+        i += if control && words.get(i + 1) == Some(&words[i]) {
+            2
+        } else {
+            1
+        };
+        boundaries.push(i);
+    }
+    boundaries
+}
+
+// Create a function to write the cues out as .scc with 29.97 drop-frame timecodes.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let rate = TimecodeRate::ntsc(true);
+    let erase = format!(
+        "{:02x}{:02x}",
+        with_parity(0x14),
+        with_parity(ERASE_DISPLAYED_MEMORY)
+    );
+    let mut packets: Vec<(i64, Vec<String>)> = Vec::new();
+    // The frame the caption on screen ends at, erased unless the next caption replaces it.
+    let mut displayed_end: Option<i64> = None;
+    for cue in &file.cues {
+        let mut words: Vec<String> = match (&cue.settings, file.format) {
+            (Some(words), Format::Scc) => words.split_whitespace().map(|w| w.to_string()).collect(),
+            _ => encode_caption(&cue.lines),
+        };
+        // Start sending early enough for the end of caption to land on the cue start.
+        let start = rate.ms_to_frames(cue.start_ms);
+        let end_of_caption = words
+            .iter()
+            .position(|word| word == "942f")
+            .unwrap_or(words.len().saturating_sub(1));
+        let mut packet_start = start - end_of_caption as i64;
+        if let Some(end) = displayed_end {
+            if end + 2 <= packet_start {
+                packets.push((end, vec![erase.clone(), erase.clone()]));
+            } else if end + 2 <= start {
+                // The caption on screen ends while this one is loaded into non-displayed memory: the erase
+                // is sent in the middle of the loading, which starts 2 frames earlier to make room for it.
+                packet_start -= 2;
+                let cut = word_boundaries(&words[..end_of_caption])
+                    .into_iter()
+                    .rfind(|cut| packet_start + *cut as i64 <= end)
+                    .unwrap_or(0);
+                words.splice(cut..cut, [erase.clone(), erase.clone()]);
+            }
+            // Otherwise the end of caption replaces the caption on screen, at most a frame after it ends.
+        }
+        packets.push((packet_start, words));
+        displayed_end = Some(rate.ms_to_frames(cue.end_ms));
+    }
+    if let Some(end) = displayed_end {
+        packets.push((end, vec![erase.clone(), erase]));
+    }
+    let mut output = format!("{}\n\n", HEADER);
+    let mut next_free = 0;
+    for (start, words) in packets {
+        // Packets can't overlap: a caption shown for less time than it takes to send the next one
+        // can only be followed as soon as it is sent.
+        let start = start.max(next_free);
+        next_free = start + words.len() as i64;
+        output.push_str(&format!(
+            "{}\t{}\n\n",
+            frames_to_timecode(start),
+            words.join(" ")
+        ));
+    }
+    output
+}
+
+// The Scenarist Closed Captions format, as registered in the format registry.
+pub struct Scc;
+
+impl SubtitleFormat for Scc {
+    fn format(&self) -> Format {
+        Format::Scc
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["scc"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["scc"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: true,
+            frame_based: true,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        sniff_text(contents).starts_with("Scenarist_SCC")
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn srt(times: &[(i32, i32, &str)]) -> SubtitleFile {
+        let cues = times
+            .iter()
+            .enumerate()
+            .map(|(i, (start_ms, end_ms, text))| Cue {
+                index: i + 1,
+                start_ms: *start_ms,
+                end_ms: *end_ms,
+                lines: text.split('\n').map(String::from).collect(),
+                ..Default::default()
+            })
+            .collect();
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues,
+            trailer: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_cue_times_within_a_frame() {
+        let file = srt(&[
+            (1000, 3000, "Hello there, this is a line."),
+            (3500, 6800, "And a second one\nthat has two lines."),
+            (7000, 9000, "Third."),
+            (9000, 10500, "<i>Fourth, right after.</i>"),
+            (
+                10600,
+                12000,
+                "A fifth line loaded while the fourth is shown.",
+            ),
+            (15000, 16000, "Alone."),
+        ]);
+        let parsed = parse(&serialize(&file)).unwrap();
+        assert_eq!(parsed.cues.len(), file.cues.len());
+        let frame = 1001.0 / 30.0;
+        for (cue, original) in parsed.cues.iter().zip(&file.cues) {
+            assert_eq!(cue.lines, original.lines);
+            assert!(
+                ((cue.start_ms - original.start_ms) as f64).abs() <= frame,
+                "cue {} starts at {} instead of {}",
+                original.index,
+                cue.start_ms,
+                original.start_ms
+            );
+            assert!(
+                ((cue.end_ms - original.end_ms) as f64).abs() <= frame,
+                "cue {} ends at {} instead of {}",
+                original.index,
+                cue.end_ms,
+                original.end_ms
+            );
+        }
+    }
+
+    #[test]
+    fn scc_round_trip_is_unchanged() {
+        let file = srt(&[
+            (1000, 3000, "One."),
+            (3100, 5000, "Two, loaded while one is shown."),
+        ]);
+        let scc = serialize(&file);
+        assert_eq!(serialize(&parse(&scc).unwrap()), scc);
+    }
+
+    #[test]
+    fn timecodes_are_drop_frame_or_not_by_separator() {
+        assert_eq!(timecode_to_frames("00:01:00;02"), Some(1800));
+        assert_eq!(timecode_to_frames("00:01:00:02"), Some(1802));
+        assert_eq!(timecode_to_frames("00:10:00;00"), Some(17982));
+        assert_eq!(frames_to_timecode(1800), "00:01:00;02");
+        assert_eq!(frames_to_timecode(17982), "00:10:00;00");
+        assert_eq!(timecode_to_frames("1:00:00;00"), None);
+    }
+
+    #[test]
+    fn missing_header_is_an_error() {
+        assert!(parse("00:00:01;00\t9420 9420").is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use regex::Regex;
+
+use super::SubtitleFile;
+
+// What is removed from subtitles for the deaf and hard of hearing (SDH) to make plain subtitles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Annotation {
+    // The name of whoever speaks, before their line: JOHN:, MAN #2:.
+    SpeakerLabel,
+    // A sound or the way a line is said, in brackets or parentheses: [door slams], (sighs).
+    SoundDescription,
+    // A cue with only music notes.
+    Music,
+    // A cue left without text once its annotations are removed.
+    EmptyCue,
+}
+
+impl Annotation {
+    // Create a function to name an annotation, as printed in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Annotation::SpeakerLabel => "speaker label",
+            Annotation::SoundDescription => "sound description",
+            Annotation::Music => "music",
+            Annotation::EmptyCue => "empty cue",
+        }
+    }
+}
+
+// An annotation removed from a cue, with the times of the cue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Removal {
+    pub annotation: Annotation,
+    // Index of the cue, as numbered before the removal.
+    pub cue: usize,
+    pub start_ms: i32,
+    pub end_ms: i32,
+    // The removed text, or the text of the removed cue.
+    pub text: String,
+}
+
+// Create a function to remove the hearing-impaired annotations of subtitles: speaker labels in capitals
+// at the start of a line, descriptions in brackets or parentheses (even over two lines), and cues with
+// only music notes (♪, ♫ or #). Lines left empty or with a lone dialogue dash are removed, as are cues
+// left without text; the cues left keep their numbers. Returns everything removed, in the order of the
+// cues.
+pub fn remove_sdh(subtitles: &mut SubtitleFile) -> Vec<Removal> {
+    let description = Regex::new(r"\[[^\]]*\]|\([^)]*\)").unwrap();
+    // The dash and formatting tags ahead of a label are kept.
+    let label = Regex::new(
+        r"(?m)^((?:\s*(?:<[^>]+>|\{[^}]*\}))*\s*(?:-\s*)?)(\p{Lu}[\p{Lu}\d .'#&-]*[\p{Lu}\d])\s*:(?:[ \t]+|$)",
+    )
+    .unwrap();
+    let tag = Regex::new(r"</?[a-zA-Z][^>]*>|\{[^}]*\}").unwrap();
+    let empty_tags = Regex::new(r"<([a-zA-Z]+)[^>]*>\s*</([a-zA-Z]+)>").unwrap();
+    let spaces = Regex::new(r"[ \t]{2,}").unwrap();
+    let mut removals = Vec::new();
+    let mut cues = Vec::new();
+    for mut cue in subtitles.cues.drain(..) {
+        let mut found: Vec<(Annotation, String)> = Vec::new();
+        let text = cue.lines.join("\n");
+        let plain = tag.replace_all(&text, "");
+        if plain.contains(['♪', '♫', '#'])
+            && plain
+                .chars()
+                .all(|character| "♪♫#*".contains(character) || character.is_whitespace())
+        {
+            found.push((Annotation::Music, cue.lines.join(" / ")));
+        }
+        found.extend(description.find_iter(&text).map(|description| {
+            (
+                Annotation::SoundDescription,
+                description.as_str().to_string(),
+            )
+        }));
+        let text = description.replace_all(&text, "");
+        found.extend(
+            label
+                .captures_iter(&text)
+                .map(|label| (Annotation::SpeakerLabel, label[2].to_string())),
+        );
+        let text = label.replace_all(&text, "$1");
+        let text = empty_tags.replace_all(&text, |tags: &regex::Captures| {
+            if tags[1].eq_ignore_ascii_case(&tags[2]) {
+                String::new()
+            } else {
+                tags[0].to_string()
+            }
+        });
+        let dashed = cue
+            .lines
+            .iter()
+            .filter(|line| tag.replace_all(line, "").trim_start().starts_with('-'))
+            .count();
+        let mut lines: Vec<String> = text
+            .lines()
+            .map(|line| spaces.replace_all(line, " ").trim().to_string())
+            .filter(|line| {
+                let plain = tag.replace_all(line, "");
+                !plain.trim_start_matches('-').trim().is_empty()
+            })
+            .collect();
+        // A dialogue dash is only needed when two people speak.
+        if dashed > 1 && lines.len() == 1 {
+            let plain = tag.replace_all(&lines[0], "");
+            if let Some(dash) = plain.trim_start().strip_prefix('-') {
+                if !tag.is_match(&lines[0]) {
+                    lines[0] = dash.trim_start().to_string();
+                }
+            }
+        }
+        // Cues without annotations are left as they were.
+        if found.is_empty() {
+            cues.push(cue);
+            continue;
+        }
+        let music = found[0].0 == Annotation::Music;
+        if lines.is_empty() && !music {
+            found.push((Annotation::EmptyCue, String::new()));
+        }
+        if music {
+            found.truncate(1);
+        }
+        removals.extend(found.into_iter().map(|(annotation, text)| Removal {
+            annotation,
+            cue: cue.index,
+            start_ms: cue.start_ms,
+            end_ms: cue.end_ms,
+            text: text.trim().to_string(),
+        }));
+        if !lines.is_empty() && !music {
+            cue.lines = lines;
+            cues.push(cue);
+        }
+    }
+    subtitles.cues = cues;
+    removals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::{Cue, Format};
+
+    // Create a function to remove the annotations of cues numbered from 1, a second apart.
+    fn cleaned(texts: &[&str]) -> (Vec<(usize, String)>, Vec<Removal>) {
+        let mut subtitles = SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: texts
+                .iter()
+                .enumerate()
+                .map(|(i, text)| Cue {
+                    index: i + 1,
+                    start_ms: i as i32 * 1000,
+                    end_ms: i as i32 * 1000 + 900,
+                    lines: text.lines().map(str::to_string).collect(),
+                    ..Default::default()
+                })
+                .collect(),
+            trailer: Vec::new(),
+        };
+        let removals = remove_sdh(&mut subtitles);
+        (
+            subtitles
+                .cues
+                .iter()
+                .map(|cue| (cue.index, cue.lines.join("\n")))
+                .collect(),
+            removals,
+        )
+    }
+
+    fn removed(removals: &[Removal]) -> Vec<(Annotation, usize, &str)> {
+        removals
+            .iter()
+            .map(|removal| (removal.annotation, removal.cue, removal.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn labels_and_descriptions_are_removed() {
+        let (cues, removals) = cleaned(&[
+            "JOHN: Hello.",
+            "(sighs) Fine.",
+            "[thunder\nrumbling] Run!",
+            "MAN #2: Get down!",
+            "Note: it's 10:30.",
+        ]);
+        assert_eq!(
+            cues,
+            [
+                (1, "Hello.".to_string()),
+                (2, "Fine.".to_string()),
+                (3, "Run!".to_string()),
+                (4, "Get down!".to_string()),
+                (5, "Note: it's 10:30.".to_string()),
+            ]
+        );
+        assert_eq!(
+            removed(&removals),
+            [
+                (Annotation::SpeakerLabel, 1, "JOHN"),
+                (Annotation::SoundDescription, 2, "(sighs)"),
+                (Annotation::SoundDescription, 3, "[thunder\nrumbling]"),
+                (Annotation::SpeakerLabel, 4, "MAN #2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cues_left_without_text_are_removed_and_the_others_keep_their_numbers() {
+        let (cues, removals) = cleaned(&["[door slams]", "♪ ♪", "♪ Singing in the rain ♪", "Yes."]);
+        assert_eq!(
+            cues,
+            [
+                (3, "♪ Singing in the rain ♪".to_string()),
+                (4, "Yes.".to_string())
+            ]
+        );
+        assert_eq!(
+            removed(&removals),
+            [
+                (Annotation::SoundDescription, 1, "[door slams]"),
+                (Annotation::EmptyCue, 1, ""),
+                (Annotation::Music, 2, "♪ ♪"),
+            ]
+        );
+    }
+
+    #[test]
+    fn dashes_and_tags_are_kept_only_around_text() {
+        let (cues, _) = cleaned(&[
+            "- [screams]\n- Help!",
+            "- JOHN: Duck!\n- MARY: Why?",
+            "<i>JOHN: Hi</i>",
+            "<i>[music]</i>\nYes",
+        ]);
+        assert_eq!(
+            cues,
+            [
+                (1, "Help!".to_string()),
+                (2, "- Duck!\n- Why?".to_string()),
+                (3, "<i>Hi</i>".to_string()),
+                (4, "Yes".to_string()),
+            ]
+        );
+    }
+}
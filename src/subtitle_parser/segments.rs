@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+
+use super::timing::{parse_duration, TimeRange};
+use super::{vtt, SubtitleFile};
+
+// A time range of a file moved by its own offset, such as the part between two removed ad breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    pub range: TimeRange,
+    pub offset: i32,
+}
+
+// A time in a TOML segment file: a duration like "1:02.5" or "-30s", or a number of miliseconds.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Time {
+    Text(String),
+    Miliseconds(i64),
+}
+
+impl Time {
+    // Create a function to read a time of a TOML segment file in miliseconds.
+    fn to_ms(&self) -> Result<i32, String> {
+        match self {
+            Time::Text(text) => parse_duration(text),
+            Time::Miliseconds(value) => {
+                i32::try_from(*value).map_err(|_| format!("Invalid duration '{}'", value))
+            }
+        }
+    }
+}
+
+// A [[segment]] table of a TOML segment file.
+#[derive(Debug, Deserialize)]
+struct SegmentTable {
+    start: Option<Time>,
+    end: Option<Time>,
+    offset: Time,
+}
+
+// A TOML segment file.
+#[derive(Debug, Deserialize)]
+struct SegmentFile {
+    segment: Vec<SegmentTable>,
+}
+
+// Create a function to read a TOML segment file, with a [[segment]] table per segment:
+//     [[segment]]
+//     start = "00:12:30"
+//     end = "00:27:45"
+//     offset = "-30s"
+// start and end can be left out for segments that run from the start or to the end of the file.
+fn parse_toml(text: &str) -> Result<Vec<Segment>, String> {
+    let file: SegmentFile = toml::from_str(text).map_err(|error| error.to_string())?;
+    file.segment
+        .iter()
+        .map(|segment| {
+            Ok(Segment {
+                range: TimeRange::new(
+                    segment.start.as_ref().map(Time::to_ms).transpose()?,
+                    segment.end.as_ref().map(Time::to_ms).transpose()?,
+                )?,
+                offset: segment.offset.to_ms()?,
+            })
+        })
+        .collect()
+}
+
+// Create a function to read a CSV segment file, with a start,end,offset row per segment:
+//     start,end,offset
+//     00:12:30,00:27:45,-30s
+//     00:27:45,,-62.5s
+// An empty start or end runs from the start or to the end of the file. The header row, blank lines
+// and lines starting with # are skipped.
+fn parse_csv(text: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.to_lowercase().starts_with("start") {
+            continue;
+        }
+        let error = |error: String| format!("Line {}: {}", number + 1, error);
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        if fields.len() != 3 {
+            return Err(error("expected start,end,offset".to_string()));
+        }
+        let time = |field: &str| {
+            if field.is_empty() {
+                Ok(None)
+            } else {
+                parse_duration(field).map(Some)
+            }
+        };
+        segments.push(Segment {
+            range: TimeRange::new(
+                time(fields[0]).map_err(error)?,
+                time(fields[1]).map_err(error)?,
+            )
+            .map_err(error)?,
+            offset: parse_duration(fields[2]).map_err(error)?,
+        });
+    }
+    Ok(segments)
+}
+
+// Create a function to read a segment file, in TOML or CSV as told by its name, and check it with
+// check_segments. The segments are returned in time order.
+pub fn parse_segments(name: &str, text: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = if name.to_lowercase().ends_with(".toml") {
+        parse_toml(text)
+    } else {
+        parse_csv(text)
+    }
+    .map_err(|error| format!("Unable to read {}: {}", name, error))?;
+    check_segments(&mut segments)?;
+    Ok(segments)
+}
+
+// Create a function to sort segments by time, and check that they don't overlap, neither before
+// nor after they are moved by their offsets.
+pub fn check_segments(segments: &mut [Segment]) -> Result<(), String> {
+    if segments.is_empty() {
+        return Err("No segment given".to_string());
+    }
+    segments.sort_by_key(|segment| segment.range.after.unwrap_or(i32::MIN));
+    let describe = |segment: &Segment| {
+        format!(
+            "{} to {}",
+            segment
+                .range
+                .after
+                .map_or("start".to_string(), vtt::convert_to_time),
+            segment
+                .range
+                .before
+                .map_or("end".to_string(), vtt::convert_to_time)
+        )
+    };
+    for pair in segments.windows(2) {
+        if pair[1].range.after.unwrap_or(i32::MIN) < pair[0].range.before.unwrap_or(i32::MAX) {
+            return Err(format!(
+                "The segments {} and {} overlap",
+                describe(&pair[0]),
+                describe(&pair[1])
+            ));
+        }
+    }
+    // Segments can be moved past each other (reordered scenes), so they are compared in their new order.
+    let moved = |segment: &Segment| {
+        let offset = segment.offset as i64;
+        (
+            segment
+                .range
+                .after
+                .map_or(i64::MIN, |after| after as i64 + offset),
+            segment
+                .range
+                .before
+                .map_or(i64::MAX, |before| before as i64 + offset),
+        )
+    };
+    let mut order: Vec<&Segment> = segments.iter().collect();
+    order.sort_by_key(|segment| moved(segment).0);
+    for pair in order.windows(2) {
+        if moved(pair[1]).0 < moved(pair[0]).1 {
+            return Err(format!(
+                "The segments {} and {} would overlap once moved by their offsets",
+                describe(pair[0]),
+                describe(pair[1])
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl SubtitleFile {
+    // Create a function to move the cues of each segment by its offset, in one pass. Cues outside every
+    // segment are left untouched. Returns the number of cues cut or removed for starting before zero.
+    pub fn apply_segments(&mut self, segments: &[Segment]) -> usize {
+        for cue in self.cues.iter_mut() {
+            if let Some(segment) = segments.iter().find(|segment| segment.range.contains(cue)) {
+                cue.start_ms = cue.start_ms.saturating_add(segment.offset);
+                cue.end_ms = cue.end_ms.saturating_add(segment.offset);
+            }
+        }
+        self.cues.sort_by_key(|cue| cue.start_ms);
+        for (i, cue) in self.cues.iter_mut().enumerate() {
+            cue.index = i + 1;
+        }
+        self.clamp_to_zero()
+    }
+
+    // Create a function to keep only the cues of the given segments, moving each by the offset of its
+    // segment, as when commercials are cut out of a recording. Cues that run past the end of their
+    // segment are cut at it. Returns the number of cues removed.
+    pub fn keep_segments(&mut self, segments: &[Segment]) -> usize {
+        let before = self.cues.len();
+        self.cues.retain_mut(|cue| {
+            let Some(segment) = segments.iter().find(|segment| segment.range.contains(cue)) else {
+                return false;
+            };
+            if let Some(end) = segment.range.before {
+                cue.end_ms = cue.end_ms.min(end);
+            }
+            cue.start_ms = cue.start_ms.saturating_add(segment.offset);
+            cue.end_ms = cue.end_ms.saturating_add(segment.offset);
+            true
+        });
+        self.cues.sort_by_key(|cue| cue.start_ms);
+        for (i, cue) in self.cues.iter_mut().enumerate() {
+            cue.index = i + 1;
+        }
+        self.clamp_to_zero();
+        before - self.cues.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::{Cue, Format};
+
+    const TOML: &str = "[[segment]]
+end = \"00:12:30\"
+offset = 0
+
+[[segment]]
+start = \"00:28:17.5\"
+offset = \"-62.5s\"
+
+[[segment]]
+start = \"00:13:00\"
+end = \"00:27:45\"
+offset = -30000
+";
+
+    const CSV: &str = "start,end,offset
+# Ad breaks at 00:12:30 and 00:27:45, 30 and 32.5 seconds long.
+,00:12:30,0
+00:13:00,00:27:45,-30s
+
+00:28:17.5,,-62.5s
+";
+
+    fn file(starts: &[i32]) -> SubtitleFile {
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: starts
+                .iter()
+                .enumerate()
+                .map(|(i, start_ms)| Cue {
+                    index: i + 1,
+                    start_ms: *start_ms,
+                    end_ms: start_ms + 1000,
+                    lines: vec![format!("Cue {}", i + 1)],
+                    ..Default::default()
+                })
+                .collect(),
+            trailer: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn toml_and_csv_files_are_the_same() {
+        let segments = parse_segments("cuts.toml", TOML).unwrap();
+        assert_eq!(segments, parse_segments("cuts.csv", CSV).unwrap());
+        assert_eq!(
+            segments[1].range,
+            TimeRange::new(Some(780000), Some(1665000)).unwrap()
+        );
+        assert_eq!(segments[1].offset, -30000);
+        assert_eq!(segments[2].offset, -62500);
+    }
+
+    #[test]
+    fn each_segment_moves_by_its_own_offset() {
+        let segments = parse_segments("cuts.csv", CSV).unwrap();
+        let mut subtitles = file(&[1000, 800000, 1700000]);
+        assert_eq!(subtitles.apply_segments(&segments), 0);
+        let starts: Vec<i32> = subtitles.cues.iter().map(|cue| cue.start_ms).collect();
+        assert_eq!(starts, [1000, 770000, 1637500]);
+    }
+
+    #[test]
+    fn cues_outside_kept_segments_are_removed() {
+        let segments = vec![
+            Segment {
+                range: TimeRange::new(None, Some(10000)).unwrap(),
+                offset: 0,
+            },
+            Segment {
+                range: TimeRange::new(Some(20000), None).unwrap(),
+                offset: -10000,
+            },
+        ];
+        let mut subtitles = file(&[1000, 9500, 15000, 25000]);
+        assert_eq!(subtitles.keep_segments(&segments), 1);
+        let times: Vec<(usize, i32, i32)> = subtitles
+            .cues
+            .iter()
+            .map(|cue| (cue.index, cue.start_ms, cue.end_ms))
+            .collect();
+        assert_eq!(
+            times,
+            [(1, 1000, 2000), (2, 9500, 10000), (3, 15000, 16000)]
+        );
+    }
+
+    #[test]
+    fn overlapping_and_malformed_segments_are_errors() {
+        assert!(parse_segments("cuts.csv", "0:00,0:20,0\n0:10,0:30,5s\n").is_err());
+        assert!(parse_segments("cuts.csv", "0:00,0:10,0\n0:10,0:30,-15s\n").is_err());
+        assert!(parse_segments("cuts.csv", "0:00,0:10\n").is_err());
+        assert!(parse_segments("cuts.csv", "0:00,0:10,soon\n").is_err());
+        assert!(parse_segments("cuts.csv", "start,end,offset\n").is_err());
+        assert!(parse_segments("cuts.toml", "[[segment]]\nstart = \"0:10\"\n").is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use encoding_rs::{Encoding, UTF_8};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Most spelling suggestions given for a word.
+const SUGGESTIONS: usize = 3;
+
+// How the flags of words and affixes are written in a dictionary: a character each, two characters
+// each (FLAG long), or comma-separated numbers (FLAG num).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagType {
+    Char,
+    Long,
+    Num,
+}
+
+impl FlagType {
+    // Create a function to split a list of flags into its flags.
+    fn split(&self, flags: &str) -> Vec<String> {
+        match self {
+            FlagType::Char => flags.chars().map(String::from).collect(),
+            FlagType::Long => {
+                let characters: Vec<char> = flags.chars().collect();
+                characters
+                    .chunks(2)
+                    .map(|pair| pair.iter().collect())
+                    .collect()
+            }
+            FlagType::Num => flags
+                .split(',')
+                .map(|flag| flag.trim().to_string())
+                .collect(),
+        }
+    }
+}
+
+// A prefix or suffix rule: words with its flag also exist with the strip characters replaced by the
+// added ones, when the rest of the word matches the condition.
+#[derive(Debug, Clone)]
+struct Affix {
+    flag: String,
+    // Whether it combines with affixes of the other kind.
+    cross_product: bool,
+    strip: String,
+    add: String,
+    condition: Option<Regex>,
+}
+
+// A Hunspell dictionary: its words with their flags, and the prefix and suffix rules of its .aff file.
+// Compounds, and the rarer options of the format, are not supported.
+#[derive(Debug, Clone, Default)]
+pub struct Dictionary {
+    words: HashMap<String, Vec<Vec<String>>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    // Characters to try in suggestions, the most common first.
+    try_characters: Vec<char>,
+    // Flags of words that are wrong, and of words that only exist with an affix.
+    forbidden: Option<String>,
+    need_affix: Vec<String>,
+    // Replacements made before checking a word, as typographic apostrophes.
+    conversions: Vec<(String, String)>,
+}
+
+impl Dictionary {
+    // Create a function to read a Hunspell dictionary from its .dic file, the .aff file being next to it.
+    // Both are decoded from the encoding the SET line of the .aff file names.
+    pub fn load(dic_path: &Path) -> Result<Dictionary, String> {
+        let aff_path = dic_path.with_extension("aff");
+        let read = |path: &Path| {
+            fs::read(path).map_err(|error| format!("Unable to read {}: {}", path.display(), error))
+        };
+        let (aff, dic) = (read(&aff_path)?, read(dic_path)?);
+        let encoding = String::from_utf8_lossy(&aff)
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("SET "))
+            .and_then(|label| Encoding::for_label(label.trim().as_bytes()))
+            .unwrap_or(UTF_8);
+        let (aff, _, _) = encoding.decode(&aff);
+        let (dic, _, _) = encoding.decode(&dic);
+        let mut dictionary = Dictionary::parse_affixes(&aff)
+            .map_err(|error| format!("Invalid {}: {}", aff_path.display(), error))?;
+        let flag_type = flag_type(&aff);
+        // The first line is the number of words.
+        for line in dic.lines().skip(1) {
+            let entry = line.split_whitespace().next().unwrap_or_default();
+            let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+            if !word.is_empty() {
+                dictionary
+                    .words
+                    .entry(word.to_string())
+                    .or_default()
+                    .push(flag_type.split(flags));
+            }
+        }
+        Ok(dictionary)
+    }
+
+    // Create a function to read the affix rules and options of an .aff file.
+    fn parse_affixes(aff: &str) -> Result<Dictionary, String> {
+        let mut dictionary = Dictionary::default();
+        let mut cross_products: HashMap<(String, String), bool> = HashMap::new();
+        for (number, line) in aff.lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let invalid = || format!("line {}: '{}'", number + 1, line);
+            match fields.as_slice() {
+                ["TRY", characters, ..] => dictionary.try_characters = characters.chars().collect(),
+                ["FORBIDDENWORD", flag, ..] => dictionary.forbidden = Some(flag.to_string()),
+                ["NEEDAFFIX" | "ONLYINCOMPOUND", flag, ..] => {
+                    dictionary.need_affix.push(flag.to_string())
+                }
+                ["ICONV", from, to, ..] => dictionary
+                    .conversions
+                    .push((from.to_string(), to.to_string())),
+                // The header of a rule group: its flag, whether it combines, and its number of rules.
+                [kind @ ("PFX" | "SFX"), flag, cross_product, count]
+                    if count.parse::<usize>().is_ok() =>
+                {
+                    cross_products
+                        .insert((kind.to_string(), flag.to_string()), *cross_product == "Y");
+                }
+                [kind @ ("PFX" | "SFX"), flag, strip, add, rest @ ..] => {
+                    let suffix = *kind == "SFX";
+                    let condition = match rest.first() {
+                        None | Some(&".") => None,
+                        Some(condition) if suffix => Some(format!("{}$", condition)),
+                        Some(condition) => Some(format!("^{}", condition)),
+                    };
+                    let condition = condition
+                        .map(|condition| Regex::new(&condition).map_err(|_| invalid()))
+                        .transpose()?;
+                    // Flags of the affix itself, for twofold affixes, are not supported.
+                    let add = add.split('/').next().unwrap_or_default();
+                    let affix = Affix {
+                        flag: flag.to_string(),
+                        cross_product: cross_products
+                            .get(&(kind.to_string(), flag.to_string()))
+                            .copied()
+                            .unwrap_or(false),
+                        strip: if *strip == "0" { "" } else { strip }.to_string(),
+                        add: if add == "0" { "" } else { add }.to_string(),
+                        condition,
+                    };
+                    if suffix {
+                        dictionary.suffixes.push(affix);
+                    } else {
+                        dictionary.prefixes.push(affix);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(dictionary)
+    }
+
+    // Create a function to add words that are always right, as the names of a user dictionary.
+    pub fn add_words<'a>(&mut self, words: impl IntoIterator<Item = &'a str>) {
+        for word in words {
+            self.words
+                .entry(word.to_string())
+                .or_default()
+                .push(Vec::new());
+        }
+    }
+
+    // Create a function to tell whether a word is spelled right. A capitalized or uppercase word is also
+    // right when its lowercase form is, as at the start of a sentence, and an uppercase one when its
+    // capitalized form is, as a name in a shouted line.
+    pub fn check(&self, word: &str) -> bool {
+        let mut word = word.replace('’', "'");
+        for (from, to) in &self.conversions {
+            word = word.replace(from.as_str(), to);
+        }
+        if self.lookup(&word) {
+            return true;
+        }
+        let mut characters = word.chars();
+        let first = characters.next().unwrap_or_default();
+        let rest: String = characters.collect();
+        if !first.is_uppercase() {
+            return false;
+        }
+        let lowercase = word.to_lowercase();
+        let capitalized: String = first
+            .to_uppercase()
+            .chain(rest.to_lowercase().chars())
+            .collect();
+        self.lookup(&lowercase) || (word != capitalized && self.lookup(&capitalized))
+    }
+
+    // Create a function to suggest right words for a wrong one: those one letter away, by removing,
+    // swapping, replacing or adding a letter, and the pairs of words it may be missing a space between.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let characters: Vec<char> = word.chars().collect();
+        let alphabet: Vec<char> = if self.try_characters.is_empty() {
+            "etaoinshrdlcumwfgypbvkjxqz'".chars().collect()
+        } else {
+            self.try_characters.clone()
+        };
+        let join = |characters: &[char]| characters.iter().collect::<String>();
+        // The most common typos first: swapped letters, a missing letter or space, an extra letter, and
+        // a wrong letter.
+        let mut candidates = Vec::new();
+        for i in 1..characters.len() {
+            let mut swapped = characters.clone();
+            swapped.swap(i - 1, i);
+            candidates.push(join(&swapped));
+        }
+        for i in 0..=characters.len() {
+            for letter in &alphabet {
+                candidates.push(join(
+                    &[&characters[..i], &[*letter], &characters[i..]].concat(),
+                ));
+            }
+        }
+        for i in 1..characters.len() {
+            let (first, second) = (join(&characters[..i]), join(&characters[i..]));
+            if self.check(&first) && self.check(&second) {
+                candidates.push(format!("{} {}", first, second));
+            }
+        }
+        for i in 0..characters.len() {
+            candidates.push(join(&[&characters[..i], &characters[i + 1..]].concat()));
+        }
+        for i in 0..characters.len() {
+            for letter in &alphabet {
+                let mut replaced = characters.clone();
+                replaced[i] = *letter;
+                candidates.push(join(&replaced));
+            }
+        }
+        // Candidates differing only in case, like what and What, are suggested once, in the case of the
+        // word.
+        let mut suggestions: Vec<String> = Vec::new();
+        for candidate in candidates {
+            if suggestions.len() == SUGGESTIONS {
+                break;
+            }
+            let candidate = match_case(&candidate, word);
+            if candidate != word
+                && !suggestions
+                    .iter()
+                    .any(|suggestion| suggestion.to_lowercase() == candidate.to_lowercase())
+                && (candidate.contains(' ') || self.check(&candidate))
+            {
+                suggestions.push(candidate);
+            }
+        }
+        suggestions
+    }
+
+    // Create a function to find a word as written, on its own or made of a word of the dictionary and
+    // the affixes its flags allow.
+    fn lookup(&self, word: &str) -> bool {
+        if self.has_word(word, &[], true) {
+            return true;
+        }
+        for suffix in &self.suffixes {
+            if let Some(root) = suffix.remove_suffix(word) {
+                if self.has_word(&root, &[&suffix.flag], false) {
+                    return true;
+                }
+            }
+        }
+        for prefix in &self.prefixes {
+            let Some(root) = prefix.remove_prefix(word) else {
+                continue;
+            };
+            if self.has_word(&root, &[&prefix.flag], false) {
+                return true;
+            }
+            if !prefix.cross_product {
+                continue;
+            }
+            for suffix in self.suffixes.iter().filter(|suffix| suffix.cross_product) {
+                if let Some(root) = suffix.remove_suffix(&root) {
+                    if self.has_word(&root, &[&prefix.flag, &suffix.flag], false) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // Create a function to tell whether a word of the dictionary has the given flags, and is not
+    // forbidden, or only used with affixes when it stands alone.
+    fn has_word(&self, word: &str, flags: &[&str], alone: bool) -> bool {
+        self.words.get(word).is_some_and(|homonyms| {
+            homonyms.iter().any(|homonym| {
+                let has = |flag: &str| homonym.iter().any(|own| own == flag);
+                flags.iter().all(|flag| has(flag))
+                    && !self.forbidden.as_deref().is_some_and(has)
+                    && !(alone && self.need_affix.iter().any(|flag| has(flag)))
+            })
+        })
+    }
+}
+
+impl Affix {
+    // Create a function to get the word a suffixed word comes from, when this suffix can make it.
+    fn remove_suffix(&self, word: &str) -> Option<String> {
+        let stem = word.strip_suffix(self.add.as_str())?;
+        let root = format!("{}{}", stem, self.strip);
+        let fits = self
+            .condition
+            .as_ref()
+            .is_none_or(|condition| condition.is_match(&root));
+        Some(root).filter(|_| !stem.is_empty() && fits)
+    }
+
+    // Create a function to get the word a prefixed word comes from, when this prefix can make it.
+    fn remove_prefix(&self, word: &str) -> Option<String> {
+        let stem = word.strip_prefix(self.add.as_str())?;
+        let root = format!("{}{}", self.strip, stem);
+        let fits = self
+            .condition
+            .as_ref()
+            .is_none_or(|condition| condition.is_match(&root));
+        Some(root).filter(|_| !stem.is_empty() && fits)
+    }
+}
+
+// Create a function to write a suggestion in the case of the misspelled word: uppercase for an uppercase
+// word, capitalized for a capitalized one. Other suggestions keep their own case, as names do.
+fn match_case(suggestion: &str, word: &str) -> String {
+    if !word.chars().next().is_some_and(char::is_uppercase) {
+        return suggestion.to_string();
+    }
+    if word.chars().count() > 1 && word.chars().all(|c| !c.is_lowercase()) {
+        return suggestion.to_uppercase();
+    }
+    let mut suggestion_characters = suggestion.chars();
+    match suggestion_characters.next() {
+        Some(first) => first.to_uppercase().chain(suggestion_characters).collect(),
+        None => String::new(),
+    }
+}
+
+// Create a function to read how the flags of a dictionary are written, from the FLAG line of its .aff file.
+fn flag_type(aff: &str) -> FlagType {
+    let flag = aff
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("FLAG "))
+        .map(|flag| flag.trim().to_lowercase());
+    match flag.as_deref() {
+        Some("long") => FlagType::Long,
+        Some("num") => FlagType::Num,
+        _ => FlagType::Char,
+    }
+}
+
+// Create a function to find the .dic file of a language, as en_US or de-AT, in the directories Hunspell
+// looks in: those of the DICPATH environment variable, then the user and system ones. A language without
+// a region (en) matches the first of its regions found.
+pub fn find_dictionary(language: &str) -> Option<PathBuf> {
+    // Dictionaries are named with a lowercase language and an uppercase region, as en_US.
+    let language = match language.replace('-', "_").split_once('_') {
+        Some((language, region)) => {
+            format!("{}_{}", language.to_lowercase(), region.to_uppercase())
+        }
+        None => language.to_lowercase(),
+    };
+    let mut directories: Vec<PathBuf> = std::env::var_os("DICPATH")
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        directories.push(home.join(".local/share/hunspell"));
+        directories.push(home.join("Library/Spelling"));
+    }
+    for directory in [
+        "/usr/share/hunspell",
+        "/usr/local/share/hunspell",
+        "/usr/share/myspell",
+        "/usr/share/myspell/dicts",
+        "/Library/Spelling",
+    ] {
+        directories.push(PathBuf::from(directory));
+    }
+    let exact = directories
+        .iter()
+        .map(|directory| directory.join(format!("{}.dic", language)))
+        .find(|path| path.is_file());
+    exact.or_else(|| {
+        directories.iter().find_map(|directory| {
+            let mut regions: Vec<PathBuf> = fs::read_dir(directory)
+                .ok()?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension().is_some_and(|extension| extension == "dic")
+                        && path.file_stem().is_some_and(|stem| {
+                            stem.to_string_lossy()
+                                .starts_with(&format!("{}_", language))
+                        })
+                })
+                .collect();
+            regions.sort();
+            regions.into_iter().next()
+        })
+    })
+}
+
+// Create a function to split text into the words to check, leaving out numbers.
+pub fn words(text: &str) -> Vec<&str> {
+    text.unicode_words()
+        .filter(|word| !word.chars().any(|character| character.is_numeric()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwz
+FORBIDDENWORD !
+NEEDAFFIX _
+ICONV 1
+ICONV ﬁ fi
+PFX U Y 1
+PFX U 0 un .
+SFX S Y 3
+SFX S y ies [^aeiou]y
+SFX S 0 s [aeiou]y
+SFX S 0 s [^y]
+SFX D N 1
+SFX D 0 ed [^e]
+";
+
+    const DIC: &str = "9
+do/U
+try/S
+toy/S
+lock/UDS
+walk/D
+colour/!
+behav/_D
+fix/D
+Rivendell
+";
+
+    // Create a function to load a dictionary written to a temporary path, its .aff file encoded as it says.
+    fn load(aff: &[u8], dic: &[u8]) -> Dictionary {
+        let path = std::env::temp_dir().join(format!("subsync-{}.dic", uuid::Uuid::new_v4()));
+        fs::write(&path, dic).unwrap();
+        fs::write(path.with_extension("aff"), aff).unwrap();
+        let dictionary = Dictionary::load(&path);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(path.with_extension("aff")).unwrap();
+        dictionary.unwrap()
+    }
+
+    #[test]
+    fn affixes_are_stripped_when_their_conditions_match() {
+        let dictionary = load(AFF.as_bytes(), DIC.as_bytes());
+        for (word, right) in [
+            ("tries", true),
+            ("trys", false),
+            ("toys", true),
+            ("toies", false),
+            ("locks", true),
+            ("unlock", true),
+            ("unlocks", true),
+            ("locked", true),
+            // Past tenses don't combine with prefixes, and walk takes no plural.
+            ("unlocked", false),
+            ("walked", true),
+            ("walks", false),
+            ("undo", true),
+            ("undos", false),
+        ] {
+            assert_eq!(dictionary.check(word), right, "{}", word);
+        }
+    }
+
+    #[test]
+    fn forbidden_and_affix_only_words_are_wrong() {
+        let dictionary = load(AFF.as_bytes(), DIC.as_bytes());
+        assert!(!dictionary.check("colour"));
+        assert!(!dictionary.check("behav"));
+        assert!(dictionary.check("behaved"));
+        // Conversions apply before checking, as typographic ligatures.
+        assert!(dictionary.check("ﬁxed"));
+    }
+
+    #[test]
+    fn case_follows_the_start_of_sentences_and_shouted_lines() {
+        let mut dictionary = load(AFF.as_bytes(), DIC.as_bytes());
+        assert!(dictionary.check("Tries"));
+        assert!(dictionary.check("TOYS"));
+        assert!(!dictionary.check("tOYS"));
+        assert!(dictionary.check("RIVENDELL"));
+        assert!(!dictionary.check("rivendell"));
+        dictionary.add_words(["Gandalf"]);
+        assert!(dictionary.check("Gandalf"));
+        assert!(!dictionary.check("gandalf"));
+    }
+
+    #[test]
+    fn dictionaries_are_decoded_and_split_as_their_aff_file_says() {
+        let aff = b"SET ISO8859-1\nFLAG long\nSFX Ss Y 1\nSFX Ss 0 s .\n";
+        let dictionary = load(aff, b"1\ncaf\xe9/SsAb\n");
+        assert!(dictionary.check("caf\u{e9}s"));
+        let aff = b"FLAG num\nSFX 101 Y 1\nSFX 101 0 s .\n";
+        let dictionary = load(aff, b"1\ncat/7,101\n");
+        assert!(dictionary.check("cats"));
+        assert!(!dictionary.check("cat7"));
+    }
+
+    #[test]
+    fn suggestions_are_one_letter_or_space_away() {
+        let dictionary = load(AFF.as_bytes(), DIC.as_bytes());
+        assert_eq!(dictionary.suggest("tpy"), ["try", "toy"]);
+        assert_eq!(dictionary.suggest("Lcok"), ["Lock"]);
+        assert_eq!(dictionary.suggest("TOYSS"), ["TOYS"]);
+        assert_eq!(dictionary.suggest("undolock"), ["undo lock"]);
+    }
+
+    #[test]
+    fn numbers_are_not_words() {
+        assert_eq!(
+            words("It's 3 o'clock, 2nd call."),
+            ["It's", "o'clock", "call"]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use regex::Regex;
+
+use super::{
+    decode_text, split_blocks, tags, Capabilities, Cue, Format, ParseOptions, SubtitleFile,
+    SubtitleFormat,
+};
+
+// Create a function to convert a hh:mm:ss,mmm string to miliseconds as an integer.
+pub fn convert_to_miliseconds(time: &str) -> Option<i32> {
+    let re = Regex::new(r"^(\d{2,}):(\d{2}):(\d{2}),(\d{3})$").unwrap();
+    let caps = re.captures(time.trim())?;
+    let hours = caps.get(1)?.as_str().parse::<i32>().ok()?;
+    let minutes = caps.get(2)?.as_str().parse::<i32>().ok()?;
+    let seconds = caps.get(3)?.as_str().parse::<i32>().ok()?;
+    let miliseconds = caps.get(4)?.as_str().parse::<i32>().ok()?;
+    Some((hours * 3600000) + (minutes * 60000) + (seconds * 1000) + miliseconds)
+}
+
+// Create a function to convert miliseconds to a hh:mm:ss,mmm string.
+pub fn convert_to_time(miliseconds: i32) -> String {
+    let miliseconds = miliseconds.max(0);
+    let hours = miliseconds / 3600000;
+    let minutes = (miliseconds - (hours * 3600000)) / 60000;
+    let seconds = (miliseconds - (hours * 3600000) - (minutes * 60000)) / 1000;
+    let miliseconds = miliseconds - (hours * 3600000) - (minutes * 60000) - (seconds * 1000);
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        hours, minutes, seconds, miliseconds
+    )
+}
+
+// Create a function to parse the contents of an .srt file.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let mut cues = Vec::new();
+    for block in split_blocks(contents) {
+        if block.len() < 2 {
+            return Err(format!("Incomplete cue: '{}'", block.join(" ")));
+        }
+        let index = block[0]
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid cue index '{}'", block[0]))?;
+        let (start, end) = block[1]
+            .split_once("-->")
+            .ok_or_else(|| format!("Cue {}: invalid timing line '{}'", index, block[1]))?;
+        // Coordinates (X1:.. X2:.. Y1:.. Y2:..) may follow the end time, they are kept as cue settings.
+        let mut end = end.trim().splitn(2, char::is_whitespace);
+        let (end, settings) = (end.next().unwrap_or(""), end.next());
+        let settings = settings
+            .map(|settings| settings.trim().to_string())
+            .filter(|settings| !settings.is_empty());
+        let start_ms = convert_to_miliseconds(start)
+            .ok_or_else(|| format!("Cue {}: invalid timestamp '{}'", index, start.trim()))?;
+        let end_ms = convert_to_miliseconds(end)
+            .ok_or_else(|| format!("Cue {}: invalid timestamp '{}'", index, end))?;
+        cues.push(Cue {
+            index,
+            start_ms,
+            end_ms,
+            settings,
+            lines: block[2..].iter().map(|line| line.to_string()).collect(),
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::Srt,
+        framerate: None,
+        header: Vec::new(),
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to write the cues out as .srt. Coordinates are only written back for SRT input.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let mut output = String::new();
+    for cue in &file.cues {
+        output.push_str(&format!("{}\n", cue.index));
+        output.push_str(&format!(
+            "{} --> {}",
+            convert_to_time(cue.start_ms),
+            convert_to_time(cue.end_ms)
+        ));
+        if let (Some(settings), Format::Srt) = (&cue.settings, file.format) {
+            output.push(' ');
+            output.push_str(settings);
+        }
+        output.push('\n');
+        // Positions from WebVTT cue settings or ASS styles become an {\anN} override.
+        let alignment = match file.format {
+            Format::Vtt | Format::Ass => file.cue_alignment(cue),
+            _ => None,
+        };
+        for (i, line) in cue.lines.iter().enumerate() {
+            match alignment {
+                Some(alignment) if i == 0 && tags::alignment(line).is_none() => {
+                    output.push_str(&format!("{{\\an{}}}", alignment))
+                }
+                _ => {}
+            }
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// The SubRip format, as registered in the format registry.
+pub struct Srt;
+
+impl SubtitleFormat for Srt {
+    fn format(&self) -> Format {
+        Format::Srt
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["srt", "subrip"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["srt"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: true,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRT: &str = "1\n00:00:01,000 --> 00:00:02,500\nHello.\n\n2\n00:01:02,003 --> 01:00:00,000 X1:10 X2:20 Y1:30 Y2:40\n<i>Two</i>\nlines.\n\n";
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(SRT).unwrap();
+        assert_eq!(file.cues.len(), 2);
+        assert_eq!(file.cues[1].start_ms, 62003);
+        assert_eq!(file.cues[1].end_ms, 3600000);
+        assert_eq!(
+            file.cues[1].settings.as_deref(),
+            Some("X1:10 X2:20 Y1:30 Y2:40")
+        );
+        assert_eq!(serialize(&file), SRT);
+    }
+
+    #[test]
+    fn times_convert_both_ways() {
+        assert_eq!(convert_to_miliseconds("00:00:00,000"), Some(0));
+        assert_eq!(convert_to_miliseconds("100:00:00,001"), Some(360000001));
+        assert_eq!(convert_to_time(3723004), "01:02:03,004");
+        assert_eq!(convert_to_time(-5), "00:00:00,000");
+        assert_eq!(convert_to_miliseconds("00:00:01.000"), None);
+        assert_eq!(convert_to_miliseconds("0:00:01,000"), None);
+    }
+
+    #[test]
+    fn malformed_cues_are_errors() {
+        assert!(parse("one\n00:00:01,000 --> 00:00:02,000\nText\n").is_err());
+        assert!(parse("1\n00:00:01,000 00:00:02,000\nText\n").is_err());
+        assert!(parse("1\n00:00:01,000 --> 00:00:2,000\nText\n").is_err());
+        assert!(parse("1\n").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+const DEFAULT_HEADER: &str = "<?xml version=\"1.0\" encoding=\"utf-8\" ?><timedtext format=\"3\">
+<body>";
+
+const DEFAULT_TRAILER: &str = "</body>
+</timedtext>";
+
+// Create a function to read the attributes of an element into a map.
+fn attributes(element: &str) -> HashMap<String, String> {
+    Regex::new(r#"([\w:]+)\s*=\s*"([^"]*)""#)
+        .unwrap()
+        .captures_iter(element)
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+// Create a function to replace XML character references with the characters they stand for.
+fn unescape(text: &str) -> String {
+    let reference = Regex::new(r"&(#x[0-9a-fA-F]+|#\d+|lt|gt|quot|apos|amp);").unwrap();
+    reference
+        .replace_all(text, |caps: &regex::Captures| {
+            let name = &caps[1];
+            let code = if let Some(hex) = name.strip_prefix("#x") {
+                u32::from_str_radix(hex, 16).ok()
+            } else if let Some(decimal) = name.strip_prefix('#') {
+                decimal.parse::<u32>().ok()
+            } else {
+                None
+            };
+            match (code.and_then(char::from_u32), name) {
+                (Some(character), _) => character.to_string(),
+                (None, "lt") => "<".to_string(),
+                (None, "gt") => ">".to_string(),
+                (None, "quot") => "\"".to_string(),
+                (None, "apos") => "'".to_string(),
+                (None, "amp") => "&".to_string(),
+                _ => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+// Create a function to wrap text in the <i> and <b> tags of a pen.
+fn apply_pen(text: &str, pen: Option<&(bool, bool)>) -> String {
+    match pen {
+        Some((italic, bold)) => {
+            let mut text = text.to_string();
+            if *bold {
+                text = format!("<b>{}</b>", text);
+            }
+            if *italic {
+                text = format!("<i>{}</i>", text);
+            }
+            text
+        }
+        None => text.to_string(),
+    }
+}
+
+// Create a function to parse YouTube srv3 (format 3 timed text) captions, as downloaded by yt-dlp.
+// <p> elements hold the start (t) and duration (d) in miliseconds. Italic and bold pens become <i> and
+// <b> tags, and window positions other than bottom center become an {\anN} override on the first line.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let pen = Regex::new(r"<pen\s([^>]*?)/?>").unwrap();
+    let window_position = Regex::new(r"<wp\s([^>]*?)/?>").unwrap();
+    let paragraph = Regex::new(r"(?s)<p\s([^>]*?)(?:/>|>(.*?)</p>)").unwrap();
+    let segment = Regex::new(r"(?s)<s(\s[^>]*)?>(.*?)</s>").unwrap();
+    let markup = Regex::new(r"<[^>]*>").unwrap();
+    let attribute = Regex::new(r#"([\w:]+)\s*=\s*"[^"]*""#).unwrap();
+    let body = contents
+        .find("<body>")
+        .map(|start| start + "<body>".len())
+        .ok_or_else(|| "Missing <body> element".to_string())?;
+    let header = &contents[..body];
+    let pens: HashMap<String, (bool, bool)> = pen
+        .captures_iter(header)
+        .map(|caps| {
+            let pen = attributes(&caps[1]);
+            let flag = |name: &str| pen.get(name).is_some_and(|value| value == "1");
+            (
+                pen.get("id").cloned().unwrap_or_default(),
+                (flag("i"), flag("b")),
+            )
+        })
+        .collect();
+    // Window anchor points, from 0 (top left) to 8 (bottom right), in numeric keypad order.
+    let anchors: HashMap<String, u32> = window_position
+        .captures_iter(header)
+        .filter_map(|caps| {
+            let position = attributes(&caps[1]);
+            let anchor = position.get("ap")?.parse::<u32>().ok()?;
+            let keypad = [7, 8, 9, 4, 5, 6, 1, 2, 3].get(anchor as usize)?;
+            Some((position.get("id").cloned().unwrap_or_default(), *keypad))
+        })
+        .collect();
+    let end = contents
+        .rfind("</body>")
+        .ok_or_else(|| "Missing </body> element".to_string())?;
+    let mut cues = Vec::new();
+    for caps in paragraph.captures_iter(&contents[body..end]) {
+        let index = cues.len() + 1;
+        let paragraph = attributes(&caps[1]);
+        let time = |name: &str| {
+            let value = paragraph
+                .get(name)
+                .ok_or_else(|| format!("Cue {}: missing {} attribute", index, name))?;
+            value
+                .parse::<i32>()
+                .map_err(|_| format!("Cue {}: invalid time '{}'", index, value))
+        };
+        let start_ms = time("t")?;
+        let end_ms = start_ms + time("d")?;
+        let inner = caps.get(2).map_or("", |m| m.as_str());
+        let mut text = String::new();
+        let mut position = 0;
+        for segment in segment.captures_iter(inner) {
+            let whole = segment.get(0).unwrap();
+            text.push_str(&markup.replace_all(&inner[position..whole.start()], ""));
+            position = whole.end();
+            let pen = attributes(segment.get(1).map_or("", |m| m.as_str()))
+                .get("p")
+                .and_then(|id| pens.get(id));
+            text.push_str(&apply_pen(&markup.replace_all(&segment[2], ""), pen));
+        }
+        text.push_str(&markup.replace_all(&inner[position..], ""));
+        let text = apply_pen(
+            &unescape(&text),
+            paragraph.get("p").and_then(|id| pens.get(id)),
+        );
+        let mut lines: Vec<String> = text
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        // Auto-generated captions use empty paragraphs to scroll the previous line up.
+        if lines.is_empty() {
+            continue;
+        }
+        if let Some(anchor) = paragraph.get("wp").and_then(|id| anchors.get(id)) {
+            if *anchor != 2 {
+                lines[0] = format!("{{\\an{}}}{}", anchor, lines[0]);
+            }
+        }
+        let settings: Vec<String> = attribute
+            .captures_iter(&caps[1])
+            .filter(|attribute| &attribute[1] != "t" && &attribute[1] != "d")
+            .map(|attribute| attribute[0].to_string())
+            .collect();
+        cues.push(Cue {
+            index,
+            start_ms,
+            end_ms,
+            settings: Some(settings.join(" ")).filter(|settings| !settings.is_empty()),
+            lines,
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::Srv3,
+        framerate: None,
+        header: vec![header.to_string()],
+        cues,
+        trailer: vec![contents[end..].trim().to_string()],
+    })
+}
+
+// Create a function to write the cues out as srv3. Positions are only kept for srv3 input, through the
+// window positions and pens of its header; inline tags such as <i> and {\an8} are removed.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let is_srv3 = file.format == Format::Srv3;
+    let header = match file.header.first() {
+        Some(header) if is_srv3 => header.as_str(),
+        _ => DEFAULT_HEADER,
+    };
+    let trailer = match file.trailer.first() {
+        Some(trailer) if is_srv3 => trailer.as_str(),
+        _ => DEFAULT_TRAILER,
+    };
+    let tag = Regex::new(r"</?[a-zA-Z][^>]*>|\{[^}]*\}").unwrap();
+    let mut output = format!("{}\n", header);
+    for cue in &file.cues {
+        let settings = match &cue.settings {
+            Some(settings) if is_srv3 => format!(" {}", settings),
+            _ => String::new(),
+        };
+        let lines: Vec<String> = cue
+            .lines
+            .iter()
+            .map(|line| {
+                tag.replace_all(line, "")
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;")
+            })
+            .collect();
+        output.push_str(&format!(
+            "<p t=\"{}\" d=\"{}\"{}>{}</p>\n",
+            cue.start_ms.max(0),
+            (cue.end_ms - cue.start_ms).max(0),
+            settings,
+            lines.join("\n")
+        ));
+    }
+    output.push_str(trailer);
+    output.push('\n');
+    output
+}
+
+// The YouTube srv3 format, as registered in the format registry.
+pub struct Srv3;
+
+impl SubtitleFormat for Srv3 {
+    fn format(&self) -> Format {
+        Format::Srv3
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["srv3", "ytt"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["srv3", "ytt"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: true,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        sniff_text(contents).contains("<timedtext")
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRV3: &str = "<?xml version=\"1.0\" encoding=\"utf-8\" ?><timedtext format=\"3\">
+<head><pen id=\"1\" i=\"1\"/><wp id=\"1\" ap=\"1\"/></head><body>
+<p t=\"1000\" d=\"1500\">Hello &amp; bye</p>
+<p t=\"3600000\" d=\"2000\" wp=\"1\">Two
+lines</p>
+</body>
+</timedtext>
+";
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let file = parse(SRV3).unwrap();
+        assert_eq!((file.cues[0].start_ms, file.cues[0].end_ms), (1000, 2500));
+        assert_eq!(file.cues[0].lines, ["Hello & bye"]);
+        assert_eq!(file.cues[1].lines, ["{\\an8}Two", "lines"]);
+        assert_eq!(serialize(&file), SRV3);
+    }
+
+    #[test]
+    fn pens_become_tags_and_empty_paragraphs_are_skipped() {
+        let contents = SRV3.replace(
+            "<p t=\"1000\" d=\"1500\">Hello &amp; bye</p>",
+            "<p t=\"1000\" d=\"1500\"><s p=\"1\">Hello</s> &#39;bye&#39;</p>\n<p t=\"2000\" d=\"1\"/>",
+        );
+        let file = parse(&contents).unwrap();
+        assert_eq!(file.cues.len(), 2);
+        assert_eq!(file.cues[0].lines, ["<i>Hello</i> 'bye'"]);
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        assert!(parse("<timedtext format=\"3\"></timedtext>").is_err());
+        assert!(parse("<timedtext><body><p t=\"10\">A</p></body></timedtext>").is_err());
+        assert!(parse("<timedtext><body><p t=\"soon\" d=\"10\">A</p></body></timedtext>").is_err());
+    }
+}
@@ -0,0 +1,523 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat};
+
+const GSI_SIZE: usize = 1024;
+const TTI_SIZE: usize = 128;
+const TEXT_FIELD_SIZE: usize = 112;
+
+// Text field control codes.
+const ITALICS_ON: u8 = 0x80;
+const ITALICS_OFF: u8 = 0x81;
+const NEWLINE: u8 = 0x8A;
+const UNUSED_SPACE: u8 = 0x8F;
+
+// Non-spacing diacritical marks of the ISO 6937 Latin table, followed by the letters they combine with.
+// Each pair of characters is a base letter and the accented letter it becomes.
+const DIACRITICS: [(u8, &str); 13] = [
+    (0xC1, "AÀaàEÈeèIÌiìOÒoòUÙuù"),
+    (0xC2, "AÁaáCĆcćEÉeéIÍiíLĹlĺNŃnńOÓoóRŔrŕSŚsśUÚuúYÝyýZŹzź"),
+    (0xC3, "AÂaâCĈcĉEÊeêGĜgĝHĤhĥIÎiîJĴjĵOÔoôSŜsŝUÛuûWŴwŵYŶyŷ"),
+    (0xC4, "AÃaãIĨiĩNÑnñOÕoõUŨuũ"),
+    (0xC5, "AĀaāEĒeēIĪiīOŌoōUŪuū"),
+    (0xC6, "AĂaăEĔeĕGĞgğIĬiĭOŎoŏUŬuŭ"),
+    (0xC7, "CĊcċEĖeėGĠgġIİZŻzż"),
+    (0xC8, "AÄaäEËeëIÏiïOÖoöUÜuüYŸyÿ"),
+    (0xCA, "AÅaåUŮuů"),
+    (0xCB, "CÇcçGĢgģKĶkķLĻlļNŅnņRŖrŗSŞsşTŢtţ"),
+    (0xCD, "OŐoőUŰuű"),
+    (0xCE, "AĄaąEĘeęIĮiįUŲuų"),
+    (0xCF, "CČcčDĎdďEĚeěLĽlľNŇnňRŘrřSŠsšTŤtťZŽzž"),
+];
+
+// Spacing characters of the ISO 6937 Latin table outside of the ASCII range.
+const SPECIAL_CHARACTERS: [(u8, char); 32] = [
+    (0xA1, '¡'),
+    (0xA2, '¢'),
+    (0xA3, '£'),
+    (0xA5, '¥'),
+    (0xA7, '§'),
+    (0xA9, '‘'),
+    (0xAA, '“'),
+    (0xAB, '«'),
+    (0xB0, '°'),
+    (0xB1, '±'),
+    (0xB2, '²'),
+    (0xB3, '³'),
+    (0xB4, '×'),
+    (0xB5, 'µ'),
+    (0xB6, '¶'),
+    (0xB7, '·'),
+    (0xB8, '÷'),
+    (0xB9, '’'),
+    (0xBA, '”'),
+    (0xBB, '»'),
+    (0xBF, '¿'),
+    (0xD0, '―'),
+    (0xE1, 'Æ'),
+    (0xE2, 'Đ'),
+    (0xE8, 'Ł'),
+    (0xE9, 'Ø'),
+    (0xEA, 'Œ'),
+    (0xF1, 'æ'),
+    (0xF2, 'đ'),
+    (0xF8, 'ł'),
+    (0xF9, 'ø'),
+    (0xFB, 'ß'),
+];
+
+// Create a function to read a space padded text field of the GSI block.
+fn gsi_field(gsi: &[u8], start: usize, length: usize) -> String {
+    String::from_utf8_lossy(&gsi[start..start + length])
+        .trim()
+        .to_string()
+}
+
+// Create a function to read the framerate from the disk format code (STL25.01, STL30.01).
+fn disk_format_framerate(gsi: &[u8]) -> Option<f32> {
+    gsi_field(gsi, 3, 8)
+        .strip_prefix("STL")?
+        .split('.')
+        .next()?
+        .parse::<f32>()
+        .ok()
+}
+
+// Create a function to convert a HH:MM:SS:FF timecode to miliseconds at the given framerate.
+// Timecodes count whole frames per second (30 for 29.97), so they are turned into a frame count first.
+fn timecode_to_miliseconds(timecode: &[u8], framerate: f32) -> i32 {
+    let seconds = timecode[0] as i64 * 3600 + timecode[1] as i64 * 60 + timecode[2] as i64;
+    let frames = seconds * framerate.round() as i64 + timecode[3] as i64;
+    (frames as f64 * 1000.0 / framerate as f64).round() as i32
+}
+
+// Create a function to convert miliseconds to a HH:MM:SS:FF timecode at the given framerate.
+fn miliseconds_to_timecode(miliseconds: i32, framerate: f32) -> [u8; 4] {
+    let frames_per_second = framerate.round() as i64;
+    let frames = (miliseconds.max(0) as f64 * framerate as f64 / 1000.0).round() as i64;
+    let seconds = frames / frames_per_second;
+    [
+        (seconds / 3600) as u8,
+        (seconds / 60 % 60) as u8,
+        (seconds % 60) as u8,
+        (frames % frames_per_second) as u8,
+    ]
+}
+
+// Create a function to read the time code start of programme (TCP, HHMMSSFF) in miliseconds.
+// A field that isn't 8 digits counts as zero.
+fn programme_start(gsi: &[u8], framerate: f32) -> i32 {
+    let field = &gsi[256..264];
+    if !field.iter().all(u8::is_ascii_digit) {
+        return 0;
+    }
+    let timecode: Vec<u8> = field
+        .chunks(2)
+        .map(|digits| (digits[0] - b'0') * 10 + digits[1] - b'0')
+        .collect();
+    timecode_to_miliseconds(&timecode, framerate)
+}
+
+// Create a function to decode a TTI text field into subtitle lines.
+fn decode_text(text: &[u8]) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut diacritic = None;
+    for &byte in text {
+        let line = lines.last_mut().unwrap();
+        match byte {
+            UNUSED_SPACE => break,
+            NEWLINE => lines.push(String::new()),
+            ITALICS_ON => line.push_str("<i>"),
+            ITALICS_OFF => line.push_str("</i>"),
+            0xC1..=0xCF => diacritic = DIACRITICS.iter().find(|(code, _)| *code == byte),
+            0x20..=0x7E => {
+                let base = byte as char;
+                let letters: Vec<char> = diacritic
+                    .take()
+                    .map_or(Vec::new(), |(_, letters)| letters.chars().collect());
+                let accented = letters
+                    .chunks(2)
+                    .find(|pair| pair[0] == base)
+                    .map_or(base, |pair| pair[1]);
+                line.push(accented);
+            }
+            _ => {
+                if let Some((_, character)) =
+                    SPECIAL_CHARACTERS.iter().find(|(code, _)| *code == byte)
+                {
+                    line.push(*character);
+                }
+            }
+        }
+    }
+    lines
+        .iter()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+// Create a function to encode subtitle lines into a TTI text field.
+// Characters that the Latin table can't represent are replaced with a question mark.
+fn encode_text(lines: &[String]) -> Vec<u8> {
+    let mut text = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            text.push(NEWLINE);
+        }
+        let line = line.replace("<i>", "\u{1}").replace("</i>", "\u{2}");
+        for character in line.chars() {
+            match character {
+                '\u{1}' => text.push(ITALICS_ON),
+                '\u{2}' => text.push(ITALICS_OFF),
+                ' '..='~' => text.push(character as u8),
+                _ => {
+                    if let Some((code, _)) =
+                        SPECIAL_CHARACTERS.iter().find(|(_, c)| *c == character)
+                    {
+                        text.push(*code);
+                    } else if let Some((code, base)) =
+                        DIACRITICS.iter().find_map(|(code, letters)| {
+                            let letters: Vec<char> = letters.chars().collect();
+                            letters
+                                .chunks(2)
+                                .find(|pair| pair[1] == character)
+                                .map(|pair| (*code, pair[0]))
+                        })
+                    {
+                        text.push(code);
+                        text.push(base as u8);
+                    } else {
+                        text.push(b'?');
+                    }
+                }
+            }
+        }
+    }
+    text
+}
+
+// Create a function to cut an encoded text into the text fields of a subtitle's extension blocks, never
+// between a diacritical mark and the letter it goes on.
+fn text_chunks(text: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > TEXT_FIELD_SIZE {
+        let mut size = TEXT_FIELD_SIZE;
+        if (0xC1..=0xCF).contains(&rest[size - 1]) {
+            size -= 1;
+        }
+        let (chunk, after) = rest.split_at(size);
+        chunks.push(chunk);
+        rest = after;
+    }
+    chunks.push(rest);
+    chunks
+}
+
+// Create a function to parse the contents of a binary EBU STL file.
+// Timecodes are read relative to the programme start timecode (TCP) of the GSI block.
+pub fn parse(contents: &[u8], framerate: Option<f32>) -> Result<SubtitleFile, String> {
+    if contents.len() < GSI_SIZE {
+        return Err("File is too short for an EBU STL GSI block".to_string());
+    }
+    let gsi = &contents[..GSI_SIZE];
+    let code_table = gsi_field(gsi, 12, 2);
+    if code_table != "00" && !code_table.is_empty() {
+        return Err(format!(
+            "Unsupported character code table '{}', only the Latin table (00) is supported",
+            code_table
+        ));
+    }
+    let framerate = framerate
+        .or_else(|| disk_format_framerate(gsi))
+        .ok_or_else(|| format!("Unknown disk format code '{}'", gsi_field(gsi, 3, 8)))?;
+    let programme_start = programme_start(gsi, framerate);
+
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut text = Vec::new();
+    for block in contents[GSI_SIZE..].chunks_exact(TTI_SIZE) {
+        let subtitle_number = u16::from_le_bytes([block[1], block[2]]) as usize;
+        let extension_block = block[3];
+        let comment = block[15] == 1;
+        if comment || extension_block == 0xFE {
+            continue;
+        }
+        text.extend_from_slice(&block[16..16 + TEXT_FIELD_SIZE]);
+        if let Some(end) = text.iter().position(|byte| *byte == UNUSED_SPACE) {
+            text.truncate(end);
+        }
+        if extension_block != 0xFF {
+            continue;
+        }
+        cues.push(Cue {
+            index: subtitle_number,
+            start_ms: timecode_to_miliseconds(&block[5..9], framerate) - programme_start,
+            end_ms: timecode_to_miliseconds(&block[9..13], framerate) - programme_start,
+            settings: Some(format!("VP={} JC={}", block[13], block[14])),
+            lines: decode_text(&std::mem::take(&mut text)),
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::Stl,
+        framerate: Some(framerate),
+        header: vec![gsi.iter().map(|byte| *byte as char).collect()],
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to get today's date as YYMMDD, for the GSI creation and revision dates.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / 86400)
+        .unwrap_or(0) as i64;
+    // Convert days since 1970-01-01 to a civil date.
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:02}{:02}{:02}", year % 100, month, day)
+}
+
+// Create a function to write a space padded text field into the GSI block.
+fn set_gsi_field(gsi: &mut [u8], start: usize, length: usize, value: &str) {
+    let field = format!("{:<width$}", value, width = length);
+    gsi[start..start + length].copy_from_slice(&field.as_bytes()[..length]);
+}
+
+// Create a function to pick the disk format code of a framerate and the rate its timecodes count frames
+// at. EBU STL only has 25 and 30 fps disk formats: 29.97 and 59.94 fps subtitles count 29.97 fps frames
+// under STL30.01, and other framerates are written at 25 fps, the cue times staying the same.
+fn disk_format(framerate: f32) -> (&'static str, f32) {
+    match framerate.round() as i32 {
+        30 => ("STL30.01", framerate),
+        60 => ("STL30.01", framerate / 2.0),
+        _ => ("STL25.01", 25.0),
+    }
+}
+
+// Create a function to write the cues out as binary EBU STL, using the file's framerate.
+// The GSI block of an STL input is kept, with the framerate and block counts updated.
+pub fn serialize(file: &SubtitleFile) -> Vec<u8> {
+    let (disk_format, framerate) = disk_format(file.framerate.unwrap_or(25.0));
+    let mut gsi: Vec<u8> = match file.header.first() {
+        Some(header) if file.format == Format::Stl && header.chars().count() == GSI_SIZE => {
+            header.chars().map(|character| character as u8).collect()
+        }
+        _ => {
+            let mut gsi = vec![b' '; GSI_SIZE];
+            set_gsi_field(&mut gsi, 0, 3, "850");
+            set_gsi_field(&mut gsi, 11, 1, "1");
+            set_gsi_field(&mut gsi, 12, 2, "00");
+            set_gsi_field(&mut gsi, 14, 2, "00");
+            set_gsi_field(&mut gsi, 224, 6, &today());
+            set_gsi_field(&mut gsi, 230, 6, &today());
+            set_gsi_field(&mut gsi, 236, 2, "00");
+            set_gsi_field(&mut gsi, 251, 2, "40");
+            set_gsi_field(&mut gsi, 253, 2, "23");
+            set_gsi_field(&mut gsi, 255, 1, "1");
+            set_gsi_field(&mut gsi, 256, 8, "00000000");
+            set_gsi_field(&mut gsi, 272, 1, "1");
+            set_gsi_field(&mut gsi, 273, 1, "1");
+            gsi
+        }
+    };
+    let programme_start = programme_start(&gsi, framerate);
+
+    let mut blocks = Vec::new();
+    for (i, cue) in file.cues.iter().enumerate() {
+        let text = encode_text(&cue.lines);
+        let (vertical_position, justification) = match (&cue.settings, file.format) {
+            (Some(settings), Format::Stl) => {
+                let value = |name: &str| {
+                    settings
+                        .split_whitespace()
+                        .find_map(|setting| setting.strip_prefix(name))
+                        .and_then(|value| value.parse::<u8>().ok())
+                };
+                (value("VP=").unwrap_or(20), value("JC=").unwrap_or(2))
+            }
+            _ => (23 - cue.lines.len().min(22) as u8, 2),
+        };
+        let chunks = text_chunks(&text);
+        for (extension, chunk) in chunks.iter().enumerate() {
+            let mut block = vec![0u8; TTI_SIZE];
+            block[1..3].copy_from_slice(&((i + 1) as u16).to_le_bytes());
+            block[3] = if extension + 1 == chunks.len() {
+                0xFF
+            } else {
+                extension as u8
+            };
+            block[5..9].copy_from_slice(&miliseconds_to_timecode(
+                cue.start_ms + programme_start,
+                framerate,
+            ));
+            block[9..13].copy_from_slice(&miliseconds_to_timecode(
+                cue.end_ms + programme_start,
+                framerate,
+            ));
+            block[13] = vertical_position;
+            block[14] = justification;
+            block[16..16 + chunk.len()].copy_from_slice(chunk);
+            block[16 + chunk.len()..].fill(UNUSED_SPACE);
+            blocks.push(block);
+        }
+    }
+
+    set_gsi_field(&mut gsi, 3, 8, disk_format);
+    set_gsi_field(&mut gsi, 238, 5, &format!("{:05}", blocks.len()));
+    set_gsi_field(&mut gsi, 243, 5, &format!("{:05}", file.cues.len()));
+    set_gsi_field(&mut gsi, 248, 3, "001");
+    if let Some(first) = file.cues.first() {
+        let timecode = miliseconds_to_timecode(first.start_ms + programme_start, framerate);
+        let timecode: String = timecode.iter().map(|part| format!("{:02}", part)).collect();
+        set_gsi_field(&mut gsi, 264, 8, &timecode);
+    }
+    let mut output = gsi;
+    for block in blocks {
+        output.extend_from_slice(&block);
+    }
+    output
+}
+
+// The EBU STL format, as registered in the format registry.
+pub struct Stl;
+
+impl SubtitleFormat for Stl {
+    fn format(&self) -> Format {
+        Format::Stl
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["stl", "ebu"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["stl"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: true,
+            frame_based: true,
+            end_times: true,
+        }
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        contents.len() > 11 && &contents[3..6] == b"STL"
+    }
+
+    fn parse(&self, contents: &[u8], options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(contents, options.framerate)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cues(framerate: f32, cues: &[(i32, i32, &[&str])]) -> SubtitleFile {
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: Some(framerate),
+            header: Vec::new(),
+            cues: cues
+                .iter()
+                .enumerate()
+                .map(|(i, (start_ms, end_ms, lines))| Cue {
+                    index: i + 1,
+                    start_ms: *start_ms,
+                    end_ms: *end_ms,
+                    lines: lines.iter().map(|line| line.to_string()).collect(),
+                    ..Default::default()
+                })
+                .collect(),
+            trailer: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_text_and_frame_times() {
+        let long = "é".repeat(70);
+        let file = cues(
+            25.0,
+            &[
+                (1000, 2480, &["<i>Ça va ?</i>", "Größe, æ et ø"]),
+                (3000, 4000, &[long.as_str()]),
+            ],
+        );
+        let stl = serialize(&file);
+        assert_eq!(gsi_field(&stl, 3, 8), "STL25.01");
+        // The second cue needs two text fields, each accent taking two bytes.
+        assert_eq!(stl.len(), GSI_SIZE + 3 * TTI_SIZE);
+        let parsed = parse(&stl, None).unwrap();
+        assert_eq!(parsed.framerate, Some(25.0));
+        let times: Vec<(i32, i32)> = parsed
+            .cues
+            .iter()
+            .map(|cue| (cue.start_ms, cue.end_ms))
+            .collect();
+        assert_eq!(times, [(1000, 2480), (3000, 4000)]);
+        assert_eq!(parsed.cues[0].lines, file.cues[0].lines);
+        assert_eq!(parsed.cues[1].lines, [long]);
+        assert_eq!(serialize(&parsed), stl);
+    }
+
+    #[test]
+    fn only_25_and_30_fps_disk_formats_are_written() {
+        assert_eq!(disk_format(29.97), ("STL30.01", 29.97));
+        assert_eq!(disk_format(59.94), ("STL30.01", 29.97));
+        assert_eq!(disk_format(24.0), ("STL25.01", 25.0));
+        let stl = serialize(&cues(23.976, &[(1000, 2000, &["Film"])]));
+        assert_eq!(gsi_field(&stl, 3, 8), "STL25.01");
+        assert_eq!(parse(&stl, None).unwrap().cues[0].start_ms, 1000);
+    }
+
+    #[test]
+    fn times_are_read_from_the_programme_start() {
+        let mut stl = serialize(&cues(25.0, &[(1000, 2000, &["Text"])]));
+        stl[256..264].copy_from_slice(b"10000000");
+        let block = GSI_SIZE;
+        stl[block + 5..block + 9].copy_from_slice(&[10, 0, 1, 0]);
+        stl[block + 9..block + 13].copy_from_slice(&[10, 0, 2, 12]);
+        let cue = &parse(&stl, None).unwrap().cues[0];
+        assert_eq!((cue.start_ms, cue.end_ms), (1000, 2480));
+        // A programme start that isn't a timecode counts as zero.
+        stl[256..264].copy_from_slice(b"10:00:00");
+        assert_eq!(parse(&stl, None).unwrap().cues[0].start_ms, 36001000);
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        assert!(parse(&[b' '; 100], None).is_err());
+        let mut stl = serialize(&cues(25.0, &[(1000, 2000, &["Text"])]));
+        stl[3..11].copy_from_slice(b"XXXXXXXX");
+        assert!(parse(&stl, None).is_err());
+        assert!(parse(&stl, Some(25.0)).is_ok());
+        stl[12..14].copy_from_slice(b"01");
+        assert!(parse(&stl, Some(25.0)).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+use regex::Regex;
+
+// How a format writes inline styling in its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagDialect {
+    // HTML-like tags, as used by SRT: <i>, <b>, <u>, <s> and <font color="...">.
+    Html,
+    // ASS override blocks: {\i1}, {\b1}, {\c&HBBGGRR&}, ...
+    Ass,
+    // WebVTT cue text tags: <i>, <b>, <u> and <c.class>.
+    Vtt,
+}
+
+// Named colors understood in <font color="...">, with their RGB values.
+const COLORS: [(&str, u32); 19] = [
+    ("white", 0xffffff),
+    ("black", 0x000000),
+    ("red", 0xff0000),
+    ("lime", 0x00ff00),
+    ("blue", 0x0000ff),
+    ("yellow", 0xffff00),
+    ("cyan", 0x00ffff),
+    ("magenta", 0xff00ff),
+    ("aqua", 0x00ffff),
+    ("fuchsia", 0xff00ff),
+    ("green", 0x008000),
+    ("gray", 0x808080),
+    ("grey", 0x808080),
+    ("silver", 0xc0c0c0),
+    ("maroon", 0x800000),
+    ("navy", 0x000080),
+    ("olive", 0x808000),
+    ("purple", 0x800080),
+    ("teal", 0x008080),
+];
+
+// The color classes every WebVTT player knows.
+const VTT_COLORS: [&str; 8] = [
+    "white", "lime", "cyan", "red", "yellow", "magenta", "blue", "black",
+];
+
+// Create a function to read a color name or #rrggbb value as RGB.
+pub fn parse_color(color: &str) -> Option<u32> {
+    let color = color.trim().trim_matches('"').to_lowercase();
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16).ok(),
+        Some(hex) if hex.len() == 3 => {
+            let value = u32::from_str_radix(hex, 16).ok()?;
+            let (r, g, b) = (value >> 8, (value >> 4) & 0xf, value & 0xf);
+            Some(((r * 0x11) << 16) | ((g * 0x11) << 8) | (b * 0x11))
+        }
+        Some(_) => None,
+        None => COLORS
+            .iter()
+            .find(|(name, _)| *name == color)
+            .map(|(_, rgb)| *rgb),
+    }
+}
+
+// Create a function to find the WebVTT color class closest to an RGB color.
+pub fn nearest_vtt_color(rgb: u32) -> &'static str {
+    let channels = |rgb: u32| {
+        [
+            (rgb >> 16) as i32,
+            ((rgb >> 8) & 0xff) as i32,
+            (rgb & 0xff) as i32,
+        ]
+    };
+    let target = channels(rgb);
+    VTT_COLORS
+        .iter()
+        .min_by_key(|name| {
+            let color = channels(parse_color(name).unwrap());
+            (0..3).map(|i| (color[i] - target[i]).pow(2)).sum::<i32>()
+        })
+        .unwrap()
+}
+
+// Create a function to turn ASS override tags into HTML-like tags. Overrides without an HTML
+// equivalent (positioning, fades, ...) are kept in their {} block.
+fn ass_to_html(line: &str) -> String {
+    let block = Regex::new(r"\{([^}]*)\}").unwrap();
+    let tag = Regex::new(r"\\(?:([ibus])(\d+)|1?c(?:&H([0-9a-fA-F]+)&?)?|r)").unwrap();
+    let mut open: Vec<&str> = Vec::new();
+    let mut output = String::new();
+    let mut position = 0;
+    for caps in block.captures_iter(line) {
+        let whole = caps.get(0).unwrap();
+        output.push_str(&line[position..whole.start()]);
+        position = whole.end();
+        let mut kept = String::new();
+        let mut last = 0;
+        let overrides = &caps[1];
+        for tag in tag.captures_iter(overrides) {
+            let found = tag.get(0).unwrap();
+            kept.push_str(&overrides[last..found.start()]);
+            last = found.end();
+            let close = |open: &mut Vec<&str>, output: &mut String, name: &str| {
+                if let Some(i) = open.iter().rposition(|open| *open == name) {
+                    open.remove(i);
+                    output.push_str(&format!("</{}>", name));
+                }
+            };
+            match (tag.get(1), tag.get(2), tag.get(3)) {
+                (Some(name), Some(value), _) => {
+                    let name = match name.as_str() {
+                        "i" => "i",
+                        "b" => "b",
+                        "u" => "u",
+                        _ => "s",
+                    };
+                    if value.as_str() == "0" {
+                        close(&mut open, &mut output, name);
+                    } else if !open.contains(&name) {
+                        open.push(name);
+                        output.push_str(&format!("<{}>", name));
+                    }
+                }
+                (None, None, Some(color)) => {
+                    close(&mut open, &mut output, "font");
+                    // ASS colors are &HBBGGRR, optionally with an alpha byte in front.
+                    let bgr = u32::from_str_radix(color.as_str(), 16).unwrap_or(0) & 0xffffff;
+                    let rgb = ((bgr & 0xff) << 16) | (bgr & 0xff00) | (bgr >> 16);
+                    open.push("font");
+                    output.push_str(&format!("<font color=\"#{:06x}\">", rgb));
+                }
+                _ if found.as_str() == "\\r" => {
+                    while let Some(name) = open.pop() {
+                        output.push_str(&format!("</{}>", name));
+                    }
+                }
+                _ => close(&mut open, &mut output, "font"),
+            }
+        }
+        kept.push_str(&overrides[last..]);
+        if !kept.trim().is_empty() {
+            output.push_str(&format!("{{{}}}", kept));
+        }
+    }
+    output.push_str(&line[position..]);
+    while let Some(name) = open.pop() {
+        output.push_str(&format!("</{}>", name));
+    }
+    output.replace("\\h", "\u{a0}")
+}
+
+// Create a function to turn HTML-like tags into ASS override tags. Other tags are dropped, as ASS
+// would show them as text.
+fn html_to_ass(line: &str) -> String {
+    let tag = Regex::new(r"<(/?)([a-zA-Z]+)([^>]*)>").unwrap();
+    let color = Regex::new(r#"(?i)color\s*=\s*"?([#\w]+)"?"#).unwrap();
+    tag.replace_all(line, |caps: &regex::Captures| {
+        let closing = &caps[1] == "/";
+        match (caps[2].to_lowercase().as_str(), closing) {
+            (name @ ("i" | "b" | "u" | "s"), _) => {
+                format!("{{\\{}{}}}", name, if closing { 0 } else { 1 })
+            }
+            ("font", true) => "{\\c}".to_string(),
+            ("font", false) => match color
+                .captures(&caps[3])
+                .and_then(|color| parse_color(&color[1]))
+            {
+                Some(rgb) => {
+                    let bgr = ((rgb & 0xff) << 16) | (rgb & 0xff00) | (rgb >> 16);
+                    format!("{{\\c&H{:06X}&}}", bgr)
+                }
+                None => String::new(),
+            },
+            _ => String::new(),
+        }
+    })
+    .to_string()
+}
+
+// Create a function to turn HTML-like tags into WebVTT tags. Font colors become the closest of the
+// WebVTT color classes.
+fn html_to_vtt(line: &str) -> String {
+    let tag = Regex::new(r"<(/?)([a-zA-Z]+)([^>]*)>").unwrap();
+    let color = Regex::new(r#"(?i)color\s*=\s*"?([#\w]+)"?"#).unwrap();
+    let entity = Regex::new(r"&([a-zA-Z]+|#\d+|#x[0-9a-fA-F]+);").unwrap();
+    // A bare & has to be escaped in WebVTT.
+    let mut escaped = String::new();
+    let mut position = 0;
+    for (i, _) in line.match_indices('&') {
+        if i < position
+            || entity
+                .find_at(line, i)
+                .is_some_and(|found| found.start() == i)
+        {
+            continue;
+        }
+        escaped.push_str(&line[position..i]);
+        escaped.push_str("&amp;");
+        position = i + 1;
+    }
+    escaped.push_str(&line[position..]);
+    let mut fonts = Vec::new();
+    tag.replace_all(&escaped, |caps: &regex::Captures| {
+        let closing = &caps[1] == "/";
+        match (caps[2].to_lowercase().as_str(), closing) {
+            (name @ ("i" | "b" | "u"), _) => format!("<{}{}>", &caps[1], name),
+            ("font", true) => match fonts.pop() {
+                Some(true) => "</c>".to_string(),
+                _ => String::new(),
+            },
+            ("font", false) => {
+                let rgb = color
+                    .captures(&caps[3])
+                    .and_then(|color| parse_color(&color[1]));
+                fonts.push(rgb.is_some());
+                rgb.map_or(String::new(), |rgb| {
+                    format!("<c.{}>", nearest_vtt_color(rgb))
+                })
+            }
+            _ => String::new(),
+        }
+    })
+    .to_string()
+}
+
+// Create a function to turn WebVTT tags into HTML-like tags. Color classes become font colors, other
+// classes, voices, languages, ruby text and timestamps are dropped.
+fn vtt_to_html(line: &str) -> String {
+    let tag = Regex::new(r"<(/?)([a-zA-Z]+)([^>]*)>|<\d[\d:.]*>").unwrap();
+    let mut classes = Vec::new();
+    let text = tag.replace_all(line, |caps: &regex::Captures| {
+        let Some(name) = caps.get(2) else {
+            return String::new();
+        };
+        let closing = &caps[1] == "/";
+        match (name.as_str(), closing) {
+            (name @ ("i" | "b" | "u"), _) => format!("<{}{}>", &caps[1], name),
+            ("c", true) => match classes.pop() {
+                Some(true) => "</font>".to_string(),
+                _ => String::new(),
+            },
+            ("c", false) => {
+                let color = caps[3]
+                    .split('.')
+                    .find(|class| parse_color(class).is_some() && !class.starts_with('#'));
+                classes.push(color.is_some());
+                color.map_or(String::new(), |color| format!("<font color=\"{}\">", color))
+            }
+            _ => String::new(),
+        }
+    });
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&amp;", "&")
+}
+
+// Create a function to find an {\anN} positioning override in a line, as a numeric keypad position.
+pub fn alignment(line: &str) -> Option<u8> {
+    let re = Regex::new(r"\{[^}]*\\an([1-9])[^}]*\}").unwrap();
+    re.captures(line)
+        .and_then(|caps| caps[1].parse::<u8>().ok())
+}
+
+// Create a function to remove every ASS override block ({\an8}, {\pos(x,y)}, ...) from a line.
+pub fn strip_overrides(line: &str) -> String {
+    let block = Regex::new(r"\{\\[^}]*\}").unwrap();
+    block.replace_all(line, "").to_string()
+}
+
+// Create a function to read the keypad position of a cue from its WebVTT cue settings.
+// A line near the top (line:0, or under a third of the screen) is top, around the middle is middle.
+pub fn vtt_alignment(settings: &str) -> Option<u8> {
+    let mut row = 0;
+    let mut column = 2;
+    for setting in settings.split_whitespace() {
+        match setting.split_once(':') {
+            Some(("line", value)) => {
+                let value = value.split(',').next().unwrap_or("");
+                row = match value.strip_suffix('%') {
+                    Some(percent) => match percent.parse::<f32>().ok()? {
+                        percent if percent < 33.0 => 6,
+                        percent if percent < 66.0 => 3,
+                        _ => 0,
+                    },
+                    None if value.parse::<i32>().ok()? >= 0 => 6,
+                    None => 0,
+                };
+            }
+            Some(("align", "left" | "start")) => column = 1,
+            Some(("align", "right" | "end")) => column = 3,
+            _ => {}
+        }
+    }
+    Some(row + column).filter(|alignment| *alignment != 2)
+}
+
+// Create a function to write a keypad position as WebVTT cue settings.
+pub fn vtt_settings(alignment: u8) -> Option<String> {
+    let mut settings = Vec::new();
+    match alignment {
+        7..=9 => settings.push("line:0"),
+        4..=6 => settings.push("line:50%"),
+        _ => {}
+    }
+    match alignment % 3 {
+        1 => settings.push("align:left"),
+        0 => settings.push("align:right"),
+        _ => {}
+    }
+    Some(settings.join(" ")).filter(|settings| !settings.is_empty())
+}
+
+// Create a function to translate the inline styling of a line from one tag dialect to another.
+pub fn translate(line: &str, from: TagDialect, to: TagDialect) -> String {
+    if from == to {
+        return line.to_string();
+    }
+    let html = match from {
+        TagDialect::Html => line.to_string(),
+        TagDialect::Ass => ass_to_html(line),
+        TagDialect::Vtt => vtt_to_html(line),
+    };
+    match to {
+        TagDialect::Html => html,
+        TagDialect::Ass => html_to_ass(&html),
+        TagDialect::Vtt => html_to_vtt(&html),
+    }
+}
@@ -0,0 +1,288 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::SubtitleFile;
+
+// The rate SMPTE timecodes (HH:MM:SS:FF) count frames at. Drop-frame timecodes skip frame labels at the
+// start of every minute but the tenth (2 at 29.97 fps, 4 at 59.94 fps) to keep up with the real time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimecodeRate {
+    // Real number of frames per second, 30000/1001 for 29.97.
+    pub framerate: f64,
+    pub drop_frame: bool,
+}
+
+impl TimecodeRate {
+    // Create a function to build a timecode rate, reading the NTSC rates given as 23.976, 29.97 or 59.94
+    // as their exact values. Only 29.97 and 59.94 fps can be drop-frame.
+    pub fn new(framerate: f64, drop_frame: bool) -> Result<TimecodeRate, String> {
+        let nominal = framerate.round();
+        let ntsc = nominal * 1000.0 / 1001.0;
+        let framerate = if nominal != framerate && (framerate - ntsc).abs() < 0.01 {
+            ntsc
+        } else {
+            framerate
+        };
+        if !(framerate > 0.0 && framerate.is_finite()) {
+            return Err(format!("Invalid timecode rate '{}'", framerate));
+        }
+        if drop_frame && !(framerate == ntsc && (nominal == 30.0 || nominal == 60.0)) {
+            return Err(format!(
+                "Drop-frame timecodes only exist at 29.97 and 59.94 fps, not {}",
+                framerate
+            ));
+        }
+        Ok(TimecodeRate {
+            framerate,
+            drop_frame,
+        })
+    }
+
+    // Create a function to get the 29.97 fps timecode rate of NTSC video, as used by SCC captions.
+    pub fn ntsc(drop_frame: bool) -> TimecodeRate {
+        TimecodeRate {
+            framerate: 30000.0 / 1001.0,
+            drop_frame,
+        }
+    }
+
+    // Create a function to get the number of frame labels per second, 30 for 29.97 fps.
+    fn nominal(&self) -> i64 {
+        self.framerate.round() as i64
+    }
+
+    // Create a function to get the number of frame labels skipped at the start of a minute.
+    fn dropped(&self) -> i64 {
+        if self.drop_frame {
+            self.nominal() / 15
+        } else {
+            0
+        }
+    }
+
+    // Create a function to count the frames before a timecode label.
+    pub fn label_to_frames(&self, hours: i64, minutes: i64, seconds: i64, frames: i64) -> i64 {
+        let total_minutes = hours * 60 + minutes;
+        (hours * 3600 + minutes * 60 + seconds) * self.nominal() + frames
+            - self.dropped() * (total_minutes - total_minutes / 10)
+    }
+
+    // Create a function to find the timecode label of a frame, as hours, minutes, seconds and frames.
+    pub fn frames_to_label(&self, frames: i64) -> (i64, i64, i64, i64) {
+        let (nominal, dropped) = (self.nominal(), self.dropped());
+        let mut label = frames.max(0);
+        if dropped > 0 {
+            let per_ten_minutes = nominal * 600 - dropped * 9;
+            let per_minute = nominal * 60 - dropped;
+            let (tens, remainder) = (label / per_ten_minutes, label % per_ten_minutes);
+            label += dropped * 9 * tens;
+            if remainder >= dropped {
+                label += dropped * ((remainder - dropped) / per_minute);
+            }
+        }
+        (
+            label / (nominal * 3600),
+            label / (nominal * 60) % 60,
+            label / nominal % 60,
+            label % nominal,
+        )
+    }
+
+    // Create a function to get the framerate as a ratio of whole numbers, 30000/1001 for 29.97, for the
+    // framerates that are one; None for the others.
+    fn ratio(&self) -> Option<(i64, i64)> {
+        let nominal = self.nominal();
+        if self.framerate == nominal as f64 {
+            Some((nominal, 1))
+        } else if self.framerate == nominal as f64 * 1000.0 / 1001.0 {
+            Some((nominal * 1000, 1001))
+        } else {
+            None
+        }
+    }
+
+    // Create a function to convert between frames and miliseconds, rounding halves away from zero. Whole
+    // and NTSC framerates are computed exactly, so the frames that end half a milisecond past one always
+    // round up.
+    pub fn frames_to_ms(&self, frames: i64) -> i32 {
+        match self.ratio() {
+            Some((numerator, denominator)) => {
+                divide_rounded(frames * 1000 * denominator, numerator)
+                    .clamp(i32::MIN as i64, i32::MAX as i64) as i32
+            }
+            None => (frames as f64 * 1000.0 / self.framerate).round() as i32,
+        }
+    }
+
+    pub fn ms_to_frames(&self, miliseconds: i32) -> i64 {
+        match self.ratio() {
+            Some((numerator, denominator)) => {
+                divide_rounded(miliseconds as i64 * numerator, 1000 * denominator)
+            }
+            None => (miliseconds as f64 * self.framerate / 1000.0).round() as i64,
+        }
+    }
+
+    // Create a function to read a timecode, HH:MM:SS:FF (or HH:MM:SS;FF), in miliseconds. Labels a
+    // drop-frame timecode skips, such as 00:01:00;00, are invalid.
+    pub fn parse(&self, timecode: &str) -> Option<i32> {
+        let re = Regex::new(r"^(-)?(\d+):(\d{2}):(\d{2})[:;.,](\d{2,3})$").unwrap();
+        let caps = re.captures(timecode.trim())?;
+        let field = |i: usize| caps[i].parse::<i64>().ok();
+        let (minutes, seconds, frames) = (field(3)?, field(4)?, field(5)?);
+        if frames >= self.nominal()
+            || (seconds == 0 && frames < self.dropped() && minutes % 10 != 0)
+        {
+            return None;
+        }
+        let total = self.label_to_frames(field(2)?, minutes, seconds, frames);
+        let miliseconds = self.frames_to_ms(total);
+        Some(if caps.get(1).is_some() {
+            -miliseconds
+        } else {
+            miliseconds
+        })
+    }
+
+    // Create a function to write a time as a timecode at the nearest frame, with a ';' before the
+    // frames of drop-frame timecodes.
+    pub fn format(&self, miliseconds: i32) -> String {
+        let (hours, minutes, seconds, frames) =
+            self.frames_to_label(self.ms_to_frames(miliseconds));
+        format!(
+            "{:02}:{:02}:{:02}{}{:02}",
+            hours,
+            minutes,
+            seconds,
+            if self.drop_frame { ';' } else { ':' },
+            frames
+        )
+    }
+}
+
+// Create a function to divide by a positive divisor, rounding halves away from zero.
+fn divide_rounded(dividend: i64, divisor: i64) -> i64 {
+    let quotient = (dividend.abs() * 2 + divisor) / (divisor * 2);
+    quotient * dividend.signum()
+}
+
+// Create a function to parse a framerate argument, as a number (23.976) or a ratio (24000/1001).
+pub fn parse_framerate(value: &str) -> Result<f64, String> {
+    let framerate = match value.split_once('/') {
+        Some((numerator, denominator)) => numerator
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .zip(denominator.trim().parse::<f64>().ok())
+            .map(|(numerator, denominator)| numerator / denominator),
+        None => value.trim().parse::<f64>().ok(),
+    };
+    framerate
+        .filter(|framerate| framerate.is_finite() && *framerate > 0.0)
+        .ok_or_else(|| format!("Invalid framerate '{}'", value))
+}
+
+// Create a function to move a time read with one timecode counting to what the same timecode label
+// means in another, keeping the part of a frame it is past its frame.
+pub fn relabel(time: i32, from: &TimecodeRate, to: &TimecodeRate) -> i32 {
+    let frames = from.ms_to_frames(time);
+    let (hours, minutes, seconds, label) = from.frames_to_label(frames);
+    let relabeled = to.label_to_frames(hours, minutes, seconds, label);
+    to.frames_to_ms(relabeled) + time - from.frames_to_ms(frames)
+}
+
+impl SubtitleFile {
+    // Create a function to reinterpret the timecodes of the cues from one counting to another, such as
+    // drop-frame times read as non-drop-frame. This isn't a framerate conversion: both count frames at
+    // the same rate, but the same label means a time up to 3.6 seconds per hour apart.
+    pub fn relabel_timecodes(&mut self, from: &TimecodeRate, to: &TimecodeRate) {
+        for cue in self.cues.iter_mut() {
+            cue.start_ms = relabel(cue.start_ms, from, to);
+            cue.end_ms = relabel(cue.end_ms, from, to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_frame_labels_skip_at_minutes_but_every_tenth() {
+        let rate = TimecodeRate::ntsc(true);
+        for (label, frames) in [
+            ((0, 0, 59, 29), 1799),
+            ((0, 1, 0, 2), 1800),
+            ((0, 9, 59, 29), 17981),
+            ((0, 10, 0, 0), 17982),
+            ((0, 10, 0, 2), 17984),
+            ((0, 11, 0, 2), 19782),
+            ((1, 0, 0, 0), 107892),
+        ] {
+            assert_eq!(
+                rate.label_to_frames(label.0, label.1, label.2, label.3),
+                frames
+            );
+            assert_eq!(rate.frames_to_label(frames), label);
+        }
+        let rate = TimecodeRate::new(59.94, true).unwrap();
+        assert_eq!(rate.frames_to_label(3599), (0, 0, 59, 59));
+        assert_eq!(rate.frames_to_label(3600), (0, 1, 0, 4));
+        assert_eq!(rate.frames_to_label(35964), (0, 10, 0, 0));
+    }
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        for (drop_frame, timecode) in [
+            (true, "00:01:00;02"),
+            (true, "00:10:00;00"),
+            (true, "01:23:45;12"),
+            (false, "01:00:00:00"),
+        ] {
+            let rate = TimecodeRate::ntsc(drop_frame);
+            assert_eq!(rate.format(rate.parse(timecode).unwrap()), timecode);
+        }
+        let rate = TimecodeRate::new(25.0, false).unwrap();
+        assert_eq!(rate.parse("00:00:01:12"), Some(1480));
+        assert_eq!(rate.format(1480), "00:00:01:12");
+    }
+
+    #[test]
+    fn relabeling_moves_drop_frame_times_to_non_drop_frame() {
+        let (drop_frame, non_drop_frame) = (TimecodeRate::ntsc(true), TimecodeRate::ntsc(false));
+        let time = drop_frame.parse("01:00:00;00").unwrap();
+        let relabeled = relabel(time, &drop_frame, &non_drop_frame);
+        assert_eq!(non_drop_frame.format(relabeled), "01:00:00:00");
+        // 108 labels are dropped in an hour, 3.6036 seconds at 29.97 fps.
+        assert_eq!(relabeled - time, 3604);
+    }
+
+    #[test]
+    fn ntsc_frames_are_timed_exactly() {
+        // Frame 12 at 24000/1001 fps starts 500.5 ms in, which floating point rounds down.
+        let film = TimecodeRate::new(23.976, false).unwrap();
+        assert_eq!(film.frames_to_ms(12), 501);
+        assert_eq!(film.frames_to_ms(-12), -501);
+        assert_eq!(film.ms_to_frames(501), 12);
+        // 17982 frames of 1001/30 ms are 599999.4 ms, the drop-frame label runs behind the clock.
+        let rate = TimecodeRate::ntsc(true);
+        assert_eq!(rate.frames_to_ms(17982), 599999);
+        assert_eq!(rate.ms_to_frames(599999), 17982);
+        let pal = TimecodeRate::new(25.0, false).unwrap();
+        assert_eq!(pal.frames_to_ms(90000), 3600000);
+    }
+
+    #[test]
+    fn malformed_timecodes_are_errors() {
+        let rate = TimecodeRate::ntsc(true);
+        assert_eq!(rate.parse("00:01:00;00"), None);
+        assert_eq!(rate.parse("00:01:00;01"), None);
+        assert_eq!(rate.parse("00:00:00;30"), None);
+        assert_eq!(rate.parse("1:02"), None);
+        assert!(TimecodeRate::ntsc(false).parse("00:01:00:00").is_some());
+        assert!(TimecodeRate::new(25.0, true).is_err());
+        assert!(TimecodeRate::new(0.0, false).is_err());
+        assert!(parse_framerate("24000/0").is_err());
+        assert_eq!(parse_framerate("30000/1001"), Ok(30000.0 / 1001.0));
+    }
+}
@@ -0,0 +1,732 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use super::timecode::{parse_framerate, TimecodeRate};
+use super::{Cue, SubtitleFile};
+
+// The cues a retiming applies to, by their start time: from after (included) to before (excluded).
+// Unbounded sides are None, so the default range holds every cue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeRange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<i32>,
+}
+
+impl TimeRange {
+    // Create a function to build a range, checking that it isn't empty.
+    pub fn new(after: Option<i32>, before: Option<i32>) -> Result<TimeRange, String> {
+        if let (Some(after), Some(before)) = (after, before) {
+            if before <= after {
+                return Err("--before has to be later than --after".to_string());
+            }
+        }
+        Ok(TimeRange { after, before })
+    }
+
+    // Create a function to tell whether a cue starts inside the range.
+    pub fn contains(&self, cue: &Cue) -> bool {
+        self.after.is_none_or(|after| cue.start_ms >= after)
+            && self.before.is_none_or(|before| cue.start_ms < before)
+    }
+
+    // Create a function to tell whether the range holds every cue.
+    pub fn is_all(&self) -> bool {
+        self.after.is_none() && self.before.is_none()
+    }
+}
+
+// How a retimed time that falls between two miliseconds is rounded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rounding {
+    // To the nearest milisecond, halves away from zero.
+    Nearest,
+    // To the nearest milisecond, halves to the even one (banker's rounding), so halves don't all drift
+    // the same way over repeated conversions.
+    Even,
+    Floor,
+    Ceil,
+    // To the nearest frame boundary of a framerate, so times that were on frames stay on frames and
+    // converting back and forth is lossless.
+    Frame(f64),
+}
+
+impl Rounding {
+    // Create a function to pick a rounding from its name on the command line. frame rounds to the
+    // frames of the given framerate.
+    pub fn from_name(name: &str, framerate: f64) -> Result<Rounding, String> {
+        match name {
+            "nearest" => Ok(Rounding::Nearest),
+            "even" | "bankers" => Ok(Rounding::Even),
+            "floor" => Ok(Rounding::Floor),
+            "ceil" => Ok(Rounding::Ceil),
+            "frame" => Ok(Rounding::Frame(
+                TimecodeRate::new(framerate, false)?.framerate,
+            )),
+            _ => Err(format!(
+                "Unknown rounding '{}', expected nearest, even, floor, ceil or frame",
+                name
+            )),
+        }
+    }
+
+    // Create a function to round a time in miliseconds.
+    pub fn apply(self, time: f64) -> i32 {
+        match self {
+            Rounding::Nearest => time.round() as i32,
+            Rounding::Even => time.round_ties_even() as i32,
+            Rounding::Floor => time.floor() as i32,
+            Rounding::Ceil => time.ceil() as i32,
+            Rounding::Frame(framerate) => {
+                let frame = (time * framerate / 1000.0).round();
+                (frame * 1000.0 / framerate).round() as i32
+            }
+        }
+    }
+}
+
+// Create a function to read a duration given on the command line, in miliseconds. It can be negative,
+// and given in miliseconds (1500ms, or a bare number), seconds (-2.5s), as [hh:]mm:ss[.mmm] (1:02.5),
+// or as a SMPTE timecode with its framerate (01:00:10:12@25). Drop-frame timecodes (01:00:10;12) are
+// 29.97 fps unless a rate is given.
+pub fn parse_duration(text: &str) -> Result<i32, String> {
+    if let Some(miliseconds) = parse_timecode(text)? {
+        return Ok(miliseconds);
+    }
+    let invalid = || {
+        format!(
+            "Invalid duration '{}', expected for example 1500ms, -2.5s or 1:02.5",
+            text
+        )
+    };
+    let value = text.trim();
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1.0, value),
+        None => (1.0, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let clock = Regex::new(r"^(?:(\d+):)?(\d+):(\d+(?:[.,]\d+)?)$").unwrap();
+    let miliseconds = if let Some(caps) = clock.captures(value) {
+        let hours = caps
+            .get(1)
+            .map_or(0.0, |hours| hours.as_str().parse().unwrap());
+        let minutes: f64 = caps[2].parse().unwrap();
+        let seconds: f64 = caps[3].replace(',', ".").parse().unwrap();
+        (hours * 3600.0 + minutes * 60.0 + seconds) * 1000.0
+    } else if let Some(value) = value.strip_suffix("ms") {
+        value.trim().parse::<f64>().map_err(|_| invalid())?
+    } else if let Some(value) = value.strip_suffix('s') {
+        value.trim().parse::<f64>().map_err(|_| invalid())? * 1000.0
+    } else {
+        value.parse::<f64>().map_err(|_| invalid())?
+    };
+    if !miliseconds.is_finite() || miliseconds.abs() > i32::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok((sign * miliseconds).round() as i32)
+}
+
+// Create a function to read a HH:MM:SS:FF timecode followed by @<framerate>, or None when the text
+// isn't a timecode. The framerate is a number (23.976) or a ratio (24000/1001).
+fn parse_timecode(text: &str) -> Result<Option<i32>, String> {
+    let re = Regex::new(r"^([-+]?\d+:\d{2}:\d{2}([:;])\d{2,3})(?:@(.+))?$").unwrap();
+    let caps = match re.captures(text.trim()) {
+        Some(caps) => caps,
+        None => return Ok(None),
+    };
+    let drop_frame = &caps[2] == ";";
+    let framerate = match caps.get(3) {
+        Some(rate) => parse_framerate(rate.as_str())?,
+        None if drop_frame => 30000.0 / 1001.0,
+        None => {
+            return Err(format!(
+                "Timecode '{}' needs a framerate, for example {}@25",
+                text, &caps[1]
+            ))
+        }
+    };
+    let rate = TimecodeRate::new(framerate, drop_frame)?;
+    let timecode = caps[1].trim_start_matches('+');
+    rate.parse(timecode).map(Some).ok_or_else(|| {
+        format!(
+            "Invalid timecode '{}', it has more frames than the framerate",
+            text
+        )
+    })
+}
+
+// Create a function to read an anchor given on the command line, such as 0:45:10.2=0:45:12: a time in
+// the subtitles, and the time it should be at. Both are durations, see parse_duration.
+pub fn parse_anchor(text: &str) -> Result<(i32, i32), String> {
+    let (from, to) = text
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid anchor '{}', expected <time>=<new time>", text))?;
+    Ok((parse_duration(from)?, parse_duration(to)?))
+}
+
+// Create a function to sort anchors by their time in the subtitles, and check that they keep the cues
+// in order: two anchors can't share a time, and later anchors can't move to earlier times.
+pub fn check_anchors(anchors: &mut [(i32, i32)]) -> Result<(), String> {
+    if anchors.is_empty() {
+        return Err("At least one anchor is needed".to_string());
+    }
+    anchors.sort_by_key(|(from, _)| *from);
+    for pair in anchors.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            return Err(format!(
+                "Two anchors are at the same time ({} ms)",
+                pair[0].0
+            ));
+        }
+        if pair[1].1 <= pair[0].1 {
+            return Err(format!(
+                "The anchors at {} ms and {} ms would swap the order of the cues",
+                pair[0].0, pair[1].0
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Create a function to move a time with a piecewise-linear warp through sorted anchors: between two
+// anchors the time is moved linearly, before the first and after the last it is moved like by the
+// nearest section. A single anchor is a constant offset.
+pub fn warp_time(anchors: &[(i32, i32)], time: i32) -> i32 {
+    warped(anchors, time).round() as i32
+}
+
+// Create a function to move a time like warp_time, before rounding it.
+fn warped(anchors: &[(i32, i32)], time: i32) -> f64 {
+    if anchors.len() == 1 {
+        return time as f64 + anchors[0].1 as f64 - anchors[0].0 as f64;
+    }
+    let section = anchors
+        .windows(2)
+        .position(|pair| time < pair[1].0)
+        .unwrap_or(anchors.len() - 2);
+    let (from_start, to_start) = anchors[section];
+    let (from_end, to_end) = anchors[section + 1];
+    let ratio = (to_end as f64 - to_start as f64) / (from_end as f64 - from_start as f64);
+    to_start as f64 + ((time as f64 - from_start as f64) * ratio).round()
+}
+
+// Subtitles ending more than this share of the video before its end get a warning. Credits usually
+// run without subtitles, so some room is left.
+pub const EARLY_END: f64 = 0.1;
+
+// Subtitles ending within this share of the video before its end, or at most a second after it, are
+// taken to be timed for it.
+const CLOSE_END: f64 = 0.03;
+
+// Framerate conversions that change the length of a film, from the framerate subtitles were timed for
+// to the framerate of the video: PAL speedup (film at 25 fps) and back. 24 and 23.976 fps differ too
+// little for the duration to tell them apart.
+const SPEED_CONVERSIONS: [(f32, f32); 4] =
+    [(25.0, 23.976), (23.976, 25.0), (25.0, 24.0), (24.0, 25.0)];
+
+// How the end of subtitles compares to the end of the video they play with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurationCheck {
+    // End of the last cue, in miliseconds.
+    pub end_ms: i32,
+    pub video_ms: i32,
+    // The framerate conversion (from, to) that makes the subtitles end with the video when they don't,
+    // to the framerate of the video.
+    pub conversion: Option<(f32, f32)>,
+}
+
+impl DurationCheck {
+    // Create a function to compare the end of the last cue to the duration and framerate of a video.
+    pub fn new(end_ms: i32, video_ms: i32, video_framerate: f64) -> DurationCheck {
+        let mut check = DurationCheck {
+            end_ms,
+            video_ms,
+            conversion: None,
+        };
+        if end_ms > 0 && !check.fits() {
+            check.conversion = SPEED_CONVERSIONS
+                .iter()
+                .filter(|(_, to)| (*to as f64 - video_framerate).abs() < 0.01)
+                .find(|(from, to)| check.is_close(end_ms as f64 * (*from as f64 / *to as f64)))
+                .copied();
+        }
+        check
+    }
+
+    // Create a function to tell whether subtitles ending at a time are timed for the video.
+    fn is_close(&self, end_ms: f64) -> bool {
+        let video = self.video_ms as f64;
+        end_ms <= video + 1000.0 && end_ms >= video * (1.0 - CLOSE_END)
+    }
+
+    // Create a function to tell whether the subtitles end with the video, give or take the credits.
+    pub fn fits(&self) -> bool {
+        self.is_close(self.end_ms as f64)
+    }
+
+    // Create a function to tell whether the subtitles run past the end of the video.
+    pub fn ends_after(&self) -> bool {
+        self.end_ms > self.video_ms + 1000
+    }
+
+    // Create a function to tell whether the subtitles end well before the video, see EARLY_END.
+    pub fn ends_early(&self) -> bool {
+        (self.end_ms as f64) < self.video_ms as f64 * (1.0 - EARLY_END)
+    }
+
+    // Create a function to get how much longer the video is than the subtitles, as a share of the
+    // subtitles: 0.0427 for a video 4.27% longer, negative for a shorter one.
+    pub fn mismatch(&self) -> f64 {
+        self.video_ms as f64 / self.end_ms.max(1) as f64 - 1.0
+    }
+}
+
+impl SubtitleFile {
+    // Create a function to check that scaling the cue times of a range by a factor from an origin, then
+    // moving them by an offset, keeps them within the ±24.8 days of miliseconds a time can hold, so a
+    // retiming that can't be written is refused rather than saturated.
+    pub fn check_retiming(
+        &self,
+        range: &TimeRange,
+        factor: f64,
+        origin: i32,
+        offset: i32,
+    ) -> Result<(), String> {
+        self.check_times(range, |time| {
+            origin as f64 + (time as f64 - origin as f64) * factor + offset as f64
+        })
+    }
+
+    // Create a function to check that warping every cue time through anchors keeps it within the times
+    // a time can hold, see check_retiming.
+    pub fn check_warp(&self, anchors: &[(i32, i32)]) -> Result<(), String> {
+        self.check_times(&TimeRange::default(), |time| warped(anchors, time))
+    }
+
+    // Create a function to check the start and end times of the cues of a range once retimed.
+    fn check_times(&self, range: &TimeRange, retimed: impl Fn(i32) -> f64) -> Result<(), String> {
+        for cue in self.cues.iter().filter(|cue| range.contains(cue)) {
+            for time in [retimed(cue.start_ms), retimed(cue.end_ms)] {
+                if !time.is_finite() || time.abs() > i32::MAX as f64 {
+                    return Err(format!(
+                        "The retiming would move cue {} to {:.0} ms, beyond the times subtitles can hold",
+                        cue.index, time
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Create a function to move every cue by an offset in miliseconds, earlier when it is negative.
+    // Returns the number of cues cut or removed for starting before zero, see clamp_to_zero.
+    pub fn shift(&mut self, offset: i32) -> usize {
+        for cue in self.cues.iter_mut() {
+            cue.start_ms = cue.start_ms.saturating_add(offset);
+            cue.end_ms = cue.end_ms.saturating_add(offset);
+        }
+        self.clamp_to_zero()
+    }
+
+    // Create a function to keep cues from starting before zero after retiming: cues that end before
+    // it are removed, and cues that start before it start at zero. Returns the number of cues removed
+    // or cut that way.
+    pub fn clamp_to_zero(&mut self) -> usize {
+        let before = self.cues.len();
+        let mut cut = 0;
+        self.cues.retain(|cue| cue.end_ms > 0);
+        for cue in self.cues.iter_mut().filter(|cue| cue.start_ms < 0) {
+            cue.start_ms = 0;
+            cut += 1;
+        }
+        if self.cues.len() != before {
+            for (i, cue) in self.cues.iter_mut().enumerate() {
+                cue.index = i + 1;
+            }
+        }
+        before - self.cues.len() + cut
+    }
+
+    // Create a function to stretch every cue time by a factor, away from an origin time (0 for the start
+    // of the video): a factor above 1 makes the cues after it later and longer, below 1 earlier and shorter.
+    pub fn scale(&mut self, factor: f64, origin: i32) {
+        self.scale_rounded(factor, origin, Rounding::Nearest);
+    }
+
+    // Create a function to scale every cue time like scale, rounding the scaled times the given way.
+    pub fn scale_rounded(&mut self, factor: f64, origin: i32, rounding: Rounding) {
+        let scale =
+            |time: i32| rounding.apply(origin as f64 + (time as f64 - origin as f64) * factor);
+        for cue in self.cues.iter_mut() {
+            cue.start_ms = scale(cue.start_ms);
+            cue.end_ms = scale(cue.end_ms);
+        }
+    }
+
+    // Create a function to scale the start times like scale_rounded, keeping the duration of every cue,
+    // for durations authored to reading-speed standards. A cue that would run into the next one, which it
+    // didn't before, is cut at its start. Returns the number of cues cut.
+    pub fn scale_starts(&mut self, factor: f64, origin: i32, rounding: Rounding) -> usize {
+        let original: Vec<(i32, i32)> = self
+            .cues
+            .iter()
+            .map(|cue| (cue.start_ms, cue.end_ms))
+            .collect();
+        self.scale_rounded(factor, origin, rounding);
+        let starts: Vec<i32> = self.cues.iter().map(|cue| cue.start_ms).collect();
+        let mut cut = 0;
+        for (i, cue) in self.cues.iter_mut().enumerate() {
+            let (start, end) = original[i];
+            cue.end_ms = cue.start_ms.saturating_add(end.saturating_sub(start));
+            let next = (i + 1..starts.len()).find(|next| original[*next].0 > start);
+            if let Some(next) = next.filter(|next| original[*next].0 >= end) {
+                if cue.end_ms > starts[next] {
+                    cue.end_ms = starts[next];
+                    cut += 1;
+                }
+            }
+        }
+        cut
+    }
+
+    // Create a function to round every start and end time to the nearest frame boundary of a framerate,
+    // as broadcast QC tools require. A cue shorter than a frame is kept one frame long.
+    pub fn snap_to_frames(&mut self, framerate: f64) {
+        let frame = |time: i32| (time as f64 * framerate / 1000.0).round();
+        let time = |frame: f64| (frame * 1000.0 / framerate).round() as i32;
+        for cue in self.cues.iter_mut() {
+            let start = frame(cue.start_ms);
+            let end = frame(cue.end_ms).max(start + 1.0);
+            cue.start_ms = time(start);
+            cue.end_ms = time(end);
+        }
+    }
+
+    // Create a function to retime only the cues inside a range: they are handed to the retiming as a
+    // file of their own, and put back between the cues outside of it, which are left untouched, numbers
+    // included. Retimed cues are cut where the cues around the range start and end rather than overlap
+    // them, and removed when nothing is left of them. Returns the result of the retiming and the number
+    // of cues cut or removed that way.
+    pub fn within<R>(
+        &mut self,
+        range: &TimeRange,
+        retime: impl FnOnce(&mut SubtitleFile) -> R,
+    ) -> (R, usize) {
+        if range.is_all() {
+            return (retime(self), 0);
+        }
+        let (mut earlier, mut inside, mut later) = (Vec::new(), Vec::new(), Vec::new());
+        for cue in self.cues.drain(..) {
+            if range.contains(&cue) {
+                inside.push(cue);
+            } else if range.after.is_some_and(|after| cue.start_ms < after) {
+                earlier.push(cue);
+            } else {
+                later.push(cue);
+            }
+        }
+        // The room between the cues around the range, never less than the range had before.
+        let low = earlier
+            .iter()
+            .max_by_key(|cue| cue.start_ms)
+            .map_or(i32::MIN, |cue| cue.end_ms)
+            .min(
+                inside
+                    .iter()
+                    .map(|cue| cue.start_ms)
+                    .min()
+                    .unwrap_or(i32::MAX),
+            );
+        let high = later
+            .iter()
+            .map(|cue| cue.start_ms)
+            .min()
+            .unwrap_or(i32::MAX)
+            .max(
+                inside
+                    .iter()
+                    .map(|cue| cue.end_ms)
+                    .max()
+                    .unwrap_or(i32::MIN),
+            );
+        let indices: Vec<usize> = inside.iter().map(|cue| cue.index).collect();
+        let mut part = SubtitleFile {
+            cues: inside,
+            ..self.clone()
+        };
+        let result = retime(&mut part);
+        part.cues.sort_by_key(|cue| cue.start_ms);
+        // A retiming that removes cues numbers the rest from 1, they are numbered from the first
+        // number of the range instead.
+        let first = indices.first().copied().unwrap_or(1);
+        let same = part.cues.len() == indices.len();
+        for (i, cue) in part.cues.iter_mut().enumerate() {
+            cue.index = if same { indices[i] } else { first + i };
+        }
+        let mut cut = 0;
+        part.cues.retain_mut(|cue| {
+            if cue.start_ms >= low && cue.end_ms <= high {
+                return true;
+            }
+            cut += 1;
+            cue.start_ms = cue.start_ms.max(low);
+            cue.end_ms = cue.end_ms.min(high);
+            cue.end_ms > cue.start_ms
+        });
+        self.cues = earlier;
+        self.cues.extend(part.cues);
+        self.cues.extend(later);
+        (result, cut)
+    }
+
+    // Create a function to find where within puts the cues of a range once retimed: their positions in
+    // the file, from 0. None when the range holds every cue.
+    pub fn positions_within(&self, range: &TimeRange) -> Option<Range<usize>> {
+        if range.is_all() {
+            return None;
+        }
+        let earlier = self
+            .cues
+            .iter()
+            .filter(|cue| {
+                !range.contains(cue) && range.after.is_some_and(|after| cue.start_ms < after)
+            })
+            .count();
+        let inside = self.cues.iter().filter(|cue| range.contains(cue)).count();
+        Some(earlier..earlier + inside)
+    }
+
+    // Create a function to retime only the cues at the given positions, as found by positions_within,
+    // keeping their numbers. The other cues are left untouched, wherever the retimed cues land.
+    pub fn at_positions<R>(
+        &mut self,
+        positions: &Range<usize>,
+        retime: impl FnOnce(&mut SubtitleFile) -> R,
+    ) -> R {
+        let end = positions.end.min(self.cues.len());
+        let start = positions.start.min(end);
+        let mut part = SubtitleFile {
+            cues: self.cues.drain(start..end).collect(),
+            ..self.clone()
+        };
+        let indices: Vec<usize> = part.cues.iter().map(|cue| cue.index).collect();
+        let result = retime(&mut part);
+        part.cues.sort_by_key(|cue| cue.start_ms);
+        for (cue, index) in part.cues.iter_mut().zip(indices) {
+            cue.index = index;
+        }
+        self.cues.splice(start..start, part.cues);
+        result
+    }
+
+    // Create a function to split a file in parts at times sorted in increasing order, each cue going to
+    // the part it starts in and ending at the end of it at the latest. Every part keeps the header, the
+    // last one the trailer, and each is numbered from 1; times are left as they are.
+    pub fn split_at(&self, times: &[i32]) -> Vec<SubtitleFile> {
+        let mut parts = Vec::new();
+        let bounds: Vec<i32> = [i32::MIN]
+            .into_iter()
+            .chain(times.iter().copied())
+            .chain([i32::MAX])
+            .collect();
+        for (i, bound) in bounds.windows(2).enumerate() {
+            let cues = self
+                .cues
+                .iter()
+                .filter(|cue| cue.start_ms >= bound[0] && cue.start_ms < bound[1])
+                .enumerate()
+                .map(|(index, cue)| Cue {
+                    index: index + 1,
+                    end_ms: cue.end_ms.min(bound[1]),
+                    ..cue.clone()
+                })
+                .collect();
+            parts.push(SubtitleFile {
+                format: self.format,
+                framerate: self.framerate,
+                header: self.header.clone(),
+                cues,
+                trailer: if i == times.len() {
+                    self.trailer.clone()
+                } else {
+                    Vec::new()
+                },
+            });
+        }
+        parts
+    }
+
+    // Create a function to join parts of a file, each moved later by its offset in miliseconds, the
+    // time it starts at: the cues are put in order and numbered from 1, the header is that of the first
+    // part and the trailer that of the last. None without parts.
+    pub fn join(parts: Vec<SubtitleFile>, offsets: &[i32]) -> Option<SubtitleFile> {
+        let mut joined: Option<SubtitleFile> = None;
+        for (mut part, offset) in parts.into_iter().zip(offsets) {
+            part.shift(*offset);
+            match &mut joined {
+                None => joined = Some(part),
+                Some(joined) => {
+                    joined.cues.extend(part.cues);
+                    joined.trailer = part.trailer;
+                }
+            }
+        }
+        let mut joined = joined?;
+        joined.cues.sort_by_key(|cue| cue.start_ms);
+        for (i, cue) in joined.cues.iter_mut().enumerate() {
+            cue.index = i + 1;
+        }
+        Some(joined)
+    }
+
+    // Create a function to retime every cue with a piecewise-linear warp through anchors sorted with
+    // check_anchors, see warp_time. Returns the number of cues cut or removed for starting before zero.
+    pub fn warp(&mut self, anchors: &[(i32, i32)]) -> usize {
+        for cue in self.cues.iter_mut() {
+            cue.start_ms = warp_time(anchors, cue.start_ms);
+            cue.end_ms = warp_time(anchors, cue.end_ms);
+        }
+        self.clamp_to_zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::{Cue, Format};
+
+    fn file(times: &[(i32, i32)]) -> SubtitleFile {
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: times
+                .iter()
+                .enumerate()
+                .map(|(i, (start_ms, end_ms))| Cue {
+                    index: i + 1,
+                    start_ms: *start_ms,
+                    end_ms: *end_ms,
+                    lines: vec![format!("Cue {}", i + 1)],
+                    ..Default::default()
+                })
+                .collect(),
+            trailer: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn retimings_past_the_last_representable_time_are_refused() {
+        let subtitles = file(&[(1000, 2000), (3_000_000, 3_002_000)]);
+        let all = TimeRange::default();
+        assert!(subtitles.check_retiming(&all, 2.0, 0, 0).is_ok());
+        assert!(subtitles.check_retiming(&all, 1_000_000.0, 0, 0).is_err());
+        assert!(subtitles.check_retiming(&all, 1.0, 0, i32::MAX).is_err());
+        assert!(subtitles.check_retiming(&all, f64::INFINITY, 0, 0).is_err());
+        // Only the cues in the range move.
+        let first = TimeRange::new(None, Some(2500)).unwrap();
+        assert!(subtitles.check_retiming(&first, 1_000_000.0, 0, 0).is_ok());
+    }
+
+    fn times(file: &SubtitleFile) -> Vec<(usize, i32, i32)> {
+        file.cues
+            .iter()
+            .map(|cue| (cue.index, cue.start_ms, cue.end_ms))
+            .collect()
+    }
+
+    #[test]
+    fn only_the_cues_starting_in_a_range_are_retimed() {
+        let mut subtitles = file(&[(1000, 2000), (5000, 6000), (7000, 8000), (9000, 10000)]);
+        let range = TimeRange::new(Some(5000), Some(9000)).unwrap();
+        assert!(range.contains(&subtitles.cues[1]));
+        assert!(!range.contains(&subtitles.cues[3]));
+        let (_, cut) = subtitles.within(&range, |part| part.shift(1500));
+        // The last cue of the range is cut where the cue after the range starts.
+        assert_eq!(
+            times(&subtitles),
+            [
+                (1, 1000, 2000),
+                (2, 6500, 7500),
+                (3, 8500, 9000),
+                (4, 9000, 10000)
+            ]
+        );
+        assert_eq!(cut, 1);
+    }
+
+    #[test]
+    fn cues_pushed_past_the_cues_around_a_range_are_removed() {
+        let mut subtitles = file(&[(1000, 2000), (3000, 4000), (5000, 6000)]);
+        let range = TimeRange::new(Some(2500), Some(4500)).unwrap();
+        let (_, cut) = subtitles.within(&range, |part| part.shift(-2500));
+        assert_eq!(times(&subtitles), [(1, 1000, 2000), (3, 5000, 6000)]);
+        assert_eq!(cut, 1);
+        assert!(TimeRange::new(Some(2000), Some(2000)).is_err());
+        assert!(TimeRange::default().is_all());
+    }
+
+    #[test]
+    fn halves_are_rounded_the_way_asked() {
+        assert_eq!(Rounding::Nearest.apply(2.5), 3);
+        assert_eq!(Rounding::Even.apply(2.5), 2);
+        assert_eq!(Rounding::Even.apply(3.5), 4);
+        assert_eq!(Rounding::Floor.apply(-0.5), -1);
+        assert_eq!(Rounding::Ceil.apply(-0.5), 0);
+        assert!(Rounding::from_name("up", 25.0).is_err());
+    }
+
+    #[test]
+    fn frame_rounding_converts_back_and_forth_losslessly() {
+        let pal: Vec<(i32, i32)> = (1..50).map(|i| (i * 1040, i * 1040 + 680)).collect();
+        let mut subtitles = file(&pal);
+        let film = Rounding::from_name("frame", 23.976).unwrap();
+        subtitles.scale_rounded(25.0 / (24000.0 / 1001.0), 0, film);
+        // Every time is on a frame of 24000/1001 fps.
+        assert!(subtitles.cues.iter().all(|cue| {
+            let frames = cue.start_ms as f64 * 24.0 / 1001.0;
+            (frames - frames.round()).abs() < 0.03
+        }));
+        let back = Rounding::from_name("frame", 25.0).unwrap();
+        subtitles.scale_rounded(24000.0 / 1001.0 / 25.0, 0, back);
+        assert_eq!(subtitles, file(&pal));
+    }
+
+    #[test]
+    fn warps_follow_the_nearest_section() {
+        let mut anchors = vec![(60000, 62000), (0, 1000)];
+        check_anchors(&mut anchors).unwrap();
+        // From 0 to 1:00 times stretch by 61/60, and keep stretching after the last anchor.
+        assert_eq!(warp_time(&anchors, 0), 1000);
+        assert_eq!(warp_time(&anchors, 30000), 31500);
+        assert_eq!(warp_time(&anchors, 120000), 123000);
+        assert_eq!(warp_time(&anchors, -6000), -5100);
+        assert_eq!(warp_time(&[(1000, 500)], 3000), 2500);
+        assert!(check_anchors(&mut [(0, 1000), (0, 2000)]).is_err());
+        assert!(check_anchors(&mut [(0, 2000), (1000, 1500)]).is_err());
+        assert!(check_anchors(&mut []).is_err());
+    }
+
+    #[test]
+    fn warps_past_the_last_representable_time_are_refused() {
+        let subtitles = file(&[(1000, 2000), (500_000, 501_000)]);
+        assert!(subtitles
+            .check_warp(&[(0, 0), (1000, 2_000_000_000)])
+            .is_err());
+        assert!(subtitles.check_warp(&[(0, 2_147_000_000)]).is_err());
+        assert!(subtitles.check_warp(&[(0, 0), (1000, 2000)]).is_ok());
+        assert_eq!(warp_time(&[(-2_000_000_000, 0)], 2_000_000_000), i32::MAX);
+    }
+
+    #[test]
+    fn kept_durations_are_cut_only_where_they_run_into_the_next_cue() {
+        let mut subtitles = file(&[(1000, 3000), (4000, 6000), (5000, 5500)]);
+        assert_eq!(subtitles.scale_starts(0.5, 0, Rounding::Nearest), 1);
+        // The second cue already overlapped the third one, and keeps doing so.
+        assert_eq!(
+            times(&subtitles),
+            [(1, 500, 2000), (2, 2000, 4000), (3, 2500, 3000)]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use regex::Regex;
+
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+// Create a function to parse the contents of a TMPlayer file: one hh:mm:ss:text line per cue.
+// TMPlayer has no end times, so a cue lasts the default duration, or until the next cue starts.
+pub fn parse(contents: &str, default_duration: i32) -> Result<SubtitleFile, String> {
+    let re = Regex::new(r"^(\d{1,2}):(\d{2}):(\d{2})[:=](.*)$").unwrap();
+    let mut cues: Vec<Cue> = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let caps = re
+            .captures(line.trim())
+            .ok_or_else(|| format!("Invalid TMPlayer line '{}'", line))?;
+        let hours = caps[1].parse::<i32>().map_err(|e| e.to_string())?;
+        let minutes = caps[2].parse::<i32>().map_err(|e| e.to_string())?;
+        let seconds = caps[3].parse::<i32>().map_err(|e| e.to_string())?;
+        let start_ms = (hours * 3600 + minutes * 60 + seconds) * 1000;
+        if let Some(previous) = cues.last_mut() {
+            previous.end_ms = previous.end_ms.min(start_ms).max(previous.start_ms);
+        }
+        cues.push(Cue {
+            index: cues.len() + 1,
+            start_ms,
+            end_ms: start_ms + default_duration,
+            lines: caps[4].split('|').map(|text| text.to_string()).collect(),
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::Tmp,
+        framerate: None,
+        header: Vec::new(),
+        cues,
+        trailer: Vec::new(),
+    })
+}
+
+// Create a function to write the cues out as TMPlayer, rounding start times to the nearest second.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let mut output = String::new();
+    for cue in &file.cues {
+        let seconds = (cue.start_ms.max(0) + 500) / 1000;
+        output.push_str(&format!(
+            "{:02}:{:02}:{:02}:{}\n",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            cue.lines.join("|")
+        ));
+    }
+    output
+}
+
+// The TMPlayer format, as registered in the format registry.
+pub struct Tmp;
+
+impl SubtitleFormat for Tmp {
+    fn format(&self) -> Format {
+        Format::Tmp
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["tmplayer", "tmp"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["tmp"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: false,
+            positions: false,
+            frame_based: false,
+            end_times: false,
+        }
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        Regex::new(r"^\d{1,2}:\d{2}:\d{2}[:=]")
+            .unwrap()
+            .is_match(&sniff_text(contents))
+    }
+
+    fn parse(&self, contents: &[u8], options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?, options.default_duration)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TMP: &str = "00:00:01:Hello.\n00:00:02:Right after|on two lines.\n01:00:00:Much later.\n";
+
+    #[test]
+    fn cues_end_after_the_default_duration_or_at_the_next_cue() {
+        let file = parse(TMP, 3000).unwrap();
+        let times: Vec<(i32, i32)> = file
+            .cues
+            .iter()
+            .map(|cue| (cue.start_ms, cue.end_ms))
+            .collect();
+        assert_eq!(times, [(1000, 2000), (2000, 5000), (3600000, 3603000)]);
+        assert_eq!(file.cues[1].lines, ["Right after", "on two lines."]);
+    }
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        assert_eq!(serialize(&parse(TMP, 3000).unwrap()), TMP);
+        let file = parse("0:00:01=Equals sign\n", 3000).unwrap();
+        assert_eq!(serialize(&file), "00:00:01:Equals sign\n");
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert!(parse("00:01:Hello.\n", 3000).is_err());
+        assert!(parse("00:00:01 Hello.\n", 3000).is_err());
+    }
+}
@@ -0,0 +1,428 @@
+use regex::Regex;
+
+use super::timecode::TimecodeRate;
+use super::{
+    decode_text, sniff_text, Capabilities, Cue, Format, ParseOptions, SubtitleFile, SubtitleFormat,
+};
+
+const DEFAULT_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" xml:lang=\"en\">
+<body>
+<div>";
+
+const DEFAULT_TRAILER: &str = "</div>
+</body>
+</tt>";
+
+// Create a function to build the header of an iTT file converted from another format, counting SMPTE
+// timecodes at the file's framerate (29.97 when it has none).
+fn itt_header(framerate: Option<f32>) -> String {
+    let rate = TimecodeRate::new(
+        framerate.map_or(30000.0 / 1001.0, |rate| rate as f64),
+        false,
+    )
+    .unwrap_or(TimecodeRate {
+        framerate: 30.0,
+        drop_frame: false,
+    });
+    let nominal = rate.framerate.round();
+    let multiplier = if nominal != rate.framerate {
+        " ttp:frameRateMultiplier=\"1000 1001\""
+    } else {
+        ""
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" xml:lang=\"en\" ttp:timeBase=\"smpte\" ttp:frameRate=\"{}\"{} ttp:dropMode=\"nonDrop\">
+<body>
+<div>",
+        nominal, multiplier
+    )
+}
+
+// Timing parameters declared on the <tt> element, needed to read frame and tick based times.
+struct TimingParameters {
+    frame_rate: f64,
+    tick_rate: Option<f64>,
+    // Set when ttp:timeBase is smpte: clock times with frames are timecodes, drop-frame when
+    // ttp:dropMode says so.
+    timecode: Option<TimecodeRate>,
+}
+
+// Create a function to read the ttp:frameRate, ttp:frameRateMultiplier, ttp:tickRate, ttp:timeBase and
+// ttp:dropMode attributes.
+fn timing_parameters(header: &str, framerate: Option<f32>) -> TimingParameters {
+    let attribute = |name: &str| {
+        Regex::new(&format!(r#"ttp:{}\s*=\s*"([^"]*)""#, name))
+            .unwrap()
+            .captures(header)
+            .map(|caps| caps[1].to_string())
+    };
+    let mut frame_rate = attribute("frameRate")
+        .and_then(|rate| rate.trim().parse::<f64>().ok())
+        .unwrap_or(30.0);
+    if let Some(multiplier) = attribute("frameRateMultiplier") {
+        let parts: Vec<f64> = multiplier
+            .split_whitespace()
+            .filter_map(|part| part.parse::<f64>().ok())
+            .collect();
+        if parts.len() == 2 && parts[1] != 0.0 {
+            frame_rate = frame_rate * parts[0] / parts[1];
+        }
+    }
+    if let Some(framerate) = framerate {
+        frame_rate = framerate as f64;
+    }
+    let timecode = match attribute("timeBase") {
+        Some(base) if base.trim() == "smpte" => {
+            let drop_frame = attribute("dropMode").is_some_and(|mode| mode.starts_with("drop"));
+            TimecodeRate::new(frame_rate, drop_frame)
+                .or_else(|_| TimecodeRate::new(frame_rate, false))
+                .ok()
+        }
+        _ => None,
+    };
+    TimingParameters {
+        frame_rate,
+        tick_rate: attribute("tickRate").and_then(|rate| rate.trim().parse::<f64>().ok()),
+        timecode,
+    }
+}
+
+// Create a function to convert a TTML time expression (clock time or offset time) to miliseconds.
+fn convert_to_miliseconds(time: &str, parameters: &TimingParameters) -> Option<i32> {
+    let clock = Regex::new(r"^(\d+):(\d{2}):(\d{2})(?:(\.\d+)|:(\d+)(?:\.\d+)?)?$").unwrap();
+    let offset = Regex::new(r"^(\d+(?:\.\d+)?)(h|ms|m|s|f|t)$").unwrap();
+    let time = time.trim();
+    let seconds = if let Some(caps) = clock.captures(time) {
+        let hours = caps[1].parse::<f64>().ok()?;
+        let minutes = caps[2].parse::<f64>().ok()?;
+        let seconds = caps[3].parse::<f64>().ok()?;
+        let fraction = match (caps.get(4), caps.get(5)) {
+            (Some(fraction), _) => fraction.as_str().parse::<f64>().ok()?,
+            (None, Some(frames)) if parameters.timecode.is_some() => {
+                let rate = parameters.timecode.unwrap();
+                let field = |i: usize| caps[i].parse::<i64>().ok();
+                let frames = frames.as_str().parse::<i64>().ok()?;
+                let total = rate.label_to_frames(field(1)?, field(2)?, field(3)?, frames);
+                return Some(rate.frames_to_ms(total));
+            }
+            (None, Some(frames)) => frames.as_str().parse::<f64>().ok()? / parameters.frame_rate,
+            _ => 0.0,
+        };
+        hours * 3600.0 + minutes * 60.0 + seconds + fraction
+    } else {
+        let caps = offset.captures(time)?;
+        let value = caps[1].parse::<f64>().ok()?;
+        match &caps[2] {
+            "h" => value * 3600.0,
+            "m" => value * 60.0,
+            "s" => value,
+            "ms" => value / 1000.0,
+            "f" => value / parameters.frame_rate,
+            _ => value / parameters.tick_rate.unwrap_or(1.0),
+        }
+    };
+    Some((seconds * 1000.0).round() as i32)
+}
+
+// Create a function to convert miliseconds to a hh:mm:ss.mmm clock time.
+fn convert_to_time(miliseconds: i32) -> String {
+    super::vtt::convert_to_time(miliseconds)
+}
+
+// Create a function to turn the markup of a <p> element into subtitle lines.
+// Italic and bold spans become <i> and <b> tags, other markup is dropped.
+fn parse_text(text: &str) -> Vec<String> {
+    let tag = Regex::new(r"<(/?)([\w:]+)([^>]*?)/?>").unwrap();
+    let mut lines = vec![String::new()];
+    let mut spans = Vec::new();
+    let mut position = 0;
+    for caps in tag.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        lines
+            .last_mut()
+            .unwrap()
+            .push_str(&text[position..whole.start()]);
+        position = whole.end();
+        let closing = &caps[1] == "/";
+        let name = caps[2].rsplit(':').next().unwrap();
+        let line = lines.last_mut().unwrap();
+        match name {
+            "br" => lines.push(String::new()),
+            "span" if closing => line.push_str(spans.pop().flatten().unwrap_or("")),
+            "span" => {
+                let attributes = &caps[3];
+                if attributes.contains("fontStyle=\"italic\"") {
+                    line.push_str("<i>");
+                    spans.push(Some("</i>"));
+                } else if attributes.contains("fontWeight=\"bold\"") {
+                    line.push_str("<b>");
+                    spans.push(Some("</b>"));
+                } else {
+                    spans.push(None);
+                }
+            }
+            _ => {}
+        }
+    }
+    lines.last_mut().unwrap().push_str(&text[position..]);
+    lines
+        .iter()
+        .map(|line| {
+            line.split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&")
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+// Create a function to turn a subtitle line back into TTML markup.
+fn serialize_text(line: &str) -> String {
+    let escaped = line
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let tag = Regex::new(r"&lt;(/?)(\w+)[^&]*?&gt;").unwrap();
+    tag.replace_all(&escaped, |caps: &regex::Captures| {
+        match (&caps[1], caps[2].to_lowercase().as_str()) {
+            ("", "i") => "<span tts:fontStyle=\"italic\">".to_string(),
+            ("", "b") => "<span tts:fontWeight=\"bold\">".to_string(),
+            ("/", "i") | ("/", "b") => "</span>".to_string(),
+            _ => String::new(),
+        }
+    })
+    .to_string()
+}
+
+// Create a function to parse the contents of a TTML / DFXP or iTT file.
+// The framerate, if given, overrides ttp:frameRate for frame based times.
+pub fn parse(
+    contents: &str,
+    framerate: Option<f32>,
+    format: Format,
+) -> Result<SubtitleFile, String> {
+    let paragraph = Regex::new(r"(?s)<p(\s[^>]*)?>(.*?)</p>").unwrap();
+    let attribute = Regex::new(r#"([\w:]+)\s*=\s*"([^"]*)""#).unwrap();
+    let first = paragraph
+        .find(contents)
+        .ok_or_else(|| "No <p> elements found".to_string())?;
+    let header = contents[..first.start()].trim_end().to_string();
+    let parameters = timing_parameters(&header, framerate);
+    let mut cues = Vec::new();
+    let mut last_end = first.start();
+    for caps in paragraph.captures_iter(contents) {
+        last_end = caps.get(0).unwrap().end();
+        let index = cues.len() + 1;
+        let mut begin = None;
+        let mut end = None;
+        let mut duration = None;
+        let mut settings = Vec::new();
+        for attribute in attribute.captures_iter(caps.get(1).map_or("", |m| m.as_str())) {
+            let time = || {
+                convert_to_miliseconds(&attribute[2], &parameters).ok_or_else(|| {
+                    format!("Cue {}: invalid time expression '{}'", index, &attribute[2])
+                })
+            };
+            match &attribute[1] {
+                "begin" => begin = Some(time()?),
+                "end" => end = Some(time()?),
+                "dur" => duration = Some(time()?),
+                _ => settings.push(attribute[0].to_string()),
+            }
+        }
+        let start_ms = begin.ok_or_else(|| format!("Cue {}: missing begin attribute", index))?;
+        let end_ms = match (end, duration) {
+            (Some(end), _) => end,
+            (None, Some(duration)) => start_ms + duration,
+            (None, None) => return Err(format!("Cue {}: missing end or dur attribute", index)),
+        };
+        cues.push(Cue {
+            index,
+            start_ms,
+            end_ms,
+            settings: Some(settings.join(" ")).filter(|settings| !settings.is_empty()),
+            lines: parse_text(&caps[2]),
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format,
+        framerate: parameters.timecode.map(|rate| rate.framerate as f32),
+        header: vec![header],
+        cues,
+        trailer: vec![contents[last_end..].trim().to_string()],
+    })
+}
+
+// Create a function to write the cues out as TTML, or iTT when format is Format::Itt. Documents with
+// an SMPTE ttp:timeBase are written with timecodes, documents that declare a ttp:tickRate with tick
+// times, others with clock times.
+pub fn serialize(file: &SubtitleFile, format: Format) -> String {
+    let is_ttml = matches!(file.format, Format::Ttml | Format::Itt);
+    let header = match file.header.first() {
+        Some(header) if is_ttml => header.clone(),
+        _ if format == Format::Itt => itt_header(file.framerate),
+        _ => DEFAULT_HEADER.to_string(),
+    };
+    let trailer = match file.trailer.first() {
+        Some(trailer) if is_ttml => trailer.as_str(),
+        _ => DEFAULT_TRAILER,
+    };
+    let parameters = timing_parameters(&header, None);
+    // TTML separates the frames with a colon even in drop-frame timecodes.
+    let time = |miliseconds: i32| match (parameters.timecode, parameters.tick_rate) {
+        (Some(rate), _) => rate.format(miliseconds).replace(';', ":"),
+        (None, Some(tick_rate)) => {
+            format!("{}t", (miliseconds as f64 * tick_rate / 1000.0).round())
+        }
+        (None, None) => convert_to_time(miliseconds),
+    };
+    let mut output = format!("{}\n", header);
+    for cue in &file.cues {
+        let settings = match &cue.settings {
+            Some(settings) if is_ttml => format!(" {}", settings),
+            _ => String::new(),
+        };
+        let text: Vec<String> = cue.lines.iter().map(|line| serialize_text(line)).collect();
+        output.push_str(&format!(
+            "<p begin=\"{}\" end=\"{}\"{}>{}</p>\n",
+            time(cue.start_ms),
+            time(cue.end_ms),
+            settings,
+            text.join("<br/>")
+        ));
+    }
+    output.push_str(trailer);
+    output.push('\n');
+    output
+}
+
+// The TTML formats, as registered in the format registry: Format::Ttml or Format::Itt.
+pub struct Ttml(pub Format);
+
+impl SubtitleFormat for Ttml {
+    fn format(&self) -> Format {
+        self.0
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        if self.0 == Format::Itt {
+            &["itt"]
+        } else {
+            &["ttml", "dfxp"]
+        }
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        if self.0 == Format::Itt {
+            &["itt"]
+        } else {
+            &["ttml", "dfxp"]
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: true,
+            frame_based: self.0 == Format::Itt,
+            end_times: true,
+        }
+    }
+
+    // iTT files are told apart by their SMPTE time base.
+    fn detect(&self, contents: &[u8]) -> bool {
+        let contents = sniff_text(contents);
+        let smpte = Regex::new(r#"ttp:timeBase\s*=\s*"smpte""#).unwrap();
+        Regex::new(r"<tt[\s>]").unwrap().is_match(&contents)
+            && (self.0 == Format::Ttml || smpte.is_match(&contents))
+    }
+
+    fn parse(&self, contents: &[u8], options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?, options.framerate, self.0)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file, self.0).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(attributes: &str, paragraphs: &str) -> String {
+        format!(
+            "<tt xmlns=\"http://www.w3.org/ns/ttml\" {}>\n<body>\n<div>\n{}</div>\n</body>\n</tt>",
+            attributes, paragraphs
+        )
+    }
+
+    fn times(file: &SubtitleFile) -> Vec<(i32, i32)> {
+        file.cues
+            .iter()
+            .map(|cue| (cue.start_ms, cue.end_ms))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_is_unchanged() {
+        let ttml = document(
+            "xml:lang=\"en\"",
+            "<p begin=\"00:00:01.000\" end=\"00:00:02.500\" region=\"bottom\">Hello <span tts:fontStyle=\"italic\">there</span><br/>&amp; welcome</p>\n",
+        );
+        let file = parse(&ttml, None, Format::Ttml).unwrap();
+        assert_eq!(file.cues[0].lines, ["Hello <i>there</i>", "& welcome"]);
+        assert_eq!(file.cues[0].settings.as_deref(), Some("region=\"bottom\""));
+        assert_eq!(serialize(&file, Format::Ttml), ttml + "\n");
+    }
+
+    #[test]
+    fn offset_times_use_the_declared_rates() {
+        let ttml = document(
+            "ttp:frameRate=\"25\" ttp:tickRate=\"10000000\"",
+            "<p begin=\"25f\" dur=\"1.5s\">One</p>\n<p begin=\"30000000t\" end=\"3500ms\">Two</p>\n<p begin=\"0.1h\" end=\"6.5m\">Three</p>\n",
+        );
+        let file = parse(&ttml, None, Format::Ttml).unwrap();
+        assert_eq!(times(&file), [(1000, 2500), (3000, 3500), (360000, 390000)]);
+    }
+
+    #[test]
+    fn drop_frame_timecodes_skip_labels_at_minute_boundaries() {
+        let ttml = document(
+            "ttp:timeBase=\"smpte\" ttp:frameRate=\"30\" ttp:frameRateMultiplier=\"1000 1001\" ttp:dropMode=\"dropNTSC\"",
+            "<p begin=\"00:00:59:29\" end=\"00:01:00:02\">Minute</p>\n<p begin=\"00:09:59:29\" end=\"00:10:00:00\">Ten minutes</p>\n",
+        );
+        let file = parse(&ttml, None, Format::Ttml).unwrap();
+        let rate = TimecodeRate::ntsc(true);
+        assert_eq!(
+            times(&file),
+            [
+                (rate.frames_to_ms(1799), rate.frames_to_ms(1800)),
+                (rate.frames_to_ms(17981), rate.frames_to_ms(17982)),
+            ]
+        );
+        // The timecodes are written back as they were.
+        let output = serialize(&file, Format::Ttml);
+        assert!(output.contains("begin=\"00:00:59:29\" end=\"00:01:00:02\""));
+        assert!(output.contains("begin=\"00:09:59:29\" end=\"00:10:00:00\""));
+    }
+
+    #[test]
+    fn malformed_documents_are_errors() {
+        assert!(parse(&document("", ""), None, Format::Ttml).is_err());
+        let missing_begin = document("", "<p end=\"00:00:01.000\">Text</p>\n");
+        assert!(parse(&missing_begin, None, Format::Ttml).is_err());
+        let missing_end = document("", "<p begin=\"00:00:01.000\">Text</p>\n");
+        assert!(parse(&missing_end, None, Format::Ttml).is_err());
+        let bad_time = document("", "<p begin=\"1 second\" end=\"2s\">Text</p>\n");
+        assert!(parse(&bad_time, None, Format::Ttml).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+// Voice activity detection: finds the parts of a soundtrack where someone speaks, from the energy of
+// short frames of audio. Audio is fed in as 16-bit samples of a single channel.
+
+// Length of the frames whose energy is measured, in miliseconds.
+pub const FRAME_MS: i32 = 20;
+
+// How speech is told apart from silence and background noise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VadOptions {
+    // How far above the noise floor a frame has to be to count as speech, in dB.
+    pub margin_db: f32,
+    // Shortest speech interval kept, in miliseconds.
+    pub min_speech_ms: i32,
+    // Pauses shorter than this are bridged, in miliseconds.
+    pub min_gap_ms: i32,
+}
+
+impl Default for VadOptions {
+    fn default() -> VadOptions {
+        VadOptions {
+            margin_db: 10.0,
+            min_speech_ms: 200,
+            min_gap_ms: 300,
+        }
+    }
+}
+
+// Measures the energy of consecutive frames of audio, fed in chunks of any size. Samples go through a
+// pre-emphasis filter first, so the low rumble of music and effects weighs less than voices.
+pub struct EnergyMeter {
+    frame_length: usize,
+    previous: f32,
+    sum: f64,
+    count: usize,
+    energies: Vec<f32>,
+}
+
+impl EnergyMeter {
+    // Create a function to build a meter for audio at a sample rate.
+    pub fn new(sample_rate: u32) -> EnergyMeter {
+        EnergyMeter {
+            frame_length: (sample_rate as usize * FRAME_MS as usize / 1000).max(1),
+            previous: 0.0,
+            sum: 0.0,
+            count: 0,
+            energies: Vec::new(),
+        }
+    }
+
+    // Create a function to add samples to the measure.
+    pub fn push(&mut self, samples: &[i16]) {
+        for sample in samples {
+            let sample = *sample as f32 / 32768.0;
+            let filtered = sample - 0.97 * self.previous;
+            self.previous = sample;
+            self.sum += (filtered * filtered) as f64;
+            self.count += 1;
+            if self.count == self.frame_length {
+                self.energies
+                    .push((10.0 * (self.sum / self.count as f64 + 1e-10).log10()) as f32);
+                self.sum = 0.0;
+                self.count = 0;
+            }
+        }
+    }
+
+    // Create a function to get the energy of every frame, in dB. A last partial frame is dropped.
+    pub fn finish(self) -> Vec<f32> {
+        self.energies
+    }
+}
+
+// Create a function to find the speech intervals of a soundtrack from the energy of its frames, as
+// start and end times in miliseconds. The noise floor is the 10th percentile of the energies; frames
+// more than the margin above it are speech, short pauses are bridged and short bursts dropped.
+pub fn speech_intervals(energies: &[f32], options: &VadOptions) -> Vec<(i32, i32)> {
+    if energies.is_empty() {
+        return Vec::new();
+    }
+    let mut sorted: Vec<f32> = energies.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let floor = sorted[sorted.len() / 10];
+    let peak = sorted[sorted.len() * 99 / 100];
+    // Audio without quiet parts would have no speech at all, so the threshold stays under the peaks.
+    let threshold = (floor + options.margin_db).min(peak - 3.0);
+
+    let mut intervals: Vec<(i32, i32)> = Vec::new();
+    let mut start = None;
+    for (i, energy) in energies.iter().enumerate() {
+        let time = i as i32 * FRAME_MS;
+        match (start, *energy > threshold) {
+            (None, true) => start = Some(time),
+            (Some(from), false) => {
+                intervals.push((from, time));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        intervals.push((from, energies.len() as i32 * FRAME_MS));
+    }
+
+    let mut bridged: Vec<(i32, i32)> = Vec::new();
+    for (start, end) in intervals {
+        match bridged.last_mut() {
+            Some(last) if start - last.1 < options.min_gap_ms => last.1 = end,
+            _ => bridged.push((start, end)),
+        }
+    }
+    bridged.retain(|(start, end)| end - start >= options.min_speech_ms);
+    bridged
+}
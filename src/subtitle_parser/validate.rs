@@ -0,0 +1,1693 @@
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use super::{encoding, language, vtt, Cue, SubtitleFile};
+
+// Cues shorter than this, in miliseconds, flash by too fast to be read.
+const MIN_DURATION: f64 = 100.0;
+
+// Cues longer than this, in miliseconds, usually lost their end time.
+const MAX_DURATION: f64 = 10000.0;
+
+// Cues among the first or last ones of a file, where release groups put their credits and ads.
+const EDGE_CUES: usize = 3;
+
+// Few viewers can read faster than this, in characters per second; style guides set lower limits.
+const MAX_CPS: f64 = 25.0;
+
+// How bad a problem is: errors break playback in most players, warnings only make the subtitles harder
+// to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    // Create a function to name a severity, as printed in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    // Create a function to read a severity from its name, None for "off", which turns a rule off.
+    pub fn from_name(name: &str) -> Result<Option<Severity>, String> {
+        match name.to_lowercase().as_str() {
+            "warning" => Ok(Some(Severity::Warning)),
+            "error" => Ok(Some(Severity::Error)),
+            "off" => Ok(None),
+            _ => Err(format!(
+                "Unknown severity '{}', expected error, warning or off",
+                name
+            )),
+        }
+    }
+}
+
+// The limit a rule checks and how bad breaking it is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    pub value: f64,
+    pub severity: Severity,
+}
+
+impl Limit {
+    // Create a function to make a limit whose breaking is a warning.
+    pub fn warning(value: f64) -> Limit {
+        Limit {
+            value,
+            severity: Severity::Warning,
+        }
+    }
+}
+
+// Line limits of a language, which replace the general ones for subtitles in that language: CJK
+// characters are wider, so fewer fit on a line.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LineLimits {
+    pub max_lines: Option<Limit>,
+    pub max_chars_per_line: Option<Limit>,
+}
+
+// The rules cues are checked against, each turned off when None. Cues that end before they start, start
+// before the start of the video or before the cue ahead of them are always errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    // Most characters per second, without formatting tags and line breaks, and most words per minute.
+    pub max_cps: Option<Limit>,
+    pub max_wpm: Option<Limit>,
+    // Shortest and longest duration of a cue, in miliseconds.
+    pub min_duration: Option<Limit>,
+    pub max_duration: Option<Limit>,
+    pub max_lines: Option<Limit>,
+    pub max_chars_per_line: Option<Limit>,
+    // Shortest time between the end of a cue and the start of the next one, in miliseconds.
+    pub min_gap: Option<Limit>,
+    // Cues that overlap the next one, cues without text, and cues not numbered 1, 2, 3... in order.
+    pub overlap: Option<Severity>,
+    pub empty: Option<Severity>,
+    pub numbering: Option<Severity>,
+    // Text damaged by decoding it from the wrong encoding.
+    pub mojibake: Option<Severity>,
+    // Ads, web addresses and release group credits, see find_ads.
+    pub ads: Option<Severity>,
+    // The ISO 639-1 code of the language the subtitles must be in, see language::normalize.
+    pub expected_language: Option<&'static str>,
+    // Line limits by language code (ja, zh, ...), see for_language.
+    pub languages: BTreeMap<String, LineLimits>,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            max_cps: Some(Limit::warning(MAX_CPS)),
+            max_wpm: None,
+            min_duration: Some(Limit::warning(MIN_DURATION)),
+            max_duration: Some(Limit::warning(MAX_DURATION)),
+            max_lines: None,
+            max_chars_per_line: None,
+            min_gap: None,
+            overlap: Some(Severity::Warning),
+            empty: Some(Severity::Warning),
+            numbering: Some(Severity::Warning),
+            mojibake: Some(Severity::Warning),
+            ads: Some(Severity::Warning),
+            expected_language: None,
+            languages: BTreeMap::new(),
+        }
+    }
+}
+
+impl Rules {
+    // Create a function to get the rules of a style guide, by name: the reading speed, line length and
+    // duration limits of its delivery specifications, breaking which is an error. Overlaps and cues
+    // without text stay warnings.
+    //     netflix = the Netflix timed text style guide for English: 20 characters per second, 2 lines of
+    //         42 characters, from 5/6 of a second to 7 seconds, 2 frames between cues at 24 fps; lines of
+    //         13 characters in Japanese, 16 in Chinese and Korean
+    //     bbc = the BBC subtitle guidelines: 180 words per minute, 2 lines of 37 characters, from 1 to 7
+    //         seconds, 1 frame between cues at 25 fps
+    //     ebu = teletext subtitles after EBU Tech 3264 (EBU STL): 15 characters per second, 2 lines of
+    //         38 characters, from 1 to 7 seconds, 2 frames between cues at 25 fps
+    pub fn preset(name: &str) -> Result<Rules, String> {
+        // The reading speed is in characters per second, or in words per minute for the BBC.
+        let (max_cps, max_wpm, max_chars_per_line, min_duration, min_gap) =
+            match name.to_lowercase().as_str() {
+                "netflix" => (Some(20.0), None, 42.0, 833.0, 83.0),
+                "bbc" => (None, Some(180.0), 37.0, 1000.0, 40.0),
+                "ebu" => (Some(15.0), None, 38.0, 1000.0, 80.0),
+                _ => {
+                    return Err(format!(
+                        "Unknown preset '{}', expected netflix, bbc or ebu",
+                        name
+                    ))
+                }
+            };
+        let error = |value: f64| {
+            Some(Limit {
+                value,
+                severity: Severity::Error,
+            })
+        };
+        let languages = match name.to_lowercase().as_str() {
+            "netflix" => [("ja", 13.0), ("ko", 16.0), ("zh", 16.0)]
+                .into_iter()
+                .map(|(language, max_chars_per_line)| {
+                    (
+                        language.to_string(),
+                        LineLimits {
+                            max_lines: error(2.0),
+                            max_chars_per_line: error(max_chars_per_line),
+                        },
+                    )
+                })
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+        Ok(Rules {
+            languages,
+            max_cps: max_cps.and_then(error),
+            max_wpm: max_wpm.and_then(error),
+            min_duration: error(min_duration),
+            max_duration: error(7000.0),
+            max_lines: error(2.0),
+            max_chars_per_line: error(max_chars_per_line),
+            min_gap: error(min_gap),
+            ..Rules::default()
+        })
+    }
+
+    // Create a function to get the rules for subtitles in a language, its line limits replacing the
+    // general ones. Regional variants use the limits of their language (zh for zh-TW), and the
+    // three-letter codes of CJK languages their two-letter ones.
+    pub fn for_language(&self, language: Option<&str>) -> Rules {
+        let mut rules = self.clone();
+        let limits = language.and_then(|language| {
+            let language = language.to_lowercase();
+            let primary = match language.split(['-', '_']).next().unwrap_or_default() {
+                "jpn" => "ja",
+                "chi" | "zho" => "zh",
+                "kor" => "ko",
+                primary => primary,
+            };
+            self.languages
+                .get(&language)
+                .or_else(|| self.languages.get(primary))
+        });
+        if let Some(limits) = limits {
+            rules.max_lines = limits.max_lines;
+            rules.max_chars_per_line = limits.max_chars_per_line;
+        }
+        rules
+    }
+}
+
+// Create a function to tell the language of a subtitle file from its name, as in movie.ja.srt or
+// movie.zh-TW.srt.
+pub fn language_from_name(name: &str) -> Option<String> {
+    let stem = Path::new(name).file_stem()?.to_str()?;
+    let language = Regex::new(r"\.([a-zA-Z]{2,3}(?:[-_][a-zA-Z]{2,4})?)$").unwrap();
+    language
+        .captures(stem)
+        .map(|captures| captures[1].to_lowercase())
+}
+
+// What is wrong with a cue, with the numbers the rule compared.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    EndsBeforeStart,
+    StartsBeforeZero,
+    // Starts before the cue ahead of it in the file, numbered previous.
+    OutOfOrder {
+        previous: usize,
+    },
+    MinDuration {
+        duration_ms: i32,
+        limit_ms: f64,
+    },
+    MaxDuration {
+        duration_ms: i32,
+        limit_ms: f64,
+    },
+    // Still shown when the cue numbered other starts.
+    Overlap {
+        other: usize,
+    },
+    MinGap {
+        next: usize,
+        gap_ms: i32,
+        limit_ms: f64,
+    },
+    // Numbered from something else than 1.
+    Numbering {
+        expected: usize,
+    },
+    // Has the index of a cue before it in the file; position counts the cues from 1, in the file.
+    DuplicateIndex {
+        position: usize,
+    },
+    // The cues numbered first to last are in no place of the file, the cue being the next one.
+    MissingIndices {
+        first: usize,
+        last: usize,
+    },
+    // Numbered lower than the cue before it in the file, numbered previous.
+    IndexNotIncreasing {
+        previous: usize,
+    },
+    Empty,
+    // UTF-8 text read as the encoding named read_as, with the damaged sequences found and what they were.
+    Mojibake {
+        read_as: &'static str,
+        sequences: Vec<(String, String)>,
+    },
+    // An ad or release credit rather than dialogue, with why it looks like one.
+    Ad {
+        reasons: Vec<&'static str>,
+    },
+    // Holds replacement characters (�) for bytes that weren't valid in the encoding the file was read as.
+    Undecodable,
+    // The subtitles are mostly in another language than the expected one, both ISO 639-1 codes.
+    WrongLanguage {
+        expected: &'static str,
+        found: &'static str,
+        confidence: f64,
+    },
+    // Too little text to tell the language of the subtitles, the best guess being found.
+    UnknownLanguage {
+        expected: &'static str,
+        found: Option<&'static str>,
+    },
+    MaxCps {
+        cps: f64,
+        limit: f64,
+    },
+    MaxWpm {
+        wpm: f64,
+        limit: f64,
+    },
+    MaxLines {
+        lines: usize,
+        limit: f64,
+    },
+    MaxCharsPerLine {
+        chars: usize,
+        limit: f64,
+    },
+}
+
+impl IssueKind {
+    // Create a function to name the rule broken, as in the rules file (max_cps, overlap, ...).
+    pub fn rule(&self) -> &'static str {
+        match self {
+            IssueKind::EndsBeforeStart => "ends_before_start",
+            IssueKind::StartsBeforeZero => "starts_before_zero",
+            IssueKind::OutOfOrder { .. } => "out_of_order",
+            IssueKind::MinDuration { .. } => "min_duration",
+            IssueKind::MaxDuration { .. } => "max_duration",
+            IssueKind::Overlap { .. } => "overlap",
+            IssueKind::MinGap { .. } => "min_gap",
+            IssueKind::Numbering { .. }
+            | IssueKind::DuplicateIndex { .. }
+            | IssueKind::MissingIndices { .. }
+            | IssueKind::IndexNotIncreasing { .. } => "numbering",
+            IssueKind::Empty => "empty",
+            IssueKind::Mojibake { .. } | IssueKind::Undecodable => "mojibake",
+            IssueKind::Ad { .. } => "ads",
+            IssueKind::WrongLanguage { .. } | IssueKind::UnknownLanguage { .. } => "language",
+            IssueKind::MaxCps { .. } => "max_cps",
+            IssueKind::MaxWpm { .. } => "max_wpm",
+            IssueKind::MaxLines { .. } => "max_lines",
+            IssueKind::MaxCharsPerLine { .. } => "max_chars_per_line",
+        }
+    }
+}
+
+// A problem found in a cue, or in the whole file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub kind: IssueKind,
+    pub severity: Severity,
+    // Index of the cue, as numbered in the file, 0 for the whole file from its first cue to its last.
+    pub cue: usize,
+    pub start_ms: i32,
+    pub end_ms: i32,
+    // How to fix it, as a change to the cue.
+    pub suggestion: String,
+}
+
+impl ValidationIssue {
+    // Create a function to name the rule broken, see IssueKind::rule.
+    pub fn rule(&self) -> &'static str {
+        self.kind.rule()
+    }
+
+    // Create a function to describe the problem in a sentence, as printed in reports.
+    pub fn message(&self) -> String {
+        let cue = self.cue;
+        match &self.kind {
+            IssueKind::EndsBeforeStart => format!("Cue {} ends before it starts", cue),
+            IssueKind::StartsBeforeZero => {
+                format!("Cue {} starts before the start of the video", cue)
+            }
+            IssueKind::OutOfOrder { previous } => {
+                format!("Cue {} starts before cue {}", cue, previous)
+            }
+            IssueKind::MinDuration {
+                duration_ms,
+                limit_ms,
+            } => format!(
+                "Cue {} is very short ({} ms, less than {})",
+                cue, duration_ms, limit_ms
+            ),
+            IssueKind::MaxDuration {
+                duration_ms,
+                limit_ms,
+            } => format!(
+                "Cue {} is very long ({} ms, more than {})",
+                cue, duration_ms, limit_ms
+            ),
+            IssueKind::Overlap { other } => format!("Cue {} overlaps cue {}", cue, other),
+            IssueKind::MinGap { next, gap_ms, .. } => {
+                format!("Cue {} is only {} ms before cue {}", cue, gap_ms, next)
+            }
+            IssueKind::Numbering { expected } => format!(
+                "Cue {} is numbered out of sequence, expected {}",
+                cue, expected
+            ),
+            IssueKind::DuplicateIndex { position } => format!(
+                "Cue {} at position {} has the same index as a cue before it",
+                cue, position
+            ),
+            IssueKind::MissingIndices { first, last } if first == last => {
+                format!("Cue {} is missing before cue {}", first, cue)
+            }
+            IssueKind::MissingIndices { first, last } => {
+                format!("Cues {} to {} are missing before cue {}", first, last, cue)
+            }
+            IssueKind::IndexNotIncreasing { previous } => format!(
+                "Cue {} is numbered lower than cue {} before it",
+                cue, previous
+            ),
+            IssueKind::Empty => format!("Cue {} has no text", cue),
+            IssueKind::Mojibake { read_as, sequences } => {
+                let sequences: Vec<String> = sequences
+                    .iter()
+                    .take(3)
+                    .map(|(damaged, original)| format!("{} for {}", damaged, original))
+                    .collect();
+                format!(
+                    "Cue {} looks like UTF-8 read as {}: {}",
+                    cue,
+                    read_as,
+                    sequences.join(", ")
+                )
+            }
+            IssueKind::WrongLanguage {
+                expected,
+                found,
+                confidence,
+            } => format!(
+                "The subtitles are in {}, not {} ({:.0}% sure)",
+                language::name(found),
+                language::name(expected),
+                confidence * 100.0
+            ),
+            IssueKind::UnknownLanguage { expected, found } => format!(
+                "The subtitles have too little text to tell if they are in {}{}",
+                language::name(expected),
+                found.map_or(String::new(), |found| format!(
+                    ", they look like {}",
+                    language::name(found)
+                ))
+            ),
+            IssueKind::Ad { reasons } => format!(
+                "Cue {} looks like an ad or release credit: {}",
+                cue,
+                reasons.join(", ")
+            ),
+            IssueKind::Undecodable => {
+                format!("Cue {} has characters that couldn't be decoded", cue)
+            }
+            IssueKind::MaxCps { cps, limit } => format!(
+                "Cue {} reads at {:.1} characters per second, more than {}",
+                cue, cps, limit
+            ),
+            IssueKind::MaxWpm { wpm, limit } => format!(
+                "Cue {} reads at {:.0} words per minute, more than {}",
+                cue, wpm, limit
+            ),
+            IssueKind::MaxLines { lines, limit } => {
+                format!("Cue {} has {} lines, more than {}", cue, lines, limit)
+            }
+            IssueKind::MaxCharsPerLine { chars, limit } => format!(
+                "Cue {} has a line of {} characters, more than {}",
+                cue, chars, limit
+            ),
+        }
+    }
+}
+
+// Create a function to check the cues of a file against the rules.
+pub fn check(subtitles: &SubtitleFile, rules: &Rules) -> Vec<ValidationIssue> {
+    let time = vtt::convert_to_time;
+    let mut issues = Vec::new();
+    // Compare cues in the order they are shown, since cues out of order in the file can hide overlaps
+    let mut order: Vec<usize> = (0..subtitles.cues.len()).collect();
+    order.sort_by_key(|i| subtitles.cues[*i].start_ms);
+    let mut rank = vec![0; subtitles.cues.len()];
+    for (position, i) in order.iter().enumerate() {
+        rank[*i] = position;
+    }
+    let ads: BTreeMap<usize, Vec<&'static str>> = match rules.ads {
+        Some(_) => find_ads(subtitles).into_iter().collect(),
+        None => BTreeMap::new(),
+    };
+    let indices: BTreeSet<usize> = subtitles.cues.iter().map(|cue| cue.index).collect();
+    let mut seen = BTreeSet::new();
+    for (i, cue) in subtitles.cues.iter().enumerate() {
+        let mut issue = |kind: IssueKind, severity: Severity, suggestion: String| {
+            issues.push(ValidationIssue {
+                kind,
+                severity,
+                cue: cue.index,
+                start_ms: cue.start_ms,
+                end_ms: cue.end_ms,
+                suggestion,
+            })
+        };
+        let next = order.get(rank[i] + 1).map(|next| &subtitles.cues[*next]);
+        let duration = cue.end_ms.saturating_sub(cue.start_ms);
+        if duration < 0 {
+            issue(
+                IssueKind::EndsBeforeStart,
+                Severity::Error,
+                "correct the start or end time, they may have been swapped".to_string(),
+            );
+        } else if let Some(limit) = rules
+            .min_duration
+            .filter(|limit| (duration as f64) < limit.value)
+        {
+            let end = cue.start_ms.saturating_add(limit.value.ceil() as i32);
+            issue(
+                IssueKind::MinDuration {
+                    duration_ms: duration,
+                    limit_ms: limit.value,
+                },
+                limit.severity,
+                match next.filter(|next| next.start_ms < end) {
+                    Some(next) => format!(
+                        "end it at {}, moving cue {} later or merging the two",
+                        time(end),
+                        next.index
+                    ),
+                    None => format!("end it at {}", time(end)),
+                },
+            );
+        } else if let Some(limit) = rules
+            .max_duration
+            .filter(|limit| duration as f64 > limit.value)
+        {
+            issue(
+                IssueKind::MaxDuration {
+                    duration_ms: duration,
+                    limit_ms: limit.value,
+                },
+                limit.severity,
+                format!(
+                    "end it at {} or split it in two",
+                    time(cue.start_ms.saturating_add(limit.value as i32))
+                ),
+            );
+        }
+        if cue.start_ms < 0 {
+            issue(
+                IssueKind::StartsBeforeZero,
+                Severity::Error,
+                format!("start it at {} or remove it", time(0)),
+            );
+        }
+        if let Some(previous) = i.checked_sub(1).map(|i| &subtitles.cues[i]) {
+            if cue.start_ms < previous.start_ms {
+                issue(
+                    IssueKind::OutOfOrder {
+                        previous: previous.index,
+                    },
+                    Severity::Error,
+                    "sort the cues by start time".to_string(),
+                );
+            }
+        }
+        if let Some(severity) = rules.overlap {
+            // Report every cue shown while this one is, not only the next one
+            for other in order[rank[i] + 1..]
+                .iter()
+                .map(|j| &subtitles.cues[*j])
+                .take_while(|other| other.start_ms < cue.end_ms)
+            {
+                issue(
+                    IssueKind::Overlap { other: other.index },
+                    severity,
+                    format!("end it at {}", time(other.start_ms)),
+                );
+            }
+        }
+        if let Some(next) = next {
+            let gap = next.start_ms.saturating_sub(cue.end_ms);
+            if let Some(limit) = rules
+                .min_gap
+                .filter(|limit| gap >= 0 && (gap as f64) < limit.value)
+            {
+                issue(
+                    IssueKind::MinGap {
+                        next: next.index,
+                        gap_ms: gap,
+                        limit_ms: limit.value,
+                    },
+                    limit.severity,
+                    format!(
+                        "end it at {}",
+                        time(next.start_ms.saturating_sub(limit.value.ceil() as i32))
+                    ),
+                );
+            }
+        }
+        if let Some(severity) = rules.numbering {
+            // Only the breaks in the sequence, not every cue numbered after a missing or extra one.
+            let previous = i.checked_sub(1).map(|i| subtitles.cues[i].index);
+            let mut kinds = Vec::new();
+            if !seen.insert(cue.index) {
+                kinds.push(IssueKind::DuplicateIndex { position: i + 1 });
+            } else {
+                if let Some(previous) = previous.filter(|previous| cue.index < *previous) {
+                    kinds.push(IssueKind::IndexNotIncreasing { previous });
+                }
+                if cue.index > 1 && !indices.contains(&(cue.index - 1)) {
+                    let first = indices
+                        .range(..cue.index)
+                        .next_back()
+                        .map_or(1, |below| below + 1);
+                    kinds.push(IssueKind::MissingIndices {
+                        first,
+                        last: cue.index - 1,
+                    });
+                }
+                if i == 0 && cue.index == 0 {
+                    kinds.push(IssueKind::Numbering { expected: 1 });
+                }
+            }
+            for kind in kinds {
+                issue(
+                    kind,
+                    severity,
+                    "renumber the cues 1, 2, 3... in order".to_string(),
+                );
+            }
+        }
+        let length = cue.text_length();
+        if let (0, Some(severity)) = (length, rules.empty) {
+            issue(IssueKind::Empty, severity, "remove it".to_string());
+        }
+        if let (Some(reasons), Some(severity)) = (ads.get(&i), rules.ads) {
+            issue(
+                IssueKind::Ad {
+                    reasons: reasons.clone(),
+                },
+                severity,
+                "remove it, as --drop-ads does".to_string(),
+            );
+        }
+        if let Some(severity) = rules.mojibake {
+            let text = cue.lines.join("\n");
+            if let Some(mojibake) = encoding::find_mojibake(&text) {
+                issue(
+                    IssueKind::Mojibake {
+                        read_as: mojibake.read_as.name(),
+                        sequences: mojibake.sequences,
+                    },
+                    severity,
+                    "read the file with --input-encoding utf-8, or if it already is, convert its text \
+                     back, it was encoded to UTF-8 twice"
+                        .to_string(),
+                );
+            } else if text.contains('\u{fffd}') {
+                issue(
+                    IssueKind::Undecodable,
+                    severity,
+                    "give the encoding of the file with --input-encoding".to_string(),
+                );
+            }
+        }
+        if let Some(limit) = rules.max_cps.filter(|_| duration > 0) {
+            let cps = cue.chars_per_second() as f64;
+            if cps > limit.value {
+                issue(
+                    IssueKind::MaxCps {
+                        cps,
+                        limit: limit.value,
+                    },
+                    limit.severity,
+                    format!(
+                        "end it at {} or cut the text to {} characters",
+                        time(
+                            cue.start_ms.saturating_add(
+                                (length as f64 * 1000.0 / limit.value).ceil() as i32
+                            )
+                        ),
+                        (limit.value * duration as f64 / 1000.0) as usize
+                    ),
+                );
+            }
+        }
+        if let Some(limit) = rules.max_wpm.filter(|_| duration > 0) {
+            let wpm = cue.words_per_minute() as f64;
+            if wpm > limit.value {
+                issue(
+                    IssueKind::MaxWpm {
+                        wpm,
+                        limit: limit.value,
+                    },
+                    limit.severity,
+                    format!(
+                        "end it at {} or cut the text to {} words",
+                        time(cue.start_ms.saturating_add(
+                            (cue.word_count() as f64 * 60000.0 / limit.value).ceil() as i32
+                        )),
+                        (limit.value * duration as f64 / 60000.0) as usize
+                    ),
+                );
+            }
+        }
+        if let Some(limit) = rules
+            .max_lines
+            .filter(|limit| cue.lines.len() as f64 > limit.value)
+        {
+            issue(
+                IssueKind::MaxLines {
+                    lines: cue.lines.len(),
+                    limit: limit.value,
+                },
+                limit.severity,
+                format!("rewrap it on {} lines or split it in two", limit.value),
+            );
+        }
+        if let Some(limit) = rules.max_chars_per_line {
+            let longest = cue.line_lengths().into_iter().max().unwrap_or(0);
+            if longest as f64 > limit.value {
+                issue(
+                    IssueKind::MaxCharsPerLine {
+                        chars: longest,
+                        limit: limit.value,
+                    },
+                    limit.severity,
+                    format!("break its lines at {} characters at most", limit.value),
+                );
+            }
+        }
+    }
+    if let Some(expected) = rules.expected_language {
+        issues.extend(check_language(subtitles, expected));
+    }
+    issues
+}
+
+// Create a function to check that subtitles are mostly in the expected language, an error when they
+// aren't, as when the wrong file was downloaded, and a warning when they have too little text to tell.
+fn check_language(subtitles: &SubtitleFile, expected: &'static str) -> Option<ValidationIssue> {
+    let identification = language::identify(subtitles);
+    let (kind, severity, suggestion) = match identification {
+        Some(found) if found.language == expected => return None,
+        Some(found) if found.reliable => (
+            IssueKind::WrongLanguage {
+                expected,
+                found: found.language,
+                confidence: found.confidence,
+            },
+            Severity::Error,
+            format!("get the {} subtitles", language::name(expected)),
+        ),
+        found => (
+            IssueKind::UnknownLanguage {
+                expected,
+                found: found.map(|found| found.language),
+            },
+            Severity::Warning,
+            "check the language of the subtitles by hand".to_string(),
+        ),
+    };
+    Some(ValidationIssue {
+        kind,
+        severity,
+        cue: 0,
+        start_ms: subtitles.cues.first().map_or(0, |cue| cue.start_ms),
+        end_ms: subtitles.cues.last().map_or(0, |cue| cue.end_ms),
+        suggestion,
+    })
+}
+
+// A change fix made to a cue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    // Name of the rule the change fixes, see IssueKind::rule.
+    pub rule: &'static str,
+    // Index of the cue, as numbered before the fix.
+    pub cue: usize,
+    pub message: String,
+}
+
+// How overlapping cues are pulled apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlapStrategy {
+    // End the earlier cue when the later one starts.
+    #[default]
+    Trim,
+    // Meet halfway: end the earlier cue and start the later one in the middle of their overlap.
+    Split,
+}
+
+impl OverlapStrategy {
+    // Create a function to pick a strategy from its name on the command line: trim or split.
+    pub fn from_name(name: &str) -> Result<OverlapStrategy, String> {
+        match name.to_lowercase().as_str() {
+            "trim" => Ok(OverlapStrategy::Trim),
+            "split" => Ok(OverlapStrategy::Split),
+            _ => Err(format!(
+                "Unknown overlap strategy '{}', expected trim or split",
+                name
+            )),
+        }
+    }
+}
+
+// Create a function to pull apart the cues that overlap, or that are less than gap miliseconds apart,
+// returning every change made. Cues are taken in the order they are shown, without moving them in the
+// file. Splitting falls back to trimming when a cue is shown all through the one after it, or when
+// starting that one later would take it past the next ones; trimming leaves alone the cues it would
+// leave no time to.
+pub fn fix_overlaps(subtitles: &mut SubtitleFile, strategy: OverlapStrategy, gap: i32) -> Vec<Fix> {
+    let time = vtt::convert_to_time;
+    let mut order: Vec<usize> = (0..subtitles.cues.len()).collect();
+    order.sort_by_key(|i| subtitles.cues[*i].start_ms);
+    let mut fixes = Vec::new();
+    for position in 1..order.len() {
+        let (earlier, later) = (order[position - 1], order[position]);
+        let (end, start) = (
+            subtitles.cues[earlier].end_ms,
+            subtitles.cues[later].start_ms,
+        );
+        if end <= start.saturating_sub(gap) {
+            continue;
+        }
+        // The latest the later cue can start, before it ends and before the cue after it starts.
+        let latest_start = order
+            .get(position + 1)
+            .map_or(i32::MAX, |next| subtitles.cues[*next].start_ms)
+            .min(subtitles.cues[later].end_ms);
+        let middle = ((end as i64 + start as i64) / 2) as i32;
+        let (new_end, new_start) = match strategy {
+            OverlapStrategy::Split
+                if end <= subtitles.cues[later].end_ms
+                    && middle - gap / 2 > subtitles.cues[earlier].start_ms
+                    && (middle - gap / 2).saturating_add(gap) < latest_start =>
+            {
+                (middle - gap / 2, (middle - gap / 2).saturating_add(gap))
+            }
+            _ => (start.saturating_sub(gap), start),
+        };
+        if new_end <= subtitles.cues[earlier].start_ms {
+            continue;
+        }
+        let cue = &mut subtitles.cues[earlier];
+        fixes.push(Fix {
+            rule: "overlap",
+            cue: cue.index,
+            message: format!("end {} -> {}", time(cue.end_ms), time(new_end)),
+        });
+        cue.end_ms = new_end;
+        let cue = &mut subtitles.cues[later];
+        if new_start != cue.start_ms {
+            fixes.push(Fix {
+                rule: "overlap",
+                cue: cue.index,
+                message: format!("start {} -> {}", time(cue.start_ms), time(new_start)),
+            });
+            cue.start_ms = new_start;
+        }
+    }
+    fixes
+}
+
+// Create a function to make the cues shorter than min_duration miliseconds longer, as far as the next
+// cue shown lets them, ending gap miliseconds before it; returns every change made, those that fell
+// short of min_duration telling which cue stopped them. Cues that end before they start are left alone.
+pub fn extend_short(subtitles: &mut SubtitleFile, min_duration: i32, gap: i32) -> Vec<Fix> {
+    let time = vtt::convert_to_time;
+    let mut order: Vec<usize> = (0..subtitles.cues.len()).collect();
+    order.sort_by_key(|i| subtitles.cues[*i].start_ms);
+    let mut fixes = Vec::new();
+    for (position, i) in order.iter().enumerate() {
+        let next = order.get(position + 1).map(|next| &subtitles.cues[*next]);
+        let cue = &subtitles.cues[*i];
+        if cue.end_ms < cue.start_ms || cue.end_ms.saturating_sub(cue.start_ms) >= min_duration {
+            continue;
+        }
+        let target = cue.start_ms.saturating_add(min_duration);
+        let end = next
+            .map_or(target, |next| target.min(next.start_ms.saturating_sub(gap)))
+            .max(cue.end_ms);
+        if end == cue.end_ms {
+            continue;
+        }
+        let message = match next.filter(|_| end < target) {
+            Some(next) => format!(
+                "end {} -> {}, {} ms short of {} ms as cue {} starts at {}",
+                time(cue.end_ms),
+                time(end),
+                target - end,
+                min_duration,
+                next.index,
+                time(next.start_ms)
+            ),
+            None => format!("end {} -> {}", time(cue.end_ms), time(end)),
+        };
+        fixes.push(Fix {
+            rule: "min_duration",
+            cue: cue.index,
+            message,
+        });
+        subtitles.cues[*i].end_ms = end;
+    }
+    fixes
+}
+
+// Create a function to give the cues read faster than max_cps characters per second or max_wpm words per
+// minute the time they need, ending them later as far as the next cue shown lets them, then starting
+// them earlier as far as the cue before lets them, gap miliseconds apart; returns every change made,
+// those that still fell short telling by how much. Cues that end before they start are left alone.
+pub fn extend_fast(
+    subtitles: &mut SubtitleFile,
+    max_cps: Option<f64>,
+    max_wpm: Option<f64>,
+    gap: i32,
+) -> Vec<Fix> {
+    let time = vtt::convert_to_time;
+    let mut order: Vec<usize> = (0..subtitles.cues.len()).collect();
+    order.sort_by_key(|i| subtitles.cues[*i].start_ms);
+    let mut fixes = Vec::new();
+    for (position, i) in order.iter().enumerate() {
+        let cue = &subtitles.cues[*i];
+        if cue.end_ms < cue.start_ms {
+            continue;
+        }
+        // The time needed at each reading speed, and the rule of the longest.
+        let needed = [
+            max_cps.map(|cps| ("max_cps", cue.text_length() as f64 * 1000.0 / cps)),
+            max_wpm.map(|wpm| ("max_wpm", cue.word_count() as f64 * 60000.0 / wpm)),
+        ];
+        let Some((rule, needed)) = needed
+            .into_iter()
+            .flatten()
+            .map(|(rule, needed)| (rule, needed.ceil() as i32))
+            .max_by_key(|(_, needed)| *needed)
+            .filter(|(_, needed)| cue.end_ms.saturating_sub(cue.start_ms) < *needed)
+        else {
+            continue;
+        };
+        let latest_end = order.get(position + 1).map_or(i32::MAX, |next| {
+            subtitles.cues[*next].start_ms.saturating_sub(gap)
+        });
+        let end = cue
+            .start_ms
+            .saturating_add(needed)
+            .min(latest_end)
+            .max(cue.end_ms);
+        let earliest_start = position.checked_sub(1).map_or(0, |previous| {
+            subtitles.cues[order[previous]].end_ms.saturating_add(gap)
+        });
+        let start = end
+            .saturating_sub(needed)
+            .max(earliest_start)
+            .max(0)
+            .min(cue.start_ms);
+        let mut changes = Vec::new();
+        if end != cue.end_ms {
+            changes.push(format!("end {} -> {}", time(cue.end_ms), time(end)));
+        }
+        if start != cue.start_ms {
+            changes.push(format!("start {} -> {}", time(cue.start_ms), time(start)));
+        }
+        if changes.is_empty() {
+            continue;
+        }
+        if end.saturating_sub(start) < needed {
+            changes.push(format!(
+                "{} ms short of the {} ms it needs",
+                needed - (end - start),
+                needed
+            ));
+        }
+        fixes.push(Fix {
+            rule,
+            cue: cue.index,
+            message: changes.join(", "),
+        });
+        let cue = &mut subtitles.cues[*i];
+        cue.start_ms = start;
+        cue.end_ms = end;
+    }
+    fixes
+}
+
+// Where a cue can be split in two, best first: between its lines, after the end of a sentence, after a
+// comma or another break in a sentence, or between any two words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Boundary {
+    Line,
+    Sentence,
+    Clause,
+    Word,
+}
+
+// A place to split a cue: the line, the byte where the second cue starts in it (0 for a split before
+// the line), and how many characters the viewer reads before it.
+#[derive(Debug, Clone, Copy)]
+struct SplitPoint {
+    boundary: Boundary,
+    line: usize,
+    byte: usize,
+    before: usize,
+}
+
+// Create a function to find the places a cue can be split, outside of its formatting tags.
+fn split_points(cue: &Cue) -> Vec<SplitPoint> {
+    let tag = Regex::new(r"</?[a-zA-Z][^>]*>|\{[^}]*\}").unwrap();
+    let mut points = Vec::new();
+    let mut before = 0;
+    for (line, text) in cue.lines.iter().enumerate() {
+        if line > 0 {
+            points.push(SplitPoint {
+                boundary: Boundary::Line,
+                line,
+                byte: 0,
+                before,
+            });
+        }
+        let tags: Vec<_> = tag.find_iter(text).map(|tag| tag.range()).collect();
+        let mut previous = None;
+        for (byte, character) in text.char_indices() {
+            if tags.iter().any(|tag| tag.contains(&byte)) {
+                continue;
+            }
+            before += 1;
+            if character.is_whitespace() {
+                if let Some(previous) = previous.filter(|previous: &char| !previous.is_whitespace())
+                {
+                    let boundary = match previous {
+                        '.' | '?' | '!' | '…' | '。' | '？' | '！' => Boundary::Sentence,
+                        ',' | ';' | ':' | '–' | '—' | '、' | '，' => Boundary::Clause,
+                        _ => Boundary::Word,
+                    };
+                    points.push(SplitPoint {
+                        boundary,
+                        line,
+                        byte,
+                        before: before - 1,
+                    });
+                }
+            }
+            previous = Some(character);
+        }
+    }
+    points.retain(|point| point.before > 0 && point.before < before);
+    points
+}
+
+// Create a function to split a cue in two at its best boundary near the middle of its text, giving each
+// half a share of its time as long as its text, gap miliseconds apart, or none apart when the cue is
+// shorter than the gap. Formatting tags open at the split are closed at the end of the first half and
+// opened again at the start of the second. None when the cue is a single word.
+fn split_cue(cue: &Cue, gap: i32) -> Option<(Cue, Cue)> {
+    let length = cue
+        .plain_lines()
+        .iter()
+        .map(|line| line.chars().count())
+        .sum::<usize>();
+    let points = split_points(cue);
+    // The best kind of boundary found in the middle half of the text, else the word nearest the middle.
+    let middle = |point: &&SplitPoint| point.before * 4 >= length && point.before * 4 <= length * 3;
+    let best = points
+        .iter()
+        .filter(middle)
+        .map(|point| point.boundary)
+        .min();
+    let point = *points
+        .iter()
+        .filter(|point| best.is_none_or(|best| point.boundary == best))
+        .min_by_key(|point| (point.before * 2).abs_diff(length))?;
+
+    let mut first = cue.lines[..point.line].to_vec();
+    let mut second = cue.lines[point.line + 1..].to_vec();
+    let line = &cue.lines[point.line];
+    if point.byte == 0 {
+        second.insert(0, line.clone());
+    } else {
+        first.push(line[..point.byte].trim_end().to_string());
+        second.insert(0, line[point.byte..].trim_start().to_string());
+    }
+    let html = Regex::new(r"<(/?)([a-zA-Z]+)[^>]*>").unwrap();
+    let mut open: Vec<(String, String)> = Vec::new();
+    for tag in first.iter().flat_map(|line| html.captures_iter(line)) {
+        let name = tag[2].to_lowercase();
+        if tag[1].is_empty() {
+            open.push((name, tag[0].to_string()));
+        } else if let Some(position) = open.iter().rposition(|(open, _)| *open == name) {
+            open.truncate(position);
+        }
+    }
+    if let (Some(last), Some(next)) = (first.last_mut(), second.first_mut()) {
+        for (name, _) in open.iter().rev() {
+            last.push_str(&format!("</{}>", name));
+        }
+        let tags: String = open.iter().map(|(_, tag)| tag.as_str()).collect();
+        next.insert_str(0, &tags);
+    }
+
+    let gap = gap.min(cue.end_ms.saturating_sub(cue.start_ms)).max(0);
+    let duration = cue
+        .end_ms
+        .saturating_sub(cue.start_ms)
+        .saturating_sub(gap)
+        .max(0);
+    let end = cue
+        .start_ms
+        .saturating_add((duration as f64 * point.before as f64 / length as f64).round() as i32);
+    let first = Cue {
+        end_ms: end,
+        lines: first,
+        ..cue.clone()
+    };
+    let second = Cue {
+        identifier: None,
+        start_ms: end.saturating_add(gap),
+        lines: second,
+        comments: Vec::new(),
+        ..cue.clone()
+    };
+    Some((first, second))
+}
+
+// Create a function to split the cues longer than max_duration miliseconds or with more than max_chars
+// characters in two, again and again until they fit or are single words, and renumber the cues;
+// returns every change made. The halves are gap miliseconds apart.
+pub fn split_long(
+    subtitles: &mut SubtitleFile,
+    max_duration: Option<i32>,
+    max_chars: Option<usize>,
+    gap: i32,
+) -> Vec<Fix> {
+    let time = vtt::convert_to_time;
+    let mut fixes = Vec::new();
+    let mut cues = Vec::new();
+    let mut pending: Vec<Cue> = subtitles.cues.drain(..).rev().collect();
+    while let Some(cue) = pending.pop() {
+        let rule =
+            if max_duration.is_some_and(|limit| cue.end_ms.saturating_sub(cue.start_ms) > limit) {
+                "max_duration"
+            } else if max_chars.is_some_and(|limit| cue.text_length() > limit) {
+                "max_lines"
+            } else {
+                cues.push(cue);
+                continue;
+            };
+        match split_cue(&cue, gap) {
+            Some((first, second)) => {
+                fixes.push(Fix {
+                    rule,
+                    cue: cue.index,
+                    message: format!(
+                        "split at {} into '{}' and '{}'",
+                        time(second.start_ms),
+                        first.lines.join(" / "),
+                        second.lines.join(" / ")
+                    ),
+                });
+                pending.push(second);
+                pending.push(first);
+            }
+            None => cues.push(cue),
+        }
+    }
+    subtitles.cues = cues;
+    fixes.extend(renumber(subtitles));
+    fixes
+}
+
+// Create a function to find the cues that are ads or release credits rather than dialogue, by their
+// position in the file, with why each looks like one: a web address, a credit as "Downloaded from" or
+// "Synced by", or promotional words (presents, team, visit, ...) in capitals in one of the first or
+// last cues of the file, where release groups put them.
+pub fn find_ads(subtitles: &SubtitleFile) -> Vec<(usize, Vec<&'static str>)> {
+    let web_address = Regex::new(
+        r"(?i)\b(https?://|www\.)\S+|\b[a-z0-9-]+\.(com|net|org|info|io|tv|me|to|cc|xyz|ru|co|site|online)\b",
+    )
+    .unwrap();
+    let credit = Regex::new(
+        r"(?i)downloaded (from|at)|(subs|subtitles?|subtitled) (by|from)|\b(re-?)?sync(ed|hronized)? (and corrected )?by|corrected by|ripped by|encoded by|subbed by|translated by|opensubtitles|addic7ed|subscene|podnapisi|\byify\b|\byts\b|rarbg|support us|become (a )?vip|advertise your product|please rate",
+    )
+    .unwrap();
+    let promo = Regex::new(
+        r"(?i)\b(presents?|team|release|subs|subtitles?|enjoy|visit|follow us|rip|encoded?|download|watch online|free)\b",
+    )
+    .unwrap();
+    let count = subtitles.cues.len();
+    let mut ads = Vec::new();
+    for (i, cue) in subtitles.cues.iter().enumerate() {
+        let text = cue.plain_lines().join("\n");
+        let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+        let capitals = letters.len() >= 4
+            && letters
+                .iter()
+                .all(|c| c.is_uppercase() || !c.is_lowercase());
+        let at_edge = i < EDGE_CUES || i + EDGE_CUES >= count;
+        let promotional = promo.is_match(&text);
+        let mut reasons = Vec::new();
+        if web_address.is_match(&text) {
+            reasons.push("a web address");
+        }
+        if credit.is_match(&text) {
+            reasons.push("a release credit");
+        }
+        if reasons.is_empty() && !(promotional && capitals && at_edge) {
+            continue;
+        }
+        if promotional {
+            reasons.push("promotional words");
+        }
+        if capitals {
+            reasons.push("all in capitals");
+        }
+        if at_edge {
+            reasons.push(if i < EDGE_CUES {
+                "at the start of the file"
+            } else {
+                "at the end of the file"
+            });
+        }
+        ads.push((i, reasons));
+    }
+    ads
+}
+
+// Create a function to remove the ads and release credits find_ads finds, then number the cues left
+// 1, 2, 3..., returning every change made.
+pub fn drop_ads(subtitles: &mut SubtitleFile) -> Vec<Fix> {
+    let ads: BTreeSet<usize> = find_ads(subtitles).into_iter().map(|(i, _)| i).collect();
+    let mut fixes = Vec::new();
+    let mut position = 0;
+    subtitles.cues.retain(|cue| {
+        let ad = ads.contains(&position);
+        position += 1;
+        if ad {
+            fixes.push(Fix {
+                rule: "ads",
+                cue: cue.index,
+                message: format!("removed '{}'", cue.lines.join(" / ")),
+            });
+        }
+        !ad
+    });
+    fixes.extend(renumber(subtitles));
+    fixes
+}
+
+// Create a function to number the cues 1, 2, 3... in the order of the file, returning every change made.
+pub fn renumber(subtitles: &mut SubtitleFile) -> Vec<Fix> {
+    renumber_from(subtitles, 1)
+}
+
+// Create a function to number the cues one after the other from first, in the order of the file,
+// returning every change made.
+pub fn renumber_from(subtitles: &mut SubtitleFile, first: usize) -> Vec<Fix> {
+    let mut fixes = Vec::new();
+    for (i, cue) in subtitles.cues.iter_mut().enumerate() {
+        if cue.index != first + i {
+            fixes.push(Fix {
+                rule: "numbering",
+                cue: cue.index,
+                message: format!("renumbered {}", first + i),
+            });
+            cue.index = first + i;
+        }
+    }
+    fixes
+}
+
+// Create a function to fix the problems of a file that can be fixed mechanically, returning every change
+// made. Cues are sorted by start time and renumbered; then, under the rules that are
+// on, cues shorter than min_duration are made longer, and cues that overlap the next one or end less
+// than min_gap before it are cut, never beyond what leaves room for the next cue. Cues that end before
+// they start are left alone, nothing tells which time is wrong.
+pub fn fix(subtitles: &mut SubtitleFile, rules: &Rules) -> Vec<Fix> {
+    let mut fixes = Vec::new();
+    if subtitles
+        .cues
+        .windows(2)
+        .any(|pair| pair[1].start_ms < pair[0].start_ms)
+    {
+        let mut order: Vec<usize> = (0..subtitles.cues.len()).collect();
+        order.sort_by_key(|i| subtitles.cues[*i].start_ms);
+        for (position, was) in order.iter().enumerate() {
+            if *was != position {
+                fixes.push(Fix {
+                    rule: "out_of_order",
+                    cue: subtitles.cues[*was].index,
+                    message: format!("moved from position {} to {}", was + 1, position + 1),
+                });
+            }
+        }
+        let mut cues: Vec<Option<Cue>> = subtitles.cues.drain(..).map(Some).collect();
+        subtitles.cues = order.iter().filter_map(|i| cues[*i].take()).collect();
+    }
+    let numbered: Vec<usize> = subtitles.cues.iter().map(|cue| cue.index).collect();
+    fixes.extend(renumber(subtitles));
+    let min_duration = rules.min_duration.map(|limit| limit.value.ceil() as i32);
+    let min_gap = rules.min_gap.map(|limit| limit.value.ceil() as i32);
+    for (i, index) in numbered.into_iter().enumerate() {
+        let next_start = subtitles.cues.get(i + 1).map(|next| next.start_ms);
+        let cue = &mut subtitles.cues[i];
+        if cue.end_ms < cue.start_ms {
+            continue;
+        }
+        let mut end = cue.end_ms;
+        if let Some(duration) =
+            min_duration.filter(|duration| end.saturating_sub(cue.start_ms) < *duration)
+        {
+            end = cue.start_ms.saturating_add(duration);
+        }
+        // The latest the cue can end, leaving the gap before the next one, or at least not running into
+        // it when the next one starts too soon for a gap.
+        let latest = match (next_start, min_gap, rules.overlap) {
+            (Some(next), Some(gap), _) => Some(next.saturating_sub(gap)),
+            (Some(next), None, Some(_)) => Some(next),
+            _ => None,
+        }
+        .filter(|latest| *latest > cue.start_ms)
+        .or(next_start.filter(|next| *next > cue.start_ms && rules.overlap.is_some()));
+        if let Some(latest) = latest.filter(|latest| end > *latest) {
+            end = latest;
+        }
+        let rule = if end > cue.end_ms {
+            "min_duration"
+        } else if next_start.is_some_and(|next| cue.end_ms > next) {
+            "overlap"
+        } else {
+            "min_gap"
+        };
+        if end != cue.end_ms {
+            fixes.push(Fix {
+                rule,
+                cue: index,
+                message: format!(
+                    "end {} -> {}",
+                    vtt::convert_to_time(cue.end_ms),
+                    vtt::convert_to_time(end)
+                ),
+            });
+            cue.end_ms = end;
+        }
+    }
+    fixes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle_parser::Format;
+
+    fn file(cues: &[(i32, i32, &str)]) -> SubtitleFile {
+        SubtitleFile {
+            format: Format::Srt,
+            framerate: None,
+            header: Vec::new(),
+            cues: cues
+                .iter()
+                .enumerate()
+                .map(|(i, (start_ms, end_ms, text))| Cue {
+                    index: i + 1,
+                    start_ms: *start_ms,
+                    end_ms: *end_ms,
+                    lines: text.lines().map(str::to_string).collect(),
+                    ..Default::default()
+                })
+                .collect(),
+            trailer: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn cues_at_the_last_representable_time_dont_overflow() {
+        let last = i32::MAX;
+        let mut subtitles = file(&[
+            (
+                last - 100,
+                last - 50,
+                "Far too many words for fifty miliseconds",
+            ),
+            (last - 60, last, "Overlapping the one before it"),
+            (last, last, "{2147483647}{2147483647}"),
+        ]);
+        let rules = Rules {
+            max_wpm: Some(Limit::warning(180.0)),
+            min_gap: Some(Limit::warning(80.0)),
+            ..Rules::default()
+        };
+        let issues = check(&subtitles, &rules);
+        assert!(issues
+            .iter()
+            .any(|issue| issue.rule() == "min_duration" && issue.cue == 1));
+        fix(&mut subtitles, &rules);
+        extend_short(&mut subtitles, 1000, 80);
+        extend_fast(&mut subtitles, Some(MAX_CPS), Some(180.0), 80);
+        fix_overlaps(&mut subtitles, OverlapStrategy::Split, 80);
+        split_long(&mut subtitles, Some(1000), Some(42), 80);
+        assert!(subtitles
+            .cues
+            .iter()
+            .all(|cue| 0 <= cue.start_ms && cue.start_ms <= cue.end_ms));
+    }
+
+    // Create a function to list the rules broken, by cue.
+    fn broken(subtitles: &SubtitleFile, rules: &Rules) -> Vec<(usize, &'static str)> {
+        check(subtitles, rules)
+            .iter()
+            .map(|issue| (issue.cue, issue.rule()))
+            .collect()
+    }
+
+    #[test]
+    fn broken_times_are_errors() {
+        let subtitles = file(&[
+            (5000, 4000, "Backwards."),
+            (-500, 1000, "Too early."),
+            (3000, 4000, "Third."),
+            (2000, 2500, "Fourth."),
+        ]);
+        let rules = Rules {
+            max_cps: None,
+            ..Rules::default()
+        };
+        assert_eq!(
+            broken(&subtitles, &rules),
+            [
+                (1, "ends_before_start"),
+                (2, "starts_before_zero"),
+                (2, "out_of_order"),
+                (4, "out_of_order")
+            ]
+        );
+        assert!(check(&subtitles, &rules)
+            .iter()
+            .all(|issue| issue.severity == Severity::Error));
+    }
+
+    fn times(subtitles: &SubtitleFile) -> Vec<(usize, i32, i32)> {
+        subtitles
+            .cues
+            .iter()
+            .map(|cue| (cue.index, cue.start_ms, cue.end_ms))
+            .collect()
+    }
+
+    #[test]
+    fn fix_sorts_renumbers_and_makes_room_between_cues() {
+        let mut subtitles = file(&[
+            (3100, 5000, "There."),
+            (1000, 1100, "Hi."),
+            (5050, 7000, "Bye."),
+        ]);
+        let fixes = fix(&mut subtitles, &Rules::preset("netflix").unwrap());
+        // A 100 ms cue is made 833 ms long, a cue ending 50 ms before the next one ends 83 ms before it.
+        assert_eq!(
+            times(&subtitles),
+            [(1, 1000, 1833), (2, 3100, 4967), (3, 5050, 7000)]
+        );
+        // Fixes number the cues as in the input.
+        let rules: Vec<(&str, usize)> = fixes.iter().map(|fix| (fix.rule, fix.cue)).collect();
+        assert_eq!(
+            rules,
+            [
+                ("out_of_order", 2),
+                ("out_of_order", 1),
+                ("numbering", 2),
+                ("numbering", 1),
+                ("min_duration", 2),
+                ("min_gap", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn fix_leaves_cues_that_end_before_they_start() {
+        let mut subtitles = file(&[(2000, 1000, "Backwards."), (3000, 3010, "Short.")]);
+        let fixes = fix(&mut subtitles, &Rules::default());
+        assert_eq!(times(&subtitles), [(1, 2000, 1000), (2, 3000, 3100)]);
+        assert_eq!(fixes.len(), 1);
+    }
+
+    #[test]
+    fn reading_speed_is_checked_in_characters_and_words() {
+        // 27 characters and 6 words in a second, tags left out: 27 characters per second, 360 words per
+        // minute.
+        let subtitles = file(&[(1000, 2000, "<i>One two three four five six</i>")]);
+        let rules = Rules {
+            max_wpm: Some(Limit::warning(180.0)),
+            ..Rules::default()
+        };
+        let issues = check(&subtitles, &rules);
+        let kinds: Vec<&IssueKind> = issues.iter().map(|issue| &issue.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                &IssueKind::MaxCps {
+                    cps: 27.0,
+                    limit: MAX_CPS
+                },
+                &IssueKind::MaxWpm {
+                    wpm: 360.0,
+                    limit: 180.0
+                }
+            ]
+        );
+        assert_eq!(
+            issues[0].suggestion,
+            "end it at 00:00:02.080 or cut the text to 25 characters"
+        );
+        assert_eq!(
+            issues[1].suggestion,
+            "end it at 00:00:03.000 or cut the text to 3 words"
+        );
+    }
+
+    #[test]
+    fn overlaps_with_every_cue_shown_are_reported() {
+        // The first cue is still shown when the second and third start, out of order in the file.
+        let subtitles = file(&[
+            (1000, 9000, "Long."),
+            (6000, 7000, "Third."),
+            (3000, 4000, "Second."),
+            (9500, 10000, "Fourth."),
+        ]);
+        let rules = Rules {
+            max_cps: None,
+            numbering: None,
+            ..Rules::default()
+        };
+        let overlaps: Vec<(usize, IssueKind)> = check(&subtitles, &rules)
+            .into_iter()
+            .filter(|issue| issue.rule() == "overlap")
+            .map(|issue| (issue.cue, issue.kind))
+            .collect();
+        assert_eq!(
+            overlaps,
+            [
+                (1, IssueKind::Overlap { other: 3 }),
+                (1, IssueKind::Overlap { other: 2 })
+            ]
+        );
+    }
+
+    #[test]
+    fn numbering_breaks_are_reported_once_and_renumbered() {
+        let mut subtitles = file(&[
+            (1000, 2000, "One."),
+            (3000, 4000, "Two."),
+            (5000, 6000, "Two again."),
+            (7000, 8000, "Five."),
+            (9000, 10000, "Four."),
+        ]);
+        for (cue, index) in subtitles.cues.iter_mut().zip([1, 2, 2, 5, 4]) {
+            cue.index = index;
+        }
+        let numbering: Vec<(usize, IssueKind)> = check(&subtitles, &Rules::default())
+            .into_iter()
+            .filter(|issue| issue.rule() == "numbering")
+            .map(|issue| (issue.cue, issue.kind))
+            .collect();
+        assert_eq!(
+            numbering,
+            [
+                (2, IssueKind::DuplicateIndex { position: 3 }),
+                (4, IssueKind::IndexNotIncreasing { previous: 5 }),
+                (4, IssueKind::MissingIndices { first: 3, last: 3 })
+            ]
+        );
+        let fixes = renumber_from(&mut subtitles, 101);
+        assert_eq!(fixes.len(), 5);
+        assert_eq!(fixes[3].message, "renumbered 104");
+        let indices: Vec<usize> = subtitles.cues.iter().map(|cue| cue.index).collect();
+        assert_eq!(indices, [101, 102, 103, 104, 105]);
+        assert!(renumber_from(&mut subtitles, 101).is_empty());
+    }
+
+    #[test]
+    fn ads_and_credits_at_the_edges_are_found_and_dropped() {
+        let mut cues = vec![(0, 2000, "SUBTEAM PRESENTS")];
+        cues.extend((1..30).map(|i| (i * 3000, i * 3000 + 2000, "The team is here.")));
+        cues.push((
+            90000,
+            92000,
+            "Synced and corrected by someone\nwww.example.com",
+        ));
+        let mut subtitles = file(&cues);
+        // Promotional words in dialogue, or not in capitals, aren't ads.
+        assert_eq!(
+            find_ads(&subtitles),
+            [
+                (
+                    0,
+                    vec![
+                        "promotional words",
+                        "all in capitals",
+                        "at the start of the file"
+                    ]
+                ),
+                (
+                    30,
+                    vec![
+                        "a web address",
+                        "a release credit",
+                        "at the end of the file"
+                    ]
+                )
+            ]
+        );
+        let fixes = drop_ads(&mut subtitles);
+        assert_eq!(fixes[0].message, "removed 'SUBTEAM PRESENTS'");
+        assert_eq!(subtitles.cues.len(), 29);
+        assert_eq!(subtitles.cues[0].index, 1);
+        assert!(find_ads(&subtitles).is_empty());
+    }
+
+    #[test]
+    fn overlaps_are_trimmed_or_split() {
+        let cues = [(1000, 4000, "First."), (3000, 6000, "Second.")];
+        let mut trimmed = file(&cues);
+        fix_overlaps(&mut trimmed, OverlapStrategy::Trim, 0);
+        assert_eq!(times(&trimmed), [(1, 1000, 3000), (2, 3000, 6000)]);
+        let mut split = file(&cues);
+        let fixes = fix_overlaps(&mut split, OverlapStrategy::Split, 80);
+        assert_eq!(times(&split), [(1, 1000, 3460), (2, 3540, 6000)]);
+        assert_eq!(fixes[1].message, "start 00:00:03.000 -> 00:00:03.540");
+        // A cue shown all through the next one is trimmed, even when splitting.
+        let mut around = file(&[(1000, 9000, "Long."), (2000, 3000, "Inside.")]);
+        fix_overlaps(&mut around, OverlapStrategy::Split, 0);
+        assert_eq!(times(&around), [(1, 1000, 2000), (2, 2000, 3000)]);
+    }
+
+    #[test]
+    fn short_cues_are_extended_up_to_the_next_one() {
+        let mut subtitles = file(&[
+            (1000, 1200, "Stopped."),
+            (1500, 1600, "Free."),
+            (5000, 7000, "Long enough."),
+        ]);
+        let fixes = extend_short(&mut subtitles, 1000, 80);
+        assert_eq!(
+            times(&subtitles),
+            [(1, 1000, 1420), (2, 1500, 2500), (3, 5000, 7000)]
+        );
+        assert_eq!(
+            fixes[0].message,
+            "end 00:00:01.200 -> 00:00:01.420, 580 ms short of 1000 ms as cue 2 starts at 00:00:01.500"
+        );
+        assert_eq!(fixes[1].message, "end 00:00:01.600 -> 00:00:02.500");
+    }
+
+    #[test]
+    fn long_cues_are_split_at_the_best_boundary() {
+        let mut subtitles = file(&[
+            (0, 8000, "<i>We have to go. They are coming</i> for us."),
+            (9000, 17000, "Line one\nline two"),
+        ]);
+        let fixes = split_long(&mut subtitles, Some(7000), Some(30), 80);
+        let lines: Vec<(usize, i32, i32, String)> = subtitles
+            .cues
+            .iter()
+            .map(|cue| (cue.index, cue.start_ms, cue.end_ms, cue.lines.join(" / ")))
+            .collect();
+        // Halves get a share of the time as long as their text, and tags open at the split are closed
+        // and opened again.
+        assert_eq!(
+            lines,
+            [
+                (1, 0, 2918, "<i>We have to go.</i>".to_string()),
+                (2, 2998, 8000, "<i>They are coming</i> for us.".to_string()),
+                (3, 9000, 12960, "Line one".to_string()),
+                (4, 13040, 17000, "line two".to_string())
+            ]
+        );
+        assert_eq!(fixes[0].rule, "max_duration");
+    }
+
+    #[test]
+    fn split_halves_of_a_cue_shorter_than_the_gap_dont_end_before_they_start() {
+        let mut subtitles = file(&[(1000, 1020, "Far too long for a twenty ms cue")]);
+        split_long(&mut subtitles, None, Some(20), 80);
+        assert_eq!(subtitles.cues.len(), 2);
+        assert!(subtitles
+            .cues
+            .iter()
+            .all(|cue| cue.start_ms <= cue.end_ms && cue.end_ms <= 1020));
+    }
+
+    #[test]
+    fn fast_cues_end_later_then_start_earlier() {
+        // 50 characters need 2 seconds at 25 characters per second.
+        let text = "Fifty characters of text are read in two seconds..";
+        let mut subtitles = file(&[
+            (0, 500, "Before."),
+            (1000, 2000, text),
+            (2500, 4000, "After."),
+            (6000, 7000, text),
+        ]);
+        let fixes = extend_fast(&mut subtitles, Some(MAX_CPS), None, 80);
+        assert_eq!(
+            times(&subtitles),
+            [
+                (1, 0, 500),
+                (2, 580, 2420),
+                (3, 2500, 4000),
+                (4, 6000, 8000)
+            ]
+        );
+        assert_eq!(
+            fixes[0].message,
+            "end 00:00:02.000 -> 00:00:02.420, start 00:00:01.000 -> 00:00:00.580, 160 ms short of the \
+             2000 ms it needs"
+        );
+        assert_eq!(fixes[1].message, "end 00:00:07.000 -> 00:00:08.000");
+    }
+}
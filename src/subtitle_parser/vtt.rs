@@ -0,0 +1,216 @@
+use regex::Regex;
+
+use super::{
+    decode_text, sniff_text, split_blocks, tags, Capabilities, Cue, Format, ParseOptions,
+    SubtitleFile, SubtitleFormat, TagDialect,
+};
+
+// Create a function to convert a [hh:]mm:ss.mmm string to miliseconds as an integer.
+pub fn convert_to_miliseconds(time: &str) -> Option<i32> {
+    let re = Regex::new(r"^(?:(\d{2,}):)?(\d{2}):(\d{2})\.(\d{3})$").unwrap();
+    let caps = re.captures(time.trim())?;
+    let hours = match caps.get(1) {
+        Some(hours) => hours.as_str().parse::<i32>().ok()?,
+        None => 0,
+    };
+    let minutes = caps.get(2)?.as_str().parse::<i32>().ok()?;
+    let seconds = caps.get(3)?.as_str().parse::<i32>().ok()?;
+    let miliseconds = caps.get(4)?.as_str().parse::<i32>().ok()?;
+    Some((hours * 3600000) + (minutes * 60000) + (seconds * 1000) + miliseconds)
+}
+
+// Create a function to convert miliseconds to a hh:mm:ss.mmm string.
+pub fn convert_to_time(miliseconds: i32) -> String {
+    super::srt::convert_to_time(miliseconds).replace(',', ".")
+}
+
+// Create a function to parse the contents of a .vtt file.
+pub fn parse(contents: &str) -> Result<SubtitleFile, String> {
+    let blocks = split_blocks(contents);
+    match blocks.first() {
+        Some(block) if block[0].starts_with("WEBVTT") => {}
+        _ => return Err("Missing WEBVTT header".to_string()),
+    }
+    let mut header = vec![blocks[0].join("\n")];
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut comments = Vec::new();
+    for block in &blocks[1..] {
+        if block[0].starts_with("NOTE") {
+            comments.push(block.join("\n"));
+            continue;
+        }
+        if cues.is_empty() && (block[0].starts_with("STYLE") || block[0].starts_with("REGION")) {
+            header.append(&mut comments);
+            header.push(block.join("\n"));
+            continue;
+        }
+        // The timing line is either the first line, or the second line after a cue identifier.
+        let (identifier, timing, text) = if block[0].contains("-->") {
+            (None, block[0], &block[1..])
+        } else if block.len() > 1 && block[1].contains("-->") {
+            (Some(block[0].to_string()), block[1], &block[2..])
+        } else {
+            return Err(format!("Invalid cue: '{}'", block.join(" ")));
+        };
+        let index = cues.len() + 1;
+        let (start, rest) = timing.split_once("-->").unwrap();
+        let mut rest = rest.trim().splitn(2, char::is_whitespace);
+        let end = rest.next().unwrap_or("");
+        let settings = rest
+            .next()
+            .map(|settings| settings.trim().to_string())
+            .filter(|settings| !settings.is_empty());
+        let start_ms = convert_to_miliseconds(start)
+            .ok_or_else(|| format!("Cue {}: invalid timestamp '{}'", index, start.trim()))?;
+        let end_ms = convert_to_miliseconds(end)
+            .ok_or_else(|| format!("Cue {}: invalid timestamp '{}'", index, end))?;
+        cues.push(Cue {
+            index,
+            identifier,
+            start_ms,
+            end_ms,
+            settings,
+            lines: text.iter().map(|line| line.to_string()).collect(),
+            comments: std::mem::take(&mut comments),
+            ..Default::default()
+        });
+    }
+    Ok(SubtitleFile {
+        format: Format::Vtt,
+        framerate: None,
+        header,
+        cues,
+        trailer: comments,
+    })
+}
+
+// Create a function to write the cues out as .vtt.
+pub fn serialize(file: &SubtitleFile) -> String {
+    let mut output = String::new();
+    if file.format == Format::Vtt && !file.header.is_empty() {
+        for block in &file.header {
+            output.push_str(block);
+            output.push_str("\n\n");
+        }
+    } else {
+        output.push_str("WEBVTT\n\n");
+    }
+    for cue in &file.cues {
+        for comment in cue.comments.iter().filter(|_| file.format == Format::Vtt) {
+            output.push_str(comment);
+            output.push_str("\n\n");
+        }
+        if let Some(identifier) = &cue.identifier {
+            output.push_str(identifier);
+            output.push('\n');
+        }
+        output.push_str(&format!(
+            "{} --> {}",
+            convert_to_time(cue.start_ms),
+            convert_to_time(cue.end_ms)
+        ));
+        // Positioning overrides such as {\an8} from other formats become cue settings, other ASS
+        // overrides are dropped as WebVTT would show them as text.
+        let settings = match file.format {
+            Format::Vtt => cue.settings.clone(),
+            _ => file.cue_alignment(cue).and_then(tags::vtt_settings),
+        };
+        if let Some(settings) = settings {
+            output.push(' ');
+            output.push_str(&settings);
+        }
+        output.push('\n');
+        for line in &cue.lines {
+            match file.format {
+                Format::Vtt => output.push_str(line),
+                _ => output.push_str(&tags::strip_overrides(line)),
+            }
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    for comment in file.trailer.iter().filter(|_| file.format == Format::Vtt) {
+        output.push_str(comment);
+        output.push_str("\n\n");
+    }
+    output
+}
+
+// The WebVTT format, as registered in the format registry.
+pub struct Vtt;
+
+impl SubtitleFormat for Vtt {
+    fn format(&self) -> Format {
+        Format::Vtt
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        &["vtt", "webvtt"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["vtt"]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            styles: true,
+            positions: true,
+            frame_based: false,
+            end_times: true,
+        }
+    }
+
+    fn tag_dialect(&self) -> TagDialect {
+        TagDialect::Vtt
+    }
+
+    fn detect(&self, contents: &[u8]) -> bool {
+        sniff_text(contents).starts_with("WEBVTT")
+    }
+
+    fn parse(&self, contents: &[u8], _options: &ParseOptions) -> Result<SubtitleFile, String> {
+        parse(decode_text(contents)?)
+    }
+
+    fn serialize(&self, file: &SubtitleFile) -> Vec<u8> {
+        serialize(file).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VTT: &str = "WEBVTT - A title\n\nSTYLE\n::cue { color: yellow }\n\nNOTE before the first cue\n\nintro\n00:01.000 --> 00:02.500 line:0 align:start\nHello.\n\n01:00:00.000 --> 01:00:01.000\nBye.\n\nNOTE at the end\n\n";
+
+    #[test]
+    fn round_trip_keeps_everything_but_short_times() {
+        let file = parse(VTT).unwrap();
+        assert_eq!(file.cues.len(), 2);
+        assert_eq!(file.cues[0].identifier.as_deref(), Some("intro"));
+        assert_eq!(file.cues[0].settings.as_deref(), Some("line:0 align:start"));
+        assert_eq!(file.cues[1].start_ms, 3600000);
+        assert_eq!(file.trailer, ["NOTE at the end"]);
+        // Times are always written with their hours.
+        assert_eq!(
+            serialize(&file),
+            VTT.replace("00:01.000 --> 00:02.500", "00:00:01.000 --> 00:00:02.500")
+        );
+    }
+
+    #[test]
+    fn hours_are_optional() {
+        assert_eq!(convert_to_miliseconds("01:02.003"), Some(62003));
+        assert_eq!(convert_to_miliseconds("00:01:02.003"), Some(62003));
+        assert_eq!(convert_to_time(62003), "00:01:02.003");
+        assert_eq!(convert_to_miliseconds("1:02.003"), None);
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        assert!(parse("00:01.000 --> 00:02.000\nNo header\n").is_err());
+        assert!(parse("WEBVTT\n\njust text\n").is_err());
+        assert!(parse("WEBVTT\n\n00:01.000 --> 00:02\nText\n").is_err());
+    }
+}